@@ -0,0 +1,81 @@
+//! Rough benchmark of the hot paths of a store holding a lot of resources.
+//!
+//! Run it with and without the `"fast-hash"` feature-gate to compare hashers:
+//!
+//! ```text
+//! cargo bench --bench store
+//! cargo bench --bench store --features fast-hash
+//! ```
+
+use std::fmt;
+use std::time::Instant;
+use warmy::{Load, Loaded, Res, SimpleKey, Storage, Store, StoreOpt};
+
+/// Number of resources living in the store.
+const RESOURCES: usize = 50_000;
+
+struct Dummy;
+
+#[derive(Debug)]
+struct Never;
+
+impl fmt::Display for Never {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str("never")
+  }
+}
+
+impl std::error::Error for Never {}
+
+impl Load<(), SimpleKey> for Dummy {
+  type Error = Never;
+
+  fn load(
+    _: SimpleKey,
+    _: &mut Storage<(), SimpleKey>,
+    _: &mut (),
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    Ok(Dummy.into())
+  }
+}
+
+fn bench<F>(name: &str, mut f: F) where F: FnMut() {
+  let start = Instant::now();
+  f();
+  let elapsed = start.elapsed();
+
+  println!(
+    "{:<12} {:>10.2?} ({:.0} ns per resource)",
+    name,
+    elapsed,
+    elapsed.as_nanos() as f64 / RESOURCES as f64
+  );
+}
+
+fn main() {
+  let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+  let opt = StoreOpt::default().set_root(tmp_dir.path());
+  let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+  let ctx = &mut ();
+  let keys: Vec<SimpleKey> = (0..RESOURCES).map(|i| format!("dummy-{}", i).into()).collect();
+
+  bench("get (miss)", || {
+    for key in &keys {
+      let _: Res<Dummy> = store.get(key, ctx).unwrap();
+    }
+  });
+
+  bench("get (hit)", || {
+    for key in &keys {
+      let _: Res<Dummy> = store.get(key, ctx).unwrap();
+    }
+  });
+
+  bench("sync", || {
+    for key in &keys {
+      store.invalidate(key);
+    }
+
+    store.sync(ctx);
+  });
+}
@@ -0,0 +1,45 @@
+//! Typed cache of resources.
+
+use std::any::{Any, TypeId};
+
+use crate::hash::HashMap;
+use crate::key::KeyId;
+use crate::res::Res;
+
+/// Cache holding resources of any type.
+///
+/// Resources are indexed by the identifiers of their keys along with their types, in a single map.
+pub(crate) struct Cache {
+  resources: HashMap<(KeyId, TypeId), Box<dyn Any>>,
+}
+
+impl Cache {
+  pub(crate) fn new() -> Self {
+    Cache {
+      resources: HashMap::default(),
+    }
+  }
+
+  /// Get a cached resource.
+  pub(crate) fn get<T>(&self, id: KeyId) -> Option<&Res<T>> where T: 'static {
+    self
+      .resources
+      .get(&(id, TypeId::of::<T>()))
+      .and_then(|res| res.downcast_ref())
+  }
+
+  /// Cache a resource, replacing the previous one of the same key and type, if any.
+  pub(crate) fn save<T>(&mut self, id: KeyId, res: Res<T>) where T: 'static {
+    self.resources.insert((id, TypeId::of::<T>()), Box::new(res));
+  }
+
+  /// Reserve capacity for at least `additional` more resources.
+  pub(crate) fn reserve(&mut self, additional: usize) {
+    self.resources.reserve(additional);
+  }
+
+  /// Remove a cached resource of a given type, if any.
+  pub(crate) fn remove(&mut self, id: KeyId, ty: TypeId) -> bool {
+    self.resources.remove(&(id, ty)).is_some()
+  }
+}
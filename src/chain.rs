@@ -0,0 +1,150 @@
+//! Chained stores.
+//!
+//! This module provides you with [`StoreChain`], which looks resources up in several stores in
+//! order. Large teams can share a network asset cache in a store while overriding some of its
+//! assets locally, in another store with precedence over it:
+//!
+//! ```
+//! use warmy::{Load, Loaded, Res, SimpleKey, Storage, StoreOpt};
+//! use warmy::chain::StoreChain;
+//! use warmy::testing::MockStore;
+//!
+//! struct Text(String);
+//!
+//! impl Load<(), SimpleKey> for Text {
+//!   type Error = std::io::Error;
+//!
+//!   fn load(
+//!     key: SimpleKey,
+//!     storage: &mut Storage<(), SimpleKey>,
+//!     _: &mut (),
+//!   ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+//!     let path: Option<std::path::PathBuf> = key.into();
+//!     storage.read_file_to_string(&path.unwrap()).map(|s| Text(s).into())
+//!   }
+//! }
+//!
+//! let mut local: MockStore<(), SimpleKey> = MockStore::new(StoreOpt::default());
+//! local.set_file("/tree.txt", "local tree");
+//!
+//! let mut shared: MockStore<(), SimpleKey> = MockStore::new(StoreOpt::default());
+//! shared.set_file("/tree.txt", "shared tree");
+//! shared.set_file("/rock.txt", "shared rock");
+//!
+//! let mut chain = StoreChain::new(local.into_store());
+//! chain.push(shared.into_store());
+//!
+//! let tree: Res<Text> = chain.get(&SimpleKey::from_path("/tree.txt"), &mut ()).unwrap();
+//! let rock: Res<Text> = chain.get(&SimpleKey::from_path("/rock.txt"), &mut ()).unwrap();
+//!
+//! assert_eq!(tree.borrow().0, "local tree");
+//! assert_eq!(rock.borrow().0, "shared rock");
+//! ```
+
+use std::path::Path;
+
+use crate::key::Key;
+use crate::load::{Load, Store, StoreErrorOr, SyncReport};
+use crate::res::Res;
+
+/// Several stores, looked up in order.
+///
+/// A store *has* a resource if the resource is loaded in it or if the file addressed by the key
+/// exists in it – see [`Key::fs_path`]. Resources which keys don’t address files are tried in each
+/// store until one loads them.
+///
+/// Lookups always go through the stores in order: a resource got from a store with less precedence
+/// is got from a store with more precedence as soon as the latter has it – e.g. when a local
+/// override is created. Both resources are then distinct.
+pub struct StoreChain<C, K> {
+  // stores, by decreasing precedence
+  stores: Vec<Store<C, K>>,
+}
+
+impl<C, K> StoreChain<C, K> where K: Key {
+  /// Create a chain of a single store.
+  pub fn new(store: Store<C, K>) -> Self {
+    StoreChain {
+      stores: vec![store],
+    }
+  }
+
+  /// Add a store to the chain, with less precedence than the stores already in it.
+  pub fn push(&mut self, store: Store<C, K>) {
+    self.stores.push(store);
+  }
+
+  /// Stores of the chain, by decreasing precedence.
+  pub fn stores(&self) -> &[Store<C, K>] {
+    &self.stores
+  }
+
+  /// Stores of the chain, by decreasing precedence, mutably.
+  pub fn stores_mut(&mut self) -> &mut [Store<C, K>] {
+    &mut self.stores
+  }
+
+  /// Consume the chain and get its stores, by decreasing precedence.
+  pub fn into_stores(self) -> Vec<Store<C, K>> {
+    self.stores
+  }
+
+  /// Get a resource from the first store that has it.
+  ///
+  /// This function uses the default loading method.
+  ///
+  /// # Failures
+  ///
+  /// If no store has the resource, the store with the least precedence reports why it cannot be
+  /// got.
+  pub fn get<T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C, K>>
+  where T: Load<C, K> {
+    self.get_by(key, ctx, ())
+  }
+
+  /// Get a resource from the first store that has it by using a specific method.
+  ///
+  /// See [`StoreChain::get`] for further details.
+  pub fn get_by<T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
+  where T: Load<C, K, M>,
+        M: Clone {
+    let mut last_error = None;
+
+    for store in &mut self.stores {
+      if store.resource_type(key).is_some() {
+        return store.get_by(key, ctx, method);
+      }
+
+      match store.key_file_exists(key) {
+        Some(true) => return store.get_by(key, ctx, method),
+        Some(false) => (),
+
+        // the only way to know whether a store has a logical resource is to load it
+        None => match store.get_by(key, ctx, method.clone()) {
+          Ok(res) => return Ok(res),
+          Err(e) => last_error = Some(e),
+        },
+      }
+    }
+
+    match last_error {
+      Some(e) => Err(e),
+      None => {
+        let store = self.stores.last_mut().expect("at least one store");
+        store.get_by(key, ctx, method)
+      }
+    }
+  }
+
+  /// Synchronize all the stores with a provided context.
+  ///
+  /// Return the report of each store, by decreasing precedence.
+  pub fn sync(&mut self, ctx: &mut C) -> Vec<SyncReport<K>> where K: for<'a> From<&'a Path> {
+    self.stores.iter_mut().map(|store| store.sync(ctx)).collect()
+  }
+}
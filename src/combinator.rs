@@ -0,0 +1,341 @@
+//! Loader combinators.
+//!
+//! Post-processing a resource loaded with a method – e.g. turning a raw deserialized configuration
+//! into the one your application uses – normally requires a full [`Load`] implementation, along
+//! with its own error type. Combinators build such loaders out of values instead:
+//!
+//!   - [`Method::map`] and [`Combine::map`] transform the resource with a function.
+//!   - [`Method::and_then`] and [`Combine::and_then`] transform the resource with a function that
+//!     can fail, failing the loading – or the reloading – if it does.
+//!   - [`Combine::with_deps`] declares additional dependencies, computed from the key.
+//!
+//! Load methods are turned into loaders with the functions of [`Method`] and loaders are combined
+//! further with the functions of [`Combine`]. The resulting [`Loader`] is given to
+//! [`Storage::get_with`], which keeps it to reload the resource.
+//!
+//! ```
+//! # #[cfg(feature = "json")] {
+//! use serde::Deserialize;
+//! use warmy::{Res, SimpleKey, Store, StoreOpt};
+//! use warmy::combinator::Method;
+//! use warmy::json::Json;
+//!
+//! #[derive(Deserialize)]
+//! struct RawConfig {
+//!   volume: u8,
+//! }
+//!
+//! struct Config {
+//!   gain: f32,
+//! }
+//!
+//! impl From<RawConfig> for Config {
+//!   fn from(raw: RawConfig) -> Self {
+//!     Config { gain: f32::from(raw.volume) / 100. }
+//!   }
+//! }
+//!
+//! # let tmp_dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(tmp_dir.path().join("config.json"), r#"{ "volume": 50 }"#).unwrap();
+//! # let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt).unwrap();
+//! let key = SimpleKey::from_path("/config.json");
+//! let loader = Json.map(|raw: RawConfig| Config::from(raw));
+//! let config: Res<Config> = store.get_with(&key, &mut (), loader).unwrap();
+//!
+//! assert_eq!(config.borrow().gain, 0.5);
+//! # }
+//! ```
+//!
+//! [`Storage::get_with`]: crate::load::Storage::get_with
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::key::Key;
+use crate::load::{Load, Loaded, Shared, Storage};
+use crate::registry::Registered;
+
+/// Loaders as values.
+///
+/// Whereas [`Load`] is implemented by the type of the resources, a [`Loader`] is a value – such as
+/// a closure – able to load resources of type [`Loader::Res`]. Loaders are built with the functions
+/// of [`Method`] and [`Combine`].
+pub trait Loader<C, K>: 'static where K: Key {
+  /// Type of the loaded resources.
+  type Res: 'static;
+
+  /// Type of error that might happen while loading.
+  type Error: Error + Send + Sync + 'static;
+
+  /// Load a resource.
+  ///
+  /// See [`Load::load`] for further details.
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self::Res, K>, Self::Error>;
+
+  /// Function called when a resource must be reloaded.
+  ///
+  /// The default implementation of that function calls [`Loader::load`] and returns its result.
+  fn reload(
+    &self,
+    _res: &Self::Res,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self::Res, Self::Error> {
+    self.load(key, storage, ctx).map(|lr| lr.res)
+  }
+}
+
+/// Load methods, turned into [`Loader`]s.
+///
+/// Implement this trait for your own methods to use combinators on them.
+pub trait Method: Sized + 'static {
+  /// Load resources of type `T` with this method.
+  fn loading<T>(self) -> By<Self, T> {
+    By(self, PhantomData)
+  }
+
+  /// Load resources of type `T` with this method and transform them with `f`.
+  fn map<T, U, F>(self, f: F) -> Map<By<Self, T>, F> where F: Fn(T) -> U {
+    self.loading().map(f)
+  }
+
+  /// Load resources of type `T` with this method and transform them with `f`, which can fail.
+  fn and_then<T, U, E, F>(self, f: F) -> AndThen<By<Self, T>, F> where F: Fn(T) -> Result<U, E> {
+    self.loading().and_then(f)
+  }
+}
+
+impl Method for () {}
+
+impl Method for Registered {}
+
+impl<M> Method for Shared<M> where M: 'static {}
+
+/// Combinators on [`Loader`]s.
+pub trait Combine: Sized {
+  /// Transform the loaded resources with `f`.
+  ///
+  /// Reloading a transformed resource loads it anew: the reloading logic of the underlying loader
+  /// is not used, as it would require the resource before transformation.
+  fn map<F>(self, f: F) -> Map<Self, F> {
+    Map(self, f)
+  }
+
+  /// Transform the loaded resources with `f`, which can fail.
+  ///
+  /// Reloading a transformed resource loads it anew, as with [`Combine::map`].
+  fn and_then<F>(self, f: F) -> AndThen<Self, F> {
+    AndThen(self, f)
+  }
+
+  /// Declare the dependencies returned by `f`, given the key of the resource, along with the ones
+  /// declared by the underlying loader.
+  fn with_deps<K, F>(self, f: F) -> WithDeps<Self, F> where F: Fn(&K) -> Vec<K> {
+    WithDeps(self, f)
+  }
+}
+
+/// Loader of resources of type `T` with the `M` method.
+///
+/// See [`Method::loading`].
+pub struct By<M, T>(M, PhantomData<fn() -> T>);
+
+impl<M, T> fmt::Debug for By<M, T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "By<{}, {}>", std::any::type_name::<M>(), std::any::type_name::<T>())
+  }
+}
+
+impl<M, T> Combine for By<M, T> {}
+
+impl<C, K, M, T> Loader<C, K> for By<M, T>
+where T: Load<C, K, M>,
+      K: Key,
+      M: 'static {
+  type Res = T;
+
+  type Error = T::Error;
+
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self::Res, K>, Self::Error> {
+    <T as Load<C, K, M>>::load(key, storage, ctx)
+  }
+
+  fn reload(
+    &self,
+    res: &Self::Res,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self::Res, Self::Error> {
+    <T as Load<C, K, M>>::reload(res, key, storage, ctx)
+  }
+}
+
+/// Loader transforming the resources of another loader.
+///
+/// See [`Combine::map`].
+pub struct Map<L, F>(L, F);
+
+impl<L, F> Combine for Map<L, F> {}
+
+impl<C, K, L, F, U> Loader<C, K> for Map<L, F>
+where L: Loader<C, K>,
+      K: Key,
+      F: 'static + Fn(L::Res) -> U,
+      U: 'static {
+  type Res = U;
+
+  type Error = L::Error;
+
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self::Res, K>, Self::Error> {
+    let Loaded { res, deps } = self.0.load(key, storage, ctx)?;
+    Ok(Loaded::with_deps((self.1)(res), deps))
+  }
+}
+
+/// Loader transforming the resources of another loader with a function that can fail.
+///
+/// See [`Combine::and_then`].
+pub struct AndThen<L, F>(L, F);
+
+impl<L, F> Combine for AndThen<L, F> {}
+
+impl<C, K, L, F, U, E> Loader<C, K> for AndThen<L, F>
+where L: Loader<C, K>,
+      K: Key,
+      F: 'static + Fn(L::Res) -> Result<U, E>,
+      U: 'static,
+      E: Error + Send + Sync + 'static {
+  type Res = U;
+
+  type Error = AndThenError<L::Error, E>;
+
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self::Res, K>, Self::Error> {
+    let Loaded { res, deps } = self.0.load(key, storage, ctx).map_err(AndThenError::LoadError)?;
+    let res = (self.1)(res).map_err(AndThenError::ThenError)?;
+
+    Ok(Loaded::with_deps(res, deps))
+  }
+}
+
+/// Possible error that might occur while loading and reloading resources with
+/// [`Combine::and_then`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AndThenError<E, F> {
+  /// The underlying loader failed.
+  LoadError(E),
+  /// The transformation failed.
+  ThenError(F),
+}
+
+impl<E, F> fmt::Display for AndThenError<E, F> where E: fmt::Display, F: fmt::Display {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      AndThenError::LoadError(ref e) => write!(f, "load error: {}", e),
+      AndThenError::ThenError(ref e) => write!(f, "transformation error: {}", e),
+    }
+  }
+}
+
+impl<E, F> Error for AndThenError<E, F>
+where E: Error + 'static,
+      F: Error + 'static {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      AndThenError::LoadError(ref e) => Some(e),
+      AndThenError::ThenError(ref e) => Some(e),
+    }
+  }
+}
+
+/// Loader declaring additional dependencies.
+///
+/// See [`Combine::with_deps`].
+pub struct WithDeps<L, F>(L, F);
+
+impl<L, F> Combine for WithDeps<L, F> {}
+
+impl<C, K, L, F> Loader<C, K> for WithDeps<L, F>
+where L: Loader<C, K>,
+      K: Key,
+      F: 'static + Fn(&K) -> Vec<K> {
+  type Res = L::Res;
+
+  type Error = L::Error;
+
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self::Res, K>, Self::Error> {
+    let mut deps = (self.1)(&key);
+    let loaded = self.0.load(key, storage, ctx)?;
+    deps.extend(loaded.deps);
+
+    Ok(Loaded::with_deps(loaded.res, deps))
+  }
+
+  fn reload(
+    &self,
+    res: &Self::Res,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self::Res, Self::Error> {
+    self.0.reload(res, key, storage, ctx)
+  }
+}
+
+/// Loading method of the resources loaded with the `L` loader, given to [`Storage::get_with`].
+///
+/// This method cannot be used with [`Storage::get_by`]: the store needs the loader itself.
+///
+/// [`Storage::get_with`]: crate::load::Storage::get_with
+/// [`Storage::get_by`]: crate::load::Storage::get_by
+pub struct Combined<L>(PhantomData<L>);
+
+impl<C, K, L, U> Load<C, K, Combined<L>> for U
+where L: Loader<C, K, Res = U>,
+      K: Key,
+      U: 'static {
+  type Error = L::Error;
+
+  fn load(key: K, storage: &mut Storage<C, K>, ctx: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let loader = storage.combined_loader::<L>(&key);
+    Loader::load(&*loader, key, storage, ctx)
+  }
+
+  fn reload(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error> {
+    let loader = storage.combined_loader::<L>(&key);
+    Loader::reload(&*loader, self, key, storage, ctx)
+  }
+}
@@ -1,5 +1,8 @@
 //! Context related functions and types.
 
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
 /// The inspect context trait.
 ///
 /// This trait is very similar to the [`BorrowMut`] trait but is specific to the need of this crate
@@ -37,7 +40,8 @@
 ///     mutable reference to something.
 ///   - The inspector type – which doesn’t exist in the `BorrowMut` trait.
 ///   - The method type – i.e. doesn’t exist in `BorrowMut` and only serves to have different kind
-///     of inspection regarding the method you use.
+///     of inspection regarding the method you use. It should be the same as the method of the
+///     [`Load`] implementation inspecting the context.
 ///
 /// The inspector type gives the missing semantics to the borrow to *decide* how the data should be
 /// inspected. The example above can then be rewritten correctly with the following:
@@ -82,6 +86,18 @@
 ///
 /// Those two might be useful in end libraries or binaries.
 ///
+/// An [`AnyContext`] can also be inspected as any of the services it contains.
+///
+/// Finally, tuple contexts – up to six elements – can be split into a tuple of references to each
+/// of their elements, immutably or mutably. A context of type `(A, B)` can then be inspected as
+/// `(&'a mut A, &'a mut B)`, which allows composing contexts from library-provided pieces without
+/// writing glue implementations.
+///
+/// > Note: inspecting a `(A, B)` context directly as `&'a mut A` or `&'a mut B` is not possible
+/// > with universal implementors, because both implementations would overlap whenever `A` and `B`
+/// > are the same type. You have to write such implementations yourself for your concrete context
+/// > types.
+///
 /// # A note on the lifetime
 ///
 /// Because of being generic over the borrow lifetime, you can return any kind of borrow (not only
@@ -91,28 +107,134 @@
 /// kind of data (for instance, you might want to copy / clone something and not use any reference).
 ///
 /// [`BorrowMut`]: std::borrow::BorrowMut
-pub trait Inspect<'a, Ctx, Inspected, Method = ()> {
+/// [`Load`]: crate::load::Load
+pub trait Inspect<'a, Ctx, Inspected, Method = ()> where Method: ?Sized {
   /// Inspect the context.
   fn inspect(ctx: &'a mut Ctx) -> Inspected;
 }
 
 /// No-context universal implementor.
-impl<'a, T, C, M> Inspect<'a, C, (), M> for T {
+#[allow(clippy::unused_unit)]
+impl<'a, T, C, M> Inspect<'a, C, (), M> for T where M: ?Sized {
   fn inspect(_: &'a mut C) -> () {
     ()
   }
 }
 
 /// Immutable full-context universal implementator.
-impl<'a, T, C, M> Inspect<'a, C, &'a C, M> for T {
+impl<'a, T, C, M> Inspect<'a, C, &'a C, M> for T where M: ?Sized {
   fn inspect(ctx: &'a mut C) -> &'a C {
     ctx
   }
 }
 
 /// Mutable full-context universal implementator.
-impl<'a, T, C, M> Inspect<'a, C, &'a mut C, M> for T {
+impl<'a, T, C, M> Inspect<'a, C, &'a mut C, M> for T where M: ?Sized {
   fn inspect(ctx: &'a mut C) -> &'a mut C {
     ctx
   }
 }
+
+macro_rules! impl_tuple_inspect {
+  ($($t:ident: $i:tt),+) => {
+    /// Immutable tuple-splitting universal implementor.
+    impl<'a, T, $($t,)+ M> Inspect<'a, ($($t,)+), ($(&'a $t,)+), M> for T where M: ?Sized {
+      fn inspect(ctx: &'a mut ($($t,)+)) -> ($(&'a $t,)+) {
+        ($(&ctx.$i,)+)
+      }
+    }
+
+    /// Mutable tuple-splitting universal implementor.
+    impl<'a, T, $($t,)+ M> Inspect<'a, ($($t,)+), ($(&'a mut $t,)+), M> for T
+    where M: ?Sized {
+      fn inspect(ctx: &'a mut ($($t,)+)) -> ($(&'a mut $t,)+) {
+        ($(&mut ctx.$i,)+)
+      }
+    }
+  };
+}
+
+impl_tuple_inspect!(A: 0, B: 1);
+impl_tuple_inspect!(A: 0, B: 1, C: 2);
+impl_tuple_inspect!(A: 0, B: 1, C: 2, D: 3);
+impl_tuple_inspect!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_tuple_inspect!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+
+/// A dynamic context, acting as a registry of services indexed by their types.
+///
+/// Instead of threading a big concrete context type through every [`Load`] bound, applications can
+/// drop arbitrary services – a renderer handle, an audio device, etc. – in an [`AnyContext`] and
+/// resources inspect the services they need:
+///
+/// ```
+/// use warmy::{AnyContext, Inspect};
+///
+/// struct Renderer;
+/// struct Texture;
+///
+/// let mut ctx = AnyContext::new();
+/// ctx.insert(Renderer);
+///
+/// let renderer: Option<&mut Renderer> = <Texture as Inspect<_, _>>::inspect(&mut ctx);
+/// assert!(renderer.is_some());
+/// ```
+///
+/// A service is inspected as `Option<&'a S>` or `Option<&'a mut S>`, as it might be missing.
+///
+/// [`Load`]: crate::load::Load
+#[derive(Debug, Default)]
+pub struct AnyContext {
+  services: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl AnyContext {
+  /// Create an empty context.
+  pub fn new() -> Self {
+    AnyContext::default()
+  }
+
+  /// Insert a service, returning the previous service of the same type, if any.
+  pub fn insert<S>(&mut self, service: S) -> Option<S> where S: Any {
+    let previous = self.services.insert(TypeId::of::<S>(), Box::new(service));
+    previous.and_then(|service| service.downcast().ok()).map(|service| *service)
+  }
+
+  /// Remove a service, returning it if it was present.
+  pub fn remove<S>(&mut self) -> Option<S> where S: Any {
+    let service = self.services.remove(&TypeId::of::<S>());
+    service.and_then(|service| service.downcast().ok()).map(|service| *service)
+  }
+
+  /// Check whether a service is present.
+  pub fn contains<S>(&self) -> bool where S: Any {
+    self.services.contains_key(&TypeId::of::<S>())
+  }
+
+  /// Get a service, if present.
+  pub fn get<S>(&self) -> Option<&S> where S: Any {
+    let service = self.services.get(&TypeId::of::<S>());
+    service.and_then(|service| service.downcast_ref())
+  }
+
+  /// Get a service mutably, if present.
+  pub fn get_mut<S>(&mut self) -> Option<&mut S> where S: Any {
+    let service = self.services.get_mut(&TypeId::of::<S>());
+    service.and_then(|service| service.downcast_mut())
+  }
+}
+
+/// Immutable service universal implementor.
+impl<'a, T, S, M> Inspect<'a, AnyContext, Option<&'a S>, M> for T where S: Any, M: ?Sized {
+  fn inspect(ctx: &'a mut AnyContext) -> Option<&'a S> {
+    ctx.get()
+  }
+}
+
+/// Mutable service universal implementor.
+impl<'a, T, S, M> Inspect<'a, AnyContext, Option<&'a mut S>, M> for T
+where S: Any,
+      M: ?Sized {
+  fn inspect(ctx: &'a mut AnyContext) -> Option<&'a mut S> {
+    ctx.get_mut()
+  }
+}
@@ -0,0 +1,276 @@
+//! Tiny HTTP server exposing the state of a store for remote debugging.
+//!
+//! The server runs in its own thread and never touches the store directly: the store publishes a
+//! summary of its resources after each synchronization and drains the reload requests received by
+//! the server before each synchronization. See [`Store::serve_debug`] for the list of endpoints.
+//!
+//! [`Store::serve_debug`]: crate::load::Store::serve_debug
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::key::{Described, Key};
+use crate::load::StoreSnapshot;
+
+// time a client is given to send its request and to read the response; the server answers
+// connections one at a time, so a silent client must not hold it forever
+const IO_TIMEOUT: Duration = Duration::from_secs(1);
+
+// maximum length of a request line, so that a client cannot make the server buffer without bound
+const MAX_REQUEST_LINE: u64 = 8192;
+
+/// Summary of a resource, as published to the server.
+pub(crate) struct Entry {
+  key: String,
+  ty: &'static str,
+  reloads: u32,
+  dirty: bool,
+  proxy: bool,
+  last_error: Option<String>,
+}
+
+/// Handle to a running debug server, owned by the store.
+///
+/// The server thread stops when the handle is dropped.
+pub(crate) struct DebugServer {
+  addr: SocketAddr,
+  // resources published by the store
+  entries: Arc<Mutex<Vec<Entry>>>,
+  // keys – as described by `Key::describe` – the server was asked to reload
+  reloads: Receiver<String>,
+  // flag telling the server thread to stop
+  stop: Arc<AtomicBool>,
+}
+
+impl DebugServer {
+  /// Bind the server and spawn its thread.
+  pub(crate) fn spawn<A>(addr: A) -> io::Result<Self> where A: ToSocketAddrs {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+    listener.set_nonblocking(true)?;
+
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let (reload_sx, reloads) = channel();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let entries_ = entries.clone();
+    let stop_ = stop.clone();
+    thread::spawn(move || serve(listener, entries_, reload_sx, stop_));
+
+    Ok(DebugServer {
+      addr,
+      entries,
+      reloads,
+      stop,
+    })
+  }
+
+  /// Address the server is listening on.
+  pub(crate) fn addr(&self) -> SocketAddr {
+    self.addr
+  }
+
+  /// Publish the state of the store.
+  pub(crate) fn publish<K>(&self, snapshot: StoreSnapshot<K>) where K: Key {
+    let mut entries: Vec<_> = snapshot
+      .resources
+      .into_iter()
+      .map(|info| Entry {
+        key: format!("{:?}", Described(&info.key)),
+        ty: info.type_name,
+        reloads: info.reloads,
+        dirty: info.dirty,
+        proxy: info.proxy,
+        last_error: info.last_error.map(|error| error.error().to_string()),
+      })
+      .collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+
+    if let Ok(mut published) = self.entries.lock() {
+      *published = entries;
+    }
+  }
+
+  /// Keys – as described by `Key::describe` – the server was asked to reload since the last call.
+  pub(crate) fn requested_reloads(&self) -> Vec<String> {
+    self.reloads.try_iter().collect()
+  }
+}
+
+impl Drop for DebugServer {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::Relaxed);
+  }
+}
+
+/// Accept connections until asked to stop.
+fn serve(
+  listener: TcpListener,
+  entries: Arc<Mutex<Vec<Entry>>>,
+  reloads: Sender<String>,
+  stop: Arc<AtomicBool>,
+) {
+  while !stop.load(Ordering::Relaxed) {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        // a misbehaving client must not take the server down
+        let _ = handle(stream, &entries, &reloads);
+      }
+
+      Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+        thread::sleep(Duration::from_millis(50));
+      }
+
+      Err(_) => break,
+    }
+  }
+}
+
+/// Answer a single request.
+fn handle(
+  mut stream: TcpStream,
+  entries: &Mutex<Vec<Entry>>,
+  reloads: &Sender<String>,
+) -> io::Result<()> {
+  stream.set_nonblocking(false)?;
+  stream.set_read_timeout(Some(IO_TIMEOUT))?;
+  stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+  let mut request_line = String::new();
+  BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+
+  let mut parts = request_line.split_whitespace();
+  let method = parts.next().unwrap_or("");
+  let target = parts.next().unwrap_or("");
+  let (path, query) = match target.find('?') {
+    Some(i) => (&target[..i], &target[i + 1..]),
+    None => (target, ""),
+  };
+
+  let (status, body) = match (method, path) {
+    ("GET", "/resources") => ("200 OK", render(entries, |_| true)),
+    ("GET", "/errors") => ("200 OK", render(entries, |entry| entry.last_error.is_some())),
+
+    ("POST", "/reload") => match query_param(query, "key") {
+      Some(key) => {
+        let body = format!("{{\"queued\":{}}}", json_string(&key));
+        let _ = reloads.send(key);
+        ("202 Accepted", body)
+      }
+
+      None => ("400 Bad Request", "{\"error\":\"missing key parameter\"}".to_owned()),
+    },
+
+    _ => ("404 Not Found", "{\"error\":\"not found\"}".to_owned()),
+  };
+
+  write!(
+    stream,
+    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    body.len(),
+    body
+  )
+}
+
+/// Render the published resources satisfying a predicate as a JSON array.
+fn render<F>(entries: &Mutex<Vec<Entry>>, predicate: F) -> String where F: Fn(&Entry) -> bool {
+  let entries = match entries.lock() {
+    Ok(entries) => entries,
+    Err(_) => return "[]".to_owned(),
+  };
+  let mut json = String::from("[");
+
+  for entry in entries.iter().filter(|entry| predicate(entry)) {
+    if json.len() > 1 {
+      json.push(',');
+    }
+
+    let last_error = entry.last_error.as_deref().map_or("null".to_owned(), json_string);
+    let _ = write!(
+      json,
+      "{{\"key\":{},\"type\":{},\"reloads\":{},\"dirty\":{},\"proxy\":{},\"last_error\":{}}}",
+      json_string(&entry.key),
+      json_string(entry.ty),
+      entry.reloads,
+      entry.dirty,
+      entry.proxy,
+      last_error
+    );
+  }
+
+  json.push(']');
+  json
+}
+
+/// Encode a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+  let mut json = String::with_capacity(s.len() + 2);
+  json.push('"');
+
+  for c in s.chars() {
+    match c {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      c if (c as u32) < 0x20 => {
+        let _ = write!(json, "\\u{:04x}", c as u32);
+      }
+      c => json.push(c),
+    }
+  }
+
+  json.push('"');
+  json
+}
+
+/// Get and percent-decode a parameter of a query string.
+fn query_param(query: &str, name: &str) -> Option<String> {
+  query.split('&').find_map(|pair| {
+    let mut pair = pair.splitn(2, '=');
+
+    if pair.next() == Some(name) {
+      pair.next().map(percent_decode)
+    } else {
+      None
+    }
+  })
+}
+
+/// Decode a percent-encoded string; `+` is decoded as a space.
+fn percent_decode(s: &str) -> String {
+  let bytes = s.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    match bytes[i] {
+      b'%' if i + 2 < bytes.len() => {
+        match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+          Some(byte) => {
+            decoded.push(byte);
+            i += 3;
+            continue;
+          }
+
+          None => decoded.push(b'%'),
+        }
+      }
+
+      b'+' => decoded.push(b' '),
+      byte => decoded.push(byte),
+    }
+
+    i += 1;
+  }
+
+  String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,172 @@
+//! Native dynamic libraries.
+//!
+//! This module provides you with [`DyLib`], a resource holding a dynamic library – `.so`, `.dylib`
+//! or `.dll` – loaded with [libloading]. Rebuilding the library reloads it, which is handy to hot
+//! reload code.
+//!
+//! Libraries are not loaded from their original files but from copies in the temporary directory.
+//! Some platforms lock loaded libraries or get confused when a loaded library is overwritten: with
+//! copies, the compiler can freely overwrite the original file. Copies are removed when the
+//! libraries are dropped.
+//!
+//! # Safety
+//!
+//! Loading a library runs its initialization routines, and nothing prevents it from doing anything
+//! a native program can do. Stores refuse to load libraries unless
+//! [`StoreOpt::set_trust_dylibs`] – which is `unsafe` – was enabled: only do so if you trust all
+//! the libraries in the root of your store.
+//!
+//! Symbols borrow the library they come from, but function pointers read out of them don’t: they
+//! dangle as soon as the library is reloaded. Look symbols up again after each reload instead of
+//! keeping such pointers around.
+//!
+//! [libloading]: https://crates.io/crates/libloading
+//! [`StoreOpt::set_trust_dylibs`]: crate::load::StoreOpt::set_trust_dylibs
+
+use libloading::{self, Library, Symbol};
+use std::error::Error;
+use std::fmt;
+use std::fs::{copy, remove_file};
+use std::io;
+use std::mem::ManuallyDrop;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+// number of copies made so far, used to give each copy a unique name
+static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+/// A native dynamic library.
+#[derive(Debug)]
+pub struct DyLib {
+  // loaded library, closed before its copy gets removed
+  library: ManuallyDrop<Library>,
+  // path of the original library file
+  path: PathBuf,
+  // path of the copy the library was loaded from
+  copy: PathBuf,
+}
+
+impl DyLib {
+  /// Path of the library file.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Path of the copy the library was loaded from.
+  pub fn copy_path(&self) -> &Path {
+    &self.copy
+  }
+
+  /// Get a symbol from the library.
+  ///
+  /// # Safety
+  ///
+  /// `T` must be the actual type of the symbol. See [`Library::get`] for further details.
+  pub unsafe fn get<T>(&self, symbol: &[u8]) -> Result<Symbol<'_, T>, libloading::Error> {
+    self.library.get(symbol)
+  }
+}
+
+impl Drop for DyLib {
+  fn drop(&mut self) {
+    // the library must be closed before removing its copy, as some platforms lock loaded libraries
+    let library = unsafe { ManuallyDrop::take(&mut self.library) };
+    let _ = library.close();
+    let _ = remove_file(&self.copy);
+  }
+}
+
+/// Possible error that might occur while loading and reloading dynamic libraries.
+#[derive(Debug)]
+pub enum DyLibError {
+  /// An error in [libloading](https://crates.io/crates/libloading).
+  LibLoadingError(libloading::Error),
+  /// The file specified by the key failed to be copied to the given path.
+  CannotCopyFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+  /// The store doesn’t trust dynamic libraries – see
+  /// [`StoreOpt::set_trust_dylibs`](crate::load::StoreOpt::set_trust_dylibs).
+  Untrusted,
+}
+
+impl fmt::Display for DyLibError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      DyLibError::LibLoadingError(ref e) => write!(f, "dynamic library error: {}", e),
+
+      DyLibError::CannotCopyFile(ref path, ref e) => {
+        write!(f, "cannot copy file to {}: {}", path.display(), e)
+      }
+
+      DyLibError::NoKey => f.write_str("no path key available"),
+
+      DyLibError::Untrusted => f.write_str("dynamic libraries are not trusted by the store"),
+    }
+  }
+}
+
+impl Error for DyLibError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      DyLibError::LibLoadingError(ref e) => Some(e),
+      DyLibError::CannotCopyFile(_, ref e) => Some(e),
+      DyLibError::NoKey | DyLibError::Untrusted => None,
+    }
+  }
+}
+
+impl<C, K> Load<C, K> for DyLib where K: Key + Into<Option<PathBuf>> {
+  type Error = DyLibError;
+
+  fn load(
+    key: K,
+    storage: &mut Storage<C, K>,
+    _: &mut C,
+  ) -> Result<Loaded<Self, K>, Self::Error> {
+    if !storage.trusts_dylibs() {
+      return Err(DyLibError::Untrusted);
+    }
+
+    let path = key.into().ok_or(DyLibError::NoKey)?;
+    let copy_path = copy_path(&path);
+
+    copy(&path, &copy_path).map_err(|ioerr| DyLibError::CannotCopyFile(copy_path.clone(), ioerr))?;
+
+    // the store was told to trust the libraries it loads; see StoreOpt::set_trust_dylibs
+    match unsafe { Library::new(&copy_path) } {
+      Ok(library) => {
+        let dylib = DyLib {
+          library: ManuallyDrop::new(library),
+          path,
+          copy: copy_path,
+        };
+
+        Ok(dylib.into())
+      }
+
+      Err(e) => {
+        let _ = remove_file(&copy_path);
+        Err(DyLibError::LibLoadingError(e))
+      }
+    }
+  }
+}
+
+/// Unique path of a copy of a library in the temporary directory, keeping its extension.
+fn copy_path(path: &Path) -> PathBuf {
+  let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+  let n = COPIES.fetch_add(1, Ordering::Relaxed);
+  let mut name = format!("warmy-{}-{}-{}", stem, process::id(), n);
+
+  if let Some(ext) = path.extension() {
+    name.push('.');
+    name.push_str(&ext.to_string_lossy());
+  }
+
+  std::env::temp_dir().join(name)
+}
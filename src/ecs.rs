@@ -0,0 +1,134 @@
+//! ECS integration.
+//!
+//! ECS worlds – such as the ones of [specs] or [legion] – require their resources to be [`Send`]
+//! and [`Sync`], so that systems can run in parallel. A [`Store`] holds reload functions and
+//! observers that are neither, so it cannot live in a world itself. However, with the `"arc"`
+//! feature-gate, the [`Res`] handles it gives away can: [`Handles`] is a table of such handles you
+//! register as a world resource, while the store stays in your main loop and keeps synchronizing.
+//! Systems look handles up by key and see reloaded values as soon as the store reloads them.
+//!
+//! ```
+//! # use warmy::{Load, Loaded, Storage};
+//! use warmy::{Handles, Res, SimpleKey, Store, StoreOpt};
+//!
+//! struct Gravity(f32);
+//!
+//! impl Load<(), SimpleKey> for Gravity {
+//!   type Error = std::io::Error;
+//!
+//!   fn load(
+//!     _: SimpleKey,
+//!     _: &mut Storage<(), SimpleKey>,
+//!     _: &mut (),
+//!   ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+//!     Ok(Gravity(9.81).into())
+//!   }
+//! }
+//!
+//! let tmp_dir = tempfile::tempdir().unwrap();
+//! let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt).unwrap();
+//! let mut handles = Handles::new();
+//! let key = SimpleKey::from("gravity");
+//!
+//! handles.fetch::<Gravity, _>(&mut store, &key, &mut ()).unwrap();
+//!
+//! // handles is Send + Sync and can be inserted in a world; from within a system:
+//! let gravity: Res<Gravity> = handles.get(&key).unwrap();
+//! assert_eq!(gravity.borrow().0, 9.81);
+//! ```
+//!
+//! With the `"specs-impl"` feature-gate, [`ReadHandles`] is the system data to use in [specs]
+//! systems.
+//!
+//! [specs]: https://crates.io/crates/specs
+//! [legion]: https://crates.io/crates/legion
+//! [`Store`]: crate::load::Store
+
+use std::any::{Any, TypeId};
+
+use crate::hash::HashMap;
+use crate::key::Key;
+use crate::load::{Load, Storage, StoreErrorOr};
+use crate::res::Res;
+
+/// Table of resource handles, shareable across threads.
+///
+/// Handles are indexed by key and type, so that the same key can be registered with several types
+/// – as with [`Storage::get`].
+pub struct Handles<K> {
+  // handles, indexed by the type of their resources and then by key
+  handles: HashMap<TypeId, HashMap<K, Box<dyn Any + Send + Sync>>>,
+}
+
+impl<K> Handles<K> where K: Key {
+  /// Create an empty table.
+  pub fn new() -> Self {
+    Handles {
+      handles: HashMap::default(),
+    }
+  }
+
+  /// Register a handle, replacing the handle of the same type registered with the same key, if any.
+  pub fn insert<T>(&mut self, key: K, res: Res<T>) where T: 'static + Send {
+    self
+      .handles
+      .entry(TypeId::of::<T>())
+      .or_default()
+      .insert(key, Box::new(res));
+  }
+
+  /// Get a resource from a [`Storage`] and register its handle.
+  ///
+  /// The handle is registered with `key` as provided, which is the key to use with
+  /// [`Handles::get`].
+  pub fn fetch<T, C>(
+    &mut self,
+    storage: &mut Storage<C, K>,
+    key: &K,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K>>
+  where T: Load<C, K> + Send {
+    let res = storage.get::<T>(key, ctx)?;
+    self.insert(key.clone(), res.clone());
+    Ok(res)
+  }
+
+  /// Get the handle of a resource.
+  pub fn get<T>(&self, key: &K) -> Option<Res<T>> where T: 'static {
+    self
+      .handles
+      .get(&TypeId::of::<T>())?
+      .get(key)?
+      .downcast_ref::<Res<T>>()
+      .cloned()
+  }
+
+  /// Unregister the handle of a resource, returning it.
+  pub fn remove<T>(&mut self, key: &K) -> Option<Res<T>> where T: 'static {
+    let res = self.handles.get_mut(&TypeId::of::<T>())?.remove(key)?;
+    res.downcast::<Res<T>>().ok().map(|res| *res)
+  }
+
+  /// Number of registered handles.
+  pub fn len(&self) -> usize {
+    self.handles.values().map(HashMap::len).sum()
+  }
+
+  /// Check whether no handle is registered.
+  pub fn is_empty(&self) -> bool {
+    self.handles.values().all(HashMap::is_empty)
+  }
+}
+
+impl<K> Default for Handles<K> where K: Key {
+  fn default() -> Self {
+    Handles::new()
+  }
+}
+
+/// System data reading the [`Handles`] registered in a [specs] world.
+///
+/// [specs]: https://crates.io/crates/specs
+#[cfg(feature = "specs-impl")]
+pub type ReadHandles<'a, K> = specs::Read<'a, Handles<K>>;
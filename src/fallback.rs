@@ -0,0 +1,161 @@
+//! Fallback methods.
+//!
+//! This module provides you with [`FirstOf`], a method trying several methods in order and
+//! returning the first resource that loads. This is handy with mixed asset directories, in which
+//! resources of the same type come in several formats:
+//!
+//! ```
+//! # #[cfg(all(feature = "json", feature = "toml-impl"))] {
+//! use serde::Deserialize;
+//! use warmy::{Res, SimpleKey, Store, StoreOpt};
+//! use warmy::fallback::FirstOf;
+//! use warmy::json::Json;
+//! use warmy::toml::Toml;
+//!
+//! #[derive(Deserialize)]
+//! struct Dog {
+//!   name: String,
+//! }
+//!
+//! # let tmp_dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(tmp_dir.path().join("rex.toml"), "name = \"Rex\"").unwrap();
+//! # let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt).unwrap();
+//! let key = SimpleKey::from_path("/rex.toml");
+//! let dog: Res<Dog> = store.get_by(&key, &mut (), FirstOf::<(Json, Toml)>::new()).unwrap();
+//!
+//! assert_eq!(dog.borrow().name, "Rex");
+//! # }
+//! ```
+//!
+//! Reloading tries the methods in the same order, so that a resource can change format without
+//! being loaded again.
+
+use std::any;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::combinator::Method;
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+/// Method trying the methods of the `Methods` tuple in order, returning the first resource that
+/// loads.
+pub struct FirstOf<Methods>(PhantomData<Methods>);
+
+impl<M> FirstOf<M> {
+  /// Create the method.
+  pub fn new() -> Self {
+    FirstOf(PhantomData)
+  }
+}
+
+impl<M> Default for FirstOf<M> {
+  fn default() -> Self {
+    FirstOf::new()
+  }
+}
+
+impl<M> Clone for FirstOf<M> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<M> Copy for FirstOf<M> {}
+
+impl<M> fmt::Debug for FirstOf<M> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "FirstOf<{}>", any::type_name::<M>())
+  }
+}
+
+impl<M> Method for FirstOf<M> where M: 'static {}
+
+/// Error that occurs when no method of a [`FirstOf`] could load a resource.
+///
+/// It holds the error of each method, in order.
+#[derive(Debug)]
+pub struct FirstOfError {
+  // name of each method along with its error
+  errors: Vec<(&'static str, Box<dyn Error + Send + Sync>)>,
+}
+
+impl FirstOfError {
+  /// Errors of the methods, in order, along with the names of the methods.
+  ///
+  /// Errors can be downcast back to the error types of the methods.
+  pub fn errors(&self) -> &[(&'static str, Box<dyn Error + Send + Sync>)] {
+    &self.errors
+  }
+}
+
+impl fmt::Display for FirstOfError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str("no method could load the resource")?;
+
+    for (method, e) in &self.errors {
+      write!(f, "; {}: {}", method, e)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Error for FirstOfError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    self.errors.first().map(|(_, e)| &**e as &(dyn Error + 'static))
+  }
+}
+
+macro_rules! impl_tuple_first_of {
+  ($($m:ident),+) => {
+    impl<C, K, T, $($m,)+> Load<C, K, FirstOf<($($m,)+)>> for T
+    where K: Key,
+          $(T: Load<C, K, $m>,)+ {
+      type Error = FirstOfError;
+
+      fn load(
+        key: K,
+        storage: &mut Storage<C, K>,
+        ctx: &mut C,
+      ) -> Result<Loaded<Self, K>, Self::Error> {
+        let mut errors: Vec<(&'static str, Box<dyn Error + Send + Sync>)> = Vec::new();
+
+        $(
+          match <T as Load<C, K, $m>>::load(key.clone(), storage, ctx) {
+            Ok(loaded) => return Ok(loaded),
+            Err(e) => errors.push((any::type_name::<$m>(), Box::new(e))),
+          }
+        )+
+
+        Err(FirstOfError { errors })
+      }
+
+      fn reload(
+        &self,
+        key: K,
+        storage: &mut Storage<C, K>,
+        ctx: &mut C,
+      ) -> Result<Self, Self::Error> {
+        let mut errors: Vec<(&'static str, Box<dyn Error + Send + Sync>)> = Vec::new();
+
+        $(
+          match <T as Load<C, K, $m>>::reload(self, key.clone(), storage, ctx) {
+            Ok(res) => return Ok(res),
+            Err(e) => errors.push((any::type_name::<$m>(), Box::new(e))),
+          }
+        )+
+
+        Err(FirstOfError { errors })
+      }
+    }
+  };
+}
+
+impl_tuple_first_of!(M0, M1);
+impl_tuple_first_of!(M0, M1, M2);
+impl_tuple_first_of!(M0, M1, M2, M3);
+impl_tuple_first_of!(M0, M1, M2, M3, M4);
+impl_tuple_first_of!(M0, M1, M2, M3, M4, M5);
@@ -0,0 +1,263 @@
+//! Game-loop integration.
+//!
+//! Game frameworks such as [ggez] or [winit] call an `update` function once per frame. Calling
+//! [`Store::sync`] from there works, but synchronizing every frame wastes time and a
+//! synchronization reloading a lot of resources makes the frame stutter. [`FrameSync`] wraps
+//! [`Store::sync`] so that it only runs every few frames and within a time budget:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use warmy::{FrameSync, SimpleKey, Store, StoreOpt};
+//!
+//! struct Game {
+//!   store: Store<(), SimpleKey>,
+//!   frame_sync: FrameSync,
+//! }
+//!
+//! impl Game {
+//!   fn update(&mut self) {
+//!     if let Some(report) = self.frame_sync.update(&mut self.store, &mut ()) {
+//!       for error in report.failed() {
+//!         eprintln!("{}", error);
+//!       }
+//!     }
+//!   }
+//! }
+//!
+//! let store = Store::new(StoreOpt::default()).unwrap();
+//! let frame_sync = FrameSync::new()
+//!   .set_interval(10)
+//!   .set_budget(Duration::from_millis(2));
+//! let mut game = Game { store, frame_sync };
+//!
+//! game.update();
+//! ```
+//!
+//! When the store is passed around a lot, it’s easy to synchronize it several times per frame or to
+//! forget to synchronize it. [`Store::sync_scope`] returns a [`SyncScope`] guard, giving access to
+//! the store and synchronizing it once when dropped – typically at the end of the frame.
+//!
+//! [ggez]: https://crates.io/crates/ggez
+//! [winit]: https://crates.io/crates/winit
+//! [`Store::sync`]: crate::load::Store::sync
+//! [`Store::sync_scope`]: crate::load::Store::sync_scope
+
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::key::Key;
+use crate::load::{Store, StoreError, SyncReport};
+
+/// Per-frame synchronization of a [`Store`].
+///
+/// Call [`FrameSync::update`] once per frame: the store is synchronized every
+/// [`FrameSync::interval`] frames, and each synchronization stops reloading resources once it has
+/// spent [`FrameSync::budget`]. Resources that remain to be reloaded are reloaded on the next
+/// synchronizations, so that reloading a lot of resources is spread over several frames.
+///
+/// The budget of the frame synchronization overrides the one of the store – see
+/// [`StoreOpt::set_sync_budget`] – if any.
+///
+/// [`StoreOpt::set_sync_budget`]: crate::load::StoreOpt::set_sync_budget
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrameSync {
+  // number of frames between two synchronizations
+  interval: u32,
+  // time budget of a synchronization, if any
+  budget: Option<Duration>,
+  // number of frames updated so far
+  frames: u64,
+}
+
+impl FrameSync {
+  /// Create a frame synchronization synchronizing every frame, without time budget.
+  pub fn new() -> Self {
+    FrameSync {
+      interval: 1,
+      budget: None,
+      frames: 0,
+    }
+  }
+
+  /// Change the number of frames between two synchronizations.
+  ///
+  /// An interval of `0` is treated as `1`.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `1`: the store is synchronized every frame.
+  #[inline]
+  pub fn set_interval(self, interval: u32) -> Self {
+    FrameSync {
+      interval: interval.max(1),
+      ..self
+    }
+  }
+
+  /// Get the number of frames between two synchronizations.
+  #[inline]
+  pub fn interval(&self) -> u32 {
+    self.interval
+  }
+
+  /// Change the time budget of a synchronization.
+  ///
+  /// # Default
+  ///
+  /// Synchronizations are not time-bounded by default, unless the store is.
+  #[inline]
+  pub fn set_budget(self, budget: Duration) -> Self {
+    FrameSync {
+      budget: Some(budget),
+      ..self
+    }
+  }
+
+  /// Get the time budget of a synchronization, if any.
+  #[inline]
+  pub fn budget(&self) -> Option<Duration> {
+    self.budget
+  }
+
+  /// Number of frames updated so far.
+  #[inline]
+  pub fn frames(&self) -> u64 {
+    self.frames
+  }
+
+  /// Update a frame, synchronizing the store with a provided context if it’s time to.
+  ///
+  /// Return the report of the synchronization, if any happened.
+  pub fn update<C, K>(&mut self, store: &mut Store<C, K>, ctx: &mut C) -> Option<SyncReport<K>>
+  where K: Key + for<'a> From<&'a Path> {
+    if !self.tick() {
+      return None;
+    }
+
+    Some(self.sync(store, |store| store.sync(ctx)))
+  }
+
+  /// Update a frame, synchronizing the store with its owned context if it’s time to.
+  ///
+  /// Return the report of the synchronization, if any happened.
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`StoreError::NoOwnedContext`] if it’s time to synchronize the store
+  /// but it was not created with [`Store::with_context`].
+  pub fn update_owned<C, K>(
+    &mut self,
+    store: &mut Store<C, K>,
+  ) -> Result<Option<SyncReport<K>>, StoreError<K>>
+  where K: Key + for<'a> From<&'a Path> {
+    if !self.tick() {
+      return Ok(None);
+    }
+
+    self.sync(store, Store::sync_owned).map(Some)
+  }
+
+  /// Count a frame and check whether the store must be synchronized.
+  // u64::is_multiple_of is too recent for the toolchains the crate supports
+  #[allow(clippy::manual_is_multiple_of)]
+  fn tick(&mut self) -> bool {
+    let due = self.frames % u64::from(self.interval) == 0;
+    self.frames += 1;
+    due
+  }
+
+  /// Synchronize the store, applying the time budget if any.
+  fn sync<C, K, F, R>(&self, store: &mut Store<C, K>, f: F) -> R
+  where K: Key, F: FnOnce(&mut Store<C, K>) -> R {
+    match self.budget {
+      Some(budget) => store.with_sync_budget(Some(budget), f),
+      None => f(store),
+    }
+  }
+}
+
+impl Default for FrameSync {
+  fn default() -> Self {
+    FrameSync::new()
+  }
+}
+
+/// Guard synchronizing a [`Store`] when dropped.
+///
+/// Created with [`Store::sync_scope`], it dereferences to the store and synchronizes it with the
+/// context it was created with once it goes out of scope. Use [`SyncScope::split`] to access both
+/// the store and the context while the guard is alive – to get resources, for instance – and
+/// [`SyncScope::finish`] to synchronize early and get the report of the synchronization, which is
+/// otherwise dropped.
+///
+/// ```
+/// use warmy::{SimpleKey, Store, StoreOpt};
+///
+/// fn frame(store: &mut Store<(), SimpleKey>, ctx: &mut ()) {
+///   let mut scope = store.sync_scope(ctx);
+///   let (store, ctx) = scope.split();
+///
+///   // get resources, pass the store around…
+///   let _ = (store, ctx);
+///
+///   // … the store is synchronized here
+/// }
+///
+/// let mut store = Store::new(StoreOpt::default()).unwrap();
+/// frame(&mut store, &mut ());
+/// ```
+pub struct SyncScope<'a, C, K> where K: Key + for<'b> From<&'b Path> {
+  // synchronized store
+  store: &'a mut Store<C, K>,
+  // context used to synchronize the store
+  ctx: &'a mut C,
+  // whether the store was already synchronized by SyncScope::finish
+  synced: bool,
+}
+
+impl<'a, C, K> SyncScope<'a, C, K> where K: Key + for<'b> From<&'b Path> {
+  pub(crate) fn new(store: &'a mut Store<C, K>, ctx: &'a mut C) -> Self {
+    SyncScope {
+      store,
+      ctx,
+      synced: false,
+    }
+  }
+
+  /// Access both the store and the context.
+  #[inline]
+  pub fn split(&mut self) -> (&mut Store<C, K>, &mut C) {
+    (&mut *self.store, &mut *self.ctx)
+  }
+
+  /// Synchronize the store now instead of when the guard is dropped.
+  ///
+  /// Return the report of the synchronization.
+  pub fn finish(mut self) -> SyncReport<K> {
+    self.synced = true;
+    self.store.sync(self.ctx)
+  }
+}
+
+impl<'a, C, K> Deref for SyncScope<'a, C, K> where K: Key + for<'b> From<&'b Path> {
+  type Target = Store<C, K>;
+
+  fn deref(&self) -> &Self::Target {
+    self.store
+  }
+}
+
+impl<'a, C, K> DerefMut for SyncScope<'a, C, K> where K: Key + for<'b> From<&'b Path> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    self.store
+  }
+}
+
+impl<'a, C, K> Drop for SyncScope<'a, C, K> where K: Key + for<'b> From<&'b Path> {
+  fn drop(&mut self) {
+    if !self.synced {
+      self.store.sync(self.ctx);
+    }
+  }
+}
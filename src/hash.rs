@@ -0,0 +1,59 @@
+//! Hash maps and sets used internally.
+//!
+//! They use the default, randomly seeded SipHash hasher unless the `"fast-hash"` feature-gate is
+//! enabled, in which case they use the much faster – but not DoS-resistant – FxHash hasher.
+
+#[cfg(feature = "fast-hash")]
+pub(crate) type BuildHasher = rustc_hash::FxBuildHasher;
+#[cfg(not(feature = "fast-hash"))]
+pub(crate) type BuildHasher = std::collections::hash_map::RandomState;
+
+pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasher>;
+pub(crate) type HashSet<T> = std::collections::HashSet<T, BuildHasher>;
+
+/// Map which keys are hashes already, so that they’re not hashed again.
+pub(crate) type HashedMap<V> =
+  std::collections::HashMap<u64, V, std::hash::BuildHasherDefault<PassThrough>>;
+
+/// Hasher passing through the hashes it’s fed with.
+#[derive(Default)]
+pub(crate) struct PassThrough(u64);
+
+impl std::hash::Hasher for PassThrough {
+  fn finish(&self) -> u64 {
+    self.0
+  }
+
+  fn write(&mut self, bytes: &[u8]) {
+    // only hashes are expected, but stay correct with anything else
+    for &byte in bytes {
+      self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+    }
+  }
+
+  fn write_u64(&mut self, hash: u64) {
+    self.0 = hash;
+  }
+}
+
+/// Hasher giving the same hashes across runs – unlike the hashers of hash maps – used to name files
+/// that outlive the process. This is 64-bit FNV-1a.
+pub(crate) struct StableHasher(u64);
+
+impl Default for StableHasher {
+  fn default() -> Self {
+    StableHasher(0xcbf2_9ce4_8422_2325)
+  }
+}
+
+impl std::hash::Hasher for StableHasher {
+  fn finish(&self) -> u64 {
+    self.0
+  }
+
+  fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+  }
+}
@@ -5,26 +5,32 @@
 //! [`serde::Deserialize`]: https://docs.rs/serde/1.0.85/serde/trait.Deserialize.html
 
 use serde::Deserialize;
-use serde_json::{self, from_reader};
+use serde_json::{self, from_slice};
+use std::error::Error;
 use std::io;
 use std::fmt;
-use std::fs::File;
-use std::path::PathBuf;
+#[cfg(feature = "rayon")] use std::fs::read;
+use std::path::{Path, PathBuf};
 
+use crate::combinator::Method;
 use crate::key::Key;
-use crate::load::{Load, Loaded, Storage};
+#[cfg(feature = "rayon")] use crate::load::LoadPar;
+use crate::load::{CodedError, ErrorCode, Load, Loaded, Storage};
 
 /// The JSON universal method. Use this with [`Storage::get_by`] or [`Storage::get_proxied_by`] to
 /// benefit from the automatic implementors.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Json;
 
+impl Method for Json {}
+
 /// Possible error that might occur while loading and reloading JSON formatted scarce resources.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum JsonError {
   /// An error in [serde_json](https://crates.io/crates/serde-json).
   JsonError(serde_json::Error),
-  /// The file specified by the key failed to open.
+  /// The file specified by the key failed to open or to be read.
   CannotOpenFile(PathBuf, io::Error),
   /// The input key doesn’t provide enough information to open a file.
   NoKey
@@ -44,6 +50,26 @@ impl fmt::Display for JsonError {
   }
 }
 
+impl CodedError for JsonError {
+  fn code(&self) -> ErrorCode {
+    match *self {
+      JsonError::JsonError(_) => ErrorCode::ParseError,
+      JsonError::CannotOpenFile(_, ref e) => ErrorCode::from_io(e),
+      JsonError::NoKey => ErrorCode::NoKey
+    }
+  }
+}
+
+impl Error for JsonError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      JsonError::JsonError(ref e) => Some(e),
+      JsonError::CannotOpenFile(_, ref e) => Some(e),
+      JsonError::NoKey => None
+    }
+  }
+}
+
 impl<C, K, T> Load<C, K, Json> for T
 where K: Key + Into<Option<PathBuf>>,
       T: 'static + for<'de> Deserialize<'de> {
@@ -51,18 +77,35 @@ where K: Key + Into<Option<PathBuf>>,
 
   fn load(
     key: K,
-    _: &mut Storage<C, K>,
+    storage: &mut Storage<C, K>,
     _: &mut C
   ) -> Result<Loaded<Self, K>, Self::Error> {
-    if let Some(path) = key.into() {
-      let file = File::open(&path)
-          .map_err(|ioerr| JsonError::CannotOpenFile(path, ioerr))?;
+    load_json(key, |path| storage.read_file(path))
+  }
+}
 
-      from_reader(file)
-        .map(Loaded::without_dep)
-        .map_err(JsonError::JsonError)
-    } else {
-      Err(JsonError::NoKey)
-    }
+#[cfg(feature = "rayon")]
+impl<C, K, T> LoadPar<C, K, Json> for T
+where K: Key + Into<Option<PathBuf>>,
+      T: 'static + Send + for<'de> Deserialize<'de> {
+  fn load_par(key: K, _: &C) -> Result<Loaded<Self, K>, Self::Error> {
+    load_json(key, |path| read(path))
+  }
+}
+
+/// Load a JSON file specified by a key, reading it with `read`.
+fn load_json<K, T, R>(key: K, read: R) -> Result<Loaded<T, K>, JsonError>
+where K: Key + Into<Option<PathBuf>>,
+      T: for<'de> Deserialize<'de>,
+      R: FnOnce(&Path) -> io::Result<Vec<u8>> {
+  if let Some(path) = key.into() {
+    // reading the whole file at once is much faster than deserializing from an unbuffered reader
+    let file_content = read(&path).map_err(|ioerr| JsonError::CannotOpenFile(path, ioerr))?;
+
+    from_slice(&file_content)
+      .map(Loaded::without_dep)
+      .map_err(JsonError::JsonError)
+  } else {
+    Err(JsonError::NoKey)
   }
 }
@@ -1,12 +1,16 @@
 //! Module exporting all key types recognized by this crate.
 
-use any_cache::CacheKey;
-use std::hash::{Hash, Hasher};
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::hash::{BuildHasher as _, Hash};
 use std::fmt::{self, Display};
-use std::marker::PhantomData;
-use std::path::{Component, Path, PathBuf};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::str::FromStr;
+use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
 
-use crate::res::Res;
+use crate::hash::{BuildHasher, HashedMap};
 
 /// Class of recognized keys.
 pub trait Key: 'static + Clone + Eq + Hash {
@@ -18,23 +22,338 @@ pub trait Key: 'static + Clone + Eq + Hash {
   ///
   /// [`prepare_key`]: crate::key::Key::prepare_key
   fn prepare_key(self, root: &Path) -> Self;
+
+  /// Normalize a key.
+  ///
+  /// This is applied to prepared keys and to keys built from filesystem events, so that different
+  /// spellings of the same file end up as the same key. If your key is akin to a file system key,
+  /// you should use [`Normalization::normalize_path`] on its path.
+  ///
+  /// The default implementation leaves the key untouched.
+  fn normalize(self, _normalization: &Normalization) -> Self {
+    self
+  }
+
+  /// Get the file system path of a prepared key, if it’s akin to a file system key.
+  ///
+  /// The [`Storage`] uses it to refuse keys escaping its root – e.g. `/../../etc/passwd` – with
+  /// [`StoreError::KeyOutsideRoot`].
+  ///
+  /// The default implementation returns `None`, so that no check is performed.
+  ///
+  /// [`Storage`]: crate::load::Storage
+  /// [`StoreError::KeyOutsideRoot`]: crate::load::StoreError::KeyOutsideRoot
+  fn fs_path(&self) -> Option<&Path> {
+    None
+  }
+
+  /// Describe a key in diagnostics – logs, error messages, recorded events, debug dumps, etc.
+  ///
+  /// The default implementation doesn’t know anything about the key and writes `<key>`; forward
+  /// to the [`fmt::Debug`] or [`Display`] implementation of your key to get readable diagnostics.
+  fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("<key>")
+  }
+}
+
+/// Key formatted with [`Key::describe`], both as [`Display`] and [`fmt::Debug`].
+pub(crate) struct Described<'a, K>(pub(crate) &'a K);
+
+impl<K> Display for Described<'_, K> where K: Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.0.describe(f)
+  }
 }
 
-/// A key that can either be a path or a logical location.
+impl<K> fmt::Debug for Described<'_, K> where K: Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.0.describe(f)
+  }
+}
+
+/// Normalization applied to keys.
+///
+/// Some file systems – typically on Windows and macOS – are case-insensitive: `Textures/Hero.png`
+/// and `textures/hero.png` name the same file but would be different keys, leading to duplicated
+/// resources and missed reloads. Normalizing keys solves that.
+///
+/// Similarly, macOS reports decomposed (NFD) Unicode file names in filesystem events while keys
+/// written in code are usually composed (NFC): `é` can be spelled in two different ways and
+/// resources with accented names would never get reloaded. Normalizing keys to NFC solves that.
+///
+/// Logical keys – e.g. [`SimpleKey::Logical`] – are compared byte-for-byte by default:
+/// `"ui/button"`, `"ui//button"` and `"UI/Button"` would be different resources. They can be
+/// trimmed, have their separators collapsed and be case-folded as well.
+///
+/// The default normalization only applies NFC normalization on macOS.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Normalization {
+  // lowercase paths
+  case_insensitive: bool,
+  // apply NFC Unicode normalization to paths
+  unicode: bool,
+  // trim whitespace around logical keys
+  logical_trim: bool,
+  // collapse repeated slashes in logical keys and remove the leading and trailing ones
+  logical_collapse_separators: bool,
+  // lowercase logical keys
+  logical_case_insensitive: bool,
+}
+
+// not derivable on macOS
+#[allow(clippy::derivable_impls)]
+impl Default for Normalization {
+  fn default() -> Self {
+    Normalization {
+      case_insensitive: false,
+      unicode: cfg!(target_os = "macos"),
+      logical_trim: false,
+      logical_collapse_separators: false,
+      logical_case_insensitive: false,
+    }
+  }
+}
+
+impl Normalization {
+  /// Treat paths as case-insensitive by lowercasing them.
+  ///
+  /// Only enable this on case-insensitive file systems, as the normalized paths are used to open
+  /// files.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_case_insensitive(self, case_insensitive: bool) -> Self {
+    Normalization {
+      case_insensitive,
+      ..self
+    }
+  }
+
+  /// Check whether paths are treated as case-insensitive.
+  #[inline]
+  pub fn case_insensitive(&self) -> bool {
+    self.case_insensitive
+  }
+
+  /// Apply NFC Unicode normalization to paths.
+  ///
+  /// Only enable this on file systems that don’t distinguish between Unicode normalization forms,
+  /// as the normalized paths are used to open files.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `true` on macOS and `false` everywhere else.
+  #[inline]
+  pub fn set_unicode(self, unicode: bool) -> Self {
+    Normalization {
+      unicode,
+      ..self
+    }
+  }
+
+  /// Check whether NFC Unicode normalization is applied to paths.
+  #[inline]
+  pub fn unicode(&self) -> bool {
+    self.unicode
+  }
+
+  /// Trim the whitespace around logical keys – e.g. `" ui/button "` becomes `"ui/button"`.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_logical_trim(self, logical_trim: bool) -> Self {
+    Normalization {
+      logical_trim,
+      ..self
+    }
+  }
+
+  /// Check whether the whitespace around logical keys is trimmed.
+  #[inline]
+  pub fn logical_trim(&self) -> bool {
+    self.logical_trim
+  }
+
+  /// Collapse repeated slashes in logical keys and remove the leading and trailing ones – e.g.
+  /// `"/ui//button/"` becomes `"ui/button"`.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_logical_collapse_separators(self, logical_collapse_separators: bool) -> Self {
+    Normalization {
+      logical_collapse_separators,
+      ..self
+    }
+  }
+
+  /// Check whether repeated slashes in logical keys are collapsed.
+  #[inline]
+  pub fn logical_collapse_separators(&self) -> bool {
+    self.logical_collapse_separators
+  }
+
+  /// Treat logical keys as case-insensitive by lowercasing them.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_logical_case_insensitive(self, logical_case_insensitive: bool) -> Self {
+    Normalization {
+      logical_case_insensitive,
+      ..self
+    }
+  }
+
+  /// Check whether logical keys are treated as case-insensitive.
+  #[inline]
+  pub fn logical_case_insensitive(&self) -> bool {
+    self.logical_case_insensitive
+  }
+
+  /// Normalize a path.
+  ///
+  /// Paths that are not valid UTF-8 are left untouched.
+  pub fn normalize_path(&self, path: PathBuf) -> PathBuf {
+    if !self.case_insensitive && !self.unicode {
+      return path;
+    }
+
+    let s = match path.to_str() {
+      Some(s) => s,
+      None => return path,
+    };
+
+    let s: String = if self.unicode { s.nfc().collect() } else { s.to_owned() };
+
+    if self.case_insensitive {
+      s.to_lowercase().into()
+    } else {
+      s.into()
+    }
+  }
+
+  /// Normalize a logical key.
+  pub fn normalize_logical(&self, name: String) -> String {
+    let name = if self.logical_trim { name.trim().to_owned() } else { name };
+
+    let name = if self.logical_collapse_separators {
+      name.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>().join("/")
+    } else {
+      name
+    };
+
+    if self.logical_case_insensitive {
+      name.to_lowercase()
+    } else {
+      name
+    }
+  }
+
+  /// Check whether normalizing a path might change it.
+  fn normalizes_paths(&self) -> bool {
+    self.case_insensitive || self.unicode
+  }
+
+  /// Check whether normalizing a logical key might change it.
+  fn normalizes_logical(&self) -> bool {
+    self.logical_trim || self.logical_collapse_separators || self.logical_case_insensitive
+  }
+}
+
+/// A key that can either be a path, a logical location or raw bytes.
+///
+/// The content of a key is shared: cloning a key – which stores do a lot, as keys end up in several
+/// maps, events and reports – doesn’t allocate.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum SimpleKey {
   /// A key to a resource living on the filesystem.
-  Path(PathBuf),
+  Path(Arc<Path>),
   /// A key to a resource living in memory or computed on the fly.
-  Logical(String),
+  Logical(Arc<str>),
+  /// A key to a resource living in memory or computed on the fly, identified by bytes that are not
+  /// necessarily valid UTF-8 – e.g. a content hash or a packed identifier.
+  ///
+  /// Such keys are displayed in lowercase hexadecimal and are never normalized.
+  Bytes(Arc<[u8]>),
 }
 
 impl SimpleKey {
   pub fn from_path<P>(path: P) -> Self where P: AsRef<Path> {
-    SimpleKey::Path(path.as_ref().to_owned())
+    SimpleKey::Path(path.as_ref().into())
+  }
+
+  /// Create a bytes key.
+  pub fn from_bytes<B>(bytes: B) -> Self where B: AsRef<[u8]> {
+    SimpleKey::Bytes(bytes.as_ref().into())
+  }
+
+  /// Get the parent of a key.
+  ///
+  /// Logical keys are organized as namespaces separated by slashes – e.g. `"shaders/post/blur"` –
+  /// so that the parent of `"shaders/post/blur"` is `"shaders/post"`. A logical key without any
+  /// slash has no parent. Path keys have their parent directory as parent. Bytes keys are opaque
+  /// and have no parent.
+  pub fn parent(&self) -> Option<Self> {
+    match *self {
+      SimpleKey::Path(ref path) => path.parent().map(SimpleKey::from_path),
+      SimpleKey::Logical(ref name) => {
+        name.rfind('/').map(|i| SimpleKey::Logical(name[..i].into()))
+      }
+      SimpleKey::Bytes(_) => None,
+    }
+  }
+
+  /// Create a child key by appending a segment to this key.
+  ///
+  /// The segment is appended to the bytes of bytes keys.
+  pub fn join(&self, segment: &str) -> Self {
+    match *self {
+      SimpleKey::Path(ref path) => SimpleKey::Path(path.join(segment).into()),
+      SimpleKey::Logical(ref name) if name.is_empty() => SimpleKey::Logical(segment.into()),
+      SimpleKey::Logical(ref name) => SimpleKey::Logical(format!("{}/{}", name, segment).into()),
+      SimpleKey::Bytes(ref bytes) => {
+        SimpleKey::Bytes([&bytes[..], segment.as_bytes()].concat().into())
+      }
+    }
+  }
+
+  /// Check whether a key lives in the namespace of another – i.e. is either that key or one of its
+  /// descendants.
+  ///
+  /// Only whole segments are considered: `"shaders/post"` is in `"shaders"` but `"shaders2"` isn’t.
+  /// Path keys never live in logical namespaces and vice versa. Bytes keys live in the namespaces of
+  /// their prefixes.
+  pub fn starts_with(&self, namespace: &Self) -> bool {
+    match (self, namespace) {
+      (SimpleKey::Path(ref path), SimpleKey::Path(ref ns)) => path.starts_with(ns),
+
+      (SimpleKey::Logical(ref name), SimpleKey::Logical(ref ns)) => {
+        ns.is_empty()
+          || name
+            .strip_prefix(&**ns)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+      }
+
+      (SimpleKey::Bytes(ref bytes), SimpleKey::Bytes(ref ns)) => bytes.starts_with(ns),
+
+      _ => false,
+    }
+  }
+
+  /// Check whether a key is a direct child of another.
+  pub fn is_child_of(&self, parent: &Self) -> bool {
+    self.parent().as_ref() == Some(parent)
   }
 }
 
+#[allow(clippy::needless_lifetimes)]
 impl<'a>  From<&'a Path> for SimpleKey {
   fn from(path: &Path) -> Self {
     SimpleKey::from_path(path)
@@ -43,28 +362,42 @@ impl<'a>  From<&'a Path> for SimpleKey {
 
 impl From<PathBuf> for SimpleKey {
   fn from(path: PathBuf) -> Self {
-    SimpleKey::Path(path)
+    SimpleKey::Path(path.into())
   }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Option<PathBuf>> for SimpleKey {
   fn into(self) -> Option<PathBuf> {
     match self {
-      SimpleKey::Path(path) => Some(path),
+      SimpleKey::Path(path) => Some(path.to_path_buf()),
       _ => None
     }
   }
 }
 
+#[allow(clippy::needless_lifetimes)]
 impl<'a> From<&'a str> for SimpleKey {
   fn from(s: &str) -> Self {
-    SimpleKey::Logical(s.to_owned())
+    SimpleKey::Logical(s.into())
   }
 }
 
 impl From<String> for SimpleKey {
   fn from(s: String) -> Self {
-    SimpleKey::Logical(s)
+    SimpleKey::Logical(s.into())
+  }
+}
+
+impl From<&[u8]> for SimpleKey {
+  fn from(bytes: &[u8]) -> Self {
+    SimpleKey::from_bytes(bytes)
+  }
+}
+
+impl From<Vec<u8>> for SimpleKey {
+  fn from(bytes: Vec<u8>) -> Self {
+    SimpleKey::Bytes(bytes.into())
   }
 }
 
@@ -72,7 +405,8 @@ impl Display for SimpleKey {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
     match *self {
       SimpleKey::Path(ref path) => write!(f, "{}", path.display()),
-      SimpleKey::Logical(ref name) => write!(f, "{}", name)
+      SimpleKey::Logical(ref name) => write!(f, "{}", name),
+      SimpleKey::Bytes(ref bytes) => bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
     }
   }
 }
@@ -80,40 +414,365 @@ impl Display for SimpleKey {
 impl Key for SimpleKey {
   fn prepare_key(self, root: &Path) -> Self {
     match self {
-      SimpleKey::Path(path) => SimpleKey::Path(vfs_substitute_path(&path, root)),
-      SimpleKey::Logical(x) => SimpleKey::Logical(x),
+      SimpleKey::Path(path) => SimpleKey::Path(vfs_substitute_path(&path, root).into()),
+      key => key,
     }
   }
+
+  fn normalize(self, normalization: &Normalization) -> Self {
+    match self {
+      SimpleKey::Path(path) if normalization.normalizes_paths() => {
+        SimpleKey::Path(normalization.normalize_path(path.to_path_buf()).into())
+      }
+      SimpleKey::Logical(x) if normalization.normalizes_logical() => {
+        SimpleKey::Logical(normalization.normalize_logical(x.to_string()).into())
+      }
+      key => key,
+    }
+  }
+
+  fn fs_path(&self) -> Option<&Path> {
+    match *self {
+      SimpleKey::Path(ref path) => Some(path),
+      _ => None,
+    }
+  }
+
+  fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Debug::fmt(self, f)
+  }
 }
-/// Substitute a VFS path by a real one.
-fn vfs_substitute_path(path: &Path, root: &Path) -> PathBuf {
-  let mut components = path.components().peekable();
-  let root_components = root.components();
 
-  match components.peek() {
-    Some(&Component::RootDir) => {
-      // drop the root component
-      root_components.chain(components.skip(1)).collect()
+/// A key addressing a resource living in a given source, expressed as an URI.
+///
+/// Such a key is made of a *source* – the URI scheme, such as `file`, `mem`, `http` or `pak` – and a
+/// path within that source – e.g. `pak://textures/hero.png`. It allows a single key type to address
+/// resources coming from several backends in the same store: [`Load`] implementors dispatch on
+/// [`UriKey::source`] to know where to look for the data.
+///
+/// Only keys with the `file` source are akin to file system keys: they get their VFS path
+/// substituted with the root of the store when prepared, as with [`SimpleKey::Path`]. The path of
+/// other sources is left untouched.
+///
+/// [`Load`]: crate::load::Load
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct UriKey {
+  source: String,
+  path: PathBuf,
+}
+
+impl UriKey {
+  /// Source of file system keys.
+  pub const FILE: &'static str = "file";
+
+  /// Create a key from a source and a path.
+  ///
+  /// The source is lowercased, as URI schemes are case-insensitive.
+  pub fn new<S, P>(source: S, path: P) -> Self where S: AsRef<str>, P: AsRef<Path> {
+    UriKey {
+      source: source.as_ref().to_lowercase(),
+      path: path.as_ref().to_owned(),
+    }
+  }
+
+  /// Create a file system key.
+  pub fn from_path<P>(path: P) -> Self where P: AsRef<Path> {
+    UriKey::new(UriKey::FILE, path)
+  }
+
+  /// Parse a key from an URI such as `"mem://config/default"`.
+  ///
+  /// # Failures
+  ///
+  /// This function fails if the URI doesn’t have any `scheme://` prefix or if the scheme is not
+  /// valid.
+  pub fn parse(uri: &str) -> Result<Self, UriKeyError> {
+    let (scheme, path) = uri
+      .split_once("://")
+      .ok_or_else(|| UriKeyError::MissingScheme(uri.to_owned()))?;
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+      && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+
+    if valid_scheme {
+      Ok(UriKey::new(scheme, path))
+    } else {
+      Err(UriKeyError::InvalidScheme(scheme.to_owned()))
+    }
+  }
+
+  /// Source of the key.
+  pub fn source(&self) -> &str {
+    &self.source
+  }
+
+  /// Path of the key within its source.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Check whether the key addresses a resource on the file system.
+  pub fn is_file(&self) -> bool {
+    self.source == UriKey::FILE
+  }
+}
+
+#[allow(clippy::needless_lifetimes)]
+impl<'a> From<&'a Path> for UriKey {
+  fn from(path: &Path) -> Self {
+    UriKey::from_path(path)
+  }
+}
+
+impl From<PathBuf> for UriKey {
+  fn from(path: PathBuf) -> Self {
+    UriKey {
+      source: UriKey::FILE.to_owned(),
+      path,
+    }
+  }
+}
+
+impl FromStr for UriKey {
+  type Err = UriKeyError;
+
+  fn from_str(uri: &str) -> Result<Self, Self::Err> {
+    UriKey::parse(uri)
+  }
+}
+
+impl Display for UriKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}://{}", self.source, self.path.display())
+  }
+}
+
+impl Key for UriKey {
+  fn prepare_key(self, root: &Path) -> Self {
+    if self.is_file() {
+      UriKey {
+        path: vfs_substitute_path(&self.path, root),
+        ..self
+      }
+    } else {
+      self
+    }
+  }
+
+  fn normalize(self, normalization: &Normalization) -> Self {
+    if self.is_file() {
+      UriKey {
+        path: normalization.normalize_path(self.path),
+        ..self
+      }
+    } else {
+      self
+    }
+  }
+
+  fn fs_path(&self) -> Option<&Path> {
+    if self.is_file() {
+      Some(&self.path)
+    } else {
+      None
     }
+  }
 
-    _ => root_components.chain(components).collect(),
+  fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Debug::fmt(self, f)
   }
 }
 
-pub(crate) struct PrivateKey<K, T>(pub(crate) K, PhantomData<T>);
+/// Error that might occur while parsing an [`UriKey`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UriKeyError {
+  /// The URI doesn’t start with a `scheme://` prefix.
+  MissingScheme(String),
+  /// The scheme of the URI contains invalid characters.
+  InvalidScheme(String),
+}
 
-impl<K, T> PrivateKey<K, T> {
-  pub(crate) fn new(key: K) -> Self {
-    PrivateKey(key, PhantomData)
+impl Display for UriKeyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      UriKeyError::MissingScheme(ref uri) => write!(f, "missing scheme in URI {}", uri),
+      UriKeyError::InvalidScheme(ref scheme) => write!(f, "invalid URI scheme: {}", scheme),
+    }
   }
 }
 
-impl<K, T> Hash for PrivateKey<K, T> where K: Hash {
-  fn hash<H>(&self, state: &mut H) where H: Hasher {
-    self.0.hash(state)
+impl Error for UriKeyError {}
+
+/// Substitute a VFS path by a real one.
+///
+/// The VFS path is considered relative to `root`, even if it starts with a `/`. `.` and `..`
+/// components are resolved lexically, so that the resulting path might lie outside of `root` –
+/// e.g. `/../../etc/passwd`. This is the substitution applied to [`SimpleKey::Path`] when prepared,
+/// and you should use it to prepare your own file system keys.
+pub fn vfs_substitute_path(path: &Path, root: &Path) -> PathBuf {
+  let mut substituted = root.to_owned();
+
+  for component in path.components() {
+    match component {
+      // the VFS path is relative to the root, so drop its root and prefix components
+      Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+      Component::ParentDir => {
+        substituted.pop();
+      }
+      Component::Normal(name) => substituted.push(name),
+    }
   }
+
+  substituted
+}
+
+/// Strip the verbatim prefix of a Windows path: `\\?\C:\…` becomes `C:\…` and
+/// `\\?\UNC\server\share\…` becomes `\\server\share\…`.
+///
+/// Canonicalized paths are verbatim on Windows while the paths of filesystem events might not be,
+/// so the [`Store`] strips the prefix of both to be able to compare them. Long paths don’t need the
+/// prefix, as the standard library adds it back when accessing files. Other paths – including
+/// all the paths on other platforms – are left untouched.
+///
+/// [`Store`]: crate::load::Store
+pub fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+  let mut components = path.components();
+
+  let prefix = match components.next() {
+    Some(Component::Prefix(prefix)) => prefix,
+    _ => return path,
+  };
+
+  let mut stripped = match prefix.kind() {
+    Prefix::VerbatimDisk(disk) => OsString::from(format!("{}:", disk as char)),
+
+    Prefix::VerbatimUNC(server, share) => {
+      let mut unc = OsString::from(r"\\");
+      unc.push(server);
+      unc.push(r"\");
+      unc.push(share);
+      unc
+    }
+
+    _ => return path,
+  };
+
+  // canonicalized paths are absolute: the root directory follows the prefix
+  stripped.push(components.as_path().as_os_str());
+  PathBuf::from(stripped)
+}
+
+/// Identifier of an interned key.
+///
+/// Such identifiers are much cheaper to copy and hash than keys, so they’re used internally to
+/// index everything related to resources.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct KeyId(u32);
+
+/// Intern table of keys.
+///
+/// Keys are only stored once: the table is indexed by their hashes, which are checked against the
+/// stored keys to resolve collisions. Keys are never removed from the table, so that identifiers
+/// remain valid forever.
+///
+/// A key can also be an alias of another one – typically a key provided by the user and its
+/// prepared version – so that aliases are resolved with a single lookup. A key that is its own
+/// alias is not stored twice.
+pub(crate) struct Interner<K> {
+  // hashes of the keys, mapped to the identifiers of the first keys having them
+  ids: HashedMap<KeyId>,
+  // identifiers of the other keys, for hashes shared by several keys
+  collisions: HashedMap<Vec<KeyId>>,
+  // interned keys, indexed by their identifiers
+  keys: Vec<K>,
+  // identifiers of the keys aliases resolve to, indexed by the identifiers of the aliases
+  aliases: Vec<Option<KeyId>>,
+  // hasher used to hash keys
+  hasher: BuildHasher,
 }
 
-impl<K, T> CacheKey for PrivateKey<K, T> where T: 'static, K: Key {
-  type Target = Res<T>;
+impl<K> Interner<K> where K: Key {
+  pub(crate) fn new() -> Self {
+    Interner {
+      ids: HashedMap::default(),
+      collisions: HashedMap::default(),
+      keys: Vec::new(),
+      aliases: Vec::new(),
+      hasher: BuildHasher::default(),
+    }
+  }
+
+  /// Reserve capacity for at least `additional` more keys.
+  pub(crate) fn reserve(&mut self, additional: usize) {
+    self.ids.reserve(additional);
+    self.keys.reserve(additional);
+    self.aliases.reserve(additional);
+  }
+
+  /// Intern a key, returning its identifier.
+  pub(crate) fn intern(&mut self, key: K) -> KeyId {
+    let hash = self.hasher.hash_one(&key);
+
+    if let Some(id) = self.find(hash, &key) {
+      return id;
+    }
+
+    let id = KeyId(self.keys.len() as u32);
+    self.keys.push(key);
+
+    match self.ids.entry(hash) {
+      Entry::Vacant(entry) => {
+        entry.insert(id);
+      }
+
+      Entry::Occupied(_) => self.collisions.entry(hash).or_default().push(id),
+    }
+
+    id
+  }
+
+  /// Intern a borrowed key, cloning it only if it was not interned yet.
+  pub(crate) fn intern_ref(&mut self, key: &K) -> KeyId {
+    match self.id(key) {
+      Some(id) => id,
+      None => self.intern(key.clone()),
+    }
+  }
+
+  /// Get the identifier of a key, if it was interned.
+  pub(crate) fn id(&self, key: &K) -> Option<KeyId> {
+    self.find(self.hasher.hash_one(key), key)
+  }
+
+  /// Get the key of an identifier.
+  pub(crate) fn key(&self, id: KeyId) -> &K {
+    &self.keys[id.0 as usize]
+  }
+
+  /// Make a key an alias of an interned key, interning it if needed.
+  pub(crate) fn alias(&mut self, alias: &K, id: KeyId) {
+    let index = self.intern_ref(alias).0 as usize;
+
+    if self.aliases.len() <= index {
+      self.aliases.resize(index + 1, None);
+    }
+
+    self.aliases[index] = Some(id);
+  }
+
+  /// Get the identifier of the key an alias resolves to, if it’s an alias.
+  pub(crate) fn resolve(&self, alias: &K) -> Option<KeyId> {
+    let id = self.id(alias)?;
+    self.aliases.get(id.0 as usize).cloned().flatten()
+  }
+
+  /// Find the identifier of a key knowing its hash.
+  fn find(&self, hash: u64, key: &K) -> Option<KeyId> {
+    let id = *self.ids.get(&hash)?;
+
+    if self.key(id) == key {
+      return Some(id);
+    }
+
+    self.collisions.get(&hash)?.iter().cloned().find(|&id| self.key(id) == key)
+  }
 }
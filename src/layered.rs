@@ -0,0 +1,247 @@
+//! Layered configurations.
+//!
+//! This module provides you with [`LayeredConfig`], a resource holding a configuration merged out
+//! of several layers, by increasing precedence:
+//!
+//!   1. `base.toml`, the configuration shared by all deployments.
+//!   2. `local.toml`, optional overrides local to a deployment – typically not versioned.
+//!   3. Environment variables, if the configuration type sets [`Layered::ENV_PREFIX`].
+//!
+//! The key of a layered configuration is the directory holding both files. Tables are merged
+//! deeply: a layer only overrides the values it sets. Both files are dependencies of the
+//! configuration, so that changing any of them – or creating `local.toml` – merges the layers
+//! again.
+//!
+//! Environment variables are named after the prefix and the path of the value to override, in
+//! uppercase and separated with double underscores: with the `APP` prefix, `APP__SERVER__PORT`
+//! overrides `port` in the `server` table. Their values are parsed as TOML values – `8080` is an
+//! integer, `true` a boolean – or taken as strings otherwise. They’re read when the configuration
+//! is loaded or reloaded: changing them doesn’t trigger a reload.
+//!
+//! ```
+//! use serde::Deserialize;
+//! use warmy::{Res, SimpleKey, Store, StoreOpt};
+//! use warmy::layered::{Layered, LayeredConfig};
+//!
+//! #[derive(Deserialize)]
+//! struct ServerConfig {
+//!   host: String,
+//!   port: u16,
+//! }
+//!
+//! impl Layered for ServerConfig {
+//!   const ENV_PREFIX: Option<&'static str> = Some("MY_SERVER");
+//! }
+//!
+//! # let tmp_dir = tempfile::tempdir().unwrap();
+//! # std::fs::create_dir(tmp_dir.path().join("config")).unwrap();
+//! # let dir = tmp_dir.path().join("config");
+//! # std::fs::write(dir.join("base.toml"), "host = \"0.0.0.0\"\nport = 80").unwrap();
+//! # std::fs::write(dir.join("local.toml"), "port = 8080").unwrap();
+//! # let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt).unwrap();
+//! let key = SimpleKey::from_path("/config");
+//! let config: Res<LayeredConfig<ServerConfig>> = store.get(&key, &mut ()).unwrap();
+//!
+//! assert_eq!(config.borrow().port, 8080);
+//! ```
+
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+/// Name of the base layer.
+const BASE: &str = "base.toml";
+
+/// Name of the local layer.
+const LOCAL: &str = "local.toml";
+
+/// Configuration types that can be loaded in layers.
+pub trait Layered {
+  /// Prefix of the environment variables overriding the configuration, if any.
+  ///
+  /// Defaults to no prefix, which disables environment overrides.
+  const ENV_PREFIX: Option<&'static str> = None;
+}
+
+/// A configuration merged out of `base.toml`, `local.toml` and environment variables.
+///
+/// It dereferences to the configuration.
+#[derive(Clone, Debug)]
+pub struct LayeredConfig<T> {
+  // merged configuration
+  config: T,
+  // merged layers, before deserialization
+  merged: Table,
+}
+
+impl<T> LayeredConfig<T> {
+  /// The merged configuration.
+  pub fn config(&self) -> &T {
+    &self.config
+  }
+
+  /// The merged layers, before deserialization – e.g. to inspect values that the configuration type
+  /// doesn’t hold.
+  pub fn merged(&self) -> &Table {
+    &self.merged
+  }
+
+  /// Consume the resource and get the merged configuration.
+  pub fn into_config(self) -> T {
+    self.config
+  }
+}
+
+impl<T> Deref for LayeredConfig<T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    &self.config
+  }
+}
+
+/// Possible error that might occur while loading and reloading layered configurations.
+#[derive(Debug)]
+pub enum LayeredConfigError {
+  /// A layer is not a valid TOML document.
+  TomlError(PathBuf, toml::de::Error),
+  /// The merged layers don’t form a valid configuration.
+  ConfigError(toml::de::Error),
+  /// The file of a layer failed to open or could not be read.
+  CannotReadFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+}
+
+impl fmt::Display for LayeredConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      LayeredConfigError::TomlError(ref path, ref e) => {
+        write!(f, "TOML error in {}: {}", path.display(), e)
+      }
+
+      LayeredConfigError::ConfigError(ref e) => write!(f, "invalid configuration: {}", e),
+
+      LayeredConfigError::CannotReadFile(ref path, ref e) => {
+        write!(f, "cannot read file {}: {}", path.display(), e)
+      }
+
+      LayeredConfigError::NoKey => f.write_str("no path key available"),
+    }
+  }
+}
+
+impl Error for LayeredConfigError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      LayeredConfigError::TomlError(_, ref e) => Some(e),
+      LayeredConfigError::ConfigError(ref e) => Some(e),
+      LayeredConfigError::CannotReadFile(_, ref e) => Some(e),
+      LayeredConfigError::NoKey => None,
+    }
+  }
+}
+
+impl<C, K, T> Load<C, K> for LayeredConfig<T>
+where K: Key + Into<Option<PathBuf>> + for<'a> From<&'a Path>,
+      T: 'static + Layered + for<'de> Deserialize<'de> {
+  type Error = LayeredConfigError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let dir = key.clone().into().ok_or(LayeredConfigError::NoKey)?;
+    let base_path = dir.join(BASE);
+    let local_path = dir.join(LOCAL);
+
+    // both layers are dependencies, even if the local one doesn’t exist yet
+    storage.depend_on_file(&key, &base_path);
+    storage.depend_on_file(&key, &local_path);
+
+    let mut merged = read_layer(storage, &base_path)?;
+
+    match storage.read_file_to_string(&local_path) {
+      Ok(local) => merge(&mut merged, parse_layer(&local_path, &local)?),
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+      Err(e) => return Err(LayeredConfigError::CannotReadFile(local_path, e)),
+    }
+
+    if let Some(prefix) = T::ENV_PREFIX {
+      merge(&mut merged, env_layer(prefix, env::vars()));
+    }
+
+    let config: T = Value::Table(merged.clone())
+      .try_into()
+      .map_err(LayeredConfigError::ConfigError)?;
+
+    Ok(LayeredConfig { config, merged }.into())
+  }
+}
+
+/// Read and parse a layer.
+fn read_layer<C, K>(storage: &Storage<C, K>, path: &Path) -> Result<Table, LayeredConfigError>
+where K: Key {
+  let source = storage
+    .read_file_to_string(path)
+    .map_err(|ioerr| LayeredConfigError::CannotReadFile(path.to_owned(), ioerr))?;
+  parse_layer(path, &source)
+}
+
+/// Parse a layer.
+fn parse_layer(path: &Path, source: &str) -> Result<Table, LayeredConfigError> {
+  toml::from_str(source).map_err(|e| LayeredConfigError::TomlError(path.to_owned(), e))
+}
+
+/// Build the layer of the environment variables having the given prefix.
+fn env_layer<I>(prefix: &str, vars: I) -> Table where I: IntoIterator<Item = (String, String)> {
+  let mut layer = Table::new();
+
+  for (name, raw) in vars {
+    let path = match name.strip_prefix(prefix).and_then(|name| name.strip_prefix("__")) {
+      Some(path) if !path.is_empty() => path.to_lowercase(),
+      _ => continue,
+    };
+
+    // values are parsed as TOML values if they can be, and taken as strings otherwise
+    let value = format!("value = {}", raw)
+      .parse::<Value>()
+      .ok()
+      .and_then(|doc| doc.get("value").cloned())
+      .unwrap_or(Value::String(raw));
+
+    // nest the value in tables, from the innermost one
+    let mut segments = path.split("__").collect::<Vec<_>>();
+    let last = segments.pop().unwrap_or_default();
+    let mut table = Table::new();
+    table.insert(last.to_owned(), value);
+
+    for segment in segments.into_iter().rev() {
+      let mut parent = Table::new();
+      parent.insert(segment.to_owned(), Value::Table(table));
+      table = parent;
+    }
+
+    merge(&mut layer, table);
+  }
+
+  layer
+}
+
+/// Merge a layer into another one, overriding its values; tables are merged recursively.
+fn merge(into: &mut Table, layer: Table) {
+  for (name, value) in layer {
+    match (into.get_mut(&name), value) {
+      (Some(Value::Table(into)), Value::Table(layer)) => merge(into, layer),
+      (_, value) => {
+        into.insert(name, value);
+      }
+    }
+  }
+}
@@ -22,14 +22,56 @@
 //!
 //!   - `"arc"`: changes the internal representation of resources in order to use [`Arc`] and
 //!     [`Mutex`], allowing for cross-thread sharing of resources. This is a current patch in the
-//!     waiting of a better asynchronous solution.
+//!     waiting of a better asynchronous solution. Also provides `Store::subscribe`, which broadcasts
+//!     resource events to other threads over a channel, and `StoreOpt::set_auto_sync`, which marks
+//!     the store as needing a synchronization from a timer thread.
+//!   - `"debug-server"`: provides `Store::serve_debug`, which spawns a tiny HTTP server exposing
+//!     JSON endpoints to list resources, view last errors and trigger force-reloads.
+//!   - `"derive"`: provides a `#[derive(Key)]` procedural macro that implements [`Key`] for your
+//!     own key types, applying the VFS substitution to the fields marked with `#[key(path)]`, and a
+//!     `#[derive(Load)]` one that implements [`Load`] by delegating to a universal method – e.g.
+//!     `#[load(format = "json", ext = "json")]` – so that `Store::get` works without stating it.
+//!   - `"dylib-impl"`: provides a `DyLib` resource type for native dynamic libraries, loaded with
+//!     [libloading] from copies of the original files, so that rebuilding a library hot reloads it.
+//!   - `"fast-hash"`: uses the [FxHash] hasher instead of SipHash for the internal maps, which
+//!     speeds up stores holding a lot of resources. FxHash is deterministic but not resistant to
+//!     DoS attacks, which doesn’t matter unless your keys come from untrusted sources.
+//!   - `"hot-reload"`: watches the filesystem and reloads resources when they change. Disabling it
+//!     compiles out the watcher thread, the reload functions and the dependency tracking: `get` just
+//!     loads and caches resources and `Store::sync` does nothing, which is what you want in shipping
+//!     builds. Implied by `"debug-server"`. **Enabled by default**
 //!   - `"json"`: provides a [`Json`] type that you can use as loading method to automatically load
 //!     any type that implements [`serde::Deserialize`] and encoded as [JSON]. You don’t even have
 //!     to implement [`Load`] by your own! **Enabled by default**
+//!   - `"log"`: emits [log] records for loads, reloads, discoveries, reload failures and dropped
+//!     filesystem events, so that you get visibility into what `warmy` does without writing any
+//!     code.
+//!   - `"lua-impl"`: provides a `LuaScript` resource type for Lua scripts, compiled with [mlua].
+//!     Scripts depend on the modules they `require`, so that editing a shared module reloads all the
+//!     scripts using it.
+//!   - `"metrics"`: records counters of loads, reloads and failures, and histograms of load
+//!     durations and resources dirty per synchronization, via the [metrics] facade.
+//!   - `"naga-impl"`: provides a `WgslShader` resource type for WGSL shaders, parsed and validated
+//!     with [naga], so that a broken shader edit is rejected at reload time – keeping the last good
+//!     shader – with a precise error.
+//!   - `"rayon"`: provides `Storage::get_many_par`, which loads resources implementing `LoadPar`
+//!     in parallel on the [rayon] thread pool – handy to load hundreds of files on start-up. The
+//!     [`Json`] type implements `LoadPar` out of the box.
 //!   - `"ron-impl"`: provides a [`Ron`] type that you can use as loading method to automatically
 //!     load any type that implemetns [`serde::Deserialize`] and encoded as [RON].
+//!   - `"specs-impl"`: provides `ReadHandles`, the system data reading the `Handles` – a table of
+//!     resource handles shareable across threads – registered in a [specs] world. Implies `"arc"`,
+//!     which alone provides `Handles` for any other ECS, such as [legion].
+//!   - `"test-util"`: provides the `test_util` module, with helpers to test hot-reloading: running
+//!     code with a store rooted in a temporary directory and synchronizing it until a condition
+//!     holds. Meant to be enabled in `[dev-dependencies]`.
 //!   - `"toml-impl"`: provides a [`Toml`] type that you can use as loading method to automatically
-//!     load any type that implements [`serde::Deserialize`] and encoded as [TOML].
+//!     load any type that implements [`serde::Deserialize`] and encoded as [TOML]. It also provides
+//!     a `LayeredConfig` resource type merging `base.toml`, `local.toml` and environment overrides.
+//!   - `"tracing"`: wraps loads, reloads and synchronizations in [tracing] spans carrying the key
+//!     and type of the resources, so that they show up in flamegraphs and traces.
+//!   - `"wasmi-impl"`: provides a `WasmModule` resource type for WebAssembly modules, compiled with
+//!     [wasmi], so that logic or plugins written in WebAssembly can be swapped at runtime.
 //!
 //! # Loading a resource
 //!
@@ -86,10 +128,10 @@
 //! ### Special case: simple keys
 //!
 //! A *simple key* (a.k.a. [`SimpleKey`]) is a key used to express common situations in which you
-//! might have resources from the filesystem and from logical locations. It’s provided for
-//! convenience, so that you don’t have to write that type and implement [`Key`]. In most
-//! situations, it should be enough for you – of course, if you need more details, feel free to
-//! define your own key type.
+//! might have resources from the filesystem and from logical locations – named, or identified by
+//! raw bytes such as content hashes. It’s provided for convenience, so that you don’t have to write
+//! that type and implement [`Key`]. In most situations, it should be enough for you – of course, if
+//! you need more details, feel free to define your own key type.
 //!
 //! ## The `Load::Error` associated type
 //!
@@ -131,6 +173,8 @@
 //!   }
 //! }
 //!
+//! impl std::error::Error for Error {}
+//!
 //! // The resource we want to take from a file.
 //! struct FromFS(String);
 //!
@@ -155,7 +199,7 @@
 //!         Ok(FromFS(s).into())
 //!       }
 //!
-//!       SimpleKey::Logical(_) => Err(Error::CannotLoadFromLogical)
+//!       _ => Err(Error::CannotLoadFromLogical)
 //!     }
 //!   }
 //! }
@@ -175,7 +219,7 @@
 //!         Ok(FromMem(key.len()).into())
 //!       }
 //!
-//!       SimpleKey::Path(_) => Err(Error::CannotLoadFromFS)
+//!       _ => Err(Error::CannotLoadFromFS)
 //!     }
 //!   }
 //! }
@@ -235,6 +279,8 @@
 //!     (non-cached) fails to load (missing resource, fail to parse, whatever), a *proxy* will be
 //!     used – passed in to [`Store::get_proxied`]. This value is lazy though, so if the loading
 //!     succeeds, that value won’t ever be evaluated.
+//!   - [`Store::get_or_default`], a shortcut for [`Store::get_proxied`] using the [`Default`] value
+//!     of the resource as proxy.
 //!
 //! Let’s focus on [`Store::get`] for this tutorial.
 //!
@@ -263,6 +309,8 @@
 //!   }
 //! }
 //!
+//! impl std::error::Error for Error {}
+//!
 //! // The resource we want to take from a file.
 //! struct FromFS(String);
 //!
@@ -284,7 +332,7 @@
 //!         Ok(FromFS(s).into())
 //!       }
 //!
-//!       SimpleKey::Logical(_) => Err(Error::CannotLoadFromLogical)
+//!       _ => Err(Error::CannotLoadFromLogical)
 //!     }
 //!   }
 //! }
@@ -319,6 +367,20 @@
 //!
 //! See the documentation of [`Load::reload`] for further details.
 //!
+//! In a game loop, call [`Store::sync`] from your `update` function – or let [`FrameSync`] do it
+//! for you: it only synchronizes the store every few frames and bounds the time spent reloading, so
+//! that reloading a lot of resources at once doesn’t make your frames stutter.
+//!
+//! When a hot-reload bug depends on the timing of filesystem events, record what happens to the
+//! store with `Store::start_recording` and feed the recorded log back into a store with the
+//! `replay` module: the store is then synchronized deterministically, without real filesystem
+//! timing.
+//!
+//! To unit-test your [`Load`] implementations and their reloading without touching the
+//! filesystem, use the `MockStore` of the `testing` module: its files live in memory and changing
+//! them queues filesystem events, handled on the next synchronization. Loaders read files with
+//! `Storage::read_file` for that to work.
+//!
 //! # Context inspection
 //!
 //! A context is a special value you can access to via a mutable reference when loading or
@@ -355,6 +417,8 @@
 //!   }
 //! }
 //!
+//! impl std::error::Error for Error {}
+//!
 //! struct Foo;
 //!
 //! struct Ctx {
@@ -413,6 +477,8 @@
 //!   }
 //! }
 //!
+//! impl std::error::Error for Error {}
+//!
 //! struct Foo;
 //!
 //! struct Ctx {
@@ -453,6 +519,70 @@
 //! You can also find several *methods* centralized in here, but you definitely don’t have to use
 //! them.
 //!
+//! When the formats to load are only known at runtime – e.g. when plugins bring their own – loaders
+//! can be registered in a [`LoaderRegistry`] and used through the [`Registered`] method – or picked
+//! by a format chosen at runtime with [`Storage::get_dyn`]. See the [`registry`] module for further
+//! details.
+//!
+//! Post-processing a resource loaded with a method doesn’t require a [`Load`] implementation either:
+//! loaders can be built with the combinators of the [`combinator`] module – e.g.
+//! `Json.map(|raw: RawConfig| Config::from(raw))` – and given to [`Storage::get_with`].
+//!
+//! Resources coming in several formats – e.g. in legacy asset directories – can be loaded with
+//! [`FirstOf`], which tries several methods in order: `FirstOf::<(Ron, Json, Toml)>::new()`.
+//!
+//! ## Inspecting per method
+//!
+//! The last type variable of [`Inspect`] is the method too. When loading a type with several
+//! methods, each [`Load`] implementation can then inspect a different part of the context by
+//! stating its method in its [`Inspect`] bound:
+//!
+//! ```rust
+//! use warmy::{Inspect, Load, Loaded, SimpleKey, Storage};
+//!
+//! struct Mesh;
+//!
+//! // methods
+//! struct Obj;
+//! struct Gltf;
+//!
+//! struct Ctx {
+//!   obj_loaded: usize,
+//!   gltf_loaded: usize
+//! }
+//!
+//! impl<'a> Inspect<'a, Ctx, &'a mut usize, Obj> for Mesh {
+//!   fn inspect(ctx: &mut Ctx) -> &mut usize {
+//!     &mut ctx.obj_loaded
+//!   }
+//! }
+//!
+//! impl<'a> Inspect<'a, Ctx, &'a mut usize, Gltf> for Mesh {
+//!   fn inspect(ctx: &mut Ctx) -> &mut usize {
+//!     &mut ctx.gltf_loaded
+//!   }
+//! }
+//!
+//! impl<C> Load<C, SimpleKey, Obj> for Mesh
+//! where Mesh: for<'a> Inspect<'a, C, &'a mut usize, Obj> {
+//!   type Error = std::convert::Infallible;
+//!
+//!   fn load(
+//!     _: SimpleKey,
+//!     _: &mut Storage<C, SimpleKey>,
+//!     ctx: &mut C
+//!   ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+//!     // the method must be stated when several inspections are possible
+//!     *<Self as Inspect<_, _, Obj>>::inspect(ctx) += 1;
+//!
+//!     Ok(Mesh.into())
+//!   }
+//! }
+//! ```
+//!
+//! Implementing `Load<C, SimpleKey, Gltf>` the same way makes loading a `Mesh` via `Gltf` count in
+//! `gltf_loaded` instead.
+//!
 //! ## Universal JSON support
 //!
 //! The crate supports *universal JSON implementation*. You can use it via the
@@ -559,7 +689,10 @@
 //! [`Store::get_by`]: crate::load::Storage::get_by
 //! [`Store::get_proxied`]: crate::load::Storage::get_proxied
 //! [`Store::get_proxied_by`]: crate::load::Storage::get_proxied_by
+//! [`Store::get_or_default`]: crate::load::Storage::get_or_default
+//! [`Default`]: std::default::Default
 //! [`Store::sync`]: crate::load::Store::sync
+//! [`FrameSync`]: crate::frame::FrameSync
 //! [`StoreOpt`]: crate::load::StoreOpt
 //! [`StoreOpt::set_discovery`]: crate::load::StoreOpt::set_discovery
 //! [`StoreOpt::discovery`]: crate::load::StoreOpt::discovery
@@ -569,19 +702,98 @@
 //! [`serde::Deserialize`]: https://docs.rs/serde/1.0.85/serde/trait.Deserialize.html
 //! [`Arc`]: std::sync::Arc
 //! [`Mutex`]: std::sync::Mutex
+//! [`LoaderRegistry`]: crate::registry::LoaderRegistry
+//! [`Registered`]: crate::registry::Registered
+//! [`registry`]: crate::registry
+//! [`combinator`]: crate::combinator
+//! [`Storage::get_dyn`]: crate::load::Storage::get_dyn
+//! [`Storage::get_with`]: crate::load::Storage::get_with
+//! [`FirstOf`]: crate::fallback::FirstOf
 //! [JSON]: https://www.json.org
 //! [TOML]: https://github.com/toml-lang/toml
 //! [RON]: https://github.com/ron-rs/ron
+//! [FxHash]: https://crates.io/crates/rustc-hash
+//! [rayon]: https://crates.io/crates/rayon
+//! [specs]: https://crates.io/crates/specs
+//! [legion]: https://crates.io/crates/legion
+//! [libloading]: https://crates.io/crates/libloading
+//! [log]: https://crates.io/crates/log
+//! [metrics]: https://crates.io/crates/metrics
+//! [mlua]: https://crates.io/crates/mlua
+//! [naga]: https://crates.io/crates/naga
+//! [tracing]: https://crates.io/crates/tracing
+//! [wasmi]: https://crates.io/crates/wasmi
+
+// Emit a log record if the "log" feature-gate is enabled; the arguments are still type-checked
+// otherwise.
+macro_rules! log_record {
+  ($level:ident, $($arg:tt)+) => {
+    #[cfg(feature = "log")]
+    log::$level!($($arg)+);
+    #[cfg(not(feature = "log"))]
+    let _ = format_args!($($arg)+);
+  };
+}
+
+// Enter a tracing span until the end of the current block if the "tracing" feature-gate is
+// enabled.
+macro_rules! enter_span {
+  ($($arg:tt)+) => {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!($($arg)+).entered();
+  };
+}
+
+// Record metrics if the "metrics" feature-gate is enabled.
+macro_rules! record_metrics {
+  ($($body:tt)+) => {
+    #[cfg(feature = "metrics")]
+    {
+      $($body)+
+    }
+  };
+}
 
+mod cache;
+pub mod chain;
+pub mod combinator;
 pub mod context;
+#[cfg(feature = "debug-server")] mod debug_server;
+#[cfg(feature = "dylib-impl")] pub mod dylib;
+#[cfg(feature = "arc")] pub mod ecs;
+pub mod fallback;
+pub mod frame;
+mod hash;
 #[cfg(feature = "json")] pub mod json;
+#[cfg(feature = "lua-impl")] pub mod lua;
 #[cfg(feature = "ron-impl")] pub mod ron;
+#[cfg(feature = "naga-impl")] pub mod shader;
 #[cfg(feature = "toml-impl")] pub mod toml;
+#[cfg(feature = "wasmi-impl")] pub mod wasm;
 pub mod key;
+#[cfg(feature = "toml-impl")] pub mod layered;
 pub mod load;
+pub mod observer;
+pub mod registry;
+#[cfg(feature = "hot-reload")] pub mod replay;
 pub mod res;
+pub mod secrets;
+#[cfg(feature = "test-util")] pub mod test_util;
+pub mod testing;
 
-pub use crate::context::Inspect;
-pub use crate::key::{Key, SimpleKey};
-pub use crate::load::{Discovery, Load, Loaded, Storage, Store, StoreError, StoreErrorOr, StoreOpt};
+pub use crate::context::{AnyContext, Inspect};
+#[cfg(feature = "arc")] pub use crate::ecs::Handles;
+#[cfg(feature = "specs-impl")] pub use crate::ecs::ReadHandles;
+pub use crate::frame::{FrameSync, SyncScope};
+pub use crate::key::{Key, Normalization, SimpleKey, UriKey, UriKeyError};
+#[cfg(feature = "derive")] pub use warmy_derive::{Key, Load};
+pub use crate::load::{
+  CodedError, DepEvents, Discovery, DynLoad, DynLoader, ErrorCode, Extension, FailurePropagation,
+  FileMetadata, FileSource, Load, LoadInfo, LoadShared, Loaded, Provenance, ReloadPanic,
+  ResourceError, ResourceInfo, ResourceType, RetryPolicy, Shared, Storage, Store, StoreError,
+  StoreErrorOr, StoreOpt, StoreSnapshot, SyncReport, TypedDiscovery,
+};
+#[cfg(feature = "arc")] pub use crate::observer::StoreEvent;
+pub use crate::observer::StoreObserver;
+pub use crate::registry::LoaderRegistry;
 pub use crate::res::Res;
@@ -2,16 +2,43 @@
 //!
 //! This module exposes traits, types and functions you need to use to load and reload objects.
 
-use any_cache::{Cache, HashCache};
-use notify::{self, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::{HashMap, HashSet};
+use glob::{MatchOptions, Pattern, PatternError};
+use std::any::{self, Any, TypeId};
+use std::cell::RefCell;
+#[cfg(feature = "arc")] use crossbeam_channel::{bounded, unbounded, Sender};
+#[cfg(feature = "hot-reload")] use std::collections::VecDeque;
+#[cfg(feature = "hot-reload")] use notify::{self, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::error::Error;
 use std::fmt::{self, Display};
-use std::ops::{Deref, DerefMut};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::marker::PhantomData;
+#[cfg(feature = "debug-server")] use std::net::{SocketAddr, ToSocketAddrs};
+use std::ops::{BitOr, BitOrAssign, Deref, DerefMut};
+#[cfg(feature = "rayon")] use rayon::iter::{IntoParallelIterator, ParallelIterator};
+#[cfg(feature = "hot-reload")] use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{channel, Receiver};
-use std::time::Duration;
+#[cfg(feature = "hot-reload")] use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::rc::Rc;
+#[cfg(feature = "hot-reload")] use std::rc::Weak;
+use std::sync::Arc;
+#[cfg(feature = "arc")] use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::key::{Key, PrivateKey};
+#[cfg(feature = "debug-server")] use crate::debug_server::DebugServer;
+use crate::cache::Cache;
+use crate::combinator::{Combined, Loader};
+use crate::frame::SyncScope;
+use crate::hash::{HashMap, HashSet, StableHasher};
+use crate::key::{
+  strip_verbatim_prefix, vfs_substitute_path, Described, Interner, Key, KeyId, Normalization,
+};
+use crate::observer::StoreObserver;
+use crate::registry::{LoaderRegistry, RegisteredAs};
+#[cfg(feature = "hot-reload")] use crate::replay::{EventLog, RecordedEvent};
+#[cfg(feature = "arc")] use crate::observer::StoreEvent;
 use crate::res::Res;
 
 /// Class of types that can be loaded and reloaded.
@@ -35,7 +62,10 @@ pub trait Load<C, K, Method = ()>: 'static + Sized
 where K: Key,
       Method: ?Sized {
   /// Type of error that might happen while loading.
-  type Error: Display + 'static;
+  ///
+  /// Reload errors are reported by [`Store::sync`] as boxed [`Error`]s, so that they can be
+  /// downcast back to this type.
+  type Error: Error + Send + Sync + 'static;
 
   /// Load a resource.
   ///
@@ -49,10 +79,10 @@ where K: Key,
     ctx: &mut C,
   ) -> Result<Loaded<Self, K>, Self::Error>;
 
-  // FIXME: add support for redeclaring the dependencies?
   /// Function called when a resource must be reloaded.
   ///
-  /// The default implementation of that function calls [`Load::load`] and returns its result.
+  /// The default implementation of that function calls [`Load::load`] and returns its result. The
+  /// dependencies it returns are dropped: see [`Storage::set_deps`] to redeclare them.
   fn reload(
     &self,
     key: K,
@@ -61,6 +91,239 @@ where K: Key,
   ) -> Result<Self, Self::Error> {
     Self::load(key, storage, ctx).map(|lr| lr.res)
   }
+
+  /// Function called before a reloaded resource replaces the current one.
+  ///
+  /// `self` is the current resource and `new` the freshly reloaded one. Returning an error rejects
+  /// the update: the current resource is kept and the reload is considered failed. This is useful
+  /// to refuse updates that are syntactically fine but semantically invalid.
+  ///
+  /// The default implementation accepts any update.
+  fn accept(&self, _new: &Self, _ctx: &mut C) -> Result<(), Self::Error> {
+    Ok(())
+  }
+}
+
+/// Class of types that can be loaded and reloaded with a read-only context.
+///
+/// This trait is the same as [`Load`] but its functions receive the context as an immutable
+/// reference. This is handy for loaders that only need to read the context, as it doesn’t conflict
+/// with other immutable borrows of the context held elsewhere in your application.
+///
+/// Types implementing this trait are loaded with [`Storage::get_shared`] and
+/// [`Storage::get_shared_by`]. They also implement [`Load`] with the [`Shared`] method, so that
+/// the store can reload them as any other resource.
+pub trait LoadShared<C, K, Method = ()>: 'static + Sized
+where K: Key,
+      Method: ?Sized {
+  /// Type of error that might happen while loading.
+  ///
+  /// Reload errors are reported by [`Store::sync`] as boxed [`Error`]s, so that they can be
+  /// downcast back to this type.
+  type Error: Error + Send + Sync + 'static;
+
+  /// Load a resource.
+  ///
+  /// See [`Load::load`] for further details.
+  fn load(
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &C,
+  ) -> Result<Loaded<Self, K>, Self::Error>;
+
+  /// Function called when a resource must be reloaded.
+  ///
+  /// The default implementation of that function calls [`LoadShared::load`] and returns its
+  /// result.
+  fn reload(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &C,
+  ) -> Result<Self, Self::Error> {
+    Self::load(key, storage, ctx).map(|lr| lr.res)
+  }
+
+  /// Function called before a reloaded resource replaces the current one.
+  ///
+  /// See [`Load::accept`] for further details. The default implementation accepts any update.
+  fn accept(&self, _new: &Self, _ctx: &C) -> Result<(), Self::Error> {
+    Ok(())
+  }
+}
+
+/// Loading method of types implementing [`LoadShared`] with the `Method` method.
+pub struct Shared<Method = ()>(PhantomData<Method>) where Method: ?Sized;
+
+impl<C, K, M, T> Load<C, K, Shared<M>> for T
+where T: LoadShared<C, K, M>,
+      K: Key,
+      M: ?Sized {
+  type Error = <T as LoadShared<C, K, M>>::Error;
+
+  fn load(
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self, K>, Self::Error> {
+    <T as LoadShared<C, K, M>>::load(key, storage, ctx)
+  }
+
+  fn reload(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error> {
+    <T as LoadShared<C, K, M>>::reload(self, key, storage, ctx)
+  }
+
+  fn accept(&self, new: &Self, ctx: &mut C) -> Result<(), Self::Error> {
+    <T as LoadShared<C, K, M>>::accept(self, new, ctx)
+  }
+}
+
+/// Object-safe counterpart of [`Load`].
+///
+/// [`Load`] cannot be used as a trait object: the resource type is the implementor and loading
+/// doesn’t take `self`. A [`DynLoad`] object is a loader on its own, producing type-erased
+/// resources, so that loaders of different types – including types only known at runtime, such as
+/// resource types defined by scripts – can be held together behind `Box<dyn DynLoad<C, K>>`.
+///
+/// Types implementing [`Load`] are turned into such objects with [`DynLoader`].
+pub trait DynLoad<C, K> where K: Key {
+  /// Type identifier of the resources produced by this loader.
+  fn res_type_id(&self) -> TypeId;
+
+  /// Name of the type of the resources produced by this loader, for diagnostics.
+  fn res_type_name(&self) -> &str;
+
+  /// Load a resource.
+  ///
+  /// The resource must be of the type identified by [`DynLoad::res_type_id`]. See [`Load::load`]
+  /// for further details.
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Box<dyn Any>, K>, Box<dyn Error + Send + Sync>>;
+
+  /// Function called when a resource must be reloaded.
+  ///
+  /// The default implementation of that function calls [`DynLoad::load`] and returns its result.
+  fn reload(
+    &self,
+    _res: &dyn Any,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Box<dyn Any>, Box<dyn Error + Send + Sync>> {
+    self.load(key, storage, ctx).map(|lr| lr.res)
+  }
+
+  /// Function called before a reloaded resource replaces the current one.
+  ///
+  /// See [`Load::accept`] for further details. The default implementation accepts any update.
+  fn accept(
+    &self,
+    _res: &dyn Any,
+    _new: &dyn Any,
+    _ctx: &mut C,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    Ok(())
+  }
+}
+
+/// [`DynLoad`] object loading resources of type `T` with the `Method` method of [`Load`].
+pub struct DynLoader<T, Method = ()>(PhantomData<fn(&Method) -> T>) where Method: ?Sized;
+
+impl<T, M> DynLoader<T, M> where M: ?Sized {
+  /// Create a loader object for `T`.
+  pub fn new() -> Self {
+    DynLoader(PhantomData)
+  }
+}
+
+impl<T, M> Default for DynLoader<T, M> where M: ?Sized {
+  fn default() -> Self {
+    DynLoader::new()
+  }
+}
+
+impl<T, M> fmt::Debug for DynLoader<T, M> where M: ?Sized {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "DynLoader<{}, {}>", any::type_name::<T>(), any::type_name::<M>())
+  }
+}
+
+impl<C, K, M, T> DynLoad<C, K> for DynLoader<T, M>
+where T: Load<C, K, M>,
+      K: Key,
+      M: ?Sized {
+  fn res_type_id(&self) -> TypeId {
+    TypeId::of::<T>()
+  }
+
+  fn res_type_name(&self) -> &str {
+    any::type_name::<T>()
+  }
+
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Box<dyn Any>, K>, Box<dyn Error + Send + Sync>> {
+    let Loaded { res, deps } = T::load(key, storage, ctx)?;
+    Ok(Loaded::with_deps(Box::new(res), deps))
+  }
+
+  fn reload(
+    &self,
+    res: &dyn Any,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Box<dyn Any>, Box<dyn Error + Send + Sync>> {
+    match res.downcast_ref::<T>() {
+      Some(res) => Ok(Box::new(res.reload(key, storage, ctx)?)),
+      // not one of ours; load it anew
+      None => Ok(Box::new(T::load(key, storage, ctx)?.res)),
+    }
+  }
+
+  fn accept(
+    &self,
+    res: &dyn Any,
+    new: &dyn Any,
+    ctx: &mut C,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match (res.downcast_ref::<T>(), new.downcast_ref::<T>()) {
+      (Some(res), Some(new)) => Ok(res.accept(new, ctx)?),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Load a resource without the storage, so that several resources can be loaded in parallel.
+///
+/// Loading a resource with [`Load::load`] requires an exclusive access to both the storage and the
+/// context, which forces loads to happen one after the other. Types implementing this trait can
+/// be loaded with only an immutable borrow of the context instead, on a thread pool, with
+/// [`Storage::get_many_par`] and [`Storage::get_many_par_by`]. They are then injected into the
+/// storage and reloaded with their [`Load`] implementation as any other resource.
+///
+/// Because the storage is not available, such resources cannot get other resources while loading;
+/// they can still declare dependencies with [`Loaded::with_deps`].
+#[cfg(feature = "rayon")]
+pub trait LoadPar<C, K, Method = ()>: Load<C, K, Method> + Send
+where K: Key,
+      Method: ?Sized {
+  /// Load a resource.
+  ///
+  /// See [`Load::load`] for further details.
+  fn load_par(key: K, ctx: &C) -> Result<Loaded<Self, K>, Self::Error>;
 }
 
 /// Result of a resource loading.
@@ -69,6 +332,20 @@ where K: Key,
 /// named dependencies. If you don’t need to run specific code on a dependency reloading, use
 /// the `.into()` function to lift your return value to [`Loaded`] or use the provided
 /// [`Loaded::without_dep`] function.
+///
+/// Dependencies can be declared all at once with [`Loaded::with_deps`] or one by one with
+/// [`Loaded::dep`]:
+///
+/// ```
+/// use warmy::{Loaded, SimpleKey};
+///
+/// let loaded: Loaded<_, SimpleKey> = Loaded::from("stone")
+///   .dep(SimpleKey::from_path("/albedo.png"))
+///   .dep(SimpleKey::from_path("/normal.png"));
+/// assert_eq!(loaded.deps.len(), 2);
+/// ```
+///
+/// A resource never depends on itself nor several times on the same key.
 pub struct Loaded<T, K> {
   /// The loaded object.
   pub res: T,
@@ -86,8 +363,24 @@ impl<T, K> Loaded<T, K> {
   }
 
   /// Return a resource along with its dependencies.
-  pub fn with_deps(res: T, deps: Vec<K>) -> Self {
-    Loaded { res, deps }
+  ///
+  /// Duplicated dependencies are only kept once.
+  pub fn with_deps<I>(res: T, deps: I) -> Self
+  where K: PartialEq,
+        I: IntoIterator,
+        I::Item: Into<K> {
+    deps.into_iter().fold(Loaded::without_dep(res), Loaded::dep)
+  }
+
+  /// Add a dependency, unless it’s already declared.
+  pub fn dep<D>(mut self, dep: D) -> Self where K: PartialEq, D: Into<K> {
+    let dep = dep.into();
+
+    if !self.deps.contains(&dep) {
+      self.deps.push(dep);
+    }
+
+    self
   }
 }
 
@@ -97,19 +390,130 @@ impl<T, K> From<T> for Loaded<T, K> {
   }
 }
 
+/// Function committing a reloaded resource, replacing the current one.
+#[cfg(feature = "hot-reload")]
+type Commit = Box<dyn FnOnce()>;
+
+/// Function reloading a resource without committing it.
+///
+/// It’s reference-counted so that it can be called while the storage is borrowed mutably.
+#[cfg(feature = "hot-reload")]
+type Reload<C, K> = Rc<dyn Fn(&mut Storage<C, K>, &mut C) -> Result<Commit, ResourceError<K>>>;
+
+/// Outcome of a reload waiting to be committed, along with the time it took and the file the
+/// resource was reloaded from, if tracked.
+#[cfg(feature = "hot-reload")]
+type Staged<K> = (Result<Commit, ResourceError<K>>, Duration, Option<Provenance>);
+
+/// Function rewriting the keys provided by the user.
+type KeyRewriter<K> = Box<dyn Fn(K) -> K>;
+
+/// Function checking whether a resource is currently borrowed.
+#[cfg(feature = "hot-reload")]
+type Borrowed = Rc<dyn Fn() -> bool>;
+
 /// Metadata about a resource.
+///
+/// Without the `"hot-reload"` feature-gate, resources are never reloaded, so no reload function is
+/// stored.
 struct ResMetaData<C, K> {
   /// Function to call each time the resource must be reloaded.
-  on_reload: Box<dyn Fn(&mut Storage<C, K>, &mut C) -> Result<(), Box<dyn Display>>>,
+  ///
+  /// The reloaded resource is only staged: the returned [`Commit`] must be called to actually
+  /// replace the current resource.
+  #[cfg(feature = "hot-reload")]
+  on_reload: Reload<C, K>,
+  /// Function checking whether the resource is borrowed, in which case it cannot be reloaded.
+  #[cfg(feature = "hot-reload")]
+  borrowed: Borrowed,
+  /// Whether the resource is a proxy waiting to be replaced by the real resource.
+  proxy: bool,
+  /// Error of the last failed loading or reloading, if any.
+  last_error: Option<ResourceError<K>>,
+  /// Type of the resource.
+  ty: ResourceType,
+  /// Number of successful reloads.
+  reloads: u32,
+  /// Time of the last successful reload, if any.
+  last_reload: Option<SystemTime>,
+  /// Timing of the last successful loading or reloading; proxies don’t have any.
+  load_info: Option<LoadInfo>,
+  /// File the resource was last successfully loaded or reloaded from, if tracked.
+  provenance: Option<Provenance>,
+  /// Marker for the context type, otherwise only used by the reload function.
+  #[cfg(not(feature = "hot-reload"))]
+  _context: PhantomData<fn(&mut C)>,
 }
 
 impl<C, K> ResMetaData<C, K> {
-  fn new<F>(f: F) -> Self
-  where F: 'static + Fn(&mut Storage<C, K>, &mut C) -> Result<(), Box<dyn Display>> {
+  #[cfg(feature = "hot-reload")]
+  fn new(ty: ResourceType, on_reload: Reload<C, K>, borrowed: Borrowed) -> Self {
+    ResMetaData {
+      on_reload,
+      borrowed,
+      proxy: false,
+      last_error: None,
+      ty,
+      reloads: 0,
+      last_reload: None,
+      load_info: None,
+      provenance: None,
+    }
+  }
+
+  #[cfg(not(feature = "hot-reload"))]
+  fn new(ty: ResourceType) -> Self {
     ResMetaData {
-      on_reload: Box::new(f),
+      proxy: false,
+      last_error: None,
+      ty,
+      reloads: 0,
+      last_reload: None,
+      load_info: None,
+      provenance: None,
+      _context: PhantomData,
+    }
+  }
+}
+
+/// Type of a resource living in a [`Storage`].
+///
+/// Keys are not typed, so this is the only way to know which type a resource was loaded as by
+/// only knowing its key. See [`Storage::resource_type`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ResourceType {
+  id: TypeId,
+  name: &'static str,
+}
+
+impl ResourceType {
+  /// Type information of `T`.
+  pub fn of<T>() -> Self where T: 'static {
+    ResourceType {
+      id: TypeId::of::<T>(),
+      name: any::type_name::<T>(),
     }
   }
+
+  /// Unique identifier of the type.
+  #[inline]
+  pub fn id(&self) -> TypeId {
+    self.id
+  }
+
+  /// Name of the type.
+  ///
+  /// This is only meant for diagnostics: the exact content of the name is not guaranteed.
+  #[inline]
+  pub fn name(&self) -> &'static str {
+    self.name
+  }
+
+  /// Check whether this is the type `T`.
+  #[inline]
+  pub fn is<T>(&self) -> bool where T: 'static {
+    self.id == TypeId::of::<T>()
+  }
 }
 
 /// Resource storage.
@@ -120,464 +524,5042 @@ pub struct Storage<C, K> {
   // canonicalized root path (used for resources loaded from the file system)
   canon_root: PathBuf,
   // resource cache, containing all living resources
-  cache: HashCache,
+  cache: Cache,
+  // intern table of prepared keys, aliased by the keys as provided by the user; everything below
+  // is indexed by the identifiers of prepared keys
+  keys: Interner<K>,
+  // paths of filesystem events, mapped to the identifiers of their keys
+  #[cfg(feature = "hot-reload")]
+  event_ids: HashMap<PathBuf, KeyId>,
   // dependencies, mapping a dependency to its dependent resources
-  deps: HashMap<K, Vec<K>>,
+  deps: HashMap<KeyId, Vec<KeyId>>,
+  // filesystem events of dependencies reloading their dependents, by (dependency, dependent)
+  // pairs; pairs not in there use the default events
+  dep_events: HashMap<(KeyId, KeyId), DepEvents>,
   // contains all metadata on resources (reload functions)
-  metadata: HashMap<K, ResMetaData<C, K>>,
+  metadata: HashMap<KeyId, ResMetaData<C, K>>,
+  // observer notified of all resource events
+  observer: Box<dyn StoreObserver<K>>,
+  // channels resource events are broadcast to; disconnected ones are dropped on the next event
+  #[cfg(feature = "arc")]
+  subscribers: Vec<Sender<StoreEvent<K>>>,
+  // groups resources belong to; resources of a same group are reloaded atomically
+  groups: HashMap<KeyId, String>,
+  // tags attached to resources, used to operate on several of them at once
+  tags: HashMap<KeyId, HashSet<String>>,
+  // time-to-live of resources that must be refreshed periodically
+  ttls: HashMap<KeyId, Ttl>,
+  // reload priorities of resources; resources not in there have a priority of 0
+  priorities: HashMap<KeyId, i32>,
+  // glob patterns resources depend on; files matching them being added or removed reload them
+  globs: HashMap<KeyId, Vec<Pattern>>,
+  // locales used to localize files, by decreasing preference
+  locales: Vec<String>,
+  // resources that were loaded from localized files
+  localized: HashSet<KeyId>,
+  // variants used to select files, by decreasing preference
+  variants: Vec<String>,
+  // resources that were loaded from variant files
+  varied: HashSet<KeyId>,
+  // normalization applied to all keys
+  normalization: Normalization,
+  // rewriting applied to the keys provided by the user, before they’re prepared
+  key_rewriter: Option<KeyRewriter<K>>,
+  // whether panics in reload code are turned into reload failures
+  #[cfg_attr(not(feature = "hot-reload"), allow(dead_code))]
+  catch_panics: bool,
+  // directory the outputs of transforms are cached in, if any
+  derived_cache: Option<PathBuf>,
+  // loaders registered at runtime
+  registry: LoaderRegistry<C, K>,
+  // loaders of the resources loaded with combinators, kept to reload them
+  combined: HashMap<KeyId, Rc<dyn Any>>,
+  // formats chosen at runtime to load resources with registered loaders, kept to reload them
+  formats: HashMap<KeyId, String>,
+  // events recorded so far, if recording
+  #[cfg(feature = "hot-reload")]
+  recording: Option<EventLog>,
+  // in-memory files replacing the filesystem, if any
+  files: Option<HashMap<PathBuf, Vec<u8>>>,
+  // whether resources not loaded yet fail instead of being loaded
+  read_only: bool,
+  // whether the files resources are loaded from are tracked
+  track_provenance: bool,
+  // first file read by each of the resources being loaded, innermost last, while tracking them
+  loading: RefCell<Vec<Option<Provenance>>>,
+  // whether native dynamic libraries may be loaded
+  #[cfg(feature = "dylib-impl")]
+  trust_dylibs: bool,
 }
 
 impl<C, K> Storage<C, K> where K: Key {
-  fn new(canon_root: PathBuf) -> Self{
+  #[allow(clippy::too_many_arguments)]
+  fn new(
+    canon_root: PathBuf,
+    observer: Box<dyn StoreObserver<K>>,
+    normalization: Normalization,
+    locales: Vec<String>,
+    variants: Vec<String>,
+    catch_panics: bool,
+    derived_cache: Option<PathBuf>,
+    registry: LoaderRegistry<C, K>,
+    files: Option<HashMap<PathBuf, Vec<u8>>>,
+  ) -> Self {
     Storage {
       canon_root,
-      cache: HashCache::new(),
-      deps: HashMap::new(),
-      metadata: HashMap::new(),
+      cache: Cache::new(),
+      keys: Interner::new(),
+      #[cfg(feature = "hot-reload")]
+      event_ids: HashMap::default(),
+      deps: HashMap::default(),
+      dep_events: HashMap::default(),
+      metadata: HashMap::default(),
+      observer,
+      #[cfg(feature = "arc")]
+      subscribers: Vec::new(),
+      groups: HashMap::default(),
+      tags: HashMap::default(),
+      ttls: HashMap::default(),
+      priorities: HashMap::default(),
+      globs: HashMap::default(),
+      locales,
+      localized: HashSet::default(),
+      variants,
+      varied: HashSet::default(),
+      normalization,
+      key_rewriter: None,
+      catch_panics,
+      derived_cache,
+      registry,
+      combined: HashMap::default(),
+      formats: HashMap::default(),
+      #[cfg(feature = "hot-reload")]
+      recording: None,
+      files,
+      read_only: false,
+      track_provenance: false,
+      loading: RefCell::new(Vec::new()),
+      #[cfg(feature = "dylib-impl")]
+      trust_dylibs: false,
     }
   }
 
+  /// Reserve capacity for at least `additional` more resources.
+  fn reserve(&mut self, additional: usize) {
+    self.cache.reserve(additional);
+    self.keys.reserve(additional);
+    self.metadata.reserve(additional);
+    self.deps.reserve(additional);
+
+    #[cfg(feature = "hot-reload")]
+    self.event_ids.reserve(additional);
+  }
+
   /// The canonicalized root the [`Storage`] is configured with.
   pub fn root(&self) -> &Path {
     &self.canon_root
   }
 
-  /// Inject a new resource in the store.
-  ///
-  /// The resource might be refused for several reasons. Further information in the documentation of
-  /// the [`StoreError`] error type.
-  fn inject<T, M>(
-    &mut self,
-    key: K,
-    resource: T,
-    deps: Vec<K>,
-  ) -> Result<Res<T>, StoreError<K>>
-  where T: Load<C, K, M> {
-    // we forbid having two resources sharing the same key
-    if self.metadata.contains_key(&key) {
-      return Err(StoreError::AlreadyRegisteredKey(key.clone()));
+  /// The normalization applied to keys.
+  pub fn normalization(&self) -> &Normalization {
+    &self.normalization
+  }
+
+  /// Rewrite a key provided by the user, if a rewriter is set.
+  fn rewrite(&self, key: &K) -> K {
+    match self.key_rewriter {
+      Some(ref rewriter) => rewriter(key.clone()),
+      None => key.clone(),
     }
+  }
 
-    // wrap the resource to make it shared mutably
-    let res = Res::new(resource);
+  /// Rewrite, prepare and normalize a key provided by the user.
+  fn prepare(&self, key: &K) -> K {
+    self.rewrite(key).prepare_key(self.root()).normalize(&self.normalization)
+  }
 
-    // create the metadata for the resource
-    let res_ = res.clone();
-    let key_ = key.clone();
-    let metadata = ResMetaData::new(move |storage, ctx| {
-      let reloaded = <T as Load<C, K, M>>::reload(&res_.borrow(), key_.clone(), storage, ctx);
-
-      match reloaded {
-        Ok(r) => {
-          // replace the current resource with the freshly loaded one
-          *res_.borrow_mut() = r;
-          Ok(())
-        }
-        Err(e) => Err(Box::new(e)),
-      }
-    });
+  /// Rewrite, prepare, normalize and intern a key provided by the user.
+  fn intern(&mut self, key: &K) -> KeyId {
+    match self.keys.resolve(key) {
+      Some(id) => id,
+      None => self.keys.intern(self.prepare(key)),
+    }
+  }
 
-    self.metadata.insert(key.clone(), metadata);
+  /// Rewrite, prepare and normalize a key provided by the user, refusing it if it escapes the
+  /// root.
+  fn prepare_checked(&self, key: &K) -> Result<K, StoreError<K>> {
+    let prepared = self.rewrite(key).prepare_key(self.root());
 
-    // register the resource as an observer of its dependencies in the dependencies graph
-    let root = &self.canon_root;
-    for dep in deps {
-      self
-        .deps
-        .entry(dep.clone().prepare_key(root))
-        .or_insert(Vec::new())
-        .push(key.clone());
+    match prepared.fs_path() {
+      Some(path) if !path.starts_with(&self.canon_root) => {
+        Err(StoreError::KeyOutsideRoot(key.clone()))
+      }
+      _ => Ok(prepared.normalize(&self.normalization)),
     }
+  }
 
-    // wrap the key in our private key so that we can use it in the cache
-    let pkey = PrivateKey::new(key);
+  /// Rewrite, prepare, normalize and intern a key provided by the user, refusing it if it escapes
+  /// the root.
+  ///
+  /// The key is remembered as an alias of its prepared version, so that resolving it again doesn’t
+  /// require to prepare – hence clone – it.
+  fn resolve(&mut self, key: &K) -> Result<KeyId, StoreError<K>> {
+    if let Some(id) = self.keys.resolve(key) {
+      return Ok(id);
+    }
 
-    // cache the resource
-    self.cache.save(pkey, res.clone());
+    let id = self.keys.intern(self.prepare_checked(key)?);
+    self.keys.alias(key, id);
+    Ok(id)
+  }
 
-    Ok(res)
+  /// Get the identifier of a key provided by the user, if it was ever interned.
+  fn lookup(&self, key: &K) -> Option<KeyId> {
+    match self.keys.resolve(key) {
+      Some(id) => Some(id),
+      None => self.keys.id(&self.prepare(key)),
+    }
   }
 
-  /// Get a resource from the [`Storage`] and return an error if its loading failed.
+  /// Get the identifier of the key matching the path of a filesystem event, if it was ever
+  /// interned.
   ///
-  /// This function uses the default loading method.
-  pub fn get<T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C, K>>
-  where T: Load<C, K> {
-    self.get_by(key, ctx, ())
+  /// Paths matching interned keys are remembered, so that further events about them don’t require
+  /// to convert them to keys again.
+  #[cfg(feature = "hot-reload")]
+  fn event_id(&mut self, path: &Path) -> Option<KeyId> where K: for<'a> From<&'a Path> {
+    if let Some(&id) = self.event_ids.get(path) {
+      return Some(id);
+    }
+
+    let id = self.keys.id(&K::from(path).normalize(&self.normalization))?;
+    self.event_ids.insert(path.to_owned(), id);
+    Some(id)
   }
 
-  /// Get a resource from the [`Storage`] by using a specific method and return and error if its
-  /// loading failed.
-  pub fn get_by<T, M>(
-    &mut self,
-    key: &K,
-    ctx: &mut C,
-    _: M,
-  ) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
-  where T: Load<C, K, M> {
-    let key = key.clone().prepare_key(self.root());
+  /// Get the identifier of the key of the resource matching the path of a filesystem event, if the
+  /// resource is known.
+  #[cfg(feature = "hot-reload")]
+  fn lookup_event(&mut self, path: &Path) -> Option<KeyId> where K: for<'a> From<&'a Path> {
+    self.event_id(path).filter(|id| self.metadata.contains_key(id))
+  }
 
-    // move the key into pkey to prevent an allocation and remove it after use
-    let pkey = PrivateKey::<K, T>::new(key);
-    let x: Option<Res<T>> = self.cache.get(&pkey).cloned();
-    let key = pkey.0;
+  /// Get the name of the type of a resource.
+  #[cfg(feature = "hot-reload")]
+  fn type_name(&self, id: KeyId) -> &'static str {
+    self.metadata.get(&id).map_or("<unknown>", |metadata| metadata.ty.name)
+  }
 
-    match x {
-      Some(resource) => Ok(resource),
-      None => {
-        let loaded =
-          <T as Load<C, K, M>>::load(key.clone(), self, ctx).map_err(StoreErrorOr::ResError)?;
-        self
-          .inject::<T, M>(key, loaded.res, loaded.deps)
-          .map_err(StoreErrorOr::StoreError)
-      }
-    }
+  /// Get the identifier of the key of a resource, if the resource is known.
+  fn lookup_known(&self, key: &K) -> Option<KeyId> {
+    self.lookup(key).filter(|id| self.metadata.contains_key(id))
   }
 
-  /// Get a resource from the [`Storage`] for the given key. If it fails, a proxied version is used,
-  /// which will get replaced by the resource once it’s available and reloaded.
+  /// Make a resource part of a reload group.
   ///
-  /// This function uses the default loading method.
-  pub fn get_proxied<T, P>(
-    &mut self,
-    key: &K,
-    proxy: P,
-    ctx: &mut C,
-  ) -> Result<Res<T>, StoreError<K>>
-  where T: Load<C, K>,
-        P: FnOnce() -> T {
-    self
-      .get(key, ctx)
-      .or_else(|_| self.inject::<T, ()>(key.clone().into(), proxy(), Vec::new()))
+  /// When several resources of the same group must be reloaded during a synchronization, they
+  /// are all reloaded first and replaced together only if every one of them reloaded
+  /// successfully. If any fails, none is replaced and the whole group is reloaded again the next
+  /// time one of its resources changes. This avoids observing a data set that is half new and half
+  /// old – e.g. all the chunks of a level.
+  ///
+  /// The resource doesn’t need to be loaded yet. A resource can belong to at most one group:
+  /// calling this function again moves it to the new group.
+  pub fn set_group<G>(&mut self, key: &K, group: G) where G: Into<String> {
+    let id = self.intern(key);
+    self.groups.insert(id, group.into());
   }
 
-  /// Get a resource from the [`Storage`] for the given key by using a specific method. If it fails, a
-  /// proxied version is used, which will get replaced by the resource once it’s available and
-  /// reloaded.
-  pub fn get_proxied_by<T, M, P>(
-    &mut self,
-    key: &K,
-    proxy: P,
-    ctx: &mut C,
-    method: M,
-  ) -> Result<Res<T>, StoreError<K>>
-  where T: Load<C, K, M>,
-        P: FnOnce() -> T {
-    self
-      .get_by(key, ctx, method)
-      .or_else(|_| self.inject::<T, M>(key.clone().into(), proxy(), Vec::new()))
+  /// Get the reload group a resource belongs to, if any.
+  pub fn group(&self, key: &K) -> Option<&str> {
+    self.lookup(key).and_then(|id| self.groups.get(&id)).map(String::as_str)
   }
-}
 
-/// Error that might happen when handling a resource store around.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum StoreError<K> {
-  /// The root path for a filesystem resource was not found.
-  RootDoesNotExist(PathBuf),
-  /// The key associated with a resource already exists in the [`Store`].
+  /// Get the timing of the last successful loading or reloading of a resource – e.g. to show how
+  /// long ago and how fast a shader was reloaded in a development HUD.
   ///
-  /// > Note: it is not currently possible to have two resources living in a [`Store`] and using an
-  /// > identical key at the same time.
-  AlreadyRegisteredKey(K),
-}
+  /// Return `None` if the resource is not loaded or is a proxy that hasn’t been replaced yet.
+  pub fn load_info(&self, key: &K) -> Option<LoadInfo> {
+    self.lookup_known(key).and_then(|id| self.metadata[&id].load_info)
+  }
 
-impl<K> Display for StoreError<K> where K: Display {
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    match *self {
-      StoreError::RootDoesNotExist(ref path) => write!(f, "root {} doesn’t exist", path.display()),
-      StoreError::AlreadyRegisteredKey(ref dk) => write!(f, "already registered key: {}", dk),
+  /// Get the file a resource was last successfully loaded or reloaded from – e.g. to know which
+  /// localized or variant file was picked, or whether it came from memory.
+  ///
+  /// This is the first file read with [`Storage::read_file`] by the loader of the resource; files
+  /// read while loading its dependencies belong to them. Return `None` if the resource is not
+  /// loaded, if it didn’t read any file or if provenance is not tracked – see
+  /// [`StoreOpt::set_track_provenance`].
+  pub fn provenance(&self, key: &K) -> Option<&Provenance> {
+    self.lookup_known(key).and_then(|id| self.metadata[&id].provenance.as_ref())
+  }
+
+  /// Check whether native dynamic libraries may be loaded – see [`StoreOpt::set_trust_dylibs`].
+  #[cfg(feature = "dylib-impl")]
+  pub fn trusts_dylibs(&self) -> bool {
+    self.trust_dylibs
+  }
+
+  /// Record the timing and provenance of a successful loading or reloading of a resource.
+  fn loaded_in(
+    &mut self,
+    id: KeyId,
+    duration: Duration,
+    provenance: Option<Provenance>,
+    reload: bool,
+  ) {
+    if let Some(metadata) = self.metadata.get_mut(&id) {
+      metadata.load_info = Some(LoadInfo {
+        at: Instant::now(),
+        duration,
+        reload,
+      });
+      metadata.provenance = provenance;
     }
   }
-}
 
-/// Either a store error or a resource loading error.
-pub enum StoreErrorOr<T, C, K, M = ()> where T: Load<C, K, M>, K: Key {
-  /// A store error.
-  StoreError(StoreError<K>),
-  /// A resource error.
-  ResError(T::Error),
-}
+  /// Load or reload a resource with `load`, tracking the first file it reads if provenance is
+  /// tracked.
+  fn tracking<F, R>(&mut self, load: F) -> (R, Option<Provenance>) where F: FnOnce(&mut Self) -> R {
+    if !self.track_provenance {
+      return (load(self), None);
+    }
 
-impl<T, C, K, M> Clone for StoreErrorOr<T, C, K, M>
-where T: Load<C, K, M>,
-      T::Error: Clone,
-      K: Key {
-  fn clone(&self) -> Self {
-    match *self {
-      StoreErrorOr::StoreError(ref e) => StoreErrorOr::StoreError(e.clone()),
-      StoreErrorOr::ResError(ref e) => StoreErrorOr::ResError(e.clone()),
+    self.loading.borrow_mut().push(None);
+    let r = load(self);
+    let provenance = self.loading.borrow_mut().pop().flatten();
+
+    (r, provenance)
+  }
+
+  /// Record a file read by the resource being loaded, unless it already read one.
+  fn record_read(&self, path: &Path, source: FileSource, content: &[u8]) {
+    if let Some(current @ None) = self.loading.borrow_mut().last_mut() {
+      let mut hasher = StableHasher::default();
+      hasher.write(content);
+
+      *current = Some(Provenance {
+        source,
+        path: path.to_owned(),
+        size: content.len() as u64,
+        hash: hasher.finish(),
+      });
     }
   }
-}
 
-impl<T, C, K, M> Eq for StoreErrorOr<T, C, K, M>
-where T: Load<C, K, M>,
-      T::Error: Eq,
-      K: Key {
-}
+  /// Attach a tag to a resource.
+  ///
+  /// Tags are free-form labels – e.g. the level or the screen a resource is used by – which the
+  /// bulk operations of [`Store`] act upon: [`Store::invalidate_tagged`] and
+  /// [`Store::remove_tagged`]. This is typically called in [`Load`] implementations, but the
+  /// resource doesn’t need to be loaded yet. A resource can have any number of tags.
+  pub fn add_tag<T>(&mut self, key: &K, tag: T) where T: Into<String> {
+    let id = self.intern(key);
+    self.tags.entry(id).or_default().insert(tag.into());
+  }
 
-impl<T, C, K, M> PartialEq for StoreErrorOr<T, C, K, M>
-where T: Load<C, K, M>,
-      T::Error: PartialEq,
-      K: Key {
-  fn eq(&self, rhs: &Self) -> bool {
-    match (self, rhs) {
-      (&StoreErrorOr::StoreError(ref a), &StoreErrorOr::StoreError(ref b)) => a == b,
-      (&StoreErrorOr::ResError(ref a), &StoreErrorOr::ResError(ref b)) => a == b,
-      _ => false,
+  /// Detach a tag from a resource.
+  ///
+  /// Return `false` if the resource didn’t have the tag.
+  pub fn remove_tag(&mut self, key: &K, tag: &str) -> bool {
+    let id = match self.lookup(key) {
+      Some(id) => id,
+      None => return false,
+    };
+
+    match self.tags.get_mut(&id) {
+      Some(tags) => {
+        let removed = tags.remove(tag);
+
+        if tags.is_empty() {
+          self.tags.remove(&id);
+        }
+
+        removed
+      }
+
+      None => false,
     }
   }
-}
 
-impl<T, C, K, M> fmt::Debug for StoreErrorOr<T, C, K, M>
-where T: Load<C, K, M>,
-      T::Error: fmt::Debug,
-      K: Key + fmt::Debug {
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    match *self {
-      StoreErrorOr::StoreError(ref e) => f.debug_tuple("StoreError").field(e).finish(),
-      StoreErrorOr::ResError(ref e) => f.debug_tuple("ResError").field(e).finish(),
+  /// Check whether a resource has a tag.
+  pub fn has_tag(&self, key: &K, tag: &str) -> bool {
+    self.lookup(key).and_then(|id| self.tags.get(&id)).is_some_and(|tags| tags.contains(tag))
+  }
+
+  /// Get the tags of a resource, in no particular order.
+  pub fn tags(&self, key: &K) -> Vec<&str> {
+    self
+      .lookup(key)
+      .and_then(|id| self.tags.get(&id))
+      .map_or_else(Vec::new, |tags| tags.iter().map(String::as_str).collect())
+  }
+
+  /// Get the prepared keys of all the resources having a tag, loaded or not, in no particular
+  /// order.
+  pub fn keys_tagged(&self, tag: &str) -> Vec<K> {
+    self.tagged(tag).into_iter().map(|id| self.keys.key(id).clone()).collect()
+  }
+
+  /// Identifiers of all the keys having a tag.
+  fn tagged(&self, tag: &str) -> Vec<KeyId> {
+    self.tags.iter().filter(|(_, tags)| tags.contains(tag)).map(|(&id, _)| id).collect()
+  }
+
+  /// Forget everything about a loaded resource but its group and priority, so that it’s loaded
+  /// again the next time it’s asked for.
+  ///
+  /// Return `false` if the resource is not loaded.
+  fn evict(&mut self, id: KeyId) -> bool {
+    let metadata = match self.metadata.remove(&id) {
+      Some(metadata) => metadata,
+      None => return false,
+    };
+
+    self.cache.remove(id, metadata.ty.id());
+    self.tags.remove(&id);
+    self.ttls.remove(&id);
+    self.globs.remove(&id);
+    self.localized.remove(&id);
+    self.varied.remove(&id);
+    self.combined.remove(&id);
+    self.formats.remove(&id);
+
+    // the resource doesn’t depend on anything anymore
+    for dependents in self.deps.values_mut() {
+      dependents.retain(|&dependent| dependent != id);
     }
+
+    self.dep_events.retain(|&(_, dependent), _| dependent != id);
+
+    true
   }
-}
 
-impl<T, C, K, M> Display for StoreErrorOr<T, C, K, M>
-where T: Load<C, K, M>,
-      T::Error: fmt::Debug,
-      K: Key + Display {
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    match *self {
-      StoreErrorOr::StoreError(ref e) => e.fmt(f),
-      StoreErrorOr::ResError(ref e) => e.fmt(f),
+  /// Set the reload priority of a resource.
+  ///
+  /// When several resources must be reloaded during the same synchronization, the ones with the
+  /// highest priority are reloaded first. Combined with [`StoreOpt::set_sync_budget`], this
+  /// ensures that important resources (e.g. cheap gameplay configuration) are reloaded as soon
+  /// as possible while expensive ones (e.g. textures) might be postponed to later
+  /// synchronizations.
+  ///
+  /// Dependencies are always reloaded before their dependents, so they inherit the priority of
+  /// the resources depending on them when it’s higher than theirs.
+  ///
+  /// Resources have a priority of `0` by default.
+  pub fn set_priority(&mut self, key: &K, priority: i32) {
+    let id = self.intern(key);
+    self.priorities.insert(id, priority);
+  }
+
+  /// Get the reload priority of a resource.
+  pub fn priority(&self, key: &K) -> i32 {
+    self.lookup(key).and_then(|id| self.priorities.get(&id)).cloned().unwrap_or(0)
+  }
+
+  /// Set the time-to-live of a resource.
+  ///
+  /// Once the time-to-live has elapsed since the resource was last (re)loaded, it’s automatically
+  /// reloaded on the next synchronization. This is especially useful for logical resources backed
+  /// by data that doesn’t live on the filesystem (a database query, a remote API, etc.), as no
+  /// filesystem event will ever tell when they change.
+  pub fn set_ttl(&mut self, key: &K, ttl: Duration) {
+    let id = self.intern(key);
+    let expires_at = Instant::now() + ttl;
+    self.ttls.insert(id, Ttl { ttl, expires_at });
+  }
+
+  /// Stop refreshing a resource periodically.
+  pub fn unset_ttl(&mut self, key: &K) {
+    if let Some(id) = self.lookup(key) {
+      self.ttls.remove(&id);
     }
   }
-}
 
-/// Resource synchronizer.
-///
-/// An object of this type is responsible to synchronize resources living in a store. It keeps in
-/// internal, optimized state to perform correct and efficient synchronization.
-struct Synchronizer<C, K> {
-  // all the resources that must be reloaded; they’re mapped to the instant they were found updated
-  dirties: HashSet<K>,
-  // keep the watcher around so that we don’t have it disconnected
-  #[allow(dead_code)]
-  watcher: RecommendedWatcher,
-  // watcher receiver part of the channel
-  watcher_rx: Receiver<DebouncedEvent>,
-  // used to accept or ignore new discoveries
-  discovery: Discovery<C, K>
-}
+  /// Get the time-to-live of a resource, if any.
+  pub fn ttl(&self, key: &K) -> Option<Duration> {
+    self.lookup(key).and_then(|id| self.ttls.get(&id)).map(|ttl| ttl.ttl)
+  }
 
-impl<C, K> Synchronizer<C, K> where K: Key {
-  fn new(
-    watcher: RecommendedWatcher,
-    watcher_rx: Receiver<DebouncedEvent>,
-    discovery: Discovery<C, K>
-  ) -> Self {
-    Synchronizer {
-      dirties: HashSet::new(),
-      watcher,
-      watcher_rx,
-      discovery
+  /// Expand a glob pattern into the list of matching files and make a resource depend on it.
+  ///
+  /// This is meant to be used in [`Load`] implementations to build collection resources – e.g. all
+  /// the maps described by `"maps/*.json"`. The pattern is relative to the root of the store: `*`
+  /// doesn’t match path separators while `**` matches any number of directories. Matching files
+  /// are returned sorted, as VFS paths – i.e. starting with a `/` – so that they can be used as
+  /// keys and dependencies directly.
+  ///
+  /// `key` is the key of the resource being loaded, as passed to [`Load::load`]: the resource is
+  /// reloaded whenever a file matching the pattern is added or removed. You should also declare
+  /// the matching files as dependencies so that the resource is reloaded when they change.
+  ///
+  /// # Failures
+  ///
+  /// This function fails if the pattern is not a valid glob pattern.
+  pub fn glob(&mut self, key: &K, pattern: &str) -> Result<Vec<PathBuf>, PatternError> {
+    let prefix = literal_prefix(pattern);
+    let pattern = Pattern::new(pattern)?;
+    let mut files = Vec::new();
+
+    // only the directory all the matching files live in needs to be scanned
+    scan_dir(&self.canon_root.join(prefix), &mut files);
+
+    let mut matched: Vec<PathBuf> = files
+      .iter()
+      .filter_map(|path| path.strip_prefix(&self.canon_root).ok())
+      .filter(|path| pattern.matches_path_with(path, glob_options()))
+      .map(|path| Path::new("/").join(path))
+      .collect();
+    matched.sort();
+
+    // the key was already prepared by the storage
+    let id = self.keys.intern_ref(key);
+    let patterns = self.globs.entry(id).or_default();
+
+    if !patterns.contains(&pattern) {
+      patterns.push(pattern);
     }
+
+    Ok(matched)
   }
 
-  /// Dequeue any file system events.
-  fn dequeue_fs_events(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) where K: for<'a> From<&'a Path> {
-    for event in self.watcher_rx.try_iter() {
-      match event {
-        DebouncedEvent::Write(ref path) | DebouncedEvent::Create(ref path) => {
-          let key = path.as_path().into();
+  /// Get the locales used to localize files, by decreasing preference.
+  pub fn locales(&self) -> &[String] {
+    &self.locales
+  }
 
-          if storage.metadata.contains_key(&key) {
-            self.dirties.insert(key);
-          } else {
-            self.discovery.discover(path, storage, ctx);
-          }
+  /// Localize a file according to the locales of the store.
+  ///
+  /// The localized versions of a file are named after it, with the locale inserted before the
+  /// extension – e.g. `strings.fr.json` for `strings.json`. This function returns the path to the
+  /// first localized version that exists, following the order of the locales, or `path` if none
+  /// exists. `path` is typically the (absolute) path held by the key of the resource being loaded.
+  ///
+  /// `key` is the key of the resource being loaded, as passed to [`Load::load`]: it’s made to
+  /// depend on all the candidate files, so that the resource gets reloaded whenever one of them is
+  /// created, changed or removed. It’s also reloaded when the locales change – see
+  /// [`Store::set_locales`].
+  pub fn localize(&mut self, key: &K, path: &Path) -> PathBuf where K: for<'a> From<&'a Path> {
+    let id = self.keys.intern_ref(key);
+    self.localized.insert(id);
+
+    let locales = self.locales.clone();
+    self.resolve_suffixed(id, path, &locales)
+  }
+
+  /// Get the variants used to select files, by decreasing preference.
+  pub fn variants(&self) -> &[String] {
+    &self.variants
+  }
+
+  /// Select the variant of a file according to the variants of the store.
+  ///
+  /// Variants work exactly like locales – see [`Storage::localize`] – but are meant to pick a file
+  /// per platform or per quality tier: with the `["hi", "windows"]` variants, `texture.png`
+  /// resolves to `texture.hi.png` if it exists, then `texture.windows.png`, then `texture.png`.
+  /// The selection is evaluated again each time the resource is reloaded and all the resources
+  /// using variants are reloaded when the variants change – see [`Store::set_variants`].
+  ///
+  /// Variants and locales can be combined by localizing the selected variant.
+  pub fn select_variant(&mut self, key: &K, path: &Path) -> PathBuf
+  where K: for<'a> From<&'a Path> {
+    let id = self.keys.intern_ref(key);
+    self.varied.insert(id);
+
+    let variants = self.variants.clone();
+    self.resolve_suffixed(id, path, &variants)
+  }
+
+  /// Make a resource depend on a file, so that it gets reloaded whenever the file changes.
+  ///
+  /// The file doesn’t have to be a resource, nor to exist: a resource depending on a file that is
+  /// created later is reloaded when it gets created. Unlike [`Loaded::with_deps`], this can be used
+  /// while reloading, which suits resources which dependencies change along with their content.
+  pub fn depend_on_file(&mut self, key: &K, path: &Path) where K: for<'a> From<&'a Path> {
+    let id = self.keys.intern_ref(key);
+    self.resolve_suffixed(id, path, &[]);
+  }
+
+  /// Replace the dependencies of a resource.
+  ///
+  /// The dependencies returned by [`Load::load`] are only registered when a resource is first
+  /// loaded. Call this in [`Load::reload`] so that a resource which dependencies change along with
+  /// its content – e.g. a script importing other files – keeps depending on the right ones. All the
+  /// previous dependencies are dropped, including the files the resource was made to depend on:
+  /// call this before declaring those again.
+  pub fn set_deps<I>(&mut self, key: &K, deps: I) where I: IntoIterator<Item = K> {
+    let id = self.intern(key);
+
+    #[cfg(feature = "hot-reload")]
+    {
+      for dependents in self.deps.values_mut() {
+        dependents.retain(|&dependent| dependent != id);
+      }
+
+      for dep in deps {
+        let dep = self.intern(&dep);
+
+        // a resource depending on itself would reload forever
+        if dep == id {
+          continue;
         }
 
-        _ => (),
+        self.deps.entry(dep).or_default().push(id);
       }
     }
+
+    // dependencies are only tracked to reload dependents
+    #[cfg(not(feature = "hot-reload"))]
+    let _ = (id, deps);
   }
 
-  /// Reload any dirty resource that fulfill its time predicate.
-  fn reload_dirties(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) {
-    self.dirties.retain(|dep_key| {
-      if let Some(metadata) = storage.metadata.remove(&dep_key) {
-        if (metadata.on_reload)(storage, ctx).is_ok() {
-          // if we have successfully reloaded the resource, notify the observers that this
-          // dependency has changed
-          if let Some(deps) = storage.deps.get(&dep_key).cloned() {
-            for dep in deps {
-              if let Some(obs_metadata) = storage.metadata.remove(&dep) {
-                // FIXME: decide what to do with the result (error?)
-                let _ = (obs_metadata.on_reload)(storage, ctx);
-
-                // reinject the dependency once afterwards
-                storage.metadata.insert(dep, obs_metadata);
-              }
-            }
-          }
+  /// Choose the filesystem events of a dependency that reload a resource – e.g. a resource listing
+  /// a directory only cares about files being created or removed in it, not written to.
+  ///
+  /// Events of the files inside a directory count as events of the directory, so that resources
+  /// can depend on whole directories. Dependencies on resources are not concerned: their
+  /// dependents are reloaded whenever they are. The dependency doesn’t need to be declared yet.
+  ///
+  /// Dependencies are reloaded by [`DepEvents::default`] otherwise.
+  pub fn set_dep_events(&mut self, key: &K, dep: &K, events: DepEvents) {
+    let id = self.intern(key);
+    let dep = self.intern(dep);
+    self.dep_events.insert((dep, id), events);
+  }
+
+  /// Get the filesystem events of a dependency that reload a resource.
+  pub fn dep_events(&self, key: &K, dep: &K) -> DepEvents {
+    match (self.lookup(key), self.lookup(dep)) {
+      (Some(id), Some(dep)) => self.dep_events_of(dep, id),
+      _ => DepEvents::default(),
+    }
+  }
+
+  /// Get the filesystem events of a dependency that reload a dependent.
+  fn dep_events_of(&self, dep: KeyId, id: KeyId) -> DepEvents {
+    self.dep_events.get(&(dep, id)).cloned().unwrap_or_default()
+  }
+
+  /// Compute the length of the longest chain of resources each resource depends on.
+  ///
+  /// Resources not depending on other resources are not in the returned map and have a depth of
+  /// `0`. Cycles are broken arbitrarily.
+  #[cfg(feature = "hot-reload")]
+  fn dependency_depths(&self) -> HashMap<KeyId, usize> {
+    fn depth(
+      id: KeyId,
+      dependencies: &HashMap<KeyId, Vec<KeyId>>,
+      depths: &mut HashMap<KeyId, usize>,
+      visiting: &mut HashSet<KeyId>,
+    ) -> usize {
+      if let Some(&depth) = depths.get(&id) {
+        return depth;
+      }
+
+      let deps = match dependencies.get(&id) {
+        Some(deps) if visiting.insert(id) => deps,
+        _ => return 0,
+      };
+      let d = deps.iter().map(|&dep| depth(dep, dependencies, depths, visiting) + 1).max().unwrap_or(0);
+
+      visiting.remove(&id);
+      depths.insert(id, d);
+      d
+    }
+
+    // resources mapped to the resources they depend on
+    let mut dependencies: HashMap<KeyId, Vec<KeyId>> = HashMap::default();
+
+    for (&dep, dependents) in &self.deps {
+      if self.metadata.contains_key(&dep) {
+        for &id in dependents {
+          dependencies.entry(id).or_default().push(dep);
+        }
+      }
+    }
+
+    let mut depths = HashMap::default();
+    let mut visiting = HashSet::default();
+
+    for &id in dependencies.keys() {
+      depth(id, &dependencies, &mut depths, &mut visiting);
+    }
+
+    depths
+  }
+
+  /// Compute the reload priority of each resource, raised to the priority of the resources
+  /// depending on it – directly or not – so that a dependency is never reloaded after a more
+  /// important dependent.
+  ///
+  /// Resources not in the returned map have a priority of `0`.
+  #[cfg(feature = "hot-reload")]
+  fn inherited_priorities(&self) -> HashMap<KeyId, i32> {
+    let mut priorities = self.priorities.clone();
+    let mut pending: Vec<(KeyId, i32)> = priorities.iter().map(|(&id, &p)| (id, p)).collect();
+
+    while let Some((id, priority)) = pending.pop() {
+      for (&dep, dependents) in &self.deps {
+        if !dependents.contains(&id) {
+          continue;
         }
 
-        storage.metadata.insert(dep_key.clone(), metadata);
+        let dep_priority = priorities.entry(dep).or_insert(0);
+
+        // priorities only ever rise, so cycles come to an end
+        if *dep_priority < priority {
+          *dep_priority = priority;
+          pending.push((dep, priority));
+        }
       }
+    }
+
+    priorities
+  }
+
+  /// Resolve a path relative to the file of a resource into a key – e.g. the textures a material
+  /// references next to its own file.
+  ///
+  /// The path is relative to the directory of the file of `current`, which can be either the
+  /// prepared key passed to [`Load::load`] or a key as provided by the user. `.` and `..` are
+  /// resolved and the returned key is a VFS path, ready to be used as a dependency or to get a
+  /// resource. Paths starting with a `/` are relative to the root, as well as all the paths if
+  /// `current` doesn’t address a file. Paths escaping the root are kept as is, so that getting them
+  /// fails with [`StoreError::KeyOutsideRoot`].
+  pub fn resolve_relative<P>(&self, current: &K, path: P) -> K
+  where K: for<'a> From<&'a Path>,
+        P: AsRef<Path> {
+    let dir = current.fs_path().and_then(|file| {
+      // keys passed to loaders are prepared, but the ones provided by the user are not
+      let file = if file.starts_with(&self.canon_root) {
+        file.to_owned()
+      } else {
+        vfs_substitute_path(file, &self.canon_root)
+      };
 
-      false
+      file.parent()?.strip_prefix(&self.canon_root).ok().map(Path::to_owned)
     });
+
+    let vfs_path = Path::new("/").join(dir.unwrap_or_default()).join(path);
+    let resolved = vfs_substitute_path(&vfs_path, &self.canon_root);
+
+    match resolved.strip_prefix(&self.canon_root) {
+      Ok(inside) => K::from(&Path::new("/").join(inside)),
+      Err(_) => K::from(&vfs_path),
+    }
+  }
+
+  /// Read the whole content of a file.
+  ///
+  /// Loaders should read their files with this function rather than with [`std::fs`], so that they
+  /// can be tested with a [`MockStore`], which files live in memory. The loaders provided by this
+  /// crate do.
+  ///
+  /// [`MockStore`]: crate::testing::MockStore
+  pub fn read_file(&self, path: &Path) -> io::Result<Vec<u8>> {
+    let (content, source) = match self.files {
+      Some(ref files) => {
+        let content = files.get(path).cloned().ok_or(io::ErrorKind::NotFound)?;
+        (content, FileSource::Memory)
+      }
+
+      None => (fs::read(path)?, FileSource::Filesystem),
+    };
+
+    self.record_read(path, source, &content);
+    Ok(content)
+  }
+
+  /// Read the whole content of a UTF-8 file.
+  ///
+  /// See [`Storage::read_file`] for further details.
+  pub fn read_file_to_string(&self, path: &Path) -> io::Result<String> {
+    let content = self.read_file(path)?;
+    String::from_utf8(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+
+  /// Get the metadata of a file – e.g. to validate a cache or to log the size of what’s loaded.
+  ///
+  /// See [`Storage::read_file`] for further details.
+  pub fn file_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+    match self.files {
+      Some(ref files) => match files.get(path) {
+        Some(content) => Ok(FileMetadata {
+          size: content.len() as u64,
+          modified: None,
+        }),
+        None => Err(io::ErrorKind::NotFound.into()),
+      },
+
+      None => {
+        let metadata = fs::metadata(path)?;
+
+        Ok(FileMetadata {
+          size: metadata.len(),
+          modified: metadata.modified().ok(),
+        })
+      }
+    }
+  }
+
+  /// Get the metadata of the file of a key.
+  ///
+  /// This is meant to be called by loaders with the key they’re loading, which is prepared: it
+  /// addresses the file to load – see [`Key::fs_path`].
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`io::ErrorKind::InvalidInput`] if the key doesn’t address a file,
+  /// and for the same reasons as [`Storage::file_metadata`] otherwise.
+  pub fn key_metadata(&self, key: &K) -> io::Result<FileMetadata> {
+    match key.fs_path() {
+      Some(path) => self.file_metadata(path),
+      None => Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("key {:?} doesn’t address a file", Described(key)),
+      )),
+    }
+  }
+
+  /// Check whether the file of a key exists, if the key addresses a file.
+  pub(crate) fn key_file_exists(&self, key: &K) -> Option<bool> {
+    let prepared = self.prepare(key);
+    prepared.fs_path().map(|path| self.is_file(path))
+  }
+
+  /// Check whether a file exists.
+  fn is_file(&self, path: &Path) -> bool {
+    match self.files {
+      Some(ref files) => files.contains_key(path),
+      None => path.is_file(),
+    }
+  }
+
+  /// In-memory files replacing the filesystem.
+  ///
+  /// # Panics
+  ///
+  /// This function panics if the files live on the filesystem.
+  pub(crate) fn files(&self) -> &HashMap<PathBuf, Vec<u8>> {
+    self.files.as_ref().expect("files live on the filesystem")
+  }
+
+  /// In-memory files replacing the filesystem, mutably.
+  ///
+  /// # Panics
+  ///
+  /// This function panics if the files live on the filesystem.
+  pub(crate) fn files_mut(&mut self) -> &mut HashMap<PathBuf, Vec<u8>> {
+    self.files.as_mut().expect("files live on the filesystem")
+  }
+
+  /// Get the loaders registered at runtime.
+  pub fn registry(&self) -> &LoaderRegistry<C, K> {
+    &self.registry
+  }
+
+  /// Get the loaders registered at runtime, to register new ones – e.g. when a plugin is loaded.
+  ///
+  /// Resources already loaded are not affected until they get reloaded.
+  pub fn registry_mut(&mut self) -> &mut LoaderRegistry<C, K> {
+    &mut self.registry
+  }
+
+  /// Make the [`Storage`] read-only, or not.
+  ///
+  /// A read-only storage only gets resources that are already loaded: getting any other resource
+  /// fails with [`StoreError::CacheMiss`] instead of touching the filesystem. Once everything is
+  /// preloaded, this enforces a “no synchronous IO during gameplay” policy. Proxies are still
+  /// injected – see [`Storage::get_proxied`] – and reloads are not affected.
+  pub fn set_read_only(&mut self, read_only: bool) {
+    self.read_only = read_only;
+  }
+
+  /// Check whether the [`Storage`] is read-only.
+  pub fn is_read_only(&self) -> bool {
+    self.read_only
+  }
+
+  /// Get the output of an expensive transform of the source of a resource – e.g. compressing a
+  /// texture or compiling a shader – from the derived cache, or run the transform and cache it.
+  ///
+  /// Outputs are stored on disk in the directory set with [`StoreOpt::set_derived_cache`], keyed by
+  /// `key`, `transform` and the hash of `source`: they are reused across runs as long as the source
+  /// doesn’t change. When it does, the transform runs again and the output of the previous source
+  /// is removed. Put a version in the name of a transform – e.g. `"bc7-v2"` – to invalidate its
+  /// outputs whenever you change it. Keys are identified by the path they address – see
+  /// [`Key::fs_path`] – or by their description otherwise – see [`Key::describe`].
+  ///
+  /// Without a derived cache, the transform always runs. Failing to read or write the cache is not
+  /// an error: the transform runs and its output is not cached.
+  pub fn derive<F, E>(&self, key: &K, transform: &str, source: &[u8], f: F) -> Result<Vec<u8>, E>
+  where F: FnOnce(&[u8]) -> Result<Vec<u8>, E> {
+    let dir = match self.derived_cache {
+      Some(ref dir) => dir,
+      None => return f(source),
+    };
+
+    let entry = derived_entry(key, transform);
+
+    let mut hasher = StableHasher::default();
+    hasher.write(source);
+    let path = dir.join(format!("{:016x}.{:016x}", entry, hasher.finish()));
+
+    if let Ok(output) = fs::read(&path) {
+      log_record!(debug, "derived cache hit for {:?} ({})", Described(key), transform);
+      record_metrics! {
+        metrics::counter!("warmy_derived_cache_hits_total").increment(1);
+      }
+
+      return Ok(output);
+    }
+
+    record_metrics! {
+      metrics::counter!("warmy_derived_cache_misses_total").increment(1);
+    }
+
+    let output = f(source)?;
+
+    if let Err(e) = write_derived(dir, entry, &path, &output) {
+      log_record!(warn, "cannot cache derived data in {}: {}", path.display(), e);
+    }
+
+    Ok(output)
+  }
+
+  /// Find the first existing file among the suffixed versions of `path` and `path` itself, making
+  /// the resource depend on all of them.
+  fn resolve_suffixed(&mut self, id: KeyId, path: &Path, suffixes: &[String]) -> PathBuf
+  where K: for<'a> From<&'a Path> {
+    let candidates = suffixes.iter().map(|suffix| with_suffix(path, suffix));
+    let candidates: Vec<PathBuf> = candidates.chain(Some(path.to_owned())).collect();
+
+    for candidate in &candidates {
+      let dep = K::from(candidate).normalize(&self.normalization);
+      let dep = self.keys.intern(dep);
+      let dependents = self.deps.entry(dep).or_default();
+
+      if !dependents.contains(&id) {
+        dependents.push(id);
+      }
+    }
+
+    let chosen = candidates.iter().find(|candidate| self.is_file(candidate));
+    chosen.unwrap_or(&candidates[candidates.len() - 1]).clone()
+  }
+
+  /// Inject a new resource in the store.
+  ///
+  /// The resource might be refused for several reasons. Further information in the documentation of
+  /// the [`StoreError`] error type.
+  fn inject<T, M>(
+    &mut self,
+    id: KeyId,
+    resource: T,
+    deps: Vec<K>,
+  ) -> Result<Res<T>, StoreError<K>>
+  where T: Load<C, K, M> {
+    // we forbid having two resources sharing the same key
+    if self.metadata.contains_key(&id) {
+      return Err(StoreError::AlreadyRegisteredKey(self.keys.key(id).clone()));
+    }
+
+    // wrap the resource to make it shared mutably
+    let res = Res::new(resource);
+
+    // create the metadata for the resource
+    #[cfg(feature = "hot-reload")]
+    let metadata = {
+      let on_reload = reloader::<T, C, K, M>(res.clone(), self.keys.key(id).clone());
+      let borrowed = {
+        let res = res.clone();
+        Rc::new(move || res.is_borrowed())
+      };
+      ResMetaData::new(ResourceType::of::<T>(), on_reload, borrowed)
+    };
+    #[cfg(not(feature = "hot-reload"))]
+    let metadata = ResMetaData::new(ResourceType::of::<T>());
+
+    self.metadata.insert(id, metadata);
+
+    // register the resource as an observer of its dependencies in the dependencies graph
+    #[cfg(feature = "hot-reload")]
+    for dep in deps {
+      let dep = self.intern(&dep);
+
+      // a resource depending on itself would reload forever
+      if dep == id {
+        continue;
+      }
+
+      let dependents = self.deps.entry(dep).or_default();
+
+      if !dependents.contains(&id) {
+        dependents.push(id);
+      }
+    }
+
+    // dependencies are only tracked to reload dependents
+    #[cfg(not(feature = "hot-reload"))]
+    let _ = deps;
+
+    // cache the resource
+    self.cache.save(id, res.clone());
+
+    Ok(res)
+  }
+
+  /// Get a resource from the [`Storage`] and return an error if its loading failed.
+  ///
+  /// This function uses the default loading method.
+  pub fn get<T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C, K>>
+  where T: Load<C, K> {
+    self.get_by(key, ctx, ())
+  }
+
+  /// Get a resource from the [`Storage`] by using a specific method and return and error if its
+  /// loading failed.
+  pub fn get_by<T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    _: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
+  where T: Load<C, K, M> {
+    self.fetch::<T, M>(key, ctx)
+  }
+
+  /// Get a resource from the [`Storage`] by using a loader built with combinators and return an
+  /// error if its loading failed.
+  ///
+  /// The loader is kept to reload the resource. It’s dropped if the resource is already loaded.
+  /// See the [`combinator`] module for further details.
+  ///
+  /// [`combinator`]: crate::combinator
+  pub fn get_with<L>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    loader: L,
+  ) -> Fetched<L::Res, C, K, Combined<L>>
+  where L: Loader<C, K> {
+    let id = self.resolve(key).map_err(StoreErrorOr::StoreError)?;
+
+    if !self.metadata.contains_key(&id) {
+      self.combined.insert(id, Rc::new(loader));
+    }
+
+    self.fetch::<L::Res, Combined<L>>(key, ctx)
+  }
+
+  /// Get a resource from the [`Storage`] with the loader registered for a format chosen at runtime
+  /// and return an error if its loading failed.
+  ///
+  /// The [`Registered`] method picks the loader from the extension of the file of the resource.
+  /// Data-driven pipelines often decide the format from elsewhere – e.g. a field of a manifest.
+  /// This function picks the loader registered for `T` and `format` in the [`LoaderRegistry`]
+  /// instead, whatever the extension of the file. The format is kept to reload the resource; it’s
+  /// ignored if the resource is already loaded. See the [`registry`] module for further details.
+  ///
+  /// [`Registered`]: crate::registry::Registered
+  /// [`registry`]: crate::registry
+  pub fn get_dyn<T>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    format: &str,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, RegisteredAs>>
+  where T: Load<C, K, RegisteredAs> {
+    let id = self.resolve(key).map_err(StoreErrorOr::StoreError)?;
+
+    if !self.metadata.contains_key(&id) {
+      self.formats.insert(id, format.to_lowercase());
+    }
+
+    self.fetch::<T, RegisteredAs>(key, ctx)
+  }
+
+  /// Get the format chosen to load a resource with [`Storage::get_dyn`], given its prepared key.
+  pub(crate) fn format(&self, key: &K) -> Option<&str> {
+    self.keys.id(key).and_then(|id| self.formats.get(&id)).map(String::as_str)
+  }
+
+  /// Get the loader of a resource loaded with [`Storage::get_with`], given its prepared key.
+  pub(crate) fn combined_loader<L>(&self, key: &K) -> Rc<L> where L: Loader<C, K> {
+    self
+      .keys
+      .id(key)
+      .and_then(|id| self.combined.get(&id))
+      .and_then(|loader| loader.clone().downcast().ok())
+      .expect("loader of a resource loaded with combinators")
+  }
+
+  /// Get a resource from the [`Storage`] with a read-only context and return an error if its
+  /// loading failed.
+  ///
+  /// This function uses the default loading method. See [`LoadShared`] for further details.
+  pub fn get_shared<T>(
+    &mut self,
+    key: &K,
+    ctx: &C,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, Shared>>
+  where T: LoadShared<C, K> {
+    self.get_shared_by(key, ctx, ())
+  }
+
+  /// Get a resource from the [`Storage`] with a read-only context by using a specific method and
+  /// return an error if its loading failed.
+  pub fn get_shared_by<T, M>(
+    &mut self,
+    key: &K,
+    ctx: &C,
+    _: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, Shared<M>>>
+  where T: LoadShared<C, K, M> {
+    self.fetch_with::<T, Shared<M>, _>(key, |key, storage| {
+      <T as LoadShared<C, K, M>>::load(key, storage, ctx)
+    })
+  }
+
+  /// Get a resource from the [`Storage`] by using a specific method, known only by its type.
+  fn fetch<T, M>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
+  where T: Load<C, K, M> {
+    self.fetch_with::<T, M, _>(key, |key, storage| <T as Load<C, K, M>>::load(key, storage, ctx))
+  }
+
+  /// Get a resource from the [`Storage`] by using a specific method, loading it with `load` if
+  /// it’s not cached yet.
+  fn fetch_with<T, M, F>(&mut self, key: &K, load: F) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
+  where T: Load<C, K, M>,
+        F: FnOnce(K, &mut Self) -> Result<Loaded<T, K>, T::Error> {
+    // known keys are resolved and looked up without being cloned
+    let id = self.resolve(key).map_err(StoreErrorOr::StoreError)?;
+
+    if let Some(resource) = self.cache.get::<T>(id) {
+      return Ok(resource.clone());
+    }
+
+    if self.read_only {
+      return Err(StoreErrorOr::StoreError(StoreError::CacheMiss(key.clone())));
+    }
+
+    let key = self.keys.key(id).clone();
+    let load_start = Instant::now();
+    let (loaded, provenance) = {
+      enter_span!("load", key = %Described(&key), ty = any::type_name::<T>());
+      self.tracking(|storage| load(key, storage))
+    };
+    let duration = load_start.elapsed();
+
+    record_metrics! {
+      metrics::histogram!("warmy_load_duration_seconds", "type" => any::type_name::<T>())
+        .record(duration);
+    }
+
+    self.complete_load::<T, M>(id, loaded, duration, provenance)
+  }
+
+  /// Inject a freshly loaded resource, or report why it failed to load.
+  fn complete_load<T, M>(
+    &mut self,
+    id: KeyId,
+    loaded: Result<Loaded<T, K>, T::Error>,
+    duration: Duration,
+    provenance: Option<Provenance>,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
+  where T: Load<C, K, M> {
+    record_metrics! {
+      let ty = any::type_name::<T>();
+      metrics::counter!("warmy_loads_total", "type" => ty).increment(1);
+
+      if loaded.is_err() {
+        metrics::counter!("warmy_load_failures_total", "type" => ty).increment(1);
+      }
+    }
+
+    let loaded = match loaded {
+      Ok(loaded) => loaded,
+      Err(e) => {
+        let ty = any::type_name::<T>();
+        log_record!(warn, "cannot load {:?} as {}: {}", Described(self.keys.key(id)), ty, e);
+        return Err(StoreErrorOr::ResError(e));
+      }
+    };
+    let res = self
+      .inject::<T, M>(id, loaded.res, loaded.deps)
+      .map_err(StoreErrorOr::StoreError)?;
+    self.loaded_in(id, duration, provenance, false);
+
+    let key = self.keys.key(id);
+    log_record!(debug, "loaded {:?} as {}", Described(key), any::type_name::<T>());
+    self.observer.loaded(key);
+
+    #[cfg(feature = "arc")]
+    self.broadcast(StoreEvent::Loaded(key.clone()));
+
+    #[cfg(feature = "hot-reload")]
+    self.record(|storage| Some(RecordedEvent::Loaded(format!("{:?}", Described(storage.keys.key(id))))));
+
+    Ok(res)
+  }
+
+  /// Get several resources from the [`Storage`], loading the ones that are not cached yet in
+  /// parallel, and return the result of each of them, in the order of the keys.
+  ///
+  /// This function uses the default loading method. See [`LoadPar`] for further details.
+  #[cfg(feature = "rayon")]
+  pub fn get_many_par<T>(
+    &mut self,
+    keys: &[K],
+    ctx: &C,
+  ) -> Vec<Fetched<T, C, K>>
+  where T: LoadPar<C, K>,
+        C: Sync,
+        K: Send + Sync {
+    self.get_many_par_by(keys, ctx, ())
+  }
+
+  /// Get several resources from the [`Storage`] by using a specific method, loading the ones that
+  /// are not cached yet in parallel, and return the result of each of them, in the order of the
+  /// keys.
+  ///
+  /// Resources are loaded on the [rayon] global thread pool and injected afterwards, on the calling
+  /// thread. Keys should be distinct: a key appearing several times is loaded as many times,
+  /// although only one resource is injected.
+  ///
+  /// [rayon]: https://crates.io/crates/rayon
+  #[cfg(feature = "rayon")]
+  pub fn get_many_par_by<T, M>(
+    &mut self,
+    keys: &[K],
+    ctx: &C,
+    _: M,
+  ) -> Vec<Fetched<T, C, K, M>>
+  where T: LoadPar<C, K, M>,
+        C: Sync,
+        K: Send + Sync {
+    // cached resources are returned right away and the other ones are loaded in parallel
+    let mut results = Vec::with_capacity(keys.len());
+    let mut misses = Vec::new();
+
+    for key in keys {
+      match self.resolve(key) {
+        Ok(id) => match self.cache.get::<T>(id) {
+          Some(res) => results.push(Some(Ok(res.clone()))),
+          None if self.read_only => {
+            results.push(Some(Err(StoreErrorOr::StoreError(StoreError::CacheMiss(key.clone())))))
+          }
+          None => {
+            misses.push((results.len(), id, self.keys.key(id).clone()));
+            results.push(None);
+          }
+        },
+
+        Err(e) => results.push(Some(Err(StoreErrorOr::StoreError(e)))),
+      }
+    }
+
+    let loaded: Vec<_> = misses
+      .into_par_iter()
+      .map(|(i, id, key)| {
+        let load_start = Instant::now();
+        let loaded = {
+          enter_span!("load", key = %Described(&key), ty = any::type_name::<T>());
+          <T as LoadPar<C, K, M>>::load_par(key, ctx)
+        };
+        let duration = load_start.elapsed();
+
+        record_metrics! {
+          metrics::histogram!("warmy_load_duration_seconds", "type" => any::type_name::<T>())
+            .record(duration);
+        }
+
+        (i, id, loaded, duration)
+      })
+      .collect();
+
+    for (i, id, loaded, duration) in loaded {
+      // a key appearing several times is only injected once
+      results[i] = match self.cache.get::<T>(id) {
+        Some(res) => Some(Ok(res.clone())),
+        // the storage is not available while loading in parallel, so no file is tracked
+        None => Some(self.complete_load::<T, M>(id, loaded, duration, None)),
+      };
+    }
+
+    // every result is known by now
+    results.into_iter().flatten().collect()
+  }
+
+  /// Get a resource from the [`Storage`] for the given key. If it fails, a proxied version is used,
+  /// which will get replaced by the resource once it’s available and reloaded.
+  ///
+  /// This function uses the default loading method.
+  pub fn get_proxied<T, P>(
+    &mut self,
+    key: &K,
+    proxy: P,
+    ctx: &mut C,
+  ) -> Result<Res<T>, StoreError<K>>
+  where T: Load<C, K>,
+        P: FnOnce() -> T {
+    self
+      .get(key, ctx)
+      .or_else(|e| self.inject_proxy::<T, ()>(key, proxy(), e))
+  }
+
+  /// Get a resource from the [`Storage`] for the given key by using a specific method. If it fails, a
+  /// proxied version is used, which will get replaced by the resource once it’s available and
+  /// reloaded.
+  pub fn get_proxied_by<T, M, P>(
+    &mut self,
+    key: &K,
+    proxy: P,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<T>, StoreError<K>>
+  where T: Load<C, K, M>,
+        P: FnOnce() -> T {
+    self
+      .get_by(key, ctx, method)
+      .or_else(|e| self.inject_proxy::<T, M>(key, proxy(), e))
+  }
+
+  /// Get a resource from the [`Storage`] for the given key. If it fails, its [`Default`] value is
+  /// used as a proxy, which will get replaced by the resource once it’s available and reloaded.
+  ///
+  /// The loading error is kept and available with [`Storage::last_error`]. This function uses the
+  /// default loading method.
+  pub fn get_or_default<T>(&mut self, key: &K, ctx: &mut C) -> Result<Res<T>, StoreError<K>>
+  where T: Load<C, K> + Default {
+    self.get_proxied(key, T::default, ctx)
+  }
+
+  /// Get a resource from the [`Storage`] for the given key by using a specific method. If it fails,
+  /// its [`Default`] value is used as a proxy, which will get replaced by the resource once it’s
+  /// available and reloaded.
+  ///
+  /// The loading error is kept and available with [`Storage::last_error`].
+  pub fn get_or_default_by<T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    method: M,
+  ) -> Result<Res<T>, StoreError<K>>
+  where T: Load<C, K, M> + Default {
+    self.get_proxied_by(key, T::default, ctx, method)
+  }
+
+  /// Inject a proxy in place of a resource that failed to load.
+  ///
+  /// The loading error is kept as the last error of the resource.
+  fn inject_proxy<T, M>(
+    &mut self,
+    key: &K,
+    proxy: T,
+    error: StoreErrorOr<T, C, K, M>,
+  ) -> Result<Res<T>, StoreError<K>>
+  where T: Load<C, K, M> {
+    let id = self.resolve(key)?;
+    let res = self.inject::<T, M>(id, proxy, Vec::new())?;
+
+    if let Some(metadata) = self.metadata.get_mut(&id) {
+      metadata.proxy = true;
+
+      if let StoreErrorOr::ResError(e) = error {
+        metadata.last_error = Some(ResourceError::new::<T, C, M>(self.keys.key(id).clone(), e));
+      }
+    }
+
+    Ok(res)
+  }
+
+  /// Broadcast an event to all subscribers, dropping the ones that disconnected.
+  #[cfg(feature = "arc")]
+  fn broadcast(&mut self, event: StoreEvent<K>) {
+    self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+  }
+
+  /// Record an event, if recording.
+  ///
+  /// The event is only built when recording.
+  #[cfg(feature = "hot-reload")]
+  fn record<F>(&mut self, event: F) where F: FnOnce(&Self) -> Option<RecordedEvent> {
+    let event = match self.recording {
+      Some(_) => event(self),
+      None => None,
+    };
+
+    if let (Some(recording), Some(event)) = (self.recording.as_mut(), event) {
+      recording.push(event);
+    }
+  }
+
+  /// Get the last error that happened while loading or reloading a resource, if any.
+  ///
+  /// This is the error that made [`Storage::get_proxied`] fall back to the proxy, or the error of
+  /// the last failed reload. It is cleared as soon as the resource is successfully reloaded.
+  pub fn last_error(&self, key: &K) -> Option<&ResourceError<K>> {
+    let id = self.lookup(key)?;
+    self.metadata.get(&id).and_then(|metadata| metadata.last_error.as_ref())
+  }
+
+  /// Check whether a resource is currently a proxy.
+  ///
+  /// A resource obtained with [`Storage::get_proxied`] or [`Storage::get_proxied_by`] is a proxy
+  /// until it gets successfully reloaded. Unknown resources are not proxies.
+  pub fn is_proxy(&self, key: &K) -> bool {
+    let metadata = self.lookup(key).and_then(|id| self.metadata.get(&id));
+    metadata.is_some_and(|metadata| metadata.proxy)
+  }
+
+  /// Get the type a resource was loaded as, if the resource is known.
+  ///
+  /// Because a key indexes at most one resource, this is handy in discovery closures – see
+  /// [`Discovery`] – to know whether a path is already loaded and as which type.
+  pub fn resource_type(&self, key: &K) -> Option<ResourceType> {
+    let id = self.lookup(key)?;
+    self.metadata.get(&id).map(|metadata| metadata.ty)
+  }
+
+  /// Check whether a resource is known and was loaded as `T`.
+  pub fn is_loaded_as<T>(&self, key: &K) -> bool where T: 'static {
+    self.resource_type(key).is_some_and(|ty| ty.is::<T>())
+  }
+}
+
+/// Result of getting a resource from a [`Storage`].
+type Fetched<T, C, K, M = ()> = Result<Res<T>, StoreErrorOr<T, C, K, M>>;
+
+/// Time-to-live of a resource.
+struct Ttl {
+  // duration after which the resource must be refreshed
+  ttl: Duration,
+  // instant at which the resource must be refreshed
+  #[cfg_attr(not(feature = "hot-reload"), allow(dead_code))]
+  expires_at: Instant,
+}
+
+/// Error that might happen when handling a resource store around.
+///
+/// See [`CodedError::code`] to branch on the kind of error without matching on it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum StoreError<K> {
+  /// The root path for a filesystem resource was not found.
+  RootDoesNotExist(PathBuf),
+  /// The key associated with a resource already exists in the [`Store`].
+  ///
+  /// > Note: it is not currently possible to have two resources living in a [`Store`] and using an
+  /// > identical key at the same time.
+  AlreadyRegisteredKey(K),
+  /// The key points outside of the root of the [`Store`] – e.g. `/../../etc/passwd`.
+  ///
+  /// The key is the one provided by the user, before its preparation. See [`Key::fs_path`] for
+  /// further details.
+  KeyOutsideRoot(K),
+  /// The file of the key didn’t appear in time. See [`Store::get_when_available`].
+  ///
+  /// The key is the one provided by the user.
+  Timeout(K),
+  /// The resource is not loaded and the [`Storage`] is read-only. See [`Storage::set_read_only`].
+  ///
+  /// The key is the one provided by the user.
+  CacheMiss(K),
+  /// The [`Store`] doesn’t own a context. See [`Store::with_context`].
+  NoOwnedContext,
+}
+
+impl<K> Display for StoreError<K> where K: Display {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      StoreError::RootDoesNotExist(ref path) => write!(f, "root {} doesn’t exist", path.display()),
+      StoreError::AlreadyRegisteredKey(ref dk) => write!(f, "already registered key: {}", dk),
+      StoreError::KeyOutsideRoot(ref dk) => write!(f, "key outside of the root: {}", dk),
+      StoreError::Timeout(ref dk) => write!(f, "timed out waiting for the file of key: {}", dk),
+      StoreError::CacheMiss(ref dk) => write!(f, "key not loaded in a read-only store: {}", dk),
+      StoreError::NoOwnedContext => f.write_str("store created without context"),
+    }
+  }
+}
+
+impl<K> Error for StoreError<K> where K: fmt::Debug + Display {}
+
+impl<K> CodedError for StoreError<K> {
+  fn code(&self) -> ErrorCode {
+    match *self {
+      StoreError::RootDoesNotExist(_) => ErrorCode::RootDoesNotExist,
+      StoreError::AlreadyRegisteredKey(_) => ErrorCode::KeyConflict,
+      StoreError::KeyOutsideRoot(_) => ErrorCode::KeyOutsideRoot,
+      StoreError::Timeout(_) => ErrorCode::Timeout,
+      StoreError::CacheMiss(_) => ErrorCode::CacheMiss,
+      StoreError::NoOwnedContext => ErrorCode::NoOwnedContext,
+    }
+  }
+}
+
+/// Stable, machine-readable code of an error.
+///
+/// Tooling and tests can branch on codes – e.g. a missing file versus a parse error – instead of
+/// matching on the display of errors, which is not stable. Codes are shared by all the errors of
+/// the crate implementing [`CodedError`], so that the store errors and the errors of the universal
+/// loaders can be told apart the same way. More codes might be added in the future.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorCode {
+  /// The root of the store doesn’t exist.
+  RootDoesNotExist,
+  /// A key is already used by another resource.
+  KeyConflict,
+  /// A key points outside of the root of the store.
+  KeyOutsideRoot,
+  /// Something didn’t happen in time.
+  Timeout,
+  /// A resource is not loaded and the store is read-only.
+  CacheMiss,
+  /// The store doesn’t own a context.
+  NoOwnedContext,
+  /// A file doesn’t exist.
+  FileMissing,
+  /// A file exists but couldn’t be read.
+  FileUnreadable,
+  /// A key doesn’t address a file while a file is required.
+  NoKey,
+  /// The content of a file couldn’t be parsed.
+  ParseError,
+  /// No loader is registered for a resource.
+  NoLoader,
+  /// A loader failed for another reason.
+  LoaderError,
+}
+
+impl ErrorCode {
+  /// Stable name of the code, in kebab case – e.g. `"file-missing"`.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ErrorCode::RootDoesNotExist => "root-does-not-exist",
+      ErrorCode::KeyConflict => "key-conflict",
+      ErrorCode::KeyOutsideRoot => "key-outside-root",
+      ErrorCode::Timeout => "timeout",
+      ErrorCode::CacheMiss => "cache-miss",
+      ErrorCode::NoOwnedContext => "no-owned-context",
+      ErrorCode::FileMissing => "file-missing",
+      ErrorCode::FileUnreadable => "file-unreadable",
+      ErrorCode::NoKey => "no-key",
+      ErrorCode::ParseError => "parse-error",
+      ErrorCode::NoLoader => "no-loader",
+      ErrorCode::LoaderError => "loader-error",
+    }
+  }
+
+  /// Code of an error that occurred while reading a file.
+  pub fn from_io(error: &io::Error) -> Self {
+    match error.kind() {
+      io::ErrorKind::NotFound => ErrorCode::FileMissing,
+      _ => ErrorCode::FileUnreadable,
+    }
+  }
+}
+
+impl Display for ErrorCode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str(self.as_str())
+  }
+}
+
+/// Errors having an [`ErrorCode`].
+pub trait CodedError {
+  /// Machine-readable code of the error.
+  fn code(&self) -> ErrorCode;
+}
+
+/// Error of a resource that failed to load or reload, along with context about the failure.
+///
+/// It wraps the error of the [`Load`] implementation of the resource – which you can get back with
+/// [`ResourceError::downcast_ref`] – with the key and type of the resource, the loading method and
+/// the time at which the failure happened, so that you know which resource failed.
+#[derive(Clone, Debug)]
+pub struct ResourceError<K> {
+  key: K,
+  ty: &'static str,
+  method: &'static str,
+  time: SystemTime,
+  error: Arc<dyn Error + Send + Sync>,
+}
+
+impl<K> ResourceError<K> {
+  fn new<T, C, M>(key: K, error: T::Error) -> Self
+  where T: Load<C, K, M>,
+        K: Key,
+        M: ?Sized {
+    Self::with_error::<T, C, M>(key, Arc::new(error))
+  }
+
+  fn with_error<T, C, M>(key: K, error: Arc<dyn Error + Send + Sync>) -> Self
+  where T: Load<C, K, M>,
+        K: Key,
+        M: ?Sized {
+    ResourceError {
+      key,
+      ty: any::type_name::<T>(),
+      method: any::type_name::<M>(),
+      time: SystemTime::now(),
+      error,
+    }
+  }
+
+  /// Key of the resource.
+  pub fn key(&self) -> &K {
+    &self.key
+  }
+
+  /// Name of the type of the resource.
+  pub fn type_name(&self) -> &'static str {
+    self.ty
+  }
+
+  /// Name of the loading method.
+  pub fn method_name(&self) -> &'static str {
+    self.method
+  }
+
+  /// Time at which the failure happened.
+  pub fn time(&self) -> SystemTime {
+    self.time
+  }
+
+  /// Error of the [`Load`] implementation of the resource.
+  pub fn error(&self) -> &(dyn Error + Send + Sync + 'static) {
+    &*self.error
+  }
+
+  /// Get the error of the [`Load`] implementation of the resource as its concrete type.
+  pub fn downcast_ref<E>(&self) -> Option<&E> where E: Error + 'static {
+    self.error.downcast_ref()
+  }
+}
+
+impl<K> Display for ResourceError<K> where K: Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(
+      f,
+      "cannot load {:?} as {} with method {}: {}",
+      Described(&self.key),
+      self.ty,
+      self.method,
+      self.error
+    )
+  }
+}
+
+/// As the error of the resource is displayed, the source is its own source.
+impl<K> Error for ResourceError<K> where K: Key + fmt::Debug {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    self.error.source()
+  }
+}
+
+/// Error of a resource which reload code panicked.
+///
+/// Reload failures carry this error if the store was configured to catch panics – see
+/// [`StoreOpt::set_catch_panics`].
+#[derive(Clone, Debug)]
+pub struct ReloadPanic {
+  message: String,
+}
+
+impl ReloadPanic {
+  #[cfg(feature = "hot-reload")]
+  fn new(payload: Box<dyn Any + Send>) -> Self {
+    let message = match payload.downcast::<String>() {
+      Ok(message) => *message,
+      Err(payload) => payload
+        .downcast_ref::<&'static str>()
+        .map_or_else(|| "unknown panic payload".to_owned(), |&message| message.to_owned()),
+    };
+
+    ReloadPanic { message }
+  }
+
+  /// Message the reload code panicked with, if it was a string.
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+}
+
+impl Display for ReloadPanic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "reload panicked: {}", self.message)
+  }
+}
+
+impl Error for ReloadPanic {}
+
+/// Either a store error or a resource loading error.
+pub enum StoreErrorOr<T, C, K, M = ()> where T: Load<C, K, M>, K: Key {
+  /// A store error.
+  StoreError(StoreError<K>),
+  /// A resource error.
+  ResError(T::Error),
+}
+
+impl<T, C, K, M> CodedError for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      T::Error: CodedError,
+      K: Key {
+  fn code(&self) -> ErrorCode {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => e.code(),
+      StoreErrorOr::ResError(ref e) => e.code(),
+    }
+  }
+}
+
+impl<T, C, K, M> Clone for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      T::Error: Clone,
+      K: Key {
+  fn clone(&self) -> Self {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => StoreErrorOr::StoreError(e.clone()),
+      StoreErrorOr::ResError(ref e) => StoreErrorOr::ResError(e.clone()),
+    }
+  }
+}
+
+impl<T, C, K, M> Eq for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      T::Error: Eq,
+      K: Key {
+}
+
+impl<T, C, K, M> PartialEq for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      T::Error: PartialEq,
+      K: Key {
+  #[allow(clippy::needless_borrowed_reference)]
+  fn eq(&self, rhs: &Self) -> bool {
+    match (self, rhs) {
+      (&StoreErrorOr::StoreError(ref a), &StoreErrorOr::StoreError(ref b)) => a == b,
+      (&StoreErrorOr::ResError(ref a), &StoreErrorOr::ResError(ref b)) => a == b,
+      _ => false,
+    }
+  }
+}
+
+impl<T, C, K, M> fmt::Debug for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      T::Error: fmt::Debug,
+      K: Key + fmt::Debug {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => f.debug_tuple("StoreError").field(e).finish(),
+      StoreErrorOr::ResError(ref e) => f.debug_tuple("ResError").field(e).finish(),
+    }
+  }
+}
+
+impl<T, C, K, M> Display for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      T::Error: fmt::Debug,
+      K: Key + Display {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => e.fmt(f),
+      StoreErrorOr::ResError(ref e) => e.fmt(f),
+    }
+  }
+}
+
+/// As the error is displayed transparently, so is its source.
+impl<T, C, K, M> Error for StoreErrorOr<T, C, K, M>
+where T: Load<C, K, M>,
+      K: Key + fmt::Debug + Display {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      StoreErrorOr::StoreError(ref e) => e.source(),
+      StoreErrorOr::ResError(ref e) => e.source(),
+    }
+  }
+}
+
+/// Interval at which the existence of a file is checked while waiting for it to appear.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time the watcher events of a file written by the store are ignored for, on top of the debounce
+/// duration.
+#[cfg(feature = "hot-reload")]
+const SUPPRESSION_SLACK: Duration = Duration::from_secs(1);
+
+/// Events dispatched to a store by a watcher.
+#[cfg(feature = "hot-reload")]
+type Inbox = Rc<RefCell<VecDeque<DebouncedEvent>>>;
+
+/// Filesystem watcher, shared by a store and its children – see [`Store::scoped`].
+///
+/// Events are dispatched to the inboxes of all the stores which roots they concern, whichever
+/// store receives them from the watcher.
+#[cfg(feature = "hot-reload")]
+struct WatcherHub {
+  // keep the watcher around so that we don’t have it disconnected; also used to watch extra files;
+  // none once closed or if files live in memory
+  watcher: Option<RecommendedWatcher>,
+  // watcher receiver part of the channel
+  watcher_rx: Receiver<DebouncedEvent>,
+  // debounce duration of the watcher
+  debounce_duration: Duration,
+  // roots of the stores sharing the watcher – or extra files they watch – along with their inboxes
+  inboxes: Vec<(PathBuf, Weak<RefCell<VecDeque<DebouncedEvent>>>)>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl WatcherHub {
+  fn new(
+    watcher: Option<RecommendedWatcher>,
+    watcher_rx: Receiver<DebouncedEvent>,
+    debounce_duration: Duration,
+  ) -> Self {
+    WatcherHub {
+      watcher,
+      watcher_rx,
+      debounce_duration,
+      inboxes: Vec::new(),
+    }
+  }
+
+  /// Watch a path, unless the hub is closed.
+  fn watch(&mut self, path: &Path, mode: RecursiveMode) {
+    if let Some(ref mut watcher) = self.watcher {
+      let _ = watcher.watch(path, mode);
+    }
+  }
+
+  /// Stop the watcher and drop the events it sent so far.
+  ///
+  /// Dropping the watcher shuts its threads down and releases its file descriptors.
+  fn close(&mut self) {
+    self.watcher = None;
+    while self.watcher_rx.try_recv().is_ok() {}
+    self.inboxes.clear();
+  }
+
+  /// Get the inbox of a store rooted at the given path.
+  fn subscribe(&mut self, root: PathBuf) -> Inbox {
+    let inbox = Inbox::default();
+    self.inboxes.push((root, Rc::downgrade(&inbox)));
+    inbox
+  }
+
+  /// Dispatch an event to the inboxes of the stores it concerns.
+  fn dispatch(&mut self, event: DebouncedEvent) {
+    // roots are not verbatim, so paths must not be either to be compared to them
+    let event = strip_event_verbatim_prefixes(event);
+
+    // the inboxes of dropped stores are gone
+    self.inboxes.retain(|(_, inbox)| inbox.strong_count() > 0);
+
+    let concerned: Vec<Inbox> = self
+      .inboxes
+      .iter()
+      .filter(|(root, _)| event_concerns(&event, root))
+      .filter_map(|(_, inbox)| inbox.upgrade())
+      .collect();
+
+    if let Some((last, others)) = concerned.split_last() {
+      for inbox in others {
+        inbox.borrow_mut().push_back(clone_event(&event));
+      }
+
+      last.borrow_mut().push_back(event);
+    }
+  }
+
+  /// Dispatch the events received so far.
+  fn dispatch_pending(&mut self) {
+    while let Ok(event) = self.watcher_rx.try_recv() {
+      self.dispatch(event);
+    }
+  }
+
+  /// Wait for an event for at most the given duration and dispatch it, along with the events
+  /// received so far.
+  fn dispatch_timeout(&mut self, timeout: Duration) {
+    match self.watcher_rx.recv_timeout(timeout) {
+      Ok(event) => {
+        self.dispatch(event);
+        self.dispatch_pending();
+      }
+
+      Err(RecvTimeoutError::Timeout) => (),
+
+      // no event will ever come; still wait, so that callers don’t spin
+      Err(RecvTimeoutError::Disconnected) => thread::sleep(timeout),
+    }
+  }
+}
+
+/// Check whether a filesystem event concerns a store rooted at the given path.
+#[cfg(feature = "hot-reload")]
+fn event_concerns(event: &DebouncedEvent, root: &Path) -> bool {
+  match *event {
+    DebouncedEvent::NoticeWrite(ref path)
+    | DebouncedEvent::NoticeRemove(ref path)
+    | DebouncedEvent::Create(ref path)
+    | DebouncedEvent::Write(ref path)
+    | DebouncedEvent::Chmod(ref path)
+    | DebouncedEvent::Remove(ref path)
+    | DebouncedEvent::Error(_, Some(ref path)) => path.starts_with(root),
+    DebouncedEvent::Rename(ref from, ref to) => from.starts_with(root) || to.starts_with(root),
+    DebouncedEvent::Rescan | DebouncedEvent::Error(_, None) => true,
+  }
+}
+
+/// Strip the verbatim prefixes of the paths of a filesystem event – see [`strip_verbatim_prefix`].
+#[cfg(feature = "hot-reload")]
+fn strip_event_verbatim_prefixes(event: DebouncedEvent) -> DebouncedEvent {
+  match event {
+    DebouncedEvent::NoticeWrite(path) => DebouncedEvent::NoticeWrite(strip_verbatim_prefix(path)),
+    DebouncedEvent::NoticeRemove(path) => DebouncedEvent::NoticeRemove(strip_verbatim_prefix(path)),
+    DebouncedEvent::Create(path) => DebouncedEvent::Create(strip_verbatim_prefix(path)),
+    DebouncedEvent::Write(path) => DebouncedEvent::Write(strip_verbatim_prefix(path)),
+    DebouncedEvent::Chmod(path) => DebouncedEvent::Chmod(strip_verbatim_prefix(path)),
+    DebouncedEvent::Remove(path) => DebouncedEvent::Remove(strip_verbatim_prefix(path)),
+    DebouncedEvent::Rename(from, to) => {
+      DebouncedEvent::Rename(strip_verbatim_prefix(from), strip_verbatim_prefix(to))
+    }
+    DebouncedEvent::Error(e, path) => DebouncedEvent::Error(e, path.map(strip_verbatim_prefix)),
+    DebouncedEvent::Rescan => DebouncedEvent::Rescan,
+  }
+}
+
+/// Clone a filesystem event.
+#[cfg(feature = "hot-reload")]
+fn clone_event(event: &DebouncedEvent) -> DebouncedEvent {
+  match *event {
+    DebouncedEvent::NoticeWrite(ref path) => DebouncedEvent::NoticeWrite(path.clone()),
+    DebouncedEvent::NoticeRemove(ref path) => DebouncedEvent::NoticeRemove(path.clone()),
+    DebouncedEvent::Create(ref path) => DebouncedEvent::Create(path.clone()),
+    DebouncedEvent::Write(ref path) => DebouncedEvent::Write(path.clone()),
+    DebouncedEvent::Chmod(ref path) => DebouncedEvent::Chmod(path.clone()),
+    DebouncedEvent::Remove(ref path) => DebouncedEvent::Remove(path.clone()),
+    DebouncedEvent::Rename(ref from, ref to) => DebouncedEvent::Rename(from.clone(), to.clone()),
+    DebouncedEvent::Rescan => DebouncedEvent::Rescan,
+    DebouncedEvent::Error(ref e, ref path) => {
+      let e = match *e {
+        notify::Error::Generic(ref e) => notify::Error::Generic(e.clone()),
+        notify::Error::Io(ref e) => notify::Error::Io(io::Error::new(e.kind(), e.to_string())),
+        notify::Error::PathNotFound => notify::Error::PathNotFound,
+        notify::Error::WatchNotFound => notify::Error::WatchNotFound,
+      };
+
+      DebouncedEvent::Error(e, path.clone())
+    }
+  }
+}
+
+/// Resource synchronizer.
+///
+/// An object of this type is responsible to synchronize resources living in a store. It keeps in
+/// internal, optimized state to perform correct and efficient synchronization.
+#[cfg(feature = "hot-reload")]
+struct Synchronizer<C, K> {
+  // all the resources that must be reloaded
+  dirties: HashSet<KeyId>,
+  // watcher, possibly shared with the parent and children of the store
+  hub: Rc<RefCell<WatcherHub>>,
+  // events dispatched to the store by the watcher
+  inbox: Inbox,
+  // events received from the watcher but not processed yet
+  events: VecDeque<DebouncedEvent>,
+  // maximum number of events processed per synchronization, if any
+  event_cap: Option<usize>,
+  // whether pending events overflowing the cap are dropped in favor of a full rescan of the root
+  rescan_on_overflow: bool,
+  // used to accept or ignore new discoveries
+  discovery: Discovery<C, K>,
+  // policy used to retry failed reloads, if any
+  retry_policy: Option<RetryPolicy>,
+  // resources that failed to reload and are waiting for another attempt
+  retries: HashMap<KeyId, Retry>,
+  // groups which reload failed, along with the resources that must be reloaded with them
+  stalled_groups: HashMap<String, HashSet<KeyId>>,
+  // maximum time a synchronization should take, if any
+  budget: Option<Duration>,
+  // files found by the initial scan of the root, waiting to be discovered
+  scanned: Vec<PathBuf>,
+  // whether the events are replayed from a log, dropping the ones of the watcher
+  replaying: bool,
+  // whether borrowed resources are skipped rather than reloaded
+  skip_borrowed: bool,
+  // type of the only resources reloaded, if any; the others are deferred
+  type_filter: Option<TypeId>,
+  // resources reloaded during the ongoing synchronization which dependents must be reloaded too
+  changed: HashSet<KeyId>,
+  // what happens to the dependents of resources that failed to reload
+  failure_propagation: FailurePropagation<K>,
+  // suffix of the companion lock files delaying the reload of the files they lock, if any
+  lock_suffix: Option<String>,
+  // changed files held until their lock files disappear
+  locked: HashSet<PathBuf>,
+  // files written by the store, which watcher events are ignored until the given instants
+  suppressed: HashMap<PathBuf, Instant>,
+  // whether the root went missing; it’s watched again once it reappears
+  root_lost: bool,
+  // report of the ongoing synchronization
+  report: SyncReport<K>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl<C, K> Synchronizer<C, K> where K: Key {
+  fn new(
+    hub: Rc<RefCell<WatcherHub>>,
+    inbox: Inbox,
+    discovery: Discovery<C, K>,
+    retry_policy: Option<RetryPolicy>,
+    budget: Option<Duration>,
+    event_cap: Option<usize>,
+    rescan_on_overflow: bool,
+  ) -> Self {
+    Synchronizer {
+      dirties: HashSet::default(),
+      hub,
+      inbox,
+      events: VecDeque::new(),
+      event_cap,
+      rescan_on_overflow,
+      discovery,
+      retry_policy,
+      retries: HashMap::default(),
+      stalled_groups: HashMap::default(),
+      budget,
+      scanned: Vec::new(),
+      replaying: false,
+      skip_borrowed: false,
+      type_filter: None,
+      changed: HashSet::default(),
+      failure_propagation: FailurePropagation::default(),
+      lock_suffix: None,
+      locked: HashSet::default(),
+      suppressed: HashMap::default(),
+      root_lost: false,
+      report: SyncReport::new(),
+    }
+  }
+
+  /// Mark a resource dirty so that it gets reloaded on the next synchronization.
+  fn invalidate(&mut self, id: KeyId) {
+    self.retries.remove(&id);
+    self.dirties.insert(id);
+  }
+
+  /// Forget about a resource removed from the store.
+  fn forget(&mut self, id: KeyId) {
+    self.dirties.remove(&id);
+    self.retries.remove(&id);
+
+    for members in self.stalled_groups.values_mut() {
+      members.remove(&id);
+    }
+  }
+
+  /// Whether a resource is waiting to be reloaded.
+  fn is_dirty(&self, id: KeyId) -> bool {
+    self.dirties.contains(&id)
+  }
+
+  /// Number of failed reloads of a resource waiting for another attempt.
+  fn retry_attempts(&self, id: KeyId) -> Option<u32> {
+    self.retries.get(&id).map(|retry| retry.attempts)
+  }
+
+  /// Check whether the next synchronization has anything to do, without doing it.
+  ///
+  /// Filesystem events are dispatched but not processed, so this might report events that end up
+  /// being ignored.
+  fn has_pending_changes(&mut self, storage: &Storage<C, K>) -> bool {
+    self.hub.borrow_mut().dispatch_pending();
+
+    if !self.inbox.borrow().is_empty()
+      || !self.events.is_empty()
+      || !self.dirties.is_empty()
+      || !self.scanned.is_empty()
+      || !self.discovery.pending.is_empty()
+      || self.root_lost && storage.canon_root.is_dir()
+    {
+      return true;
+    }
+
+    let now = Instant::now();
+
+    self.locked.iter().any(|path| !self.is_locked(path, storage))
+      || self.retries.values().any(|retry| retry.next_attempt <= now)
+      || storage.ttls.iter().any(|(id, ttl)| {
+        ttl.expires_at <= now && storage.metadata.contains_key(id)
+      })
+  }
+
+  /// Wait for a file to exist, until a deadline.
+  ///
+  /// Events received while waiting are kept, to be handled on the next synchronization. Return
+  /// whether the file exists.
+  fn wait_for_file(&mut self, path: &Path, storage: &Storage<C, K>, deadline: Instant) -> bool {
+    if storage.is_file(path) {
+      return true;
+    }
+
+    loop {
+      let now = Instant::now();
+
+      if now >= deadline {
+        return storage.is_file(path);
+      }
+
+      // events about the file are awaited, but its existence is checked regularly too, as the path
+      // of events might differ from the one of the key – e.g. when keys are case-insensitive
+      self.hub.borrow_mut().dispatch_timeout((deadline - now).min(WAIT_POLL_INTERVAL));
+
+      let received = std::mem::take(&mut *self.inbox.borrow_mut());
+      let mut appeared = false;
+
+      for event in received {
+        appeared = appeared || match event {
+          DebouncedEvent::Create(ref p) | DebouncedEvent::Write(ref p) => p == path,
+          DebouncedEvent::Rename(_, ref to) => to == path,
+          _ => false,
+        };
+
+        self.receive(event);
+      }
+
+      if appeared || storage.is_file(path) {
+        return true;
+      }
+    }
+  }
+
+  /// Ignore the watcher events of a file written by the store for a while.
+  ///
+  /// The events are debounced, so they’re expected within the debounce duration; some slack is
+  /// given to cope with loaded machines.
+  fn suppress(&mut self, path: PathBuf) {
+    let until = Instant::now() + self.hub.borrow().debounce_duration + SUPPRESSION_SLACK;
+    self.suppressed.insert(path, until);
+  }
+
+  /// Watch a single file living outside the root, dispatching its events to the store.
+  ///
+  /// Its directory is watched rather than the file itself, so that atomic saves – replacing the
+  /// file – don’t get it unwatched.
+  fn watch_file(&mut self, path: PathBuf) {
+    let mut hub = self.hub.borrow_mut();
+
+    if let Some(dir) = path.parent() {
+      hub.watch(dir, RecursiveMode::NonRecursive);
+    }
+
+    hub.inboxes.push((path, Rc::downgrade(&self.inbox)));
+  }
+
+  /// Queue an event received from the watcher, unless all its paths are suppressed.
+  fn receive(&mut self, event: DebouncedEvent) {
+    let suppressed = match event {
+      DebouncedEvent::NoticeWrite(ref path)
+      | DebouncedEvent::NoticeRemove(ref path)
+      | DebouncedEvent::Create(ref path)
+      | DebouncedEvent::Write(ref path)
+      | DebouncedEvent::Chmod(ref path)
+      | DebouncedEvent::Remove(ref path) => self.suppressed.contains_key(path),
+      DebouncedEvent::Rename(ref from, ref to) => {
+        self.suppressed.contains_key(from) && self.suppressed.contains_key(to)
+      }
+      _ => false,
+    };
+
+    if !suppressed {
+      self.events.push_back(event);
+    }
+  }
+
+  /// Dequeue file system events, up to the event cap.
+  ///
+  /// Events exceeding the cap are carried over to the next synchronization, unless the store is
+  /// configured to rescan its root on overflow.
+  fn dequeue_fs_events(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) where K: for<'a> From<&'a Path> {
+    // receiving events is cheap; processing them is not
+    self.hub.borrow_mut().dispatch_pending();
+    let received = std::mem::take(&mut *self.inbox.borrow_mut());
+
+    if !self.replaying {
+      let now = Instant::now();
+      self.suppressed.retain(|_, until| *until > now);
+
+      for event in received {
+        self.receive(event);
+      }
+    }
+
+    if let Some(cap) = self.event_cap {
+      if self.events.len() > cap {
+        let pending = self.events.len();
+        log_record!(warn, "{} filesystem events pending, exceeding the cap of {}", pending, cap);
+        self.overflowed(storage);
+
+        if self.rescan_on_overflow {
+          self.events.clear();
+          self.rescan(storage, ctx);
+          return;
+        }
+      }
+    }
+
+    let count = self.event_cap.map_or(self.events.len(), |cap| cap.min(self.events.len()));
+
+    for _ in 0..count {
+      match self.events.pop_front() {
+        Some(event) => self.handle_event(event, storage, ctx),
+        None => break,
+      }
+    }
+  }
+
+  /// Handle a single file system event.
+  fn handle_event(&mut self, event: DebouncedEvent, storage: &mut Storage<C, K>, ctx: &mut C)
+  where K: for<'a> From<&'a Path> {
+    storage.record(|storage| RecordedEvent::from_fs_event(&event, &storage.canon_root));
+
+    match event {
+      DebouncedEvent::Write(ref path) => {
+        self.changed(path, DepEvents::WRITE, storage, ctx);
+      }
+
+      DebouncedEvent::Create(ref path) => {
+        self.glob_changed(path, storage);
+        self.changed(path, DepEvents::CREATE, storage, ctx);
+      }
+
+      DebouncedEvent::Remove(ref path) => {
+        self.glob_changed(path, storage);
+        self.dependencies_changed(path, DepEvents::REMOVE, storage);
+        self.removed(path, storage);
+        self.discovery.remove(path, storage, ctx);
+      }
+
+      DebouncedEvent::Rename(ref from, ref to) => {
+        self.glob_changed(from, storage);
+        self.glob_changed(to, storage);
+        self.dependencies_changed(from, DepEvents::REMOVE, storage);
+        self.removed(from, storage);
+
+        if self.discovery.rename(from, to, storage, ctx) {
+          // the renamed file might replace a resource we know about (atomic saves)
+          if self.is_locked(to, storage) {
+            self.locked.insert(to.to_owned());
+          } else {
+            self.dependencies_changed(to, DepEvents::CREATE, storage);
+
+            if let Some(id) = storage.lookup_event(to) {
+              self.retries.remove(&id);
+              self.dirties.insert(id);
+            }
+          }
+        } else {
+          self.discovery.remove(from, storage, ctx);
+          self.changed(to, DepEvents::CREATE, storage, ctx);
+        }
+      }
+
+      DebouncedEvent::Chmod(ref path) => {
+        self.dependencies_changed(path, DepEvents::METADATA, storage);
+      }
+
+      DebouncedEvent::Rescan => {
+        log_record!(warn, "filesystem events were dropped by the watcher");
+        self.overflowed(storage);
+        self.rescan(storage, ctx);
+      }
+
+      DebouncedEvent::Error(ref e, ref path) => {
+        log_record!(warn, "filesystem watcher error ({:?}): {}", path, e);
+      }
+
+      _ => (),
+    }
+  }
+
+  /// Handle a file that was created or written to.
+  fn changed(&mut self, path: &Path, kind: DepEvents, storage: &mut Storage<C, K>, ctx: &mut C)
+  where K: for<'a> From<&'a Path> {
+    if self.is_lock_file(path) {
+      // lock files only tell whether the files they lock are complete
+      return;
+    }
+
+    if self.is_locked(path, storage) {
+      log_record!(debug, "{} is locked; holding its change", path.display());
+      self.locked.insert(path.to_owned());
+      return;
+    }
+
+    let is_dep = self.dependencies_changed(path, kind, storage);
+
+    if let Some(id) = storage.lookup_event(path) {
+      // a fresh change supersedes any pending retry
+      self.retries.remove(&id);
+      self.dirties.insert(id);
+    } else if !is_dep && path.starts_with(&storage.canon_root) {
+      // files watched outside the root are never discovered
+      log_record!(debug, "discovered {}", path.display());
+      storage.observer.discovered(path);
+      self.discovery.discover(path, storage, ctx);
+    }
+  }
+
+  /// Mark as dirty the resources depending on a file – or on one of its directories – through a
+  /// kind of event.
+  ///
+  /// Return whether the file itself is a dependency.
+  fn dependencies_changed(&mut self, path: &Path, kind: DepEvents, storage: &mut Storage<C, K>) -> bool
+  where K: for<'a> From<&'a Path> {
+    let mut is_dep = false;
+    let root = storage.canon_root.clone();
+
+    for dir in path.ancestors().take_while(|dir| dir.starts_with(&root)) {
+      // dependents of resources are reloaded along with them
+      let dep = match storage.event_id(dir) {
+        Some(dep) if !storage.metadata.contains_key(&dep) => dep,
+        _ => continue,
+      };
+      let dependents = match storage.deps.get(&dep) {
+        Some(dependents) => dependents,
+        None => continue,
+      };
+
+      is_dep |= dir == path;
+
+      for &id in dependents {
+        if storage.dep_events_of(dep, id).contains(kind) {
+          self.retries.remove(&id);
+          self.dirties.insert(id);
+        }
+      }
+    }
+
+    is_dep
+  }
+
+  /// Whether a file is a companion lock file.
+  fn is_lock_file(&self, path: &Path) -> bool {
+    match (self.lock_suffix.as_deref(), path.file_name()) {
+      (Some(suffix), Some(name)) => name.to_string_lossy().ends_with(suffix),
+      _ => false,
+    }
+  }
+
+  /// Whether the companion lock file of a file exists.
+  fn is_locked(&self, path: &Path, storage: &Storage<C, K>) -> bool {
+    self.lock_suffix.as_deref().is_some_and(|suffix| {
+      let mut lock_path = path.as_os_str().to_owned();
+      lock_path.push(suffix);
+      storage.is_file(Path::new(&lock_path))
+    })
+  }
+
+  /// Handle the held changes of the files which lock files disappeared.
+  fn dequeue_unlocked(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) where K: for<'a> From<&'a Path> {
+    if self.locked.is_empty() {
+      return;
+    }
+
+    // lock files are checked rather than their removal events awaited, as debouncing might drop them
+    let unlocked: Vec<PathBuf> =
+      self.locked.iter().filter(|path| !self.is_locked(path, storage)).cloned().collect();
+
+    for path in unlocked {
+      log_record!(debug, "{} is unlocked", path.display());
+      self.locked.remove(&path);
+      self.changed(&path, DepEvents::WRITE, storage, ctx);
+    }
+  }
+
+  /// Mark as dirty the resources depending on a glob pattern a file added or removed matches.
+  fn glob_changed(&mut self, path: &Path, storage: &Storage<C, K>) {
+    let path = match path.strip_prefix(&storage.canon_root) {
+      Ok(path) => path,
+      Err(_) => return,
+    };
+
+    for (&id, patterns) in &storage.globs {
+      let matched = patterns.iter().any(|pattern| pattern.matches_path_with(path, glob_options()));
+
+      if matched && storage.metadata.contains_key(&id) {
+        self.retries.remove(&id);
+        self.dirties.insert(id);
+      }
+    }
+  }
+
+  /// Handle a file that was removed.
+  fn removed(&mut self, path: &Path, storage: &mut Storage<C, K>) where K: for<'a> From<&'a Path> {
+    if let Some(id) = storage.lookup_event(path) {
+      storage.observer.removed(storage.keys.key(id));
+    }
+  }
+
+  /// Feed the files found by the initial scan to the discovery mechanism.
+  fn dequeue_scanned(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) where K: for<'a> From<&'a Path> {
+    for path in std::mem::take(&mut self.scanned) {
+      // resources might have been loaded between the scan and now
+      if storage.lookup_event(&path).is_none() {
+        log_record!(debug, "discovered {}", path.display());
+        storage.observer.discovered(&path);
+        self.discovery.discover(&path, storage, ctx);
+      }
+    }
+  }
+
+  /// Signal that file system events overflowed.
+  fn overflowed(&mut self, storage: &mut Storage<C, K>) {
+    record_metrics! {
+      metrics::counter!("warmy_event_overflows_total").increment(1);
+    }
+
+    storage.observer.overflowed(self.events.len());
+    self.report.overflowed = true;
+  }
+
+  /// Rescan the root, discovering the files not known yet and reloading all the resources.
+  fn rescan(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) where K: for<'a> From<&'a Path> {
+    storage.record(|_| Some(RecordedEvent::Rescan));
+
+    // events were lost, so any resource might have changed
+    for &id in storage.metadata.keys() {
+      self.retries.remove(&id);
+      self.dirties.insert(id);
+    }
+
+    scan_dir(&storage.canon_root, &mut self.scanned);
+    self.dequeue_scanned(storage, ctx);
+  }
+
+  /// Check whether the root went missing – e.g. removed or renamed by a clean build – or
+  /// reappeared.
+  ///
+  /// The watch of a missing root is lost, so the root is watched again once it reappears, and
+  /// rescanned since its files might have changed in the meantime.
+  fn check_root(&mut self, storage: &mut Storage<C, K>, ctx: &mut C)
+  where K: for<'a> From<&'a Path> {
+    // in-memory files are not watched
+    if storage.files.is_some() {
+      return;
+    }
+
+    let exists = storage.canon_root.is_dir();
+
+    if !exists {
+      if !self.root_lost {
+        log_record!(warn, "root {} went missing", storage.canon_root.display());
+        self.root_lost = true;
+        storage.observer.root_lost(&storage.canon_root);
+      }
+
+      self.report.root_lost = true;
+    } else if self.root_lost {
+      log_record!(warn, "root {} reappeared; watching it again", storage.canon_root.display());
+      self.root_lost = false;
+      self.hub.borrow_mut().watch(&storage.canon_root, RecursiveMode::Recursive);
+      self.report.root_restored = true;
+      storage.observer.root_restored(&storage.canon_root);
+      self.rescan(storage, ctx);
+    }
+  }
+
+  /// Mark as dirty the resources whose retry delay has elapsed.
+  fn dequeue_retries(&mut self) {
+    let now = Instant::now();
+
+    for (&id, retry) in &self.retries {
+      if retry.next_attempt <= now {
+        self.dirties.insert(id);
+      }
+    }
+  }
+
+  /// Mark as dirty the resources whose time-to-live has elapsed.
+  fn dequeue_expired(&mut self, storage: &mut Storage<C, K>) {
+    let now = Instant::now();
+
+    for (&id, ttl) in &mut storage.ttls {
+      if ttl.expires_at <= now && storage.metadata.contains_key(&id) {
+        ttl.expires_at = now + ttl.ttl;
+        self.dirties.insert(id);
+      }
+    }
+  }
+
+  /// Reload a resource without committing it.
+  fn stage(&mut self, id: KeyId, storage: &mut Storage<C, K>, ctx: &mut C) -> Option<Staged<K>> {
+    let on_reload = storage.metadata.get(&id)?.on_reload.clone();
+    enter_span!("reload", key = %Described(storage.keys.key(id)), ty = storage.type_name(id));
+    let reload_start = Instant::now();
+    let (staged, provenance) = storage.tracking(|storage| on_reload(storage, ctx));
+    let duration = reload_start.elapsed();
+
+    record_metrics! {
+      let ty = storage.type_name(id);
+      metrics::histogram!("warmy_reload_duration_seconds", "type" => ty)
+        .record(duration);
+      metrics::counter!("warmy_reloads_total", "type" => ty).increment(1);
+
+      if staged.is_err() {
+        metrics::counter!("warmy_reload_failures_total", "type" => ty).increment(1);
+      }
+    }
+
+    Some((staged, duration, provenance))
+  }
+
+  /// Bookkeeping of a successfully reloaded resource.
+  fn succeeded(&mut self, id: KeyId, storage: &mut Storage<C, K>) {
+    let key = storage.keys.key(id);
+    log_record!(debug, "reloaded {:?} as {}", Described(key), storage.type_name(id));
+    storage.observer.reloaded(key);
+    self.report.reloaded.push(key.clone());
+    self.retries.remove(&id);
+
+    if let Some(metadata) = storage.metadata.get_mut(&id) {
+      metadata.last_error = None;
+      metadata.reloads += 1;
+      metadata.last_reload = Some(SystemTime::now());
+
+      if metadata.proxy {
+        metadata.proxy = false;
+        storage.observer.proxy_replaced(key);
+      }
+    }
+
+    #[cfg(feature = "arc")]
+    storage.broadcast(StoreEvent::Reloaded(key.clone()));
+
+    storage.record(|storage| Some(RecordedEvent::Reloaded(format!("{:?}", Described(storage.keys.key(id))))));
+  }
+
+  /// Bookkeeping of a resource that failed to reload.
+  fn failed(&mut self, id: KeyId, error: ResourceError<K>, storage: &mut Storage<C, K>) {
+    log_record!(error, "{}", error);
+    storage.observer.reload_failed(storage.keys.key(id), &error);
+    self.report.failed.push(error.clone());
+
+    #[cfg(feature = "arc")]
+    storage.broadcast(StoreEvent::ReloadFailed(error.clone()));
+
+    storage.record(|storage| {
+      Some(RecordedEvent::ReloadFailed(format!("{:?}", Described(storage.keys.key(id)))))
+    });
+
+    if let Some(metadata) = storage.metadata.get_mut(&id) {
+      metadata.last_error = Some(error);
+    }
+
+    if let Some(ref policy) = self.retry_policy {
+      let attempts = self.retries.get(&id).map_or(0, |retry| retry.attempts) + 1;
+
+      if attempts < policy.max_attempts {
+        let next_attempt = Instant::now() + policy.delay(attempts);
+        self.retries.insert(id, Retry { attempts, next_attempt });
+      } else {
+        // give up until the resource changes again
+        self.retries.remove(&id);
+      }
+    }
+  }
+
+  /// Reload a single resource and commit it.
+  ///
+  /// Return whether the reload succeeded, or `None` if the resource was not reloaded at all.
+  fn reload(&mut self, id: KeyId, storage: &mut Storage<C, K>, ctx: &mut C) -> Option<bool> {
+    if self.is_filtered_out(id, storage) {
+      self.dirties.insert(id);
+      return None;
+    }
+
+    if self.skip_borrowed && self.is_borrowed(id, storage) {
+      self.skip(id, storage);
+      return None;
+    }
+
+    match self.stage(id, storage, ctx)? {
+      (Ok(commit), duration, provenance) => {
+        commit();
+        storage.loaded_in(id, duration, provenance, true);
+        self.succeeded(id, storage);
+        Some(true)
+      }
+
+      (Err(e), ..) => {
+        self.failed(id, e, storage);
+        Some(false)
+      }
+    }
+  }
+
+  /// Check whether the dependents of a resource that failed to reload must be reloaded anyway.
+  fn propagate_failure(&mut self, id: KeyId, storage: &Storage<C, K>) -> bool {
+    let dependents = match storage.deps.get(&id) {
+      Some(dependents) if !dependents.is_empty() => dependents,
+      _ => return false,
+    };
+
+    match self.failure_propagation {
+      FailurePropagation::Stop => false,
+      FailurePropagation::ReloadDependents => true,
+      FailurePropagation::Callback(ref mut f) => {
+        let error = storage.metadata.get(&id).and_then(|metadata| metadata.last_error.as_ref());
+        let dependents: Vec<K> = dependents.iter().map(|&dep| storage.keys.key(dep).clone()).collect();
+        error.is_some_and(|error| f(error, &dependents))
+      }
+    }
+  }
+
+  /// Check whether a resource is currently borrowed.
+  fn is_borrowed(&self, id: KeyId, storage: &Storage<C, K>) -> bool {
+    storage.metadata.get(&id).is_some_and(|metadata| (metadata.borrowed)())
+  }
+
+  /// Check whether a resource is not of the type reloaded by the ongoing synchronization, if any.
+  fn is_filtered_out(&self, id: KeyId, storage: &Storage<C, K>) -> bool {
+    self.type_filter.is_some_and(|ty| {
+      storage.metadata.get(&id).is_some_and(|metadata| metadata.ty.id != ty)
+    })
+  }
+
+  /// Postpone the reload of a borrowed resource to the next synchronization.
+  fn skip(&mut self, id: KeyId, storage: &Storage<C, K>) {
+    let key = storage.keys.key(id);
+    log_record!(debug, "{:?} is borrowed; postponing its reload", Described(key));
+    self.report.skipped.push(key.clone());
+    self.dirties.insert(id);
+  }
+
+  /// Record that a resource was reloaded, so that its dependents get reloaded after it.
+  ///
+  /// A resource that failed to reload only counts if its dependents tolerate the stale dependency.
+  fn mark_changed(&mut self, id: KeyId, reloaded: bool, storage: &Storage<C, K>) {
+    if reloaded || self.propagate_failure(id, storage) {
+      self.changed.insert(id);
+    }
+  }
+
+  /// Check whether a resource must be reloaded: either it’s dirty or one of its dependencies
+  /// changed during the ongoing synchronization.
+  fn must_reload(&self, id: KeyId, dependents: &HashSet<KeyId>, storage: &Storage<C, K>) -> bool {
+    !dependents.contains(&id)
+      || self.changed.iter().any(|dep| storage.deps.get(dep).is_some_and(|deps| deps.contains(&id)))
+  }
+
+  /// Reload all the dirty resources of a group and commit them only if they all succeeded.
+  fn reload_group(
+    &mut self,
+    group: String,
+    mut members: HashSet<KeyId>,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) {
+    // resources held back by a previous failure of the group must be reloaded as well
+    if let Some(stalled) = self.stalled_groups.remove(&group) {
+      members.extend(stalled);
+    }
+
+    // groups are reloaded as a whole, so a single member of another type defers all of them
+    if members.iter().any(|&id| self.is_filtered_out(id, storage)) {
+      self.dirties.extend(members);
+      return;
+    }
+
+    // groups are reloaded as a whole, so a single borrowed member postpones all of them
+    if self.skip_borrowed && members.iter().any(|&id| self.is_borrowed(id, storage)) {
+      for id in members {
+        self.skip(id, storage);
+      }
+
+      return;
+    }
+
+    let mut commits = Vec::with_capacity(members.len());
+    let mut failed = Vec::new();
+
+    for &id in &members {
+      match self.stage(id, storage, ctx) {
+        Some((Ok(commit), duration, provenance)) => {
+          commits.push((id, commit, duration, provenance));
+        }
+
+        Some((Err(e), ..)) => {
+          self.failed(id, e, storage);
+          failed.push(id);
+        }
+
+        None => (),
+      }
+    }
+
+    if !failed.is_empty() {
+      // drop the staged resources and wait for the group to be fixed
+      self.stalled_groups.insert(group, members);
+
+      for id in failed {
+        self.mark_changed(id, false, storage);
+      }
+
+      return;
+    }
+
+    for (id, commit, duration, provenance) in commits {
+      commit();
+      storage.loaded_in(id, duration, provenance, true);
+    }
+
+    for &id in &members {
+      self.succeeded(id, storage);
+      self.mark_changed(id, true, storage);
+    }
+  }
+
+  /// Reload any dirty resource that fulfill its time predicate.
+  ///
+  /// Resources are reloaded by decreasing priority. The dependents of dirty resources – directly or
+  /// not – are reloaded once, after all their dependencies. If a synchronization budget is set and
+  /// gets exceeded, the remaining resources are left dirty for the next synchronization.
+  fn reload_dirties(&mut self, storage: &mut Storage<C, K>, ctx: &mut C, start: Instant) {
+    record_metrics! {
+      metrics::histogram!("warmy_dirty_resources").record(self.dirties.len() as f64);
+    }
+
+    if self.dirties.is_empty() {
+      return;
+    }
+
+    // dependents which are not dirty themselves; they’re only reloaded if a dependency changes
+    let mut dependents = HashSet::default();
+    let mut pending: Vec<KeyId> = self.dirties.iter().cloned().collect();
+
+    while let Some(id) = pending.pop() {
+      for &dependent in storage.deps.get(&id).into_iter().flatten() {
+        if !self.dirties.contains(&dependent) && dependents.insert(dependent) {
+          pending.push(dependent);
+        }
+      }
+    }
+
+    let mut units: Vec<ReloadUnit> = Vec::with_capacity(self.dirties.len() + dependents.len());
+    let mut groups: HashMap<String, HashSet<KeyId>> = HashMap::default();
+
+    for id in self.dirties.drain().chain(dependents.iter().cloned()) {
+      if let Some(group) = storage.groups.get(&id) {
+        groups.entry(group.clone()).or_default().insert(id);
+      } else {
+        units.push(ReloadUnit::Single(id));
+      }
+    }
+
+    units.extend(groups.into_iter().map(|(group, members)| ReloadUnit::Group(group, members)));
+
+    // dependencies are reloaded before their dependents: they inherit the priority of their
+    // dependents and come first among resources of the same priority
+    let priorities = storage.inherited_priorities();
+    let depths = storage.dependency_depths();
+    units.sort_by_cached_key(|unit| {
+      (::std::cmp::Reverse(unit.priority(&priorities)), unit.depth(&depths))
+    });
+
+    let mut units = units.into_iter();
+    self.changed.clear();
+
+    while let Some(unit) = units.next() {
+      match unit {
+        // none of the dependencies of the resource changed
+        ReloadUnit::Single(id) if !self.must_reload(id, &dependents, storage) => (),
+
+        ReloadUnit::Single(id) => {
+          if let Some(reloaded) = self.reload(id, storage, ctx) {
+            self.mark_changed(id, reloaded, storage);
+          }
+        }
+
+        ReloadUnit::Group(group, mut members) => {
+          members.retain(|&id| self.must_reload(id, &dependents, storage));
+
+          if !members.is_empty() {
+            self.reload_group(group, members, storage, ctx);
+          }
+        }
+      }
+
+      if self.budget.is_some_and(|budget| start.elapsed() >= budget) {
+        // out of time; postpone the remaining resources to the next synchronization
+        for unit in units {
+          match unit {
+            ReloadUnit::Single(id) => {
+              if self.must_reload(id, &dependents, storage) {
+                self.dirties.insert(id);
+              }
+            }
+
+            ReloadUnit::Group(_, mut members) => {
+              members.retain(|&id| self.must_reload(id, &dependents, storage));
+              self.dirties.extend(members);
+            }
+          }
+        }
+
+        break;
+      }
+    }
+  }
+
+  /// Synchronize the [`Storage`] by updating the resources that ought to.
+  fn sync(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) -> SyncReport<K>
+  where K: for<'a> From<&'a Path> {
+    enter_span!("sync");
+    let start = Instant::now();
+
+    self.check_root(storage, ctx);
+    self.dequeue_scanned(storage, ctx);
+    self.dequeue_fs_events(storage, ctx);
+    self.dequeue_unlocked(storage, ctx);
+    self.discovery.flush(storage, ctx, self.budget.map(|budget| start + budget));
+    self.dequeue_retries();
+    self.dequeue_expired(storage);
+    self.reload_dirties(storage, ctx, start);
+    storage.record(|_| Some(RecordedEvent::Sync));
+
+    ::std::mem::replace(&mut self.report, SyncReport::new())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+impl<C, K> Drop for Synchronizer<C, K> {
+  /// Stop receiving events, closing the watcher unless the parent or children of the store still
+  /// share it.
+  fn drop(&mut self) {
+    let inbox = Rc::as_ptr(&self.inbox);
+    let mut hub = self.hub.borrow_mut();
+    hub.inboxes.retain(|(_, other)| other.as_ptr() != inbox);
+
+    if Rc::strong_count(&self.hub) == 1 {
+      hub.close();
+    }
+  }
+}
+
+/// Resource synchronizer used without the `"hot-reload"` feature-gate.
+///
+/// Resources are never reloaded, so there’s nothing to synchronize.
+#[cfg(not(feature = "hot-reload"))]
+struct Synchronizer<C, K>(PhantomData<fn(&mut C, K)>);
+
+#[cfg(not(feature = "hot-reload"))]
+impl<C, K> Synchronizer<C, K> {
+  fn new() -> Self {
+    Synchronizer(PhantomData)
+  }
+
+  #[inline]
+  fn invalidate(&mut self, _: KeyId) {}
+
+  #[inline]
+  fn forget(&mut self, _: KeyId) {}
+
+  #[inline]
+  fn is_dirty(&self, _: KeyId) -> bool {
+    false
+  }
+
+  #[inline]
+  fn retry_attempts(&self, _: KeyId) -> Option<u32> {
+    None
+  }
+
+  #[inline]
+  fn has_pending_changes(&mut self, _: &Storage<C, K>) -> bool {
+    false
+  }
+
+  /// Wait for a file to exist, until a deadline, polling the filesystem as nothing is watched.
+  fn wait_for_file(&mut self, path: &Path, storage: &Storage<C, K>, deadline: Instant) -> bool
+  where K: Key {
+    while !storage.is_file(path) {
+      let now = Instant::now();
+
+      if now >= deadline {
+        return false;
+      }
+
+      thread::sleep((deadline - now).min(WAIT_POLL_INTERVAL));
+    }
+
+    true
+  }
+}
+
+/// Read-only snapshot of the state of a [`Store`].
+///
+/// See [`Store::snapshot`].
+#[derive(Clone, Debug)]
+pub struct StoreSnapshot<K> {
+  /// All the resources living in the store.
+  pub resources: Vec<ResourceInfo<K>>,
+  /// Dependency edges, as `(dependent, dependency)` pairs: the first key gets reloaded when the
+  /// second one changes. Dependencies are not necessarily resources.
+  pub edges: Vec<(K, K)>,
+}
+
+/// Information about a resource living in a [`Store`].
+#[derive(Clone, Debug)]
+pub struct ResourceInfo<K> {
+  /// Key of the resource.
+  pub key: K,
+  /// Name of the type of the resource.
+  pub type_name: &'static str,
+  /// Number of successful reloads.
+  pub reloads: u32,
+  /// Time of the last successful reload, if any.
+  pub last_reload: Option<SystemTime>,
+  /// Error of the last failed loading or reloading, if it’s not been successfully reloaded since.
+  pub last_error: Option<ResourceError<K>>,
+  /// Number of dependencies of the resource.
+  pub dependencies: usize,
+  /// Number of resources depending on this one.
+  pub dependents: usize,
+  /// Whether the resource is waiting to be reloaded.
+  pub dirty: bool,
+  /// Whether the resource is a proxy.
+  pub proxy: bool,
+}
+
+/// Timing of the last successful loading or reloading of a resource.
+///
+/// See [`Storage::load_info`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LoadInfo {
+  /// Instant at which the resource was (re)loaded.
+  pub at: Instant,
+  /// Time the loading or reloading took.
+  ///
+  /// For reloads, this is the time spent reloading the resource, excluding its dependents.
+  pub duration: Duration,
+  /// Whether the resource was reloaded rather than loaded for the first time.
+  pub reload: bool,
+}
+
+/// Metadata of a file.
+///
+/// See [`Storage::file_metadata`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FileMetadata {
+  /// Size of the file, in bytes.
+  pub size: u64,
+  /// Time of the last modification of the file, if the platform provides it.
+  ///
+  /// Files of a [`MockStore`] don’t have one.
+  ///
+  /// [`MockStore`]: crate::testing::MockStore
+  pub modified: Option<SystemTime>,
+}
+
+/// File a resource was loaded from.
+///
+/// See [`Storage::provenance`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Provenance {
+  /// Where the file was read from.
+  pub source: FileSource,
+  /// Concrete path of the file – once localized, its variant selected, etc.
+  pub path: PathBuf,
+  /// Size of the file, in bytes.
+  pub size: u64,
+  /// Hash of the content of the file, which is stable across runs.
+  pub hash: u64,
+}
+
+/// Where a file was read from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FileSource {
+  /// The filesystem.
+  Filesystem,
+  /// The in-memory files of a [`MockStore`].
+  ///
+  /// [`MockStore`]: crate::testing::MockStore
+  Memory,
+}
+
+/// Kinds of filesystem events of a dependency that reload its dependents.
+///
+/// Kinds are combined with `|` – e.g. `DepEvents::CREATE | DepEvents::REMOVE`. See
+/// [`Storage::set_dep_events`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DepEvents(u8);
+
+impl DepEvents {
+  /// No event.
+  pub const NONE: Self = DepEvents(0);
+  /// The content of the file was written to.
+  pub const WRITE: Self = DepEvents(1);
+  /// The file was created, or another file was renamed to it.
+  pub const CREATE: Self = DepEvents(1 << 1);
+  /// The file was removed, or renamed to another file.
+  pub const REMOVE: Self = DepEvents(1 << 2);
+  /// The metadata of the file – e.g. its permissions – changed.
+  pub const METADATA: Self = DepEvents(1 << 3);
+  /// All the events.
+  pub const ALL: Self = DepEvents(0b1111);
+
+  /// Check whether all the events of `other` are in these events.
+  #[inline]
+  pub fn contains(self, other: Self) -> bool {
+    self.0 & other.0 == other.0
+  }
+}
+
+/// Dependencies are reloaded when they’re written to and created – which includes atomic saves.
+impl Default for DepEvents {
+  fn default() -> Self {
+    DepEvents::WRITE | DepEvents::CREATE
+  }
+}
+
+impl BitOr for DepEvents {
+  type Output = Self;
+
+  fn bitor(self, rhs: Self) -> Self {
+    DepEvents(self.0 | rhs.0)
+  }
+}
+
+impl BitOrAssign for DepEvents {
+  fn bitor_assign(&mut self, rhs: Self) {
+    self.0 |= rhs.0;
+  }
+}
+
+/// Report of a synchronization.
+///
+/// It lists the resources that were reloaded and the ones that failed to reload, along with their
+/// errors.
+pub struct SyncReport<K> {
+  reloaded: Vec<K>,
+  failed: Vec<ResourceError<K>>,
+  skipped: Vec<K>,
+  overflowed: bool,
+  root_lost: bool,
+  root_restored: bool,
+}
+
+impl<K> SyncReport<K> {
+  fn new() -> Self {
+    SyncReport {
+      reloaded: Vec::new(),
+      failed: Vec::new(),
+      skipped: Vec::new(),
+      overflowed: false,
+      root_lost: false,
+      root_restored: false,
+    }
+  }
+
+  /// Keys of the resources that were successfully reloaded.
+  pub fn reloaded(&self) -> &[K] {
+    &self.reloaded
+  }
+
+  /// Errors of the resources that failed to reload.
+  pub fn failed(&self) -> &[ResourceError<K>] {
+    &self.failed
+  }
+
+  /// Keys of the borrowed resources which reload was postponed to the next synchronization.
+  ///
+  /// Only [`Store::try_sync`] skips resources.
+  pub fn skipped(&self) -> &[K] {
+    &self.skipped
+  }
+
+  /// Check whether no resource failed to reload.
+  pub fn is_ok(&self) -> bool {
+    self.failed.is_empty()
+  }
+
+  /// Check whether file system events overflowed during the synchronization.
+  ///
+  /// That happens when more events than the cap were pending – see [`StoreOpt::set_event_cap`] –
+  /// or when the watcher itself dropped events.
+  pub fn overflowed(&self) -> bool {
+    self.overflowed
+  }
+
+  /// Check whether the root of the store was missing during the synchronization – e.g. removed or
+  /// renamed by a clean build.
+  ///
+  /// Filesystem events are not received anymore until the root reappears.
+  pub fn root_lost(&self) -> bool {
+    self.root_lost
+  }
+
+  /// Check whether the root of the store reappeared after going missing.
+  ///
+  /// The root is watched again and all the resources are reloaded.
+  pub fn root_restored(&self) -> bool {
+    self.root_restored
+  }
+}
+
+impl<K> fmt::Debug for SyncReport<K> where K: fmt::Debug {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_struct("SyncReport")
+      .field("reloaded", &self.reloaded)
+      .field("failed", &self.failed)
+      .field("skipped", &self.skipped)
+      .field("overflowed", &self.overflowed)
+      .field("root_lost", &self.root_lost)
+      .field("root_restored", &self.root_restored)
+      .finish()
+  }
+}
+
+/// A set of resources reloaded together during a synchronization.
+#[cfg(feature = "hot-reload")]
+enum ReloadUnit {
+  /// A single resource.
+  Single(KeyId),
+  /// All the dirty resources of a group.
+  Group(String, HashSet<KeyId>),
+}
+
+#[cfg(feature = "hot-reload")]
+impl ReloadUnit {
+  /// Priority of the unit; a group has the priority of its most important resource.
+  fn priority(&self, priorities: &HashMap<KeyId, i32>) -> i32 {
+    let priority = |id| priorities.get(id).cloned().unwrap_or(0);
+
+    match *self {
+      ReloadUnit::Single(ref id) => priority(id),
+      ReloadUnit::Group(_, ref members) => members.iter().map(priority).max().unwrap_or(0),
+    }
+  }
+
+  /// Dependency depth of the unit; a group has the depth of its deepest resource.
+  fn depth(&self, depths: &HashMap<KeyId, usize>) -> usize {
+    let depth = |id| depths.get(id).cloned().unwrap_or(0);
+
+    match *self {
+      ReloadUnit::Single(ref id) => depth(id),
+      ReloadUnit::Group(_, ref members) => members.iter().map(depth).max().unwrap_or(0),
+    }
+  }
+}
+
+/// Retry state of a resource that failed to reload.
+#[cfg(feature = "hot-reload")]
+struct Retry {
+  // number of failed attempts so far
+  attempts: u32,
+  // instant at which the next attempt should occur
+  next_attempt: Instant,
+}
+
+/// Policy used to retry reloading resources that failed to reload.
+///
+/// A file saved while being written to might fail to parse once and be perfectly fine a few
+/// milliseconds later. Without a retry policy, such a resource would keep its old value until the
+/// next change on the filesystem. With a retry policy, the resource is reloaded again on
+/// subsequent synchronizations, after a delay that grows exponentially with the number of failed
+/// attempts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+  max_attempts: u32,
+  initial_delay: Duration,
+  backoff_factor: u32,
+}
+
+impl RetryPolicy {
+  /// Create a new retry policy.
+  ///
+  /// `max_attempts` is the maximum number of reloads tried – including the first, failing one –
+  /// before giving up. `initial_delay` is the time to wait before the first retry.
+  pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+    RetryPolicy {
+      max_attempts,
+      initial_delay,
+      backoff_factor: 2,
+    }
+  }
+
+  /// Change the factor by which the delay is multiplied after each failed attempt.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `2`.
+  #[inline]
+  pub fn set_backoff_factor(self, backoff_factor: u32) -> Self {
+    RetryPolicy {
+      backoff_factor,
+      ..self
+    }
+  }
+
+  /// Get the maximum number of attempts.
+  #[inline]
+  pub fn max_attempts(&self) -> u32 {
+    self.max_attempts
+  }
+
+  /// Get the delay to wait before the first retry.
+  #[inline]
+  pub fn initial_delay(&self) -> Duration {
+    self.initial_delay
+  }
+
+  /// Get the backoff factor.
+  #[inline]
+  pub fn backoff_factor(&self) -> u32 {
+    self.backoff_factor
+  }
+
+  /// Delay to wait after the `attempts`-th failed attempt.
+  #[cfg(feature = "hot-reload")]
+  fn delay(&self, attempts: u32) -> Duration {
+    let factor = self.backoff_factor.saturating_pow(attempts.saturating_sub(1));
+    self.initial_delay * factor
+  }
+}
+
+/// Closure deciding whether the dependents of a resource that failed to reload must be reloaded.
+type FailureClosure<K> = Box<dyn FnMut(&ResourceError<K>, &[K]) -> bool>;
+
+/// What happens to the dependents of a resource that failed to reload.
+///
+/// A resource that failed to reload keeps its previous value, so its dependents are not reloaded by
+/// default: they would see the very same dependency. Some dependents tolerate stale dependencies,
+/// though – e.g. a material still rendering with an old texture – and might need to reload anyway.
+#[derive(Default)]
+pub enum FailurePropagation<K> {
+  /// Don’t reload the dependents.
+  #[default]
+  Stop,
+  /// Reload the dependents anyway.
+  ReloadDependents,
+  /// Call a function with the error of the resource and the keys of its dependents, which returns
+  /// whether they must be reloaded anyway.
+  ///
+  /// The function is only called if the resource has dependents.
+  Callback(FailureClosure<K>),
+}
+
+impl<K> FailurePropagation<K> {
+  /// Reload the dependents if a function returns `true`.
+  ///
+  /// See [`FailurePropagation::Callback`].
+  pub fn callback<F>(f: F) -> Self where F: 'static + FnMut(&ResourceError<K>, &[K]) -> bool {
+    FailurePropagation::Callback(Box::new(f))
+  }
+}
+
+impl<K> fmt::Debug for FailurePropagation<K> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      FailurePropagation::Stop => f.write_str("Stop"),
+      FailurePropagation::ReloadDependents => f.write_str("ReloadDependents"),
+      FailurePropagation::Callback(_) => f.write_str("Callback"),
+    }
+  }
+}
+
+/// Timer thread marking a [`Store`] as needing a synchronization at regular intervals.
+///
+/// The thread stops on its next tick once the timer is dropped.
+#[cfg(feature = "arc")]
+struct AutoSync {
+  // set by the timer thread when a synchronization is due, reset when it happens
+  due: Arc<AtomicBool>,
+  // set when the timer is dropped to stop the thread
+  stopped: Arc<AtomicBool>,
+  // ticks sent by the timer thread; at most one is pending
+  ticks: crossbeam_channel::Receiver<()>,
+  // timer thread, joined when the timer is dropped
+  thread: Option<thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "arc")]
+impl AutoSync {
+  fn spawn(interval: Duration) -> Self {
+    let due = Arc::new(AtomicBool::new(false));
+    let stopped = Arc::new(AtomicBool::new(false));
+    let (sx, rx) = bounded(1);
+
+    let thread_due = due.clone();
+    let thread_stopped = stopped.clone();
+    let thread = thread::spawn(move || {
+      let mut next_tick = Instant::now() + interval;
+
+      while !thread_stopped.load(Ordering::Relaxed) {
+        let now = Instant::now();
+
+        // parking rather than sleeping lets the timer be stopped right away
+        if now < next_tick {
+          thread::park_timeout(next_tick - now);
+          continue;
+        }
+
+        next_tick = now + interval;
+        thread_due.store(true, Ordering::Relaxed);
+        // a full channel means the owner hasn’t picked the previous tick up yet
+        let _ = sx.try_send(());
+      }
+    });
+
+    AutoSync {
+      due,
+      stopped,
+      ticks: rx,
+      thread: Some(thread),
+    }
+  }
+}
+
+#[cfg(feature = "arc")]
+impl Drop for AutoSync {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::Relaxed);
+
+    if let Some(thread) = self.thread.take() {
+      thread.thread().unpark();
+      let _ = thread.join();
+    }
+  }
+}
+
+/// Resource store. Responsible for holding and presenting resources.
+pub struct Store<C, K> {
+  storage: Storage<C, K>,
+  synchronizer: Synchronizer<C, K>,
+  // context owned by the store, if any
+  ctx: Option<C>,
+  // timer marking the store as needing a synchronization, if any
+  #[cfg(feature = "arc")]
+  auto_sync: Option<AutoSync>,
+  // debug server, if started
+  #[cfg(feature = "debug-server")]
+  debug_server: Option<DebugServer>,
+}
+
+impl<C, K> Store<C, K> where K: Key {
+  /// Create a new store.
+  ///
+  /// # Failures
+  ///
+  /// This function will fail if the root path in the [`StoreOpt`] doesn’t resolve to a correct
+  /// canonicalized path.
+  pub fn new(opt: StoreOpt<C, K>) -> Result<Self, StoreError<K>> {
+    // canonicalize the root because some platforms won’t correctly report file changes otherwise
+    let root = &opt.root;
+    let canon_root = root
+      .canonicalize()
+      .map(strip_verbatim_prefix)
+      .map_err(|_| StoreError::RootDoesNotExist(root.to_owned()))?;
+
+    Ok(Self::build(opt, canon_root, None, None))
+  }
+
+  /// Create a store which files live in memory, under a virtual root.
+  ///
+  /// Nothing is watched: changes to files are queued as events by the caller.
+  pub(crate) fn in_memory(opt: StoreOpt<C, K>) -> Self {
+    let root = opt.root.clone();
+    Self::build(opt, root, Some(HashMap::default()), None)
+  }
+
+  /// Create a child store rooted at a subdirectory of the root, sharing the filesystem watcher of
+  /// this store.
+  ///
+  /// Engines often have a store per subsystem – UI, audio, world. Creating them with [`Store::new`]
+  /// spawns a watcher – and its thread – per store, all watching overlapping trees. Children stores
+  /// share the watcher of their parent instead; they’re otherwise independent: they hold their own
+  /// resources, are synchronized on their own and can outlive their parent. Filesystem events are
+  /// dispatched to all the stores they concern, whichever store is synchronized.
+  ///
+  /// The child store is created with the default options. See [`Store::scoped_opt`] to change them.
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`StoreError::RootDoesNotExist`] if `subdir` is not a directory inside
+  /// the root.
+  pub fn scoped<P>(&self, subdir: P) -> Result<Self, StoreError<K>> where P: AsRef<Path> {
+    self.scoped_opt(subdir, StoreOpt::default())
+  }
+
+  /// Create a child store rooted at a subdirectory of the root with the given options, sharing the
+  /// filesystem watcher of this store.
+  ///
+  /// The root and the debounce duration of the options are ignored: the child store is rooted at
+  /// `subdir`, relative to the root of this store, and the watcher debounces events as configured
+  /// for this store. Children of a store which files live in memory have their own files. See
+  /// [`Store::scoped`] for further details.
+  pub fn scoped_opt<P>(&self, subdir: P, opt: StoreOpt<C, K>) -> Result<Self, StoreError<K>>
+  where P: AsRef<Path> {
+    let subdir = subdir.as_ref();
+    let root = self.storage.canon_root.join(subdir.strip_prefix("/").unwrap_or(subdir));
+    let inside = |root: &Path| root.is_dir() && root.starts_with(&self.storage.canon_root);
+
+    let (canon_root, files) = match self.storage.files {
+      Some(_) => (root, Some(HashMap::default())),
+      None => match root.canonicalize().map(strip_verbatim_prefix) {
+        Ok(canon_root) if inside(&canon_root) => (canon_root, None),
+        _ => return Err(StoreError::RootDoesNotExist(root)),
+      },
+    };
+
+    Ok(Self::build(opt, canon_root, files, Some(&self.synchronizer)))
+  }
+
+  /// Create a store with a canonicalized root, watching it unless its files live in memory or it
+  /// shares the watcher of a parent.
+  fn build(
+    opt: StoreOpt<C, K>,
+    canon_root: PathBuf,
+    files: Option<HashMap<PathBuf, Vec<u8>>>,
+    parent: Option<&Synchronizer<C, K>>,
+  ) -> Self {
+    // in-memory files are not watched
+    #[cfg(feature = "hot-reload")]
+    let in_memory = files.is_some();
+
+    // create the storage
+    let mut storage = Storage::new(
+      canon_root,
+      opt.observer,
+      opt.normalization,
+      opt.locales,
+      opt.variants,
+      opt.catch_panics,
+      opt.derived_cache,
+      opt.registry,
+      files,
+    );
+
+    storage.key_rewriter = opt.key_rewriter;
+    storage.track_provenance = opt.track_provenance;
+    #[cfg(feature = "dylib-impl")]
+    {
+      storage.trust_dylibs = opt.trust_dylibs;
+    }
+    storage.reserve(opt.expected_resources);
+
+    // create the synchronizer
+    #[cfg(feature = "hot-reload")]
+    let synchronizer = {
+      let hub = match parent {
+        Some(parent) => parent.hub.clone(),
+
+        None => {
+          // create the mpsc channel to communicate with the file watcher
+          let (wsx, wrx) = channel();
+
+          // spawn a new thread in which we look for events; in-memory files don’t need one
+          let watcher = if in_memory {
+            None
+          } else {
+            let mut watcher = notify::watcher(wsx, opt.debounce_duration).unwrap();
+            let _ = watcher.watch(&storage.canon_root, RecursiveMode::Recursive);
+            Some(watcher)
+          };
+
+          Rc::new(RefCell::new(WatcherHub::new(watcher, wrx, opt.debounce_duration)))
+        }
+      };
+      let inbox = hub.borrow_mut().subscribe(storage.canon_root.clone());
+
+      let mut synchronizer = Synchronizer::new(
+        hub,
+        inbox,
+        opt.discovery,
+        opt.retry_policy,
+        opt.sync_budget,
+        opt.event_cap,
+        opt.rescan_on_overflow,
+      );
+
+      synchronizer.lock_suffix = opt.lock_suffix;
+      synchronizer.failure_propagation = opt.failure_propagation;
+      synchronizer.dirties.reserve(opt.expected_resources);
+
+      if opt.scan_on_start && !in_memory {
+        // the discovery needs a context, so it will happen on the first synchronization
+        scan_dir(&storage.canon_root, &mut synchronizer.scanned);
+      }
+
+      synchronizer
+    };
+
+    // resources are never reloaded, so there’s nothing to watch
+    #[cfg(not(feature = "hot-reload"))]
+    let synchronizer = {
+      let _ = parent;
+      Synchronizer::new()
+    };
+
+    Store {
+      storage,
+      synchronizer,
+      ctx: None,
+      #[cfg(feature = "arc")]
+      auto_sync: opt.auto_sync.map(AutoSync::spawn),
+      #[cfg(feature = "debug-server")]
+      debug_server: None,
+    }
+  }
+
+  /// Close the store.
+  ///
+  /// The filesystem watcher is stopped – unless the parent or children of the store, see
+  /// [`Store::scoped`], still share it – and the events it sent but that were not handled yet are
+  /// dropped. The automatic synchronization timer, if any, is stopped as well. This function
+  /// returns once the watcher threads have been told to shut down and the timer thread has exited,
+  /// which matters for applications creating many short-lived stores – tests, per-document
+  /// editors, etc.
+  ///
+  /// Dropping the store does exactly the same; this function only makes it explicit.
+  pub fn close(self) {
+    drop(self);
+  }
+
+  /// Create a new store owning its context.
+  ///
+  /// The owned context is used by [`Store::get_owned`] and [`Store::sync_owned`], so that you don’t
+  /// have to thread it through every call site. The methods taking an external context remain
+  /// available.
+  ///
+  /// # Failures
+  ///
+  /// This function will fail for the same reasons as [`Store::new`].
+  pub fn with_context(opt: StoreOpt<C, K>, ctx: C) -> Result<Self, StoreError<K>> {
+    Self::new(opt).map(|store| Store {
+      ctx: Some(ctx),
+      ..store
+    })
+  }
+
+  /// Get the context owned by the store, if any.
+  pub fn context(&self) -> Option<&C> {
+    self.ctx.as_ref()
+  }
+
+  /// Get the context owned by the store mutably, if any.
+  pub fn context_mut(&mut self) -> Option<&mut C> {
+    self.ctx.as_mut()
+  }
+
+  /// Get a resource with the owned context.
+  ///
+  /// This function uses the default loading method. See [`Storage::get`] for further details.
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`StoreError::NoOwnedContext`] if the store was not created with
+  /// [`Store::with_context`], and for the same reasons as [`Storage::get`] otherwise.
+  pub fn get_owned<T>(&mut self, key: &K) -> Result<Res<T>, StoreErrorOr<T, C, K>>
+  where T: Load<C, K> {
+    let ctx = self.ctx.as_mut().ok_or(StoreErrorOr::StoreError(StoreError::NoOwnedContext))?;
+    self.storage.get(key, ctx)
+  }
+
+  /// Get a resource, waiting for its file to appear if it doesn’t exist yet.
+  ///
+  /// Build pipelines often produce their outputs slightly after the programs consuming them start.
+  /// If the file of the resource doesn’t exist, this function blocks until it appears, for at most
+  /// `timeout`, and then loads the resource. Filesystem events received while waiting are handled on
+  /// the next synchronization. Resources already loaded and resources which keys don’t address a
+  /// file – see [`Key::fs_path`] – are got right away.
+  ///
+  /// This function uses the default loading method. See [`Store::get_by_when_available`] to use
+  /// another one.
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`StoreError::Timeout`] if the file doesn’t appear in time, and for
+  /// the same reasons as [`Storage::get`] otherwise.
+  pub fn get_when_available<T>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    timeout: Duration,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K>>
+  where T: Load<C, K> {
+    self.get_by_when_available(key, ctx, timeout, ())
+  }
+
+  /// Get a resource with a given loading method, waiting for its file to appear if it doesn’t exist
+  /// yet.
+  ///
+  /// See [`Store::get_when_available`] for further details.
+  pub fn get_by_when_available<T, M>(
+    &mut self,
+    key: &K,
+    ctx: &mut C,
+    timeout: Duration,
+    method: M,
+  ) -> Result<Res<T>, StoreErrorOr<T, C, K, M>>
+  where T: Load<C, K, M> {
+    let deadline = Instant::now() + timeout;
+
+    // read-only stores never wait, as they never load anything
+    if !self.storage.read_only && self.storage.lookup_known(key).is_none() {
+      let prepared = self.storage.prepare_checked(key).map_err(StoreErrorOr::StoreError)?;
+
+      if let Some(path) = prepared.fs_path() {
+        if !self.synchronizer.wait_for_file(path, &self.storage, deadline) {
+          return Err(StoreErrorOr::StoreError(StoreError::Timeout(key.clone())));
+        }
+      }
+    }
+
+    self.storage.get_by(key, ctx, method)
+  }
+
+  /// Write the file of a resource atomically.
+  ///
+  /// The bytes are written to a temporary file next to the file of the resource, which is then
+  /// renamed over it, so that the resource is never reloaded from a half-written file. This is
+  /// meant for editors saving resources they’ve changed.
+  ///
+  /// The watcher events caused by the write are ignored: the resource – and the resources depending
+  /// on its file – are instead reloaded exactly once, on the next synchronization. Events about the
+  /// file received within the debounce duration – see [`StoreOpt::set_debounce_duration`] – are
+  /// ignored as well.
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`io::ErrorKind::InvalidInput`] if the key doesn’t address a file
+  /// inside the root – see [`Key::fs_path`] – and if the file cannot be written.
+  pub fn write_resource<B>(&mut self, key: &K, bytes: B) -> io::Result<()> where B: AsRef<[u8]> {
+    let prepared = self
+      .storage
+      .prepare_checked(key)
+      .map_err(|_| {
+        let message = format!("key {:?} is outside of the root", Described(key));
+        io::Error::new(io::ErrorKind::InvalidInput, message)
+      })?;
+    let path = prepared.fs_path().map(Path::to_owned).ok_or_else(|| {
+      let message = format!("key {:?} doesn’t address a file", Described(key));
+      io::Error::new(io::ErrorKind::InvalidInput, message)
+    })?;
+    #[cfg_attr(not(feature = "hot-reload"), allow(unused_variables))]
+    let existed = self.storage.is_file(&path);
+
+    match self.storage.files {
+      Some(ref mut files) => {
+        files.insert(path.clone(), bytes.as_ref().to_owned());
+      }
+
+      None => {
+        let name = path.file_name().map_or_else(Default::default, |name| name.to_string_lossy());
+        let tmp_path = path.with_file_name(format!(".{}.warmy-tmp", name));
+
+        #[cfg(feature = "hot-reload")]
+        {
+          self.synchronizer.suppress(tmp_path.clone());
+          self.synchronizer.suppress(path.clone());
+        }
+
+        let written = fs::write(&tmp_path, bytes).and_then(|_| fs::rename(&tmp_path, &path));
+
+        if written.is_err() {
+          let _ = fs::remove_file(&tmp_path);
+        }
+
+        written?;
+      }
+    }
+
+    // reload as if the watcher had seen the file change, exactly once
+    #[cfg(feature = "hot-reload")]
+    self.queue_fs_event(if existed {
+      DebouncedEvent::Write(path)
+    } else {
+      DebouncedEvent::Create(path)
+    });
+
+    Ok(())
+  }
+
+  /// Watch a single file, mapping its events to a key.
+  ///
+  /// This is meant for the few files living outside the root – typically a configuration file
+  /// such as `/etc/myapp/config.toml` – read by the loader of a logical key: writing, creating or
+  /// replacing the file gets the resource of the key reloaded on the next synchronization, as if
+  /// the file were addressed by the key. Stores which files live in memory – see [`MockStore`] –
+  /// don’t watch it.
+  ///
+  /// # Failures
+  ///
+  /// This function fails if the path cannot be canonicalized – e.g. because the file doesn’t
+  /// exist.
+  ///
+  /// [`MockStore`]: crate::testing::MockStore
+  pub fn watch_file<P>(&mut self, path: P, key: &K) -> io::Result<()> where P: AsRef<Path> {
+    let path = strip_verbatim_prefix(path.as_ref().canonicalize()?);
+
+    #[cfg(feature = "hot-reload")]
+    {
+      let id = self.storage.intern(key);
+
+      // files inside the root are already watched
+      if self.storage.files.is_none() && !path.starts_with(&self.storage.canon_root) {
+        self.synchronizer.watch_file(path.clone());
+      }
+
+      self.storage.event_ids.insert(path, id);
+    }
+
+    #[cfg(not(feature = "hot-reload"))]
+    let _ = (path, key);
+
+    Ok(())
+  }
+
+  /// Invalidate a resource so that it gets reloaded on the next synchronization.
+  ///
+  /// This is especially useful for logical resources, which are not watched on the filesystem.
+  /// Return `false` if the resource is unknown.
+  pub fn invalidate(&mut self, key: &K) -> bool {
+    match self.storage.lookup_known(key) {
+      Some(id) => {
+        self.synchronizer.invalidate(id);
+        true
+      }
+
+      None => false,
+    }
+  }
+
+  /// Invalidate all the resources which keys satisfy a predicate so that they get reloaded on the
+  /// next synchronization.
+  ///
+  /// The predicate is passed prepared keys. Combined with [`SimpleKey::starts_with`], this allows
+  /// to invalidate a whole namespace at once. Return the number of invalidated resources.
+  ///
+  /// [`SimpleKey::starts_with`]: crate::key::SimpleKey::starts_with
+  pub fn invalidate_where<F>(&mut self, mut predicate: F) -> usize where F: FnMut(&K) -> bool {
+    let keys = &self.storage.keys;
+    let ids: Vec<KeyId> =
+      self.storage.metadata.keys().filter(|&&id| predicate(keys.key(id))).cloned().collect();
+    let count = ids.len();
+
+    for id in ids {
+      self.synchronizer.invalidate(id);
+    }
+
+    count
+  }
+
+  /// Invalidate all the resources having a tag so that they get reloaded on the next
+  /// synchronization.
+  ///
+  /// See [`Storage::add_tag`]. Return the number of invalidated resources.
+  pub fn invalidate_tagged(&mut self, tag: &str) -> usize {
+    let ids = self.storage.tagged(tag);
+    let mut count = 0;
+
+    for id in ids {
+      if self.storage.metadata.contains_key(&id) {
+        self.synchronizer.invalidate(id);
+        count += 1;
+      }
+    }
+
+    count
+  }
+
+  /// Remove a resource from the store.
+  ///
+  /// The resource is not reloaded anymore and is loaded again the next time it’s asked for. Its
+  /// tags, time-to-live and dependencies are forgotten; its group and priority are kept. Handles
+  /// to the resource that are still alive remain valid but are frozen to their current value.
+  ///
+  /// Return `false` if the resource is not loaded.
+  pub fn remove(&mut self, key: &K) -> bool {
+    match self.storage.lookup_known(key) {
+      Some(id) => self.remove_id(id),
+      None => false,
+    }
+  }
+
+  /// Remove all the resources having a tag from the store – e.g. all the resources of a level
+  /// that was left.
+  ///
+  /// See [`Store::remove`] and [`Storage::add_tag`]. Return the number of removed resources.
+  pub fn remove_tagged(&mut self, tag: &str) -> usize {
+    let ids = self.storage.tagged(tag);
+    ids.into_iter().filter(|&id| self.remove_id(id)).count()
+  }
+
+  /// Remove all the resources of type `T` from the store – e.g. all the audio buffers when the
+  /// audio device is reset – leaving the resources of other types untouched.
+  ///
+  /// See [`Store::remove`]. Return the number of removed resources.
+  pub fn remove_all<T>(&mut self) -> usize where T: 'static {
+    let ty = TypeId::of::<T>();
+    let ids: Vec<KeyId> = self
+      .storage
+      .metadata
+      .iter()
+      .filter(|(_, metadata)| metadata.ty.id() == ty)
+      .map(|(&id, _)| id)
+      .collect();
+
+    ids.into_iter().filter(|&id| self.remove_id(id)).count()
+  }
+
+  /// Remove a resource from the store and forget about its pending reloads.
+  fn remove_id(&mut self, id: KeyId) -> bool {
+    if !self.storage.evict(id) {
+      return false;
+    }
+
+    self.synchronizer.forget(id);
+    true
+  }
+
+  /// Change the locales used to localize files, by decreasing preference.
+  ///
+  /// All the resources that were loaded from localized files – see [`Storage::localize`] – get
+  /// reloaded on the next synchronization.
+  pub fn set_locales<L>(&mut self, locales: L) where L: IntoIterator, L::Item: Into<String> {
+    self.storage.locales = locales.into_iter().map(Into::into).collect();
+
+    for &id in &self.storage.localized {
+      self.synchronizer.invalidate(id);
+    }
+  }
+
+  /// Change the variants used to select files, by decreasing preference.
+  ///
+  /// All the resources that were loaded from variant files – see [`Storage::select_variant`] – get
+  /// reloaded on the next synchronization.
+  pub fn set_variants<V>(&mut self, variants: V) where V: IntoIterator, V::Item: Into<String> {
+    self.storage.variants = variants.into_iter().map(Into::into).collect();
+
+    for &id in &self.storage.varied {
+      self.synchronizer.invalidate(id);
+    }
+  }
+
+  /// Take a snapshot of the state of the store.
+  ///
+  /// The snapshot is a read-only copy, meant to be rendered by debug overlays and tools. Resources
+  /// are listed in no particular order.
+  pub fn snapshot(&self) -> StoreSnapshot<K> {
+    let storage = &self.storage;
+    let resources = storage
+      .metadata
+      .iter()
+      .map(|(&id, metadata)| ResourceInfo {
+        key: storage.keys.key(id).clone(),
+        type_name: metadata.ty.name,
+        reloads: metadata.reloads,
+        last_reload: metadata.last_reload,
+        last_error: metadata.last_error.clone(),
+        dependencies: storage.deps.values().filter(|dependents| dependents.contains(&id)).count(),
+        dependents: storage.deps.get(&id).map_or(0, Vec::len),
+        dirty: self.synchronizer.is_dirty(id),
+        proxy: metadata.proxy,
+      })
+      .collect();
+    let edges = storage
+      .deps
+      .iter()
+      .flat_map(|(&dep, dependents)| {
+        let dep = storage.keys.key(dep);
+        dependents.iter().map(move |&id| (storage.keys.key(id).clone(), dep.clone()))
+      })
+      .collect();
+
+    StoreSnapshot { resources, edges }
+  }
+
+  /// Subscribe to the resource events of the store.
+  ///
+  /// Every successful load and reload, as well as every reload failure, is broadcast to all
+  /// subscribers as a [`StoreEvent`]. As the receiver is [`Send`], this is the way to get other
+  /// threads – e.g. an audio or a render thread – to react to resource changes without sharing the
+  /// store. Events are sent as they happen, so reloads are received during [`Store::sync`].
+  ///
+  /// The channel is unbounded: drop the receiver when you are not interested in events anymore.
+  #[cfg(feature = "arc")]
+  pub fn subscribe(&mut self) -> crossbeam_channel::Receiver<StoreEvent<K>> {
+    let (sx, rx) = unbounded();
+    self.storage.subscribers.push(sx);
+    rx
+  }
+
+  /// Check whether the auto-sync timer marked the store as needing a synchronization.
+  ///
+  /// The mark is removed by any synchronization. Always `false` if auto-sync is disabled – see
+  /// [`StoreOpt::set_auto_sync`].
+  #[cfg(feature = "arc")]
+  pub fn needs_sync(&self) -> bool {
+    self.auto_sync.as_ref().is_some_and(|auto_sync| auto_sync.due.load(Ordering::Relaxed))
+  }
+
+  /// Synchronize the [`Store`] with a provided context if the auto-sync timer marked it as needing
+  /// a synchronization.
+  ///
+  /// Return the report of the synchronization, if any happened. See [`StoreOpt::set_auto_sync`].
+  #[cfg(feature = "arc")]
+  pub fn sync_if_due(&mut self, ctx: &mut C) -> Option<SyncReport<K>>
+  where K: for<'a> From<&'a Path> {
+    if self.needs_sync() {
+      Some(self.sync(ctx))
+    } else {
+      None
+    }
+  }
+
+  /// Get a channel receiving a message every time the auto-sync timer marks the store as needing a
+  /// synchronization, if auto-sync is enabled.
+  ///
+  /// Applications blocking on other channels can select on this one to know when to call
+  /// [`Store::sync_if_due`]. At most one message is pending at a time. See
+  /// [`StoreOpt::set_auto_sync`].
+  #[cfg(feature = "arc")]
+  pub fn auto_sync_ticks(&self) -> Option<crossbeam_channel::Receiver<()>> {
+    self.auto_sync.as_ref().map(|auto_sync| auto_sync.ticks.clone())
+  }
+
+  /// Remove the auto-sync mark, if any.
+  fn synced(&self) {
+    #[cfg(feature = "arc")]
+    {
+      if let Some(ref auto_sync) = self.auto_sync {
+        auto_sync.due.store(false, Ordering::Relaxed);
+      }
+    }
+  }
+
+  /// Dump the state of the store in a human-readable form.
+  ///
+  /// The dump lists all the resources – along with their types, whether they’re dirty, proxied or
+  /// waiting for a retry, and their last errors – and the dependency edges. This is handy to
+  /// diagnose why a resource didn’t reload. The format is not stable and should not be parsed.
+  pub fn debug_dump(&self) -> String {
+    let storage = &self.storage;
+    let mut resources: Vec<_> = storage.metadata.iter().collect();
+    resources.sort_by_cached_key(|&(&id, _)| format!("{:?}", Described(storage.keys.key(id))));
+
+    let mut dump = format!("root: {}\nresources:\n", storage.canon_root.display());
+
+    for (&id, metadata) in resources {
+      dump += &format!("  {:?}: {}", Described(storage.keys.key(id)), metadata.ty.name);
+
+      if self.synchronizer.is_dirty(id) {
+        dump += " [dirty]";
+      }
+
+      if metadata.proxy {
+        dump += " [proxy]";
+      }
+
+      if let Some(attempts) = self.synchronizer.retry_attempts(id) {
+        dump += &format!(" [retry #{}]", attempts);
+      }
+
+      match metadata.last_error {
+        Some(ref error) => dump += &format!("\n    last error: {}\n", error.error()),
+        None => dump += "\n",
+      }
+    }
+
+    let mut edges: Vec<_> = storage
+      .deps
+      .iter()
+      .map(|(&dep, dependents)| {
+        let dependents: Vec<_> =
+          dependents.iter().map(|&id| Described(storage.keys.key(id))).collect();
+        format!("  {:?} -> {:?}\n", Described(storage.keys.key(dep)), dependents)
+      })
+      .collect();
+    edges.sort();
+
+    dump += "dependencies:\n";
+    dump.extend(edges);
+    dump
+  }
+
+  /// Start recording the events of the store, discarding the current recording, if any.
+  ///
+  /// The filesystem events handled by the store, its synchronizations and the loads and reloads of
+  /// its resources are recorded in an [`EventLog`] until [`Store::stop_recording`] is called. See
+  /// the [`replay`](crate::replay) module for further details.
+  #[cfg(feature = "hot-reload")]
+  pub fn start_recording(&mut self) {
+    self.storage.recording = Some(EventLog::new());
+  }
+
+  /// Stop recording the events of the store and get the recorded log, if recording.
+  #[cfg(feature = "hot-reload")]
+  pub fn stop_recording(&mut self) -> Option<EventLog> {
+    self.storage.recording.take()
+  }
+
+  /// Get the events recorded so far, if recording.
+  #[cfg(feature = "hot-reload")]
+  pub fn recording(&self) -> Option<&EventLog> {
+    self.storage.recording.as_ref()
+  }
+
+  /// Synchronize the [`Store`] by updating the resources that ought to with a provided context.
+  ///
+  /// Return a report of the reloaded resources and of the ones that failed to reload.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing.
+  #[cfg(feature = "hot-reload")]
+  pub fn sync(&mut self, ctx: &mut C) -> SyncReport<K> where K: for<'a> From<&'a Path> {
+    #[cfg(feature = "debug-server")]
+    self.queue_debug_reloads();
+
+    self.synced();
+    let report = self.synchronizer.sync(&mut self.storage, ctx);
+
+    #[cfg(feature = "debug-server")]
+    self.publish_debug();
+
+    report
+  }
+
+  /// Synchronize the [`Store`] with the owned context.
+  ///
+  /// # Failures
+  ///
+  /// This function fails with [`StoreError::NoOwnedContext`] – without synchronizing anything – if
+  /// the store was not created with [`Store::with_context`].
+  #[cfg(feature = "hot-reload")]
+  pub fn sync_owned(&mut self) -> Result<SyncReport<K>, StoreError<K>>
+  where K: for<'a> From<&'a Path> {
+    if self.ctx.is_none() {
+      return Err(StoreError::NoOwnedContext);
+    }
+
+    #[cfg(feature = "debug-server")]
+    self.queue_debug_reloads();
+
+    self.synced();
+    let ctx = self.ctx.as_mut().ok_or(StoreError::NoOwnedContext)?;
+    let report = self.synchronizer.sync(&mut self.storage, ctx);
+
+    #[cfg(feature = "debug-server")]
+    self.publish_debug();
+
+    Ok(report)
+  }
+
+  /// Check whether synchronizing the [`Store`] would do anything.
+  ///
+  /// On most frames, nothing changed and synchronizing is wasted bookkeeping. This function is a
+  /// cheap check of whether filesystem events were received or resources are waiting to be
+  /// reloaded – because they were invalidated, their reloads must be retried or their time-to-live
+  /// elapsed. It errs on the side of caution: a synchronization might end up doing nothing even if
+  /// this function returns `true`, but never has anything to do if it returns `false`.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function
+  /// always returns `false`.
+  pub fn has_pending_changes(&mut self) -> bool {
+    #[cfg(feature = "debug-server")]
+    self.queue_debug_reloads();
+
+    self.synchronizer.has_pending_changes(&self.storage)
+  }
+
+  /// Synchronize the [`Store`] like [`Store::sync`], skipping the resources currently borrowed.
+  ///
+  /// Reloading a resource replaces its value, which requires it not to be borrowed: [`Store::sync`]
+  /// panics – or blocks, with the `"arc"` feature-gate – if you hold a borrow of a resource it
+  /// reloads. This function postpones the reload of such resources to the next synchronization
+  /// instead, and lists them in [`SyncReport::skipped`]. Their dependents are reloaded along with
+  /// them.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing.
+  #[cfg(feature = "hot-reload")]
+  pub fn try_sync(&mut self, ctx: &mut C) -> SyncReport<K> where K: for<'a> From<&'a Path> {
+    self.synchronizer.skip_borrowed = true;
+    let report = self.sync(ctx);
+    self.synchronizer.skip_borrowed = false;
+
+    report
+  }
+
+  /// Synchronize the [`Store`] like [`Store::sync`], only reloading the resources of type `T`.
+  ///
+  /// Filesystem events are all handled, but the reload of the resources of other types – and of
+  /// the groups containing any – is deferred to the next synchronizations. This allows to apply
+  /// cheap changes right away – e.g. configurations, during gameplay – while deferring heavy ones –
+  /// e.g. textures – to a better time, such as a loading screen.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing.
+  #[cfg(feature = "hot-reload")]
+  pub fn sync_only<T>(&mut self, ctx: &mut C) -> SyncReport<K>
+  where T: 'static, K: for<'a> From<&'a Path> {
+    self.synchronizer.type_filter = Some(TypeId::of::<T>());
+    let report = self.sync(ctx);
+    self.synchronizer.type_filter = None;
+
+    report
+  }
+
+  /// Synchronize the [`Store`] by updating the resources that ought to with a provided context.
+  ///
+  /// Return a report of the reloaded resources and of the ones that failed to reload.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing.
+  #[cfg(not(feature = "hot-reload"))]
+  #[inline]
+  pub fn sync(&mut self, _: &mut C) -> SyncReport<K> where K: for<'a> From<&'a Path> {
+    self.synced();
+    SyncReport::new()
+  }
+
+  /// Synchronize the [`Store`] with the owned context.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing but failing with [`StoreError::NoOwnedContext`] if the store was not created with
+  /// [`Store::with_context`].
+  #[cfg(not(feature = "hot-reload"))]
+  #[inline]
+  pub fn sync_owned(&mut self) -> Result<SyncReport<K>, StoreError<K>>
+  where K: for<'a> From<&'a Path> {
+    if self.ctx.is_none() {
+      return Err(StoreError::NoOwnedContext);
+    }
+
+    self.synced();
+    Ok(SyncReport::new())
+  }
+
+  /// Synchronize the [`Store`] like [`Store::sync`], skipping the resources currently borrowed.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing.
+  #[cfg(not(feature = "hot-reload"))]
+  #[inline]
+  pub fn try_sync(&mut self, _: &mut C) -> SyncReport<K> where K: for<'a> From<&'a Path> {
+    SyncReport::new()
+  }
+
+  /// Synchronize the [`Store`] like [`Store::sync`], only reloading the resources of type `T`.
+  ///
+  /// Without the `"hot-reload"` feature-gate, resources are never reloaded and this function does
+  /// nothing.
+  #[cfg(not(feature = "hot-reload"))]
+  #[inline]
+  pub fn sync_only<T>(&mut self, _: &mut C) -> SyncReport<K>
+  where T: 'static, K: for<'a> From<&'a Path> {
+    SyncReport::new()
+  }
+
+  /// Borrow the [`Store`] until the end of a scope, synchronizing it with a provided context then.
+  ///
+  /// This guarantees a single synchronization per scope – typically a frame – however the store
+  /// is passed around in that scope. See [`SyncScope`] for further details.
+  ///
+  /// [`SyncScope`]: crate::frame::SyncScope
+  pub fn sync_scope<'a>(&'a mut self, ctx: &'a mut C) -> SyncScope<'a, C, K>
+  where K: for<'b> From<&'b Path> {
+    SyncScope::new(self, ctx)
+  }
+
+  /// Reload all the resources and synchronize the [`Store`].
+  ///
+  /// Every resource is marked dirty and the store is synchronized right away: resources are
+  /// reloaded by decreasing priority, dependencies before their dependents, and within the time
+  /// budget of the store, if any – the remaining resources being reloaded on the next
+  /// synchronizations. This is handy for “reload everything” developer shortcuts, or when the files
+  /// resources are loaded from changed behind the back of the watcher – e.g. after switching mod
+  /// directories. Filesystem events pending are handled as well.
+  ///
+  /// Return the report of the synchronization. Without the `"hot-reload"` feature-gate, resources
+  /// are never reloaded and this function does nothing.
+  pub fn reload_all(&mut self, ctx: &mut C) -> SyncReport<K> where K: for<'a> From<&'a Path> {
+    self.invalidate_where(|_| true);
+    self.sync(ctx)
+  }
+
+  /// Synchronize the [`Store`] in a loop, sleeping `interval` between two synchronizations.
+  ///
+  /// `f` is called with the report of every synchronization and the loop goes on as long as it
+  /// returns `true`. This is the loop small tools without a main loop of their own – configuration
+  /// watchers, static site previewers, etc. – would otherwise write by hand:
+  ///
+  /// ```no_run
+  /// use std::time::Duration;
+  /// use warmy::{SimpleKey, Store, StoreOpt};
+  ///
+  /// let mut store: Store<(), SimpleKey> = Store::new(StoreOpt::default()).unwrap();
+  ///
+  /// store.sync_forever(&mut (), Duration::from_millis(100), |report| {
+  ///   for key in report.reloaded() {
+  ///     println!("reloaded {:?}", key);
+  ///   }
+  ///
+  ///   true
+  /// });
+  /// ```
+  pub fn sync_forever<F>(&mut self, ctx: &mut C, interval: Duration, mut f: F)
+  where F: FnMut(SyncReport<K>) -> bool,
+        K: for<'a> From<&'a Path> {
+    loop {
+      let report = self.sync(ctx);
+
+      if !f(report) {
+        break;
+      }
+
+      thread::sleep(interval);
+    }
+  }
+
+  /// Queue a filesystem event, handled on the next synchronization.
+  #[cfg(feature = "hot-reload")]
+  pub(crate) fn queue_fs_event(&mut self, event: DebouncedEvent) {
+    self.synchronizer.events.push_back(event);
+  }
+
+  /// Synchronize the [`Store`] with replayed filesystem events instead of the ones of the watcher,
+  /// which are dropped.
+  #[cfg(feature = "hot-reload")]
+  pub(crate) fn sync_replayed(&mut self, events: Vec<DebouncedEvent>, ctx: &mut C) -> SyncReport<K>
+  where K: for<'a> From<&'a Path> {
+    // pending events come from the watcher too
+    self.synchronizer.events.clear();
+    self.synchronizer.events.extend(events);
+
+    self.synchronizer.replaying = true;
+    let report = self.sync(ctx);
+    self.synchronizer.replaying = false;
+
+    report
+  }
+
+  /// Run a function with the time budget of synchronizations temporarily replaced.
+  #[cfg(feature = "hot-reload")]
+  pub(crate) fn with_sync_budget<F, R>(&mut self, budget: Option<Duration>, f: F) -> R
+  where F: FnOnce(&mut Self) -> R {
+    let previous = std::mem::replace(&mut self.synchronizer.budget, budget);
+    let r = f(self);
+    self.synchronizer.budget = previous;
+    r
+  }
+
+  /// Run a function with the time budget of synchronizations temporarily replaced.
+  #[cfg(not(feature = "hot-reload"))]
+  pub(crate) fn with_sync_budget<F, R>(&mut self, _: Option<Duration>, f: F) -> R
+  where F: FnOnce(&mut Self) -> R {
+    f(self)
+  }
+
+  /// Start a tiny HTTP server exposing the state of the store, for remote debugging.
+  ///
+  /// The server runs in its own thread and answers the following requests with JSON:
+  ///
+  /// - `GET /resources` lists all the resources, along with their types, reload counts, whether
+  ///   they’re dirty or proxied and their last errors.
+  /// - `GET /errors` lists only the resources whose last reload failed.
+  /// - `POST /reload?key=…` queues a force-reload of the resource whose key, as described by
+  ///   [`Key::describe`], is the percent-encoded `key` parameter.
+  ///
+  /// The served state is refreshed after each synchronization and queued reloads happen on the next
+  /// one. Calling this function again replaces the current server, which is stopped when the store
+  /// is dropped.
+  ///
+  /// Return the address the server listens on, which is handy if you asked for port `0`.
+  #[cfg(feature = "debug-server")]
+  pub fn serve_debug<A>(&mut self, addr: A) -> io::Result<SocketAddr> where A: ToSocketAddrs {
+    let server = DebugServer::spawn(addr)?;
+    let addr = server.addr();
+    self.debug_server = Some(server);
+    self.publish_debug();
+
+    Ok(addr)
+  }
+
+  /// Mark dirty the resources the debug server was asked to reload.
+  #[cfg(feature = "debug-server")]
+  fn queue_debug_reloads(&mut self) {
+    let requested = match self.debug_server {
+      Some(ref server) => server.requested_reloads(),
+      None => return,
+    };
+
+    if requested.is_empty() {
+      return;
+    }
+
+    let storage = &self.storage;
+    let synchronizer = &mut self.synchronizer;
+
+    for &id in storage.metadata.keys() {
+      if requested.contains(&format!("{:?}", Described(storage.keys.key(id)))) {
+        synchronizer.invalidate(id);
+      }
+    }
+  }
+
+  /// Publish the state of the store to the debug server, if any.
+  #[cfg(feature = "debug-server")]
+  fn publish_debug(&self) {
+    if let Some(ref server) = self.debug_server {
+      server.publish(self.snapshot());
+    }
+  }
+}
+
+impl<C, K> Deref for Store<C, K> {
+  type Target = Storage<C, K>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.storage
+  }
+}
+
+impl<C, K> DerefMut for Store<C, K> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.storage
+  }
+}
+
+/// Create the function reloading a resource.
+#[cfg(feature = "hot-reload")]
+fn reloader<T, C, K, M>(res: Res<T>, key: K) -> Reload<C, K>
+where T: Load<C, K, M>, K: Key {
+  Rc::new(move |storage, ctx| {
+    let catch_panics = storage.catch_panics;
+    let panicked = |payload| {
+      ResourceError::with_error::<T, C, M>(key.clone(), Arc::new(ReloadPanic::new(payload)))
+    };
+
+    // the resource is borrowed outside of the guarded code so that a panic doesn’t poison it; it
+    // must not be borrowed while accepting, as it might be behind a mutex
+    let reloaded = {
+      let current = res.borrow();
+      guard_panics(catch_panics, || {
+        <T as Load<C, K, M>>::reload(&current, key.clone(), storage, ctx)
+      })
+      .map_err(&panicked)?
+    };
+    let reloaded = match reloaded {
+      Ok(r) => {
+        let current = res.borrow();
+        guard_panics(catch_panics, || <T as Load<C, K, M>>::accept(&current, &r, ctx))
+          .map_err(&panicked)?
+          .map(|_| r)
+      }
+      Err(e) => Err(e),
+    };
+
+    match reloaded {
+      Ok(r) => {
+        // replace the current resource with the freshly loaded one once committed
+        let res = res.clone();
+        let commit: Commit = Box::new(move || *res.borrow_mut() = r);
+        Ok(commit)
+      }
+      Err(e) => Err(ResourceError::new::<T, C, M>(key.clone(), e)),
+    }
+  })
+}
+
+/// Run reload code, catching its panics if asked to.
+#[cfg(feature = "hot-reload")]
+fn guard_panics<F, R>(catch_panics: bool, f: F) -> Result<R, Box<dyn Any + Send>>
+where F: FnOnce() -> R {
+  if catch_panics {
+    panic::catch_unwind(AssertUnwindSafe(f))
+  } else {
+    Ok(f())
+  }
+}
+
+/// Insert a suffix before the extension of a file – e.g. `strings.json` becomes `strings.fr.json`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut name = path.file_stem().unwrap_or_default().to_owned();
+  name.push(".");
+  name.push(suffix);
+
+  if let Some(ext) = path.extension() {
+    name.push(".");
+    name.push(ext);
+  }
+
+  path.with_file_name(name)
+}
+
+/// Identifier of the derived outputs of a resource for a transform, stable across runs and builds.
+///
+/// It’s computed from a canonical byte form of the key – the path it addresses or its description
+/// – rather than its [`Hash`] implementation, which is free to change between versions.
+fn derived_entry<K>(key: &K, transform: &str) -> u64 where K: Key {
+  let mut hasher = StableHasher::default();
+
+  match key.fs_path() {
+    Some(path) => {
+      hasher.write(b"path:");
+      hasher.write(path.to_string_lossy().as_bytes());
+    }
+
+    None => {
+      hasher.write(b"key:");
+      hasher.write(Described(key).to_string().as_bytes());
+    }
+  }
+
+  // keep the key and the transform apart, so that they cannot run into each other
+  hasher.write(&[0]);
+  hasher.write(transform.as_bytes());
+  hasher.finish()
+}
+
+/// Write the output of a transform in the derived cache, removing the outputs of previous sources.
+fn write_derived(dir: &Path, entry: u64, path: &Path, output: &[u8]) -> io::Result<()> {
+  fs::create_dir_all(dir)?;
+
+  let prefix = format!("{:016x}.", entry);
+
+  for file in fs::read_dir(dir)? {
+    let file = file?.path();
+    let name = file.file_name().and_then(|name| name.to_str());
+    let stale = name.is_some_and(|name| name.starts_with(&prefix));
+
+    if stale && file != path {
+      fs::remove_file(file)?;
+    }
+  }
+
+  // write aside and rename, so that a crash never leaves a truncated output behind
+  let tmp = path.with_extension("tmp");
+  fs::write(&tmp, output)?;
+  fs::rename(tmp, path)
+}
+
+/// Options used to match glob patterns against paths relative to the root.
+fn glob_options() -> MatchOptions {
+  MatchOptions {
+    require_literal_separator: true,
+    ..MatchOptions::new()
+  }
+}
+
+/// Longest directory of a glob pattern free of wildcards – e.g. `maps/levels` for
+/// `maps/levels/*.json`. Matching files all live somewhere in that directory.
+fn literal_prefix(pattern: &str) -> PathBuf {
+  let mut components: Vec<&str> = pattern.split('/').collect();
+
+  // the last component names files, not a directory
+  components.pop();
+
+  components
+    .into_iter()
+    .take_while(|c| !c.is_empty() && *c != "." && *c != ".." && !c.contains(&['*', '?', '['][..]))
+    .collect()
+}
+
+/// Recursively gather all the files living in a directory.
+fn scan_dir(dir: &Path, files: &mut Vec<PathBuf>) {
+  let entries = match dir.read_dir() {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+
+    match entry.file_type() {
+      Ok(file_type) if file_type.is_dir() => scan_dir(&path, files),
+      Ok(_) => files.push(path),
+      Err(_) => (),
+    }
+  }
+}
+
+/// Various options to customize a [`Store`].
+///
+/// Feel free to inspect all of its declared methods for further information.
+pub struct StoreOpt<C, K> {
+  root: PathBuf,
+  debounce_duration: Duration,
+  discovery: Discovery<C, K>,
+  retry_policy: Option<RetryPolicy>,
+  observer: Box<dyn StoreObserver<K>>,
+  sync_budget: Option<Duration>,
+  event_cap: Option<usize>,
+  rescan_on_overflow: bool,
+  scan_on_start: bool,
+  normalization: Normalization,
+  locales: Vec<String>,
+  variants: Vec<String>,
+  catch_panics: bool,
+  derived_cache: Option<PathBuf>,
+  registry: LoaderRegistry<C, K>,
+  lock_suffix: Option<String>,
+  failure_propagation: FailurePropagation<K>,
+  key_rewriter: Option<KeyRewriter<K>>,
+  #[cfg(feature = "arc")]
+  auto_sync: Option<Duration>,
+  expected_resources: usize,
+  track_provenance: bool,
+  #[cfg(feature = "dylib-impl")]
+  trust_dylibs: bool,
+}
+
+impl<C, K> Default for StoreOpt<C, K> {
+  fn default() -> Self {
+    StoreOpt {
+      root: PathBuf::from("."),
+      debounce_duration: Duration::from_millis(50),
+      discovery: Discovery::default(),
+      retry_policy: None,
+      observer: Box::new(()),
+      sync_budget: None,
+      event_cap: None,
+      rescan_on_overflow: false,
+      scan_on_start: false,
+      normalization: Normalization::default(),
+      locales: Vec::new(),
+      variants: Vec::new(),
+      catch_panics: false,
+      derived_cache: None,
+      registry: LoaderRegistry::new(),
+      lock_suffix: None,
+      failure_propagation: FailurePropagation::default(),
+      key_rewriter: None,
+      #[cfg(feature = "arc")]
+      auto_sync: None,
+      expected_resources: 0,
+      track_provenance: false,
+      #[cfg(feature = "dylib-impl")]
+      trust_dylibs: false,
+    }
+  }
+}
+
+impl<C, K> StoreOpt<C, K> {
+  /// Change the debounce duration used to determine whether a resource should be
+  /// reloaded or not.
+  ///
+  /// A [`Store`] will wait that amount of time before deciding an resource should be reloaded after
+  /// it has changed on the filesystem. That is required in order to cope with write streaming, that
+  /// generates a lot of write event.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `50` milliseconds.
+  #[inline]
+  pub fn set_debounce_duration(self, duration: Duration) -> Self {
+    StoreOpt {
+      debounce_duration: duration,
+      ..self
+    }
+  }
+
+  /// Get the debounce duration.
+  #[inline]
+  pub fn debounce_duration(&self) -> Duration {
+    self.debounce_duration
+  }
+
+  /// Change the root directory from which the [`Store`] will be watching file changes.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `"."`.
+  #[inline]
+  pub fn set_root<P>(self, root: P) -> Self
+  where P: AsRef<Path> {
+    StoreOpt {
+      root: root.as_ref().to_owned(),
+      ..self
+    }
+  }
+
+  /// Get root directory.
+  #[inline]
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  /// Change the discovery mechanism.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `Discovery::default()`.
+  #[inline]
+  pub fn set_discovery(self, discovery: Discovery<C, K>) -> Self {
+    StoreOpt {
+      discovery,
+      ..self
+    }
+  }
+
+  /// Get the discovery mechanism.
+  #[inline]
+  pub fn discovery(&self) -> &Discovery<C, K> {
+    &self.discovery
+  }
+
+  /// Retry failed reloads according to a [`RetryPolicy`].
+  ///
+  /// # Default
+  ///
+  /// Failed reloads are not retried until the resource changes again.
+  #[inline]
+  pub fn set_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+    StoreOpt {
+      retry_policy: Some(retry_policy),
+      ..self
+    }
+  }
+
+  /// Get the retry policy, if any.
+  #[inline]
+  pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+    self.retry_policy.as_ref()
+  }
+
+  /// Change the observer notified of all resource events happening in the [`Store`].
+  ///
+  /// # Default
+  ///
+  /// Defaults to `()`, which ignores every event.
+  #[inline]
+  pub fn set_observer<O>(self, observer: O) -> Self
+  where O: 'static + StoreObserver<K> {
+    StoreOpt {
+      observer: Box::new(observer),
+      ..self
+    }
+  }
+
+  /// Get the observer.
+  #[inline]
+  pub fn observer(&self) -> &dyn StoreObserver<K> {
+    &*self.observer
+  }
+
+  /// Change the time budget of a synchronization.
+  ///
+  /// Once a synchronization has spent that amount of time reloading resources, the resources that
+  /// remain to be reloaded are postponed to the next synchronization. Resources are reloaded by
+  /// decreasing priority (see [`Storage::set_priority`]) and at least one resource is reloaded
+  /// per synchronization.
+  ///
+  /// # Default
+  ///
+  /// Synchronizations are not time-bounded by default.
+  #[inline]
+  pub fn set_sync_budget(self, budget: Duration) -> Self {
+    StoreOpt {
+      sync_budget: Some(budget),
+      ..self
+    }
+  }
+
+  /// Get the time budget of a synchronization, if any.
+  #[inline]
+  pub fn sync_budget(&self) -> Option<Duration> {
+    self.sync_budget
+  }
+
+  /// Synchronize the [`Store`] automatically every `interval`.
+  ///
+  /// Applications without a main loop – tools, servers – have no natural place to call
+  /// [`Store::sync`] from. With auto-sync, the store owns a lightweight timer thread marking it as
+  /// needing a synchronization every `interval`. As the context is not [`Send`], the
+  /// synchronization itself happens on the thread owning the store, either:
+  ///
+  ///   - By calling [`Store::sync_if_due`] – e.g. before getting resources.
+  ///   - By waiting on the channel returned by [`Store::auto_sync_ticks`].
+  ///
+  /// The timer thread stops shortly after the store is dropped.
+  ///
+  /// # Default
+  ///
+  /// Auto-sync is disabled by default.
+  #[cfg(feature = "arc")]
+  #[inline]
+  pub fn set_auto_sync(self, interval: Duration) -> Self {
+    StoreOpt {
+      auto_sync: Some(interval),
+      ..self
+    }
+  }
+
+  /// Get the auto-sync interval, if any.
+  #[cfg(feature = "arc")]
+  #[inline]
+  pub fn auto_sync(&self) -> Option<Duration> {
+    self.auto_sync
+  }
+
+  /// Change the number of resources the [`Store`] is expected to hold.
+  ///
+  /// The internal maps are allocated for that many resources upfront, so that they’re not
+  /// reallocated – and rehashed – over and over during the initial bulk load of large projects.
+  /// This is only a hint: the store holds more resources just fine.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `0`: the internal maps grow as resources are loaded.
+  #[inline]
+  pub fn set_expected_resources(self, expected_resources: usize) -> Self {
+    StoreOpt {
+      expected_resources,
+      ..self
+    }
+  }
+
+  /// Get the number of resources the [`Store`] is expected to hold.
+  #[inline]
+  pub fn expected_resources(&self) -> usize {
+    self.expected_resources
+  }
+
+  /// Track the file each resource is loaded from – see [`Storage::provenance`].
+  ///
+  /// This answers “which file actually won?” when localized or variant files are involved. The
+  /// first file read by each loading and reloading is hashed, which has a cost for big files.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_track_provenance(self, track_provenance: bool) -> Self {
+    StoreOpt {
+      track_provenance,
+      ..self
+    }
+  }
+
+  /// Check whether the file each resource is loaded from is tracked.
+  #[inline]
+  pub fn track_provenance(&self) -> bool {
+    self.track_provenance
+  }
+
+  /// Allow the [`Store`] to load native dynamic libraries as [`DyLib`] resources.
+  ///
+  /// Loading a library runs its initialization routines, so loading a [`DyLib`] fails with
+  /// [`DyLibError::Untrusted`] unless this is enabled.
+  ///
+  /// # Safety
+  ///
+  /// Every library loaded from the root of the store – now or after a change on the filesystem –
+  /// runs native code in your process. Only enable this if you trust all of them. See the
+  /// [`dylib`] module for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  ///
+  /// [`DyLib`]: crate::dylib::DyLib
+  /// [`DyLibError::Untrusted`]: crate::dylib::DyLibError::Untrusted
+  /// [`dylib`]: crate::dylib
+  #[cfg(feature = "dylib-impl")]
+  #[inline]
+  pub unsafe fn set_trust_dylibs(self, trust_dylibs: bool) -> Self {
+    StoreOpt {
+      trust_dylibs,
+      ..self
+    }
+  }
+
+  /// Check whether native dynamic libraries may be loaded.
+  #[cfg(feature = "dylib-impl")]
+  #[inline]
+  pub fn trust_dylibs(&self) -> bool {
+    self.trust_dylibs
+  }
+
+  /// Change the maximum number of filesystem events processed per synchronization.
+  ///
+  /// When a lot of files change at once – e.g. a build script rewriting the whole asset tree –
+  /// processing all the events in a single synchronization might stall your application. With a
+  /// cap, the events that remain are carried over to the next synchronizations. Having more events
+  /// pending than the cap is an *overflow*, which is reported by [`SyncReport::overflowed`] and
+  /// [`StoreObserver::overflowed`]; see [`StoreOpt::set_rescan_on_overflow`] to react to it.
+  ///
+  /// # Default
+  ///
+  /// The number of events processed per synchronization is not capped by default.
+  #[inline]
+  pub fn set_event_cap(self, cap: usize) -> Self {
+    StoreOpt {
+      event_cap: Some(cap),
+      ..self
+    }
+  }
+
+  /// Get the maximum number of filesystem events processed per synchronization, if any.
+  #[inline]
+  pub fn event_cap(&self) -> Option<usize> {
+    self.event_cap
+  }
+
+  /// Rescan the root instead of processing the pending filesystem events when they overflow.
+  ///
+  /// On overflow – see [`StoreOpt::set_event_cap`] – the pending events are dropped: the files not
+  /// known yet go through the [`Discovery`] mechanism and all the resources are reloaded. That is
+  /// cheaper than handling thousands of events one by one when most of the tree changed. Events
+  /// dropped by the watcher itself always trigger a rescan.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_rescan_on_overflow(self, rescan_on_overflow: bool) -> Self {
+    StoreOpt {
+      rescan_on_overflow,
+      ..self
+    }
+  }
+
+  /// Check whether the root is rescanned when filesystem events overflow.
+  #[inline]
+  pub fn rescan_on_overflow(&self) -> bool {
+    self.rescan_on_overflow
+  }
+
+  /// Scan the root when the [`Store`] is created and discover all the files already living in it.
+  ///
+  /// Every file found by the scan goes through the [`Discovery`] mechanism on the first
+  /// synchronization, as if it had just been created. This is handy to populate a [`Store`] with
+  /// what’s already on disk.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_scan_on_start(self, scan_on_start: bool) -> Self {
+    StoreOpt {
+      scan_on_start,
+      ..self
+    }
   }
 
-  /// Synchronize the [`Storage`] by updating the resources that ought to.
-  fn sync(&mut self, storage: &mut Storage<C, K>, ctx: &mut C) where K: for<'a> From<&'a Path> {
-    self.dequeue_fs_events(storage, ctx);
-    self.reload_dirties(storage, ctx);
+  /// Check whether the root is scanned when the [`Store`] is created.
+  #[inline]
+  pub fn scan_on_start(&self) -> bool {
+    self.scan_on_start
   }
-}
-
-/// Resource store. Responsible for holding and presenting resources.
-pub struct Store<C, K> {
-  storage: Storage<C, K>,
-  synchronizer: Synchronizer<C, K>,
-}
 
-impl<C, K> Store<C, K> where K: Key {
-  /// Create a new store.
+  /// Normalization applied to keys – the ones you provide as well as the ones built from
+  /// filesystem events.
   ///
-  /// # Failures
+  /// # Default
   ///
-  /// This function will fail if the root path in the [`StoreOpt`] doesn’t resolve to a correct
-  /// canonicalized path.
-  pub fn new(opt: StoreOpt<C, K>) -> Result<Self, StoreError<K>> {
-    // canonicalize the root because some platforms won’t correctly report file changes otherwise
-    let root = &opt.root;
-    let canon_root = root
-      .canonicalize()
-      .map_err(|_| StoreError::RootDoesNotExist(root.to_owned()))?;
-
-    // create the mpsc channel to communicate with the file watcher
-    let (wsx, wrx) = channel();
-    let mut watcher = notify::watcher(wsx, opt.debounce_duration).unwrap();
+  /// Defaults to [`Normalization::default`].
+  #[inline]
+  pub fn set_normalization(self, normalization: Normalization) -> Self {
+    StoreOpt {
+      normalization,
+      ..self
+    }
+  }
 
-    // spawn a new thread in which we look for events
-    let _ = watcher.watch(&canon_root, RecursiveMode::Recursive);
+  /// Get the normalization applied to keys.
+  #[inline]
+  pub fn normalization(&self) -> &Normalization {
+    &self.normalization
+  }
 
-    // create the storage
-    let storage = Storage::new(canon_root);
+  /// Locales used to localize files, by decreasing preference – e.g. `["fr", "en"]`.
+  ///
+  /// See [`Storage::localize`] for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no locale, so that files are not localized.
+  #[inline]
+  pub fn set_locales<L>(self, locales: L) -> Self where L: IntoIterator, L::Item: Into<String> {
+    StoreOpt {
+      locales: locales.into_iter().map(Into::into).collect(),
+      ..self
+    }
+  }
 
-    // create the synchronizer
-    let synchronizer = Synchronizer::new(watcher, wrx, opt.discovery);
+  /// Get the locales used to localize files.
+  #[inline]
+  pub fn locales(&self) -> &[String] {
+    &self.locales
+  }
 
-    let store = Store {
-      storage,
-      synchronizer,
-    };
+  /// Variants used to select files, by decreasing preference – e.g. `["hi", "windows"]`.
+  ///
+  /// See [`Storage::select_variant`] for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no variant, so that files are used as-is.
+  #[inline]
+  pub fn set_variants<V>(self, variants: V) -> Self where V: IntoIterator, V::Item: Into<String> {
+    StoreOpt {
+      variants: variants.into_iter().map(Into::into).collect(),
+      ..self
+    }
+  }
 
-    Ok(store)
+  /// Get the variants used to select files.
+  #[inline]
+  pub fn variants(&self) -> &[String] {
+    &self.variants
   }
 
-  /// Synchronize the [`Store`] by updating the resources that ought to with a provided context.
-  pub fn sync(&mut self, ctx: &mut C) where K: for<'a> From<&'a Path> {
-    self.synchronizer.sync(&mut self.storage, ctx);
+  /// Catch panics happening in reload code during synchronizations.
+  ///
+  /// A panic in a [`Load::reload`] or [`Load::accept`] implementation is turned into a reload
+  /// failure – which error is a [`ReloadPanic`] – and the previous value of the resource is kept,
+  /// instead of unwinding through [`Store::sync`]. The panic hook still runs, so the panic is still
+  /// printed by default.
+  ///
+  /// # Default
+  ///
+  /// Defaults to `false`.
+  #[inline]
+  pub fn set_catch_panics(self, catch_panics: bool) -> Self {
+    StoreOpt {
+      catch_panics,
+      ..self
+    }
   }
-}
 
-impl<C, K> Deref for Store<C, K> {
-  type Target = Storage<C, K>;
+  /// Check whether panics in reload code are caught.
+  #[inline]
+  pub fn catch_panics(&self) -> bool {
+    self.catch_panics
+  }
 
-  fn deref(&self) -> &Self::Target {
-    &self.storage
+  /// Change the directory the outputs of expensive transforms are cached in, so that they are
+  /// reused across runs. The directory is created if needed.
+  ///
+  /// See [`Storage::derive`] for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to no directory: transforms always run.
+  #[inline]
+  pub fn set_derived_cache<P>(self, dir: P) -> Self where P: AsRef<Path> {
+    StoreOpt {
+      derived_cache: Some(dir.as_ref().to_owned()),
+      ..self
+    }
   }
-}
 
-impl<C, K> DerefMut for Store<C, K> {
-  fn deref_mut(&mut self) -> &mut Self::Target {
-    &mut self.storage
+  /// Get the directory the outputs of transforms are cached in, if any.
+  #[inline]
+  pub fn derived_cache(&self) -> Option<&Path> {
+    self.derived_cache.as_deref()
   }
-}
 
-/// Various options to customize a [`Store`].
-///
-/// Feel free to inspect all of its declared methods for further information.
-pub struct StoreOpt<C, K> {
-  root: PathBuf,
-  debounce_duration: Duration,
-  discovery: Discovery<C, K>
-}
+  /// Change the loaders registered at runtime, used to load resources with the [`Registered`]
+  /// method.
+  ///
+  /// See the [`registry`] module for further details.
+  ///
+  /// # Default
+  ///
+  /// Defaults to a registry without any loader.
+  ///
+  /// [`registry`]: crate::registry
+  /// [`Registered`]: crate::registry::Registered
+  #[inline]
+  pub fn set_registry(self, registry: LoaderRegistry<C, K>) -> Self {
+    StoreOpt { registry, ..self }
+  }
 
-impl<C, K> Default for StoreOpt<C, K> {
-  fn default() -> Self {
-    StoreOpt {
-      root: PathBuf::from("."),
-      debounce_duration: Duration::from_millis(50),
-      discovery: Discovery::default()
-    }
+  /// Get the loaders registered at runtime.
+  #[inline]
+  pub fn registry(&self) -> &LoaderRegistry<C, K> {
+    &self.registry
   }
-}
 
-impl<C, K> StoreOpt<C, K> {
-  /// Change the debounce duration used to determine whether a resource should be
-  /// reloaded or not.
+  /// Change the suffix of companion lock files – e.g. `".lock"`.
   ///
-  /// A [`Store`] will wait that amount of time before deciding an resource should be reloaded after
-  /// it has changed on the filesystem. That is required in order to cope with write streaming, that
-  /// generates a lot of write event.
+  /// Tools writing large files non-atomically can create a lock file next to the file they’re
+  /// writing – `foo.json.lock` for `foo.json` – and remove it once done. While the lock file exists,
+  /// changes to the locked file are held; they’re handled on the first synchronization after the lock
+  /// file disappears, so that half-written files are never reloaded. Lock files themselves are never
+  /// discovered nor reloaded.
   ///
   /// # Default
   ///
-  /// Defaults to `50` milliseconds.
+  /// Defaults to no suffix: lock files are not looked for.
   #[inline]
-  pub fn set_debounce_duration(self, duration: Duration) -> Self {
+  pub fn set_lock_suffix<S>(self, suffix: S) -> Self where S: Into<String> {
     StoreOpt {
-      debounce_duration: duration,
+      lock_suffix: Some(suffix.into()),
       ..self
     }
   }
 
-  /// Get the debounce duration.
+  /// Get the suffix of companion lock files, if any.
   #[inline]
-  pub fn debounce_duration(&self) -> Duration {
-    self.debounce_duration
+  pub fn lock_suffix(&self) -> Option<&str> {
+    self.lock_suffix.as_deref()
   }
 
-  /// Change the root directory from which the [`Store`] will be watching file changes.
+  /// Change what happens to the dependents of a resource that failed to reload.
   ///
   /// # Default
   ///
-  /// Defaults to `"."`.
+  /// Defaults to [`FailurePropagation::Stop`].
   #[inline]
-  pub fn set_root<P>(self, root: P) -> Self
-  where P: AsRef<Path> {
+  pub fn set_failure_propagation(self, failure_propagation: FailurePropagation<K>) -> Self {
     StoreOpt {
-      root: root.as_ref().to_owned(),
+      failure_propagation,
       ..self
     }
   }
 
-  /// Get root directory.
+  /// Get what happens to the dependents of a resource that failed to reload.
   #[inline]
-  pub fn root(&self) -> &Path {
-    &self.root
+  pub fn failure_propagation(&self) -> &FailurePropagation<K> {
+    &self.failure_propagation
   }
 
-  /// Change the discovery mechanism.
+  /// Rewrite all the keys provided by the user – e.g. to redirect renamed assets from their old
+  /// paths to their new ones in a single place rather than at every call site.
+  ///
+  /// Keys are rewritten before being prepared, so the rewriter is passed keys as written in code –
+  /// e.g. VFS paths. This applies to the keys of resources as well as to the keys of dependencies
+  /// and of the other functions of [`Storage`]. Resources are registered under their rewritten
+  /// keys, so the filesystem events of the files they are actually loaded from reload them.
+  ///
+  /// The rewriter must be consistent: rewriting a key must always give the same key.
   ///
   /// # Default
   ///
-  /// Defaults to `Discovery::default()`.
+  /// Defaults to no rewriting.
   #[inline]
-  pub fn set_discovery(self, discovery: Discovery<C, K>) -> Self {
+  pub fn set_key_rewriter<F>(self, rewriter: F) -> Self where F: 'static + Fn(K) -> K {
     StoreOpt {
-      discovery,
+      key_rewriter: Some(Box::new(rewriter)),
       ..self
     }
   }
 
-  /// Get the discovery mechanism.
+  /// Get the function rewriting keys, if any.
   #[inline]
-  pub fn discovery(&self) -> &Discovery<C, K> {
-    &self.discovery
+  pub fn key_rewriter(&self) -> Option<&dyn Fn(K) -> K> {
+    self.key_rewriter.as_deref()
   }
 }
 
@@ -586,9 +5568,37 @@ impl<C, K> StoreOpt<C, K> {
 /// Such an object is called whenever a new resource is discovered and is relied on to decide what
 /// to do with the resource.
 ///
+/// A path is only discovered if no resource is known with the corresponding key, whatever its type.
+/// Discovery closures can use [`Storage::resource_type`] to inspect what’s already loaded – e.g.
+/// when discovering a batch of paths over several synchronizations.
+///
 /// If you don’t care about discovering new resources, feel free to use the [`Default`] implementation.
+#[cfg_attr(not(feature = "hot-reload"), allow(dead_code))]
+#[allow(clippy::type_complexity)]
 pub struct Discovery<C, K> {
-  closure: Box<dyn FnMut(&Path, &mut Storage<C, K>, &mut C)>,
+  closure: DiscoveryClosure<C, K>,
+  // predicate a path must satisfy to be discovered; its second argument is the root of the store
+  filter: Option<Box<dyn Fn(&Path, &Path) -> bool>>,
+  // closure called when a file is removed
+  on_remove: Option<Box<dyn FnMut(&Path, &mut Storage<C, K>, &mut C)>>,
+  // closure called when a file is renamed
+  on_rename: Option<Box<dyn FnMut(&Path, &Path, &mut Storage<C, K>, &mut C)>>,
+  // maximum number of paths delivered at once to a batched closure
+  batch_size: Option<usize>,
+  // paths waiting to be delivered to a batched closure
+  pending: Vec<PathBuf>,
+  // set version of pending, used to ignore paths discovered several times before being delivered
+  queued: HashSet<PathBuf>,
+}
+
+/// Closure called on discoveries.
+#[cfg_attr(not(feature = "hot-reload"), allow(dead_code))]
+#[allow(clippy::type_complexity)]
+enum DiscoveryClosure<C, K> {
+  /// Called once per discovered path.
+  Single(Box<dyn FnMut(&Path, &mut Storage<C, K>, &mut C)>),
+  /// Called with all the paths discovered since the last call.
+  Batched(Box<dyn FnMut(&[PathBuf], &mut Storage<C, K>, &mut C)>),
 }
 
 impl<C, K> Discovery<C, K> {
@@ -603,14 +5613,208 @@ impl<C, K> Discovery<C, K> {
   ///
   /// [`get`]: crate::load::Storage::get
   pub fn new<F>(f: F) -> Self where F: 'static + FnMut(&Path, &mut Storage<C, K>, &mut C) {
+    Discovery::with_closure(DiscoveryClosure::Single(Box::new(f)))
+  }
+
+  /// Create a new filter that delivers discovered resources in batches.
+  ///
+  /// Instead of being called once per discovered path, the closure is called once per
+  /// synchronization with all the paths discovered since its last call. This is much cheaper when
+  /// lots of files appear at once – e.g. when dropping a whole directory of assets into the root.
+  /// Paths are raw and absolute, as with [`Discovery::new`].
+  ///
+  /// See [`Discovery::set_batch_size`] to spread huge batches across several synchronizations.
+  pub fn batched<F>(f: F) -> Self
+  where F: 'static + FnMut(&[PathBuf], &mut Storage<C, K>, &mut C) {
+    Discovery::with_closure(DiscoveryClosure::Batched(Box::new(f)))
+  }
+
+  fn with_closure(closure: DiscoveryClosure<C, K>) -> Self {
+    Discovery {
+      closure,
+      filter: None,
+      on_remove: None,
+      on_rename: None,
+      batch_size: None,
+      pending: Vec::new(),
+      queued: HashSet::default(),
+    }
+  }
+
+  /// Create a new filter that only discovers files with one of the given extensions.
+  ///
+  /// Extensions are given without the leading dot and are compared case-insensitively. The closure
+  /// is the same as the one passed to [`Discovery::new`].
+  pub fn for_extensions<F>(extensions: &[&str], f: F) -> Self
+  where F: 'static + FnMut(&Path, &mut Storage<C, K>, &mut C) {
+    let extensions: Vec<String> = extensions.iter().map(|ext| ext.to_lowercase()).collect();
+
+    Discovery {
+      filter: Some(Box::new(move |path, _| {
+        path
+          .extension()
+          .and_then(|ext| ext.to_str())
+          .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+      })),
+      ..Discovery::new(f)
+    }
+  }
+
+  /// Create a new filter that only discovers files matching a glob pattern.
+  ///
+  /// The pattern is matched against the path of the file relative to the root of the store – e.g.
+  /// `"textures/**/*.png"`. `*` doesn’t match path separators while `**` matches any number of
+  /// directories. The closure is the same as the one passed to [`Discovery::new`].
+  ///
+  /// # Failures
+  ///
+  /// This function fails if the pattern is not a valid glob pattern.
+  pub fn for_glob<F>(pattern: &str, f: F) -> Result<Self, PatternError>
+  where F: 'static + FnMut(&Path, &mut Storage<C, K>, &mut C) {
+    let pattern = Pattern::new(pattern)?;
+
+    Ok(Discovery {
+      filter: Some(Box::new(move |path, root| {
+        path
+          .strip_prefix(root)
+          .is_ok_and(|path| pattern.matches_path_with(path, glob_options()))
+      })),
+      ..Discovery::new(f)
+    })
+  }
+
+  /// Set the closure called when a file is removed from the root of the store.
+  ///
+  /// The closure is passed the raw and absolute path of the removed file. Paths rejected by the
+  /// filter of the discovery (if any) are ignored.
+  ///
+  /// # Default
+  ///
+  /// Removals are ignored by default.
+  pub fn set_on_remove<F>(self, f: F) -> Self
+  where F: 'static + FnMut(&Path, &mut Storage<C, K>, &mut C) {
+    Discovery {
+      on_remove: Some(Box::new(f)),
+      ..self
+    }
+  }
+
+  /// Set the closure called when a file is renamed within the root of the store.
+  ///
+  /// The closure is passed the raw and absolute paths of the file before and after being renamed.
+  /// Renames for which both paths are rejected by the filter of the discovery (if any) are ignored.
+  ///
+  /// # Default
+  ///
+  /// If no closure is set, a rename is handled as the removal of the old path followed by the
+  /// discovery of the new path.
+  pub fn set_on_rename<F>(self, f: F) -> Self
+  where F: 'static + FnMut(&Path, &Path, &mut Storage<C, K>, &mut C) {
+    Discovery {
+      on_rename: Some(Box::new(f)),
+      ..self
+    }
+  }
+
+  /// Set the maximum number of paths passed at once to a batched closure.
+  ///
+  /// Bigger batches are split and delivered in several calls. If the [`Store`] has a
+  /// synchronization budget (see [`StoreOpt::set_sync_budget`]), no more batches are delivered once
+  /// it’s exhausted: the remaining paths are kept for the next synchronization. This setting has no
+  /// effect if the discovery is not batched.
+  ///
+  /// # Default
+  ///
+  /// Batches are not split by default.
+  pub fn set_batch_size(self, batch_size: usize) -> Self {
     Discovery {
-      closure: Box::new(f)
+      batch_size: Some(batch_size.max(1)),
+      ..self
     }
   }
 
+  /// Get the maximum number of paths passed at once to a batched closure, if any.
+  pub fn batch_size(&self) -> Option<usize> {
+    self.batch_size
+  }
+
+  /// Check whether a path lives in the root and passes the filter.
+  #[cfg(feature = "hot-reload")]
+  fn accepts(&self, path: &Path, root: &Path) -> bool {
+    path.starts_with(root) && self.filter.as_ref().is_none_or(|filter| filter(path, root))
+  }
+
   /// Filter a discovery.
+  #[cfg(feature = "hot-reload")]
   fn discover(&mut self, path: &Path, storage: &mut Storage<C, K>, ctx: &mut C) {
-    (self.closure)(path, storage, ctx)
+    if self.accepts(path, &storage.canon_root) {
+      match self.closure {
+        DiscoveryClosure::Single(ref mut closure) => closure(path, storage, ctx),
+
+        DiscoveryClosure::Batched(_) => {
+          if self.queued.insert(path.to_owned()) {
+            self.pending.push(path.to_owned());
+          }
+        }
+      }
+    }
+  }
+
+  /// Deliver pending discoveries to a batched closure.
+  ///
+  /// Batches stop being delivered once the deadline, if any, is reached.
+  #[cfg(feature = "hot-reload")]
+  fn flush(&mut self, storage: &mut Storage<C, K>, ctx: &mut C, deadline: Option<Instant>) {
+    let closure = match self.closure {
+      DiscoveryClosure::Batched(ref mut closure) => closure,
+      DiscoveryClosure::Single(_) => return,
+    };
+
+    while !self.pending.is_empty() {
+      let len = self.pending.len().min(self.batch_size.unwrap_or(usize::MAX));
+      let batch: Vec<PathBuf> = self.pending.drain(..len).collect();
+
+      for path in &batch {
+        self.queued.remove(path);
+      }
+
+      closure(&batch, storage, ctx);
+
+      if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        break;
+      }
+    }
+  }
+
+  /// Filter a removal.
+  #[cfg(feature = "hot-reload")]
+  fn remove(&mut self, path: &Path, storage: &mut Storage<C, K>, ctx: &mut C) {
+    if self.accepts(path, &storage.canon_root) {
+      if let Some(ref mut on_remove) = self.on_remove {
+        on_remove(path, storage, ctx);
+      }
+    }
+  }
+
+  /// Filter a rename.
+  ///
+  /// Return `false` if no rename closure is set.
+  #[cfg(feature = "hot-reload")]
+  fn rename(&mut self, from: &Path, to: &Path, storage: &mut Storage<C, K>, ctx: &mut C) -> bool {
+    let accepted =
+      self.accepts(from, &storage.canon_root) || self.accepts(to, &storage.canon_root);
+
+    match self.on_rename {
+      Some(ref mut on_rename) => {
+        if accepted {
+          on_rename(from, to, storage, ctx);
+        }
+
+        true
+      }
+
+      None => false,
+    }
   }
 }
 
@@ -622,3 +5826,103 @@ impl<C, K> Default for Discovery<C, K> {
     Discovery::new(|_, _, _| {})
   }
 }
+
+/// Resources stored in files with a given extension.
+///
+/// This is used by [`TypedDiscovery::with_type`] to discover the files of a resource type. The
+/// `#[derive(Load)]` procedural macro – feature-gated with `"derive"` – implements it when given an
+/// `ext`.
+pub trait Extension {
+  /// The extension, without the leading dot.
+  const EXTENSION: &'static str;
+}
+
+/// Typed discovery.
+///
+/// This is a higher-level way to build a [`Discovery`] that maps file extensions to resource types
+/// and loading methods. When a file with a registered extension is discovered, it’s automatically
+/// loaded with the corresponding type and method via [`Storage::get_by`], using its path relative
+/// to the root of the store as key.
+///
+/// ```
+/// use warmy::{Discovery, SimpleKey, StoreOpt, TypedDiscovery};
+/// # use warmy::{Load, Loaded, Storage};
+/// # struct Texture;
+/// # impl<C> Load<C, SimpleKey> for Texture {
+/// #   type Error = std::convert::Infallible;
+/// #   fn load(_: SimpleKey, _: &mut Storage<C, SimpleKey>, _: &mut C) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+/// #     Ok(Texture.into())
+/// #   }
+/// # }
+///
+/// let discovery = TypedDiscovery::new().with::<Texture, ()>("png");
+/// let opt: StoreOpt<(), SimpleKey> = StoreOpt::default().set_discovery(discovery.into());
+/// ```
+///
+/// Loading errors of discovered resources are ignored.
+pub struct TypedDiscovery<C, K> {
+  loaders: Vec<(String, TypedLoader<C, K>)>,
+}
+
+/// Function loading a discovered resource.
+type TypedLoader<C, K> = Box<dyn FnMut(&K, &mut Storage<C, K>, &mut C)>;
+
+impl<C, K> TypedDiscovery<C, K> where K: Key {
+  /// Create a typed discovery that doesn’t discover anything yet.
+  pub fn new() -> Self {
+    TypedDiscovery {
+      loaders: Vec::new(),
+    }
+  }
+
+  /// Load discovered files with the given extension as `T` by using the method `M`.
+  ///
+  /// The extension is given without the leading dot and is compared case-insensitively.
+  pub fn with<T, M>(mut self, extension: &str) -> Self
+  where T: Load<C, K, M> {
+    let loader = |key: &K, storage: &mut Storage<C, K>, ctx: &mut C| {
+      let _ = storage.fetch::<T, M>(key, ctx);
+    };
+
+    self.loaders.push((extension.to_lowercase(), Box::new(loader)));
+    self
+  }
+
+  /// Load discovered files with the extension of `T` as `T` by using its default method.
+  pub fn with_type<T>(self) -> Self where T: Load<C, K> + Extension {
+    self.with::<T, ()>(T::EXTENSION)
+  }
+}
+
+impl<C, K> Default for TypedDiscovery<C, K> where K: Key {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<C, K> From<TypedDiscovery<C, K>> for Discovery<C, K>
+where C: 'static,
+      K: Key + for<'a> From<&'a Path> {
+  fn from(typed: TypedDiscovery<C, K>) -> Self {
+    let extensions: Vec<String> = typed.loaders.iter().map(|(ext, _)| ext.clone()).collect();
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+    let mut loaders = typed.loaders;
+
+    Discovery::for_extensions(&extensions, move |path, storage, ctx| {
+      let ext = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.to_lowercase(),
+        None => return,
+      };
+
+      // turn the absolute path into a key relative to the root
+      let key: K = match path.strip_prefix(&storage.canon_root) {
+        Ok(rel_path) => Path::new("/").join(rel_path).as_path().into(),
+        Err(_) => return,
+      };
+
+      for (_, loader) in loaders.iter_mut().filter(|(e, _)| *e == ext) {
+        loader(&key, storage, ctx);
+      }
+    })
+  }
+}
@@ -0,0 +1,180 @@
+//! Lua scripts.
+//!
+//! This module provides you with [`LuaScript`], a resource holding the source of a Lua script
+//! loaded with [mlua]. Scripts are compiled when loaded and reloaded, so that a syntax error makes
+//! the reload fail and the script keep its current version.
+//!
+//! The modules a script `require`s are tracked as dependencies: editing a shared module reloads
+//! all the scripts using it, directly or not. Modules are looked up as with the `?.lua` template of
+//! Lua’s `package.path`, relative to the root of the store – `require "ai.path"` looks for
+//! `ai/path.lua`. The lookup is a best-effort scan of `require` calls with a string literal
+//! argument: modules required with a computed name are not tracked.
+//!
+//! [mlua]: https://crates.io/crates/mlua
+
+use mlua::{self, Chunk, Lua, LuaOptions, StdLib};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+/// A Lua script.
+///
+/// Use [`LuaScript::chunk`] to run it in your own Lua state.
+#[derive(Clone, Debug)]
+pub struct LuaScript {
+  // path of the script
+  path: PathBuf,
+  // source code of the script
+  source: String,
+  // names of the modules the script requires, directly or not
+  requires: Vec<String>,
+}
+
+impl LuaScript {
+  /// Path of the script.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Source code of the script.
+  pub fn source(&self) -> &str {
+    &self.source
+  }
+
+  /// Names of the modules the script requires, directly or through other modules.
+  pub fn requires(&self) -> &[String] {
+    &self.requires
+  }
+
+  /// Load the script as a chunk of a Lua state, named after the path of the script.
+  pub fn chunk<'lua, 'a>(&'a self, lua: &'lua Lua) -> Chunk<'lua, 'a> {
+    lua.load(&self.source).set_name(format!("@{}", self.path.display()))
+  }
+}
+
+/// Possible error that might occur while loading and reloading Lua scripts.
+#[derive(Debug)]
+pub enum LuaError {
+  /// An error in [mlua](https://crates.io/crates/mlua), such as a syntax error.
+  LuaError(mlua::Error),
+  /// The file specified by the key failed to open or could not be read.
+  CannotReadFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+}
+
+impl fmt::Display for LuaError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      LuaError::LuaError(ref e) => write!(f, "Lua error: {}", e),
+
+      LuaError::CannotReadFile(ref path, ref e) => {
+        write!(f, "cannot read file {}: {}", path.display(), e)
+      }
+
+      LuaError::NoKey => f.write_str("no path key available"),
+    }
+  }
+}
+
+impl Error for LuaError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      LuaError::LuaError(ref e) => Some(e),
+      LuaError::CannotReadFile(_, ref e) => Some(e),
+      LuaError::NoKey => None,
+    }
+  }
+}
+
+impl<C, K> Load<C, K> for LuaScript
+where K: Key + Into<Option<PathBuf>> + for<'a> From<&'a Path> {
+  type Error = LuaError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let path = key.clone().into().ok_or(LuaError::NoKey)?;
+    let source = storage
+      .read_file_to_string(&path)
+      .map_err(|ioerr| LuaError::CannotReadFile(path.clone(), ioerr))?;
+
+    let script = LuaScript {
+      path,
+      source,
+      requires: Vec::new(),
+    };
+
+    // compile the script to reject syntax errors; no library is needed for that
+    let lua = Lua::new_with(StdLib::NONE, LuaOptions::default()).map_err(LuaError::LuaError)?;
+    script.chunk(&lua).into_function().map_err(LuaError::LuaError)?;
+
+    // follow the required modules transitively; they might not exist yet
+    let mut requires: Vec<String> = Vec::new();
+    let mut deps: Vec<K> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending: Vec<String> = scan_requires(&script.source).map(str::to_owned).collect();
+
+    while let Some(module) = pending.pop() {
+      if !seen.insert(module.clone()) {
+        continue;
+      }
+
+      let module_file = format!("{}.lua", module.replace('.', "/"));
+
+      if let Ok(module_source) = storage.read_file_to_string(&storage.root().join(&module_file)) {
+        pending.extend(scan_requires(&module_source).map(str::to_owned));
+      }
+
+      deps.push(K::from(&Path::new("/").join(module_file)));
+      requires.push(module);
+    }
+
+    Ok(Loaded::<_, K>::with_deps(LuaScript { requires, ..script }, deps))
+  }
+
+  fn reload(&self, key: K, storage: &mut Storage<C, K>, ctx: &mut C) -> Result<Self, Self::Error> {
+    // a new version of the script might require other modules
+    let Loaded { res, deps } = <Self as Load<C, K>>::load(key.clone(), storage, ctx)?;
+    storage.set_deps(&key, deps);
+    Ok(res)
+  }
+}
+
+/// Find the names of the modules required with a string literal in a Lua source.
+///
+/// Line comments are skipped – and so is the end of lines having `--` in a string literal.
+fn scan_requires(source: &str) -> impl Iterator<Item = &str> {
+  const REQUIRE: &str = "require";
+
+  source.lines().flat_map(|line| {
+    let mut rest = line.split("--").next().unwrap_or("");
+    let mut modules = Vec::new();
+
+    while let Some(i) = rest.find(REQUIRE) {
+      let preceding = rest[..i].chars().next_back();
+      let call = rest[i + REQUIRE.len()..].trim_start();
+      rest = &rest[i + REQUIRE.len()..];
+
+      // skip identifiers ending with require, as well as fields and methods named so
+      if preceding.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == ':') {
+        continue;
+      }
+
+      let arg = call.strip_prefix('(').map_or(call, str::trim_start);
+      let quote = match arg.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => quote,
+        _ => continue,
+      };
+
+      if let Some(len) = arg[1..].find(quote) {
+        modules.push(&arg[1..=len]);
+      }
+    }
+
+    modules
+  })
+}
@@ -0,0 +1,75 @@
+//! Store-wide observation of resource events.
+
+use std::path::Path;
+
+use crate::load::ResourceError;
+
+/// Class of types that observe what happens in a [`Store`].
+///
+/// A store observer is a single hook point called for all resources living in a [`Store`]. It is
+/// typically used to wire a [`Store`] into logging or metrics without having to instrument every
+/// [`Load`] implementation.
+///
+/// All methods have a default, empty implementation so that you only need to implement the ones
+/// you are interested in.
+///
+/// [`Load`]: crate::load::Load
+/// [`Store`]: crate::load::Store
+pub trait StoreObserver<K> {
+  /// A resource was successfully loaded for the first time.
+  fn loaded(&mut self, _key: &K) {}
+
+  /// A resource was successfully reloaded.
+  fn reloaded(&mut self, _key: &K) {}
+
+  /// A proxy was replaced by the real resource, which was successfully reloaded.
+  ///
+  /// This is always preceded by a call to [`StoreObserver::reloaded`].
+  fn proxy_replaced(&mut self, _key: &K) {}
+
+  /// A resource failed to reload. It keeps its current value.
+  fn reload_failed(&mut self, _key: &K, _error: &ResourceError<K>) {}
+
+  /// A new file was discovered in the root of the store.
+  fn discovered(&mut self, _path: &Path) {}
+
+  /// The file backing a resource was removed from the filesystem.
+  fn removed(&mut self, _key: &K) {}
+
+  /// Filesystem events overflowed: more events than the cap were pending at the beginning of a
+  /// synchronization, or the watcher dropped events. `pending` is the number of events waiting to
+  /// be processed.
+  ///
+  /// See [`StoreOpt::set_event_cap`].
+  ///
+  /// [`StoreOpt::set_event_cap`]: crate::load::StoreOpt::set_event_cap
+  fn overflowed(&mut self, _pending: usize) {}
+
+  /// The root of the store went missing – e.g. it was removed or renamed by a clean build. No
+  /// filesystem event is received until it reappears.
+  fn root_lost(&mut self, _root: &Path) {}
+
+  /// The root of the store reappeared after going missing. It’s watched again and all the
+  /// resources get reloaded.
+  fn root_restored(&mut self, _root: &Path) {}
+}
+
+/// The default observer, ignoring every event.
+impl<K> StoreObserver<K> for () {}
+
+/// Event broadcast to the subscribers of a [`Store`].
+///
+/// See [`Store::subscribe`].
+///
+/// [`Store`]: crate::load::Store
+/// [`Store::subscribe`]: crate::load::Store::subscribe
+#[cfg(feature = "arc")]
+#[derive(Clone, Debug)]
+pub enum StoreEvent<K> {
+  /// A resource was successfully loaded for the first time.
+  Loaded(K),
+  /// A resource was successfully reloaded.
+  Reloaded(K),
+  /// A resource failed to reload. It keeps its current value.
+  ReloadFailed(ResourceError<K>),
+}
@@ -0,0 +1,383 @@
+//! Runtime loader registry.
+//!
+//! Implementing [`Load`] requires knowing the formats to load at compile time. When they aren’t –
+//! e.g. in a plugin architecture, where plugins bring their own formats – loaders can instead be
+//! registered at runtime in a [`LoaderRegistry`], for a resource type and a file extension. The
+//! registry is set with [`StoreOpt::set_registry`] and can be extended afterwards with
+//! [`Storage::registry_mut`]. Resources are then loaded – and reloaded – with the [`Registered`]
+//! method, which picks the loader registered for the type of the resource and the extension of the
+//! file.
+//!
+//! Loaders are [`DynLoad`] objects. The simplest ones are functions turning the content of a file
+//! into a resource, registered with [`LoaderRegistry::register`]. Loaders needing the key or the
+//! storage – e.g. to declare dependencies – implement [`DynLoad`] and are registered with
+//! [`LoaderRegistry::register_dyn`]; types implementing [`Load`] are registered so with a
+//! [`DynLoader`].
+//!
+//! ```
+//! use warmy::{LoaderRegistry, Res, SimpleKey, Store, StoreOpt};
+//! use warmy::registry::Registered;
+//!
+//! struct Texture(Vec<u8>);
+//!
+//! let mut registry = LoaderRegistry::new();
+//! registry.register("png", |bytes: &[u8], _: &mut ()| {
+//!   Ok::<_, std::io::Error>(Texture(bytes.to_owned()))
+//! });
+//!
+//! # let tmp_dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(tmp_dir.path().join("hero.png"), b"\x89PNG").unwrap();
+//! # let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt.set_registry(registry)).unwrap();
+//! let key = SimpleKey::from_path("/hero.png");
+//! let texture: Res<Texture> = store.get_by(&key, &mut (), Registered).unwrap();
+//! ```
+//!
+//! When the format of a file is not given by its extension – e.g. when it’s read from a manifest –
+//! [`Storage::get_dyn`] loads a resource with the loader registered for a format chosen at runtime,
+//! with the [`RegisteredAs`] method:
+//!
+//! ```
+//! use warmy::{LoaderRegistry, Res, SimpleKey, Store, StoreOpt};
+//!
+//! struct Mesh(String);
+//!
+//! let mut registry = LoaderRegistry::new();
+//! registry.register("obj", |bytes: &[u8], _: &mut ()| {
+//!   Ok::<_, std::str::Utf8Error>(Mesh(std::str::from_utf8(bytes)?.to_owned()))
+//! });
+//!
+//! # let tmp_dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(tmp_dir.path().join("hero.mesh"), b"v 0 0 0").unwrap();
+//! # let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt.set_registry(registry)).unwrap();
+//! let key = SimpleKey::from_path("/hero.mesh");
+//! let format = "obj"; // read from a manifest
+//! let mesh: Res<Mesh> = store.get_dyn(&key, &mut (), format).unwrap();
+//! ```
+//!
+//! [`StoreOpt::set_registry`]: crate::load::StoreOpt::set_registry
+//! [`Storage::registry_mut`]: crate::load::Storage::registry_mut
+//! [`Storage::get_dyn`]: crate::load::Storage::get_dyn
+//! [`DynLoader`]: crate::load::DynLoader
+
+use std::any::{self, Any, TypeId};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::hash::HashMap;
+use crate::key::Key;
+use crate::load::{CodedError, DynLoad, ErrorCode, Load, Loaded, Storage};
+
+/// Loaders registered at runtime, by resource type and file extension.
+pub struct LoaderRegistry<C, K> {
+  // loaders, indexed by the type of their resources and the lowercase extension of their files;
+  // they’re reference-counted so that they can be called while the storage is borrowed mutably
+  loaders: HashMap<(TypeId, String), Rc<dyn DynLoad<C, K>>>,
+}
+
+impl<C, K> LoaderRegistry<C, K> {
+  /// Create a registry without any loader.
+  pub fn new() -> Self {
+    LoaderRegistry {
+      loaders: HashMap::default(),
+    }
+  }
+}
+
+impl<C, K> LoaderRegistry<C, K> where K: Key {
+  /// Register a loader for the resources of type `T` stored in files with the given extension,
+  /// replacing the loader previously registered for them, if any.
+  ///
+  /// The loader is given the content of the file. The extension is given without the leading dot
+  /// and is compared case-insensitively. It’s also the format to pass to [`Storage::get_dyn`] to
+  /// use this loader whatever the extension of the file.
+  ///
+  /// [`Storage::get_dyn`]: crate::load::Storage::get_dyn
+  pub fn register<T, E, F>(&mut self, extension: &str, loader: F)
+  where K: Into<Option<PathBuf>>,
+        T: 'static,
+        E: Error + Send + Sync + 'static,
+        F: 'static + Fn(&[u8], &mut C) -> Result<T, E>,
+        C: 'static {
+    self.register_dyn(extension, Box::new(BytesLoader(loader, PhantomData)));
+  }
+
+  /// Register a loader object for the resources it produces, stored in files with the given
+  /// extension, replacing the loader previously registered for them, if any.
+  ///
+  /// This is the most general way to register a loader, as [`DynLoad`] objects get the key, the
+  /// storage and the context. Types implementing [`Load`] are registered with a
+  /// [`DynLoader`](crate::load::DynLoader), keeping their reloading logic.
+  pub fn register_dyn(&mut self, extension: &str, loader: Box<dyn DynLoad<C, K>>) {
+    let type_id = loader.res_type_id();
+    self.loaders.insert((type_id, extension.to_lowercase()), Rc::from(loader));
+  }
+
+  /// Unregister the loader for the resources of type `T` stored in files with the given extension.
+  ///
+  /// Return whether a loader was registered.
+  pub fn unregister<T>(&mut self, extension: &str) -> bool where T: 'static {
+    self.loaders.remove(&(TypeId::of::<T>(), extension.to_lowercase())).is_some()
+  }
+
+  /// Check whether a loader is registered for the resources of type `T` stored in files with the
+  /// given extension.
+  pub fn is_registered<T>(&self, extension: &str) -> bool where T: 'static {
+    self.loaders.contains_key(&(TypeId::of::<T>(), extension.to_lowercase()))
+  }
+
+  /// Get the loader registered for the resources of type `T` stored in files with the given
+  /// lowercase extension.
+  fn loader<T>(&self, extension: &str) -> Option<Rc<dyn DynLoad<C, K>>> where T: 'static {
+    self.loaders.get(&(TypeId::of::<T>(), extension.to_owned())).cloned()
+  }
+}
+
+impl<C, K> Default for LoaderRegistry<C, K> {
+  fn default() -> Self {
+    LoaderRegistry::new()
+  }
+}
+
+impl<C, K> fmt::Debug for LoaderRegistry<C, K> where K: Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let loaders: Vec<_> = self
+      .loaders
+      .iter()
+      .map(|((_, ext), loader)| (loader.res_type_name(), ext))
+      .collect();
+    f.debug_struct("LoaderRegistry").field("loaders", &loaders).finish()
+  }
+}
+
+/// [`DynLoad`] object turning the content of files into resources with a function.
+struct BytesLoader<T, F>(F, PhantomData<fn() -> T>);
+
+impl<C, K, T, E, F> DynLoad<C, K> for BytesLoader<T, F>
+where K: Key + Into<Option<PathBuf>>,
+      T: 'static,
+      E: Error + Send + Sync + 'static,
+      F: Fn(&[u8], &mut C) -> Result<T, E> {
+  fn res_type_id(&self) -> TypeId {
+    TypeId::of::<T>()
+  }
+
+  fn res_type_name(&self) -> &str {
+    any::type_name::<T>()
+  }
+
+  fn load(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Box<dyn Any>, K>, Box<dyn Error + Send + Sync>> {
+    let path = key.into().ok_or(RegistryError::NoKey)?;
+    let bytes =
+      storage.read_file(&path).map_err(|ioerr| RegistryError::CannotReadFile(path, ioerr))?;
+    let res: Box<dyn Any> = Box::new((self.0)(&bytes, ctx)?);
+
+    Ok(res.into())
+  }
+}
+
+/// The registered method. Use this with [`Storage::get_by`] or [`Storage::get_proxied_by`] to load
+/// resources with the loaders of the [`LoaderRegistry`] of the store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Registered;
+
+/// The registered method, with a format chosen at runtime. Use this with [`Storage::get_dyn`].
+///
+/// Resources are loaded – and reloaded – with the loader registered for their type and the format
+/// passed to [`Storage::get_dyn`], whatever the extension of their files. Resources got with another
+/// function fall back to the extension of their files, as with [`Registered`].
+///
+/// [`Storage::get_dyn`]: crate::load::Storage::get_dyn
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RegisteredAs;
+
+/// Possible error that might occur while loading and reloading resources with registered loaders.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RegistryError {
+  /// No loader is registered for the type of the resource and the extension of its file – or the
+  /// format chosen with [`Storage::get_dyn`](crate::load::Storage::get_dyn).
+  NoLoader(&'static str, String),
+  /// The registered loader failed.
+  LoaderError(Box<dyn Error + Send + Sync>),
+  /// The file specified by the key failed to open or could not be read.
+  CannotReadFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+}
+
+impl fmt::Display for RegistryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      RegistryError::NoLoader(ty, ref ext) => {
+        write!(f, "no loader registered for {} with extension {:?}", ty, ext)
+      }
+
+      RegistryError::LoaderError(ref e) => write!(f, "loader error: {}", e),
+
+      RegistryError::CannotReadFile(ref path, ref e) => {
+        write!(f, "cannot read file {}: {}", path.display(), e)
+      }
+
+      RegistryError::NoKey => f.write_str("no path key available"),
+    }
+  }
+}
+
+impl RegistryError {
+  /// Wrap the error of a loader, unless it’s already a registry error.
+  fn from_loader(e: Box<dyn Error + Send + Sync>) -> Self {
+    match e.downcast::<RegistryError>() {
+      Ok(e) => *e,
+      Err(e) => RegistryError::LoaderError(e),
+    }
+  }
+}
+
+impl CodedError for RegistryError {
+  fn code(&self) -> ErrorCode {
+    match *self {
+      RegistryError::NoLoader(..) => ErrorCode::NoLoader,
+      RegistryError::LoaderError(_) => ErrorCode::LoaderError,
+      RegistryError::CannotReadFile(_, ref e) => ErrorCode::from_io(e),
+      RegistryError::NoKey => ErrorCode::NoKey,
+    }
+  }
+}
+
+impl Error for RegistryError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      RegistryError::LoaderError(ref e) => Some(&**e),
+      RegistryError::CannotReadFile(_, ref e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl<C, K, T> Load<C, K, Registered> for T
+where K: Key + Into<Option<PathBuf>>,
+      T: 'static {
+  type Error = RegistryError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, ctx: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let loader = registered_loader::<C, K, T>(&key, storage)?;
+    load_registered(&*loader, key, storage, ctx)
+  }
+
+  fn reload(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error> {
+    let loader = registered_loader::<C, K, T>(&key, storage)?;
+    reload_registered(&*loader, self, key, storage, ctx)
+  }
+}
+
+impl<C, K, T> Load<C, K, RegisteredAs> for T
+where K: Key + Into<Option<PathBuf>>,
+      T: 'static {
+  type Error = RegistryError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, ctx: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let loader = format_loader::<C, K, T>(&key, storage)?;
+    load_registered(&*loader, key, storage, ctx)
+  }
+
+  fn reload(
+    &self,
+    key: K,
+    storage: &mut Storage<C, K>,
+    ctx: &mut C,
+  ) -> Result<Self, Self::Error> {
+    let loader = format_loader::<C, K, T>(&key, storage)?;
+    reload_registered(&*loader, self, key, storage, ctx)
+  }
+}
+
+/// Load a resource with a registered loader.
+fn load_registered<C, K, T>(
+  loader: &dyn DynLoad<C, K>,
+  key: K,
+  storage: &mut Storage<C, K>,
+  ctx: &mut C,
+) -> Result<Loaded<T, K>, RegistryError>
+where K: Key,
+      T: 'static {
+  let Loaded { res, deps } = loader.load(key, storage, ctx).map_err(RegistryError::from_loader)?;
+  Ok(Loaded { res: downcast(res), deps })
+}
+
+/// Reload a resource with a registered loader.
+///
+/// [`Load::accept`] doesn’t get the key of the resource, so the registered loader cannot be looked
+/// up from there: the loader is asked to accept the reloaded resource right away instead, and a
+/// rejection fails the reload, keeping the current resource.
+fn reload_registered<C, K, T>(
+  loader: &dyn DynLoad<C, K>,
+  res: &T,
+  key: K,
+  storage: &mut Storage<C, K>,
+  ctx: &mut C,
+) -> Result<T, RegistryError>
+where K: Key,
+      T: 'static {
+  let new = loader.reload(res, key, storage, ctx).map_err(RegistryError::from_loader)?;
+  loader.accept(res, &*new, ctx).map_err(RegistryError::from_loader)?;
+  Ok(downcast(new))
+}
+
+/// Get the loader registered for the resources of type `T` and the format chosen with
+/// [`Storage::get_dyn`], falling back to the extension of the file of a key.
+///
+/// [`Storage::get_dyn`]: crate::load::Storage::get_dyn
+fn format_loader<C, K, T>(
+  key: &K,
+  storage: &Storage<C, K>,
+) -> Result<Rc<dyn DynLoad<C, K>>, RegistryError>
+where K: Key + Into<Option<PathBuf>>,
+      T: 'static {
+  match storage.format(key) {
+    Some(format) => storage
+      .registry()
+      .loader::<T>(format)
+      .ok_or_else(|| RegistryError::NoLoader(any::type_name::<T>(), format.to_owned())),
+    None => registered_loader::<C, K, T>(key, storage),
+  }
+}
+
+/// Get the loader registered for the resources of type `T` stored in the file of a key.
+fn registered_loader<C, K, T>(
+  key: &K,
+  storage: &Storage<C, K>,
+) -> Result<Rc<dyn DynLoad<C, K>>, RegistryError>
+where K: Key + Into<Option<PathBuf>>,
+      T: 'static {
+  let path = key.clone().into().ok_or(RegistryError::NoKey)?;
+  let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+
+  storage
+    .registry()
+    .loader::<T>(&ext.to_lowercase())
+    .ok_or_else(|| RegistryError::NoLoader(any::type_name::<T>(), ext.to_owned()))
+}
+
+/// Downcast a resource produced by a registered loader.
+fn downcast<T>(res: Box<dyn Any>) -> T where T: 'static {
+  // loaders are registered with the type identifier of the resources they produce
+  *res
+    .downcast::<T>()
+    .unwrap_or_else(|_| unreachable!("registered loader type mismatch"))
+}
@@ -0,0 +1,356 @@
+//! Record and replay of store events.
+//!
+//! Hot-reload bugs often depend on the timing of filesystem events – e.g. a file written twice in a
+//! row, or a dependency changing right before the resource depending on it – which makes them hard
+//! to reproduce. A store can record what happens to it in an [`EventLog`] – the filesystem events
+//! it handles, its synchronizations and the loads and reloads of its resources – with
+//! [`Store::start_recording`]. A [`Replay`] then feeds the filesystem events of a log back into a
+//! store, synchronizing it exactly where the recorded store was, regardless of the timing of the
+//! real filesystem.
+//!
+//! Logs are turned into text with [`Display`] and parsed back with [`FromStr`], so that a log
+//! recorded while a bug happened can be saved along with a test and replayed in CI. Paths are
+//! recorded relative to the root of the store, so that logs can be replayed in another directory.
+//!
+//! ```
+//! use warmy::{Res, SimpleKey, Store, StoreOpt};
+//! use warmy::replay::{EventLog, Replay};
+//! # use warmy::{Load, Loaded, Storage};
+//! #
+//! # struct Text(String);
+//! #
+//! # impl Load<(), SimpleKey> for Text {
+//! #   type Error = std::io::Error;
+//! #
+//! #   fn load(
+//! #     key: SimpleKey,
+//! #     _: &mut Storage<(), SimpleKey>,
+//! #     _: &mut (),
+//! #   ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+//! #     let path: Option<std::path::PathBuf> = key.into();
+//! #     std::fs::read_to_string(path.unwrap()).map(|s| Text(s).into())
+//! #   }
+//! # }
+//!
+//! let log: EventLog = "write\thello.txt\nsync\n".parse().unwrap();
+//!
+//! # let tmp_dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(tmp_dir.path().join("hello.txt"), "Hello").unwrap();
+//! # let opt = StoreOpt::default().set_root(tmp_dir.path());
+//! let mut store: Store<(), SimpleKey> = Store::new(opt).unwrap();
+//! let key = SimpleKey::from_path("/hello.txt");
+//! let _: Res<Text> = store.get(&key, &mut ()).unwrap();
+//!
+//! let reports = Replay::new(log).run(&mut store, &mut ());
+//!
+//! assert_eq!(reports[0].reloaded().len(), 1);
+//! ```
+//!
+//! Replaying a log only replays the filesystem events: files are read from the disk as they are
+//! when replaying and the resources must be loaded by the replaying code. The loads and reloads of
+//! a log are meant to be inspected – e.g. to compare what happened with what was expected.
+//!
+//! [`Store::start_recording`]: crate::load::Store::start_recording
+//! [`Display`]: std::fmt::Display
+
+use notify::DebouncedEvent;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::key::Key;
+use crate::load::{Store, SyncReport};
+
+/// An event recorded by a store.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RecordedEvent {
+  /// A file was written to.
+  Write(PathBuf),
+  /// A file was created.
+  Create(PathBuf),
+  /// A file was removed.
+  Remove(PathBuf),
+  /// A file was renamed.
+  Rename(PathBuf, PathBuf),
+  /// The root was rescanned, as filesystem events were lost.
+  Rescan,
+  /// The store was synchronized. The events preceding it were handled by the synchronization.
+  Sync,
+  /// A resource was loaded for the first time. Its key is formatted with [`Debug`].
+  ///
+  /// [`Debug`]: std::fmt::Debug
+  Loaded(String),
+  /// A resource was reloaded. Its key is formatted with [`Debug`].
+  ///
+  /// [`Debug`]: std::fmt::Debug
+  Reloaded(String),
+  /// A resource failed to reload. Its key is formatted with [`Debug`].
+  ///
+  /// [`Debug`]: std::fmt::Debug
+  ReloadFailed(String),
+}
+
+impl RecordedEvent {
+  /// Record a filesystem event, with paths relative to the root.
+  ///
+  /// Rescans are recorded when they happen rather than when they’re requested, as the store can
+  /// rescan its root on its own.
+  pub(crate) fn from_fs_event(event: &DebouncedEvent, root: &Path) -> Option<Self> {
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_owned();
+
+    match *event {
+      DebouncedEvent::Write(ref path) => Some(RecordedEvent::Write(relative(path))),
+      DebouncedEvent::Create(ref path) => Some(RecordedEvent::Create(relative(path))),
+      DebouncedEvent::Remove(ref path) => Some(RecordedEvent::Remove(relative(path))),
+      DebouncedEvent::Rename(ref from, ref to) => {
+        Some(RecordedEvent::Rename(relative(from), relative(to)))
+      }
+      _ => None,
+    }
+  }
+
+  /// Turn the event back into a filesystem event, with paths rebased onto the root.
+  fn to_fs_event(&self, root: &Path) -> Option<DebouncedEvent> {
+    match *self {
+      RecordedEvent::Write(ref path) => Some(DebouncedEvent::Write(root.join(path))),
+      RecordedEvent::Create(ref path) => Some(DebouncedEvent::Create(root.join(path))),
+      RecordedEvent::Remove(ref path) => Some(DebouncedEvent::Remove(root.join(path))),
+      RecordedEvent::Rename(ref from, ref to) => {
+        Some(DebouncedEvent::Rename(root.join(from), root.join(to)))
+      }
+      RecordedEvent::Rescan => Some(DebouncedEvent::Rescan),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for RecordedEvent {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let path = |path: &Path| escape(&path.to_string_lossy());
+
+    match *self {
+      RecordedEvent::Write(ref p) => write!(f, "write\t{}", path(p)),
+      RecordedEvent::Create(ref p) => write!(f, "create\t{}", path(p)),
+      RecordedEvent::Remove(ref p) => write!(f, "remove\t{}", path(p)),
+      RecordedEvent::Rename(ref from, ref to) => write!(f, "rename\t{}\t{}", path(from), path(to)),
+      RecordedEvent::Rescan => f.write_str("rescan"),
+      RecordedEvent::Sync => f.write_str("sync"),
+      RecordedEvent::Loaded(ref key) => write!(f, "loaded\t{}", escape(key)),
+      RecordedEvent::Reloaded(ref key) => write!(f, "reloaded\t{}", escape(key)),
+      RecordedEvent::ReloadFailed(ref key) => write!(f, "reload-failed\t{}", escape(key)),
+    }
+  }
+}
+
+/// A log of the events recorded by a store, in order.
+///
+/// Logs are formatted with one event per line, its kind followed by its tab-separated arguments –
+/// e.g. `rename\told.json\tnew.json`. Tabs, line breaks and backslashes in arguments are escaped
+/// with a backslash. Paths that are not valid UTF-8 are recorded lossily.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventLog {
+  // recorded events, in order
+  events: Vec<RecordedEvent>,
+}
+
+impl EventLog {
+  /// Create an empty log.
+  pub fn new() -> Self {
+    EventLog { events: Vec::new() }
+  }
+
+  /// Recorded events, in order.
+  pub fn events(&self) -> &[RecordedEvent] {
+    &self.events
+  }
+
+  /// Append an event to the log – e.g. to write a log by hand.
+  pub fn push(&mut self, event: RecordedEvent) {
+    self.events.push(event);
+  }
+
+  /// Number of recorded events.
+  pub fn len(&self) -> usize {
+    self.events.len()
+  }
+
+  /// Whether no event was recorded.
+  pub fn is_empty(&self) -> bool {
+    self.events.is_empty()
+  }
+}
+
+impl fmt::Display for EventLog {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    for event in &self.events {
+      writeln!(f, "{}", event)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl FromStr for EventLog {
+  type Err = EventLogError;
+
+  fn from_str(source: &str) -> Result<Self, Self::Err> {
+    let mut events = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      let event = parse_event(line).ok_or(EventLogError { line: i + 1 })?;
+      events.push(event);
+    }
+
+    Ok(EventLog { events })
+  }
+}
+
+/// Error that occurs when parsing an invalid [`EventLog`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EventLogError {
+  // number of the invalid line, starting at 1
+  line: usize,
+}
+
+impl EventLogError {
+  /// Number of the invalid line, starting at 1.
+  pub fn line(&self) -> usize {
+    self.line
+  }
+}
+
+impl fmt::Display for EventLogError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "invalid event at line {}", self.line)
+  }
+}
+
+impl Error for EventLogError {}
+
+/// Replay of an [`EventLog`] into a store.
+///
+/// The filesystem events of the log are fed to the store in order, and the store is synchronized
+/// wherever the recorded one was. While replaying, the events of the real filesystem are dropped,
+/// so that only the replayed ones are handled.
+#[derive(Clone, Debug)]
+pub struct Replay {
+  // replayed log
+  log: EventLog,
+  // index of the next event to replay
+  next: usize,
+}
+
+impl Replay {
+  /// Create a replay of a log, starting at its first event.
+  pub fn new(log: EventLog) -> Self {
+    Replay { log, next: 0 }
+  }
+
+  /// The replayed log.
+  pub fn log(&self) -> &EventLog {
+    &self.log
+  }
+
+  /// Whether all the synchronizations of the log were replayed.
+  pub fn is_done(&self) -> bool {
+    !self.log.events[self.next..].contains(&RecordedEvent::Sync)
+  }
+
+  /// Replay the filesystem events up to the next synchronization of the log, and synchronize the
+  /// store with a provided context.
+  ///
+  /// Return the report of the synchronization, or [`None`] if all the synchronizations of the log
+  /// were replayed. Calling this function step by step allows to change files – or to check the
+  /// resources – between two synchronizations.
+  pub fn step<C, K>(&mut self, store: &mut Store<C, K>, ctx: &mut C) -> Option<SyncReport<K>>
+  where K: Key + for<'a> From<&'a Path> {
+    let remaining = &self.log.events[self.next..];
+    let len = remaining.iter().position(|event| *event == RecordedEvent::Sync)?;
+    let root = store.root();
+    let events = remaining[..len].iter().filter_map(|event| event.to_fs_event(root)).collect();
+    self.next += len + 1;
+
+    Some(store.sync_replayed(events, ctx))
+  }
+
+  /// Replay all the remaining synchronizations of the log with a provided context.
+  ///
+  /// Return the reports of the synchronizations, in order.
+  pub fn run<C, K>(&mut self, store: &mut Store<C, K>, ctx: &mut C) -> Vec<SyncReport<K>>
+  where K: Key + for<'a> From<&'a Path> {
+    let mut reports = Vec::new();
+
+    while let Some(report) = self.step(store, ctx) {
+      reports.push(report);
+    }
+
+    reports
+  }
+}
+
+/// Parse a line of an event log.
+fn parse_event(line: &str) -> Option<RecordedEvent> {
+  let mut fields = line.split('\t');
+  let kind = fields.next()?;
+  let args = fields.map(unescape).collect::<Option<Vec<_>>>()?;
+
+  let event = match (kind, args.as_slice()) {
+    ("write", [path]) => RecordedEvent::Write(path.into()),
+    ("create", [path]) => RecordedEvent::Create(path.into()),
+    ("remove", [path]) => RecordedEvent::Remove(path.into()),
+    ("rename", [from, to]) => RecordedEvent::Rename(from.into(), to.into()),
+    ("rescan", []) => RecordedEvent::Rescan,
+    ("sync", []) => RecordedEvent::Sync,
+    ("loaded", [key]) => RecordedEvent::Loaded(key.clone()),
+    ("reloaded", [key]) => RecordedEvent::Reloaded(key.clone()),
+    ("reload-failed", [key]) => RecordedEvent::ReloadFailed(key.clone()),
+    _ => return None,
+  };
+
+  Some(event)
+}
+
+/// Escape the tabs, line breaks and backslashes of an argument.
+fn escape(arg: &str) -> String {
+  let mut escaped = String::with_capacity(arg.len());
+
+  for c in arg.chars() {
+    match c {
+      '\t' => escaped.push_str("\\t"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\\' => escaped.push_str("\\\\"),
+      c => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+/// Unescape an argument; fail on unknown escapes.
+fn unescape(arg: &str) -> Option<String> {
+  let mut unescaped = String::with_capacity(arg.len());
+  let mut chars = arg.chars();
+
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      unescaped.push(c);
+      continue;
+    }
+
+    match chars.next()? {
+      't' => unescaped.push('\t'),
+      'n' => unescaped.push('\n'),
+      'r' => unescaped.push('\r'),
+      '\\' => unescaped.push('\\'),
+      _ => return None,
+    }
+  }
+
+  Some(unescaped)
+}
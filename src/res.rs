@@ -1,6 +1,7 @@
 //! Shareable resources.
 
 #[cfg(feature = "arc")] use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(all(feature = "arc", feature = "hot-reload"))] use std::sync::TryLockError;
 #[cfg(not(feature = "arc"))] use std::{
   cell::{Ref, RefCell, RefMut},
   rc::Rc
@@ -33,14 +34,22 @@ impl<T> Res<T> {
   }
 
   /// Borrow a resource for as long as the return value lives.
+  #[allow(mismatched_lifetime_syntaxes)]
   pub fn borrow(&self) -> MutexGuard<T> {
     self.0.lock().unwrap()
   }
 
   /// Mutably borrow a resource for as long as the return value lives.
+  #[allow(mismatched_lifetime_syntaxes)]
   pub fn borrow_mut(&self) -> MutexGuard<T> {
     self.0.lock().unwrap()
   }
+
+  /// Check whether the resource is currently borrowed.
+  #[cfg(feature = "hot-reload")]
+  pub(crate) fn is_borrowed(&self) -> bool {
+    matches!(self.0.try_lock(), Err(TryLockError::WouldBlock))
+  }
 }
 
 #[cfg(not(feature = "arc"))]
@@ -51,12 +60,20 @@ impl<T> Res<T> {
   }
 
   /// Borrow a resource for as long as the return value lives.
+  #[allow(mismatched_lifetime_syntaxes)]
   pub fn borrow(&self) -> Ref<T> {
     self.0.borrow()
   }
 
   /// Mutably borrow a resource for as long as the return value lives.
+  #[allow(mismatched_lifetime_syntaxes)]
   pub fn borrow_mut(&self) -> RefMut<T> {
     self.0.borrow_mut()
   }
+
+  /// Check whether the resource is currently borrowed.
+  #[cfg(feature = "hot-reload")]
+  pub(crate) fn is_borrowed(&self) -> bool {
+    self.0.try_borrow_mut().is_err()
+  }
 }
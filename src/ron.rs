@@ -9,20 +9,24 @@
 use ron::de::{self, from_str};
 use serde::Deserialize;
 use std::fmt;
-use std::fs::read_to_string;
+use std::error::Error;
 use std::io;
 use std::path::PathBuf;
 
+use crate::combinator::Method;
 use crate::key::Key;
-use crate::load::{Load, Loaded, Storage};
+use crate::load::{CodedError, ErrorCode, Load, Loaded, Storage};
 
 /// The RON universal method. Use this with [`Storage::get_by`] or [`Storage::get_proxied_by`] to
 /// benefit from the automatic implementors.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Ron;
 
+impl Method for Ron {}
+
 /// Possible error that might occur while loading and reloading RON formatted scarce resources.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum RonError {
   /// An error in [ron](https://crates.io/crates/ron).
   RonError(de::Error),
@@ -46,15 +50,36 @@ impl fmt::Display for RonError {
   }
 }
 
+impl CodedError for RonError {
+  fn code(&self) -> ErrorCode {
+    match *self {
+      RonError::RonError(_) => ErrorCode::ParseError,
+      RonError::CannotReadFile(_, ref e) => ErrorCode::from_io(e),
+      RonError::NoKey => ErrorCode::NoKey,
+    }
+  }
+}
+
+impl Error for RonError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      RonError::RonError(ref e) => Some(e),
+      RonError::CannotReadFile(_, ref e) => Some(e),
+      RonError::NoKey => None,
+    }
+  }
+}
+
 impl<C, K, T> Load<C, K, Ron> for T
 where K: Key + Into<Option<PathBuf>>,
       T: 'static + for<'de> Deserialize<'de>, {
   type Error = RonError;
 
-  fn load(key: K, _: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+  fn load(key: K, storage: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
     if let Some(path) = key.into() {
-      let file_content =
-        read_to_string(&path).map_err(|ioerr| RonError::CannotReadFile(path, ioerr))?;
+      let file_content = storage
+        .read_file_to_string(&path)
+        .map_err(|ioerr| RonError::CannotReadFile(path, ioerr))?;
 
       from_str(&file_content)
         .map(Loaded::without_dep)
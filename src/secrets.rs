@@ -0,0 +1,258 @@
+//! Secrets.
+//!
+//! This module provides you with [`Secrets`], a resource holding the variables of a `.env`-style
+//! file – API keys, passwords, tokens. Hot-reloading such a file lets an agent rotate credentials
+//! by rewriting it, without restarting the services using them.
+//!
+//! Secrets are guarded against leaking:
+//!
+//!   - Their values are redacted when formatted with [`Debug`] or [`Display`], so that they don’t
+//!     end up in logs. Use [`Secret::expose`] to read them.
+//!   - Their values are zeroed when dropped – e.g. when rotated secrets replace them – as is the
+//!     content of the file once parsed. This is done on a best-effort basis: copies made by your own
+//!     code are not zeroed.
+//!   - Errors never hold the content of the file.
+//!
+//! Files hold one `NAME=value` variable per line, optionally prefixed with `export`. Blank lines
+//! and lines starting with `#` are ignored. Values can be quoted: single-quoted values are taken
+//! literally and double-quoted values support the `\n`, `\"` and `\\` escapes.
+//!
+//! [`Debug`]: std::fmt::Debug
+//! [`Display`]: std::fmt::Display
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
+
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+/// Placeholder of redacted values.
+const REDACTED: &str = "[REDACTED]";
+
+/// A secret value, redacted when formatted and zeroed when dropped.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Secret {
+  // value of the secret
+  value: String,
+}
+
+impl Secret {
+  /// Create a secret.
+  pub fn new(value: String) -> Self {
+    Secret { value }
+  }
+
+  /// Read the value of the secret.
+  pub fn expose(&self) -> &str {
+    &self.value
+  }
+}
+
+impl fmt::Debug for Secret {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str(REDACTED)
+  }
+}
+
+impl fmt::Display for Secret {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.write_str(REDACTED)
+  }
+}
+
+impl Drop for Secret {
+  fn drop(&mut self) {
+    zeroize_str(&mut self.value);
+  }
+}
+
+/// The variables of a `.env`-style file.
+#[derive(Clone, Default)]
+pub struct Secrets {
+  // secrets, by name
+  secrets: HashMap<String, Secret>,
+}
+
+impl Secrets {
+  /// Get a secret by name.
+  pub fn get(&self, name: &str) -> Option<&Secret> {
+    self.secrets.get(name)
+  }
+
+  /// Names of the secrets.
+  pub fn names(&self) -> impl Iterator<Item = &str> {
+    self.secrets.keys().map(String::as_str)
+  }
+
+  /// Number of secrets.
+  pub fn len(&self) -> usize {
+    self.secrets.len()
+  }
+
+  /// Whether there’s no secret.
+  pub fn is_empty(&self) -> bool {
+    self.secrets.is_empty()
+  }
+}
+
+impl fmt::Debug for Secrets {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    f.debug_map().entries(&self.secrets).finish()
+  }
+}
+
+/// Possible error that might occur while loading and reloading secrets.
+///
+/// Errors never hold the content of the file, so that they can be logged safely.
+#[derive(Debug)]
+pub enum SecretsError {
+  /// The line at the given number (starting at 1) is not a valid variable.
+  ParseError(PathBuf, usize),
+  /// The file is not valid UTF-8.
+  InvalidUtf8(PathBuf),
+  /// The file specified by the key failed to open or could not be read.
+  CannotReadFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+}
+
+impl fmt::Display for SecretsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      SecretsError::ParseError(ref path, line) => {
+        write!(f, "invalid variable in {} at line {}", path.display(), line)
+      }
+
+      SecretsError::InvalidUtf8(ref path) => write!(f, "{} is not valid UTF-8", path.display()),
+
+      SecretsError::CannotReadFile(ref path, ref e) => {
+        write!(f, "cannot read file {}: {}", path.display(), e)
+      }
+
+      SecretsError::NoKey => f.write_str("no path key available"),
+    }
+  }
+}
+
+impl Error for SecretsError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      SecretsError::CannotReadFile(_, ref e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl<C, K> Load<C, K> for Secrets where K: Key + Into<Option<PathBuf>> {
+  type Error = SecretsError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let path = key.into().ok_or(SecretsError::NoKey)?;
+    let mut content = storage
+      .read_file(&path)
+      .map_err(|ioerr| SecretsError::CannotReadFile(path.clone(), ioerr))?;
+    let secrets = match std::str::from_utf8(&content) {
+      Ok(source) => parse(source).map_err(|line| SecretsError::ParseError(path, line)),
+      Err(_) => Err(SecretsError::InvalidUtf8(path)),
+    };
+
+    zeroize(&mut content);
+
+    secrets.map(|secrets| Secrets { secrets }.into())
+  }
+}
+
+/// Parse a `.env`-style source, returning the number of the first invalid line on failure.
+fn parse(source: &str) -> Result<HashMap<String, Secret>, usize> {
+  let mut secrets = HashMap::new();
+
+  for (i, line) in source.lines().enumerate() {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+    let (name, value) = line.split_once('=').ok_or(i + 1)?;
+    let name = name.trim_end();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+      return Err(i + 1);
+    }
+
+    let value = parse_value(value.trim_start()).ok_or(i + 1)?;
+    secrets.insert(name.to_owned(), value);
+  }
+
+  Ok(secrets)
+}
+
+/// Parse the value of a variable, quoted or not.
+fn parse_value(value: &str) -> Option<Secret> {
+  if let Some(quoted) = value.strip_prefix('\'') {
+    let (literal, rest) = quoted.split_once('\'')?;
+    return trailing_comment(rest).then(|| Secret::new(literal.to_owned()));
+  }
+
+  if let Some(quoted) = value.strip_prefix('"') {
+    // the value is at most as long as the quoted one, so it’s never reallocated – and copied
+    let mut unescaped = String::with_capacity(quoted.len());
+    let mut chars = quoted.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+      match c {
+        '"' => return trailing_comment(&quoted[i + 1..]).then(|| Secret::new(unescaped)),
+
+        '\\' => match chars.next() {
+          Some((_, 'n')) => unescaped.push('\n'),
+          Some((_, c)) if c == '"' || c == '\\' => unescaped.push(c),
+          _ => {
+            zeroize_str(&mut unescaped);
+            return None;
+          }
+        },
+
+        c => unescaped.push(c),
+      }
+    }
+
+    // unterminated value
+    zeroize_str(&mut unescaped);
+    return None;
+  }
+
+  // unquoted values stop at comments
+  let value = match value.find(" #") {
+    Some(i) => &value[..i],
+    None => value,
+  };
+
+  Some(Secret::new(value.trim_end().to_owned()))
+}
+
+/// Check that what follows a quoted value is blank or a comment.
+fn trailing_comment(rest: &str) -> bool {
+  let rest = rest.trim_start();
+  rest.is_empty() || rest.starts_with('#')
+}
+
+/// Zero a string slice.
+fn zeroize_str(s: &mut str) {
+  // zero is valid UTF-8
+  zeroize(unsafe { s.as_bytes_mut() });
+}
+
+/// Zero bytes in a way the compiler doesn’t optimize out.
+fn zeroize(bytes: &mut [u8]) {
+  for byte in bytes {
+    unsafe { ptr::write_volatile(byte, 0) };
+  }
+
+  atomic::compiler_fence(Ordering::SeqCst);
+}
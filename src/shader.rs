@@ -0,0 +1,134 @@
+//! WGSL shaders.
+//!
+//! This module provides you with [`WgslShader`], a resource holding a WGSL shader parsed and
+//! validated with [naga]. As a broken shader fails to load, a broken edit makes the reload fail:
+//! the shader keeps its last good version and your renderer never sees invalid code. The error
+//! carries the diagnostic of naga, pointing at the faulty code, and is reported like any other
+//! reload failure – see [`SyncReport::failed`], [`StoreObserver::reload_failed`] and
+//! [`Storage::last_error`].
+//!
+//! Shaders are validated against the capabilities of baseline WebGPU.
+//!
+//! [naga]: https://crates.io/crates/naga
+//! [`SyncReport::failed`]: crate::load::SyncReport::failed
+//! [`StoreObserver::reload_failed`]: crate::observer::StoreObserver::reload_failed
+//! [`Storage::last_error`]: crate::load::Storage::last_error
+
+use naga::front::wgsl;
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use naga::Module;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+/// A parsed and validated WGSL shader.
+#[derive(Debug)]
+pub struct WgslShader {
+  // path of the shader
+  path: PathBuf,
+  // source code of the shader
+  source: String,
+  // intermediate representation of the shader
+  module: Module,
+  // information gathered while validating the shader, needed by naga backends
+  info: ModuleInfo,
+}
+
+impl WgslShader {
+  /// Path of the shader.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Source code of the shader, to hand to your graphics API.
+  pub fn source(&self) -> &str {
+    &self.source
+  }
+
+  /// Intermediate representation of the shader.
+  pub fn module(&self) -> &Module {
+    &self.module
+  }
+
+  /// Validation information of the shader, to compile it with a naga backend.
+  pub fn info(&self) -> &ModuleInfo {
+    &self.info
+  }
+}
+
+/// Possible error that might occur while loading and reloading WGSL shaders.
+///
+/// Parsing and validation errors hold the diagnostic of naga, with the path of the shader and the
+/// location of the error.
+#[derive(Debug)]
+pub enum ShaderError {
+  /// The shader failed to parse.
+  ParseError(String),
+  /// The shader parsed but is invalid.
+  ValidationError(String),
+  /// The file specified by the key failed to open or could not be read.
+  CannotReadFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+}
+
+impl fmt::Display for ShaderError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      ShaderError::ParseError(ref diagnostic) => write!(f, "WGSL parse error: {}", diagnostic),
+
+      ShaderError::ValidationError(ref diagnostic) => {
+        write!(f, "WGSL validation error: {}", diagnostic)
+      }
+
+      ShaderError::CannotReadFile(ref path, ref e) => {
+        write!(f, "cannot read file {}: {}", path.display(), e)
+      }
+
+      ShaderError::NoKey => f.write_str("no path key available"),
+    }
+  }
+}
+
+impl Error for ShaderError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      ShaderError::CannotReadFile(_, ref e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl<C, K> Load<C, K> for WgslShader where K: Key + Into<Option<PathBuf>> {
+  type Error = ShaderError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    let path = key.into().ok_or(ShaderError::NoKey)?;
+    let source = storage
+      .read_file_to_string(&path)
+      .map_err(|ioerr| ShaderError::CannotReadFile(path.clone(), ioerr))?;
+    let display_path = path.display().to_string();
+
+    let module = wgsl::parse_str(&source)
+      .map_err(|e| ShaderError::ParseError(e.emit_to_string_with_path(&source, &display_path)))?;
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::default())
+      .validate(&module)
+      .map_err(|e| {
+        ShaderError::ValidationError(e.emit_to_string_with_path(&source, &display_path))
+      })?;
+
+    let shader = WgslShader {
+      path,
+      source,
+      module,
+      info,
+    };
+
+    Ok(shader.into())
+  }
+}
@@ -0,0 +1,149 @@
+//! Test utilities.
+//!
+//! > This module is feature-gated with `"test-util"`. Enable it in your `[dev-dependencies]`.
+//!
+//! Testing hot-reloading requires a directory to write files to, a store rooted in it and a loop
+//! synchronizing the store until the filesystem events get in – which takes a while, as they’re
+//! debounced. This module provides you with these, so that you don’t have to write them for every
+//! resource type:
+//!
+//! ```
+//! # #[cfg(feature = "hot-reload")] {
+//! use std::fs;
+//! use warmy::{Load, Loaded, Res, SimpleKey, Storage};
+//! use warmy::test_util::{sync_until, with_store};
+//!
+//! struct Text(String);
+//!
+//! impl Load<(), SimpleKey> for Text {
+//!   type Error = std::io::Error;
+//!
+//!   fn load(
+//!     key: SimpleKey,
+//!     storage: &mut Storage<(), SimpleKey>,
+//!     _: &mut (),
+//!   ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+//!     let path: Option<std::path::PathBuf> = key.into();
+//!     storage.read_file_to_string(&path.unwrap()).map(|s| Text(s).into())
+//!   }
+//! }
+//!
+//! with_store(|mut store| {
+//!   let path = store.root().join("hello.txt");
+//!   fs::write(&path, "Hello").unwrap();
+//!
+//!   let key = SimpleKey::from_path("/hello.txt");
+//!   let text: Res<Text> = store.get(&key, &mut ()).unwrap();
+//!
+//!   fs::write(&path, "Bye").unwrap();
+//!   sync_until(&mut store, &mut (), || text.borrow().0 == "Bye");
+//! });
+//! # }
+//! ```
+//!
+//! To test resources without touching the filesystem at all, see the [`testing`] module.
+//!
+//! [`testing`]: crate::testing
+
+use std::path::Path;
+#[cfg(feature = "hot-reload")] use std::thread;
+#[cfg(feature = "hot-reload")] use std::time::{Duration, Instant};
+use tempfile::Builder;
+
+use crate::key::Key;
+use crate::load::{Store, StoreOpt};
+
+/// Time [`sync_until`] waits for a condition before giving up.
+///
+/// Filesystem events are debounced and might take a while to get in on loaded CI machines, so this
+/// is much more than what they usually take.
+#[cfg(feature = "hot-reload")]
+pub const SYNC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run a function with a temporary directory, removed afterwards.
+///
+/// # Panics
+///
+/// This function panics if the directory cannot be created or removed.
+pub fn with_tmp_dir<F, R>(f: F) -> R where F: FnOnce(&Path) -> R {
+  let tmp_dir = Builder::new().prefix("warmy").tempdir().expect("create temporary directory");
+  let r = f(tmp_dir.path());
+  tmp_dir.close().expect("remove temporary directory");
+  r
+}
+
+/// Run a function with a store rooted in a temporary directory, removed afterwards.
+///
+/// The store is created with the default options. See [`with_store_opt`] to change them.
+///
+/// # Panics
+///
+/// This function panics if the directory cannot be created or removed, or if the store cannot be
+/// created.
+pub fn with_store<C, K, F, R>(f: F) -> R where K: Key, F: FnOnce(Store<C, K>) -> R {
+  with_store_opt(StoreOpt::default(), f)
+}
+
+/// Run a function with a store rooted in a temporary directory, removed afterwards.
+///
+/// The root of the options is replaced by the temporary directory.
+///
+/// # Panics
+///
+/// This function panics if the directory cannot be created or removed, or if the store cannot be
+/// created.
+pub fn with_store_opt<C, K, F, R>(opt: StoreOpt<C, K>, f: F) -> R
+where K: Key,
+      F: FnOnce(Store<C, K>) -> R {
+  with_tmp_dir(|tmp_dir| {
+    let store = Store::new(opt.set_root(tmp_dir)).unwrap_or_else(|_| panic!("cannot create store"));
+    f(store)
+  })
+}
+
+/// Synchronize a store with a provided context until a condition holds.
+///
+/// The condition is checked before each synchronization.
+///
+/// # Panics
+///
+/// This function panics if the condition still doesn’t hold after [`SYNC_TIMEOUT`].
+#[cfg(feature = "hot-reload")]
+pub fn sync_until<C, K, F>(store: &mut Store<C, K>, ctx: &mut C, condition: F)
+where K: Key + for<'a> From<&'a Path>,
+      F: FnMut() -> bool {
+  if !sync_until_timeout(store, ctx, SYNC_TIMEOUT, condition) {
+    panic!(
+      "more than {} milliseconds were spent waiting for a filesystem event",
+      SYNC_TIMEOUT.as_millis()
+    );
+  }
+}
+
+/// Synchronize a store with a provided context until a condition holds or a timeout elapses.
+///
+/// The condition is checked before each synchronization. Return whether it holds.
+#[cfg(feature = "hot-reload")]
+pub fn sync_until_timeout<C, K, F>(
+  store: &mut Store<C, K>,
+  ctx: &mut C,
+  timeout: Duration,
+  mut condition: F,
+) -> bool
+where K: Key + for<'a> From<&'a Path>,
+      F: FnMut() -> bool {
+  let start = Instant::now();
+
+  while !condition() {
+    if start.elapsed() >= timeout {
+      return false;
+    }
+
+    store.sync(ctx);
+
+    // don’t spin while the watcher debounces events
+    thread::sleep(Duration::from_millis(1));
+  }
+
+  true
+}
@@ -0,0 +1,171 @@
+//! Testing utilities.
+//!
+//! This module provides you with [`MockStore`], a store which files live in memory. It’s meant to
+//! unit-test [`Load`] implementations and reload handling without touching the filesystem: files
+//! are set with [`MockStore::set_file`] and their changes are injected as filesystem events,
+//! handled on the next [`Store::sync`] – no temporary directory, no watcher and no timing involved.
+//!
+//! ```
+//! # #[cfg(feature = "json")] {
+//! use serde::Deserialize;
+//! use warmy::{Res, SimpleKey, StoreOpt};
+//! use warmy::json::Json;
+//! use warmy::testing::MockStore;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!   volume: u8,
+//! }
+//!
+//! let mut store: MockStore<(), SimpleKey> = MockStore::new(StoreOpt::default());
+//! store.set_file("/config.json", r#"{ "volume": 50 }"#);
+//!
+//! let key = SimpleKey::from_path("/config.json");
+//! let config: Res<Config> = store.get_by(&key, &mut (), Json).unwrap();
+//! assert_eq!(config.borrow().volume, 50);
+//!
+//! // the change is handled on the next synchronization
+//! store.set_file("/config.json", r#"{ "volume": 80 }"#);
+//! store.sync(&mut ());
+//! # #[cfg(feature = "hot-reload")]
+//! assert_eq!(config.borrow().volume, 80);
+//! # }
+//! ```
+//!
+//! Loaders must read their files with [`Storage::read_file`] or [`Storage::read_file_to_string`]
+//! for them to be found; the loaders provided by this crate do.
+//!
+//! [`Load`]: crate::load::Load
+//! [`Storage::read_file`]: crate::load::Storage::read_file
+//! [`Storage::read_file_to_string`]: crate::load::Storage::read_file_to_string
+
+#[cfg(feature = "hot-reload")] use notify::DebouncedEvent;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use crate::key::{vfs_substitute_path, Key};
+use crate::load::{Store, StoreOpt};
+
+/// A [`Store`] which files live in memory.
+///
+/// It dereferences to a [`Store`], so that resources are loaded, reloaded and synchronized exactly
+/// as with a real one. Files are referred to by their VFS paths – e.g. `/config.json` – which are
+/// relative to the root of the store, as with [`SimpleKey::Path`].
+///
+/// Changing files queues the matching filesystem events, handled on the next synchronization.
+/// Without the `"hot-reload"` feature-gate, resources are never reloaded and only the files are
+/// changed.
+///
+/// [`SimpleKey::Path`]: crate::key::SimpleKey::Path
+pub struct MockStore<C, K> {
+  // store reading files from memory
+  store: Store<C, K>,
+}
+
+impl<C, K> MockStore<C, K> where K: Key {
+  /// Create a mock store without any file.
+  ///
+  /// The root of the options is used as the virtual root of the files and doesn’t have to exist.
+  pub fn new(opt: StoreOpt<C, K>) -> Self {
+    MockStore {
+      store: Store::in_memory(opt),
+    }
+  }
+
+  /// Get the content of a file, if it exists.
+  pub fn file<P>(&self, path: P) -> Option<&[u8]> where P: AsRef<Path> {
+    let path = self.resolve(path.as_ref());
+    self.store.files().get(&path).map(Vec::as_slice)
+  }
+
+  /// Create or write to a file.
+  pub fn set_file<P, B>(&mut self, path: P, content: B) where P: AsRef<Path>, B: Into<Vec<u8>> {
+    let path = self.resolve(path.as_ref());
+    #[cfg_attr(not(feature = "hot-reload"), allow(unused_variables))]
+    let previous = self.store.files_mut().insert(path.clone(), content.into());
+
+    #[cfg(feature = "hot-reload")]
+    self.store.queue_fs_event(match previous {
+      Some(_) => DebouncedEvent::Write(path),
+      None => DebouncedEvent::Create(path),
+    });
+  }
+
+  /// Signal that a file was written to without changing its content – e.g. to check that a
+  /// resource reloads.
+  ///
+  /// Return `false` if the file doesn’t exist.
+  pub fn touch<P>(&mut self, path: P) -> bool where P: AsRef<Path> {
+    let path = self.resolve(path.as_ref());
+
+    if !self.store.files().contains_key(&path) {
+      return false;
+    }
+
+    #[cfg(feature = "hot-reload")]
+    self.store.queue_fs_event(DebouncedEvent::Write(path));
+
+    true
+  }
+
+  /// Remove a file.
+  ///
+  /// Return `false` if the file doesn’t exist.
+  pub fn remove_file<P>(&mut self, path: P) -> bool where P: AsRef<Path> {
+    let path = self.resolve(path.as_ref());
+
+    if self.store.files_mut().remove(&path).is_none() {
+      return false;
+    }
+
+    #[cfg(feature = "hot-reload")]
+    self.store.queue_fs_event(DebouncedEvent::Remove(path));
+
+    true
+  }
+
+  /// Rename a file, replacing the destination if it exists – e.g. to simulate the atomic saves of
+  /// editors.
+  ///
+  /// Return `false` if the file doesn’t exist.
+  pub fn rename_file<P, Q>(&mut self, from: P, to: Q) -> bool where P: AsRef<Path>, Q: AsRef<Path> {
+    let from = self.resolve(from.as_ref());
+    let to = self.resolve(to.as_ref());
+
+    let content = match self.store.files_mut().remove(&from) {
+      Some(content) => content,
+      None => return false,
+    };
+
+    self.store.files_mut().insert(to.clone(), content);
+
+    #[cfg(feature = "hot-reload")]
+    self.store.queue_fs_event(DebouncedEvent::Rename(from, to));
+
+    true
+  }
+
+  /// Consume the mock store and get the underlying store.
+  pub fn into_store(self) -> Store<C, K> {
+    self.store
+  }
+
+  /// Resolve the VFS path of a file.
+  fn resolve(&self, path: &Path) -> PathBuf {
+    vfs_substitute_path(path, self.store.root())
+  }
+}
+
+impl<C, K> Deref for MockStore<C, K> {
+  type Target = Store<C, K>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.store
+  }
+}
+
+impl<C, K> DerefMut for MockStore<C, K> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.store
+  }
+}
@@ -8,21 +8,25 @@
 
 use serde::Deserialize;
 use std::fmt;
-use std::fs::read_to_string;
+use std::error::Error;
 use std::io;
 use std::path::PathBuf;
 use toml::{self, from_str};
 
+use crate::combinator::Method;
 use crate::key::Key;
-use crate::load::{Load, Loaded, Storage};
+use crate::load::{CodedError, ErrorCode, Load, Loaded, Storage};
 
 /// The TOML universal method. Use this with [`Storage::get_by`] or [`Storage::get_proxied_by`] to
 /// benefit from the automatic implementors.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Toml;
 
+impl Method for Toml {}
+
 /// Possible error that might occur while loading and reloading TOML formatted scarce resources.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum TomlError {
   /// An error in [toml](https://crates.io/crates/toml).
   TomlError(toml::de::Error),
@@ -46,15 +50,36 @@ impl fmt::Display for TomlError {
   }
 }
 
+impl CodedError for TomlError {
+  fn code(&self) -> ErrorCode {
+    match *self {
+      TomlError::TomlError(_) => ErrorCode::ParseError,
+      TomlError::CannotReadFile(_, ref e) => ErrorCode::from_io(e),
+      TomlError::NoKey => ErrorCode::NoKey,
+    }
+  }
+}
+
+impl Error for TomlError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      TomlError::TomlError(ref e) => Some(e),
+      TomlError::CannotReadFile(_, ref e) => Some(e),
+      TomlError::NoKey => None,
+    }
+  }
+}
+
 impl<C, K, T> Load<C, K, Toml> for T
 where K: Key + Into<Option<PathBuf>>,
       T: 'static + for<'de> Deserialize<'de>, {
   type Error = TomlError;
 
-  fn load(key: K, _: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+  fn load(key: K, storage: &mut Storage<C, K>, _: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
     if let Some(path) = key.into() {
-      let file_content =
-        read_to_string(&path).map_err(|ioerr| TomlError::CannotReadFile(path, ioerr))?;
+      let file_content = storage
+        .read_file_to_string(&path)
+        .map_err(|ioerr| TomlError::CannotReadFile(path, ioerr))?;
 
       from_str(&file_content)
         .map(Loaded::without_dep)
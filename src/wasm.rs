@@ -0,0 +1,91 @@
+//! WebAssembly modules.
+//!
+//! This module provides you with [`WasmModule`], a resource holding a WebAssembly module compiled
+//! with [wasmi]. Game logic or plugins written in WebAssembly can then be swapped at runtime: when
+//! a `.wasm` file changes, the module is recompiled and, if the compilation fails, the current
+//! module is kept.
+//!
+//! Modules are compiled for a [`wasmi::Engine`], which the context must give access to via
+//! [`Inspect`] – using the [`wasmi::Engine`] itself as context works out of the box. Instances are
+//! not resources: instantiate the module again after it was reloaded to run the new code.
+//!
+//! [wasmi]: https://crates.io/crates/wasmi
+//! [`Inspect`]: crate::context::Inspect
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use wasmi::{self, Engine, Module};
+
+use crate::context::Inspect;
+use crate::key::Key;
+use crate::load::{Load, Loaded, Storage};
+
+/// A compiled WebAssembly module, in the binary format.
+#[derive(Debug)]
+pub struct WasmModule {
+  // compiled module
+  module: Module,
+}
+
+impl WasmModule {
+  /// The compiled module, to instantiate.
+  pub fn module(&self) -> &Module {
+    &self.module
+  }
+}
+
+/// Possible error that might occur while loading and reloading WebAssembly modules.
+#[derive(Debug)]
+pub enum WasmError {
+  /// The module failed to compile.
+  CompileError(wasmi::Error),
+  /// The file specified by the key failed to open or could not be read.
+  CannotReadFile(PathBuf, io::Error),
+  /// The input key doesn’t provide enough information to open a file.
+  NoKey,
+}
+
+impl fmt::Display for WasmError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      WasmError::CompileError(ref e) => write!(f, "WebAssembly compile error: {}", e),
+
+      WasmError::CannotReadFile(ref path, ref e) => {
+        write!(f, "cannot read file {}: {}", path.display(), e)
+      }
+
+      WasmError::NoKey => f.write_str("no path key available"),
+    }
+  }
+}
+
+impl Error for WasmError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      WasmError::CompileError(ref e) => Some(e),
+      WasmError::CannotReadFile(_, ref e) => Some(e),
+      WasmError::NoKey => None,
+    }
+  }
+}
+
+impl<C, K> Load<C, K> for WasmModule
+where K: Key + Into<Option<PathBuf>>,
+      WasmModule: for<'a> Inspect<'a, C, &'a Engine> {
+  type Error = WasmError;
+
+  fn load(key: K, storage: &mut Storage<C, K>, ctx: &mut C) -> Result<Loaded<Self, K>, Self::Error> {
+    if let Some(path) = key.into() {
+      let bytes =
+        storage.read_file(&path).map_err(|ioerr| WasmError::CannotReadFile(path, ioerr))?;
+
+      Module::new(Self::inspect(ctx), &bytes)
+        .map(|module| WasmModule { module }.into())
+        .map_err(WasmError::CompileError)
+    } else {
+      Err(WasmError::NoKey)
+    }
+  }
+}
@@ -1,9 +1,17 @@
+// most fixtures are only used by tests reloading resources
+#![cfg_attr(not(feature = "hot-reload"), allow(dead_code))]
+// fixtures keep their historical style
+#![allow(clippy::disallowed_names, clippy::unnecessary_to_owned)]
+
 use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tempfile::Builder;
-use warmy::{Inspect, Load, Loaded, Res, SimpleKey, Storage, Store};
+use warmy::{
+  AnyContext, Inspect, Key, Load, LoadShared, Loaded, Res, SimpleKey, Storage, Store, StoreError,
+  StoreErrorOr,
+};
 
 fn with_tmp_dir<F, B>(f: F)
 where F: Fn(&Path) -> B {
@@ -25,6 +33,21 @@ where F: Fn(Store<C, SimpleKey>) -> B {
 /// Timeout in milliseconds to wait before determining that there’s something wrong with notify.
 const QUEUE_TIMEOUT_MS: u64 = 5000; // 5s
 
+/// Call a closure – typically synchronizing a store and checking the outcome – until it returns
+/// `true`, failing if that takes longer than `QUEUE_TIMEOUT_MS`.
+fn wait_until<F>(mut done: F) where F: FnMut() -> bool {
+  let start_time = ::std::time::Instant::now();
+
+  while !done() {
+    if start_time.elapsed() >= ::std::time::Duration::from_millis(QUEUE_TIMEOUT_MS) {
+      panic!(
+        "more than {} milliseconds were spent waiting for a filesystem event",
+        QUEUE_TIMEOUT_MS
+      );
+    }
+  }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 struct Foo(String);
 
@@ -41,6 +64,8 @@ impl fmt::Display for TestErr {
   }
 }
 
+impl ::std::error::Error for TestErr {}
+
 impl<C> Load<C, SimpleKey> for Foo {
   type Error = TestErr;
 
@@ -49,7 +74,7 @@ impl<C> Load<C, SimpleKey> for Foo {
       let mut s = String::new();
 
       {
-        let path = key.as_path();
+        let path: &Path = key;
         eprintln!("KEY: {}", path.display());
         let mut fh = File::open(path).unwrap();
         let _ = fh.read_to_string(&mut s);
@@ -99,7 +124,7 @@ impl<C> Load<C, SimpleKey> for Zoo {
 
   fn load(key: SimpleKey, _: &mut Storage<C, SimpleKey>, _: &mut C) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
     if let SimpleKey::Logical(key) = key {
-      let content = key.as_str().to_owned();
+      let content = key.to_string();
       let zoo = Zoo(content);
 
       Ok(zoo.into())
@@ -121,7 +146,7 @@ impl<C> Load<C, SimpleKey> for LogicalFoo {
     ctx: &mut C,
   ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
     if let SimpleKey::Logical(key) = key {
-      let fs_key = Path::new(&key).into();
+      let fs_key = Path::new(&*key).into();
       let foo: Res<Foo> = storage.get(&fs_key, ctx).unwrap();
 
       let content = foo.borrow().0.clone();
@@ -140,6 +165,7 @@ fn create_store() {
   with_store(|_: Store<(), SimpleKey>| {})
 }
 
+#[cfg(feature = "hot-reload")]
 #[test]
 fn witness_sync() {
   with_store(|mut store| {
@@ -184,6 +210,7 @@ fn witness_sync() {
   })
 }
 
+#[cfg(feature = "hot-reload")]
 #[test]
 fn vfs_leading_slash() {
   with_store(|mut store| {
@@ -259,6 +286,7 @@ fn logical_resource() {
   })
 }
 
+#[cfg(feature = "hot-reload")]
 #[test]
 fn logical_with_deps() {
   with_store(|mut store| {
@@ -379,6 +407,7 @@ where Self: for<'a> Inspect<'a, C, &'a mut u32>,
   }
 }
 
+#[cfg(feature = "hot-reload")]
 #[test]
 fn foo_with_ctx() {
   with_store(|mut store| {
@@ -461,3 +490,3902 @@ fn load_two_ctx() {
     assert_eq!(ctx.pew_nb, 1);
   })
 }
+
+// A resource which reloading fails as long as the context counter is not zero.
+#[derive(Debug, Eq, PartialEq)]
+struct Flaky(String);
+
+impl Load<u32, SimpleKey> for Flaky {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    storage: &mut Storage<u32, SimpleKey>,
+    ctx: &mut u32,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    if *ctx > 0 {
+      *ctx -= 1;
+      return Err(TestErr::WrongKey(key));
+    }
+
+    let Loaded { res, deps } = <Foo as Load<_, _, ()>>::load(key, storage, ctx)?;
+    Ok(Loaded::with_deps(Flaky(res.0), deps))
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn retry_failed_reload() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_retry_policy(warmy::RetryPolicy::new(5, ::std::time::Duration::from_millis(0)));
+    let mut store: Store<u32, SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let mut ctx = 0;
+
+    let key = Path::new("flaky.txt").into();
+    let path = store.root().join("flaky.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"Hello, world!");
+    }
+
+    let r: Res<Flaky> = store.get(&key, &mut ctx).expect("initial load");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"Bye!");
+    }
+
+    // the next two reloads will fail; the retry policy must get us to the new value anyway
+    ctx = 2;
+
+    wait_until(|| {
+      store.sync(&mut ctx);
+      r.borrow().0.as_str() == "Bye!"
+    });
+
+    assert_eq!(ctx, 0);
+  })
+}
+
+// A resource that refuses to be reloaded with empty content; rejections are counted in the
+// context.
+#[derive(Debug, Eq, PartialEq)]
+struct NonEmpty(String);
+
+impl Load<u32, SimpleKey> for NonEmpty {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    storage: &mut Storage<u32, SimpleKey>,
+    ctx: &mut u32,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let Loaded { res, deps } = <Foo as Load<_, _, ()>>::load(key, storage, ctx)?;
+    Ok(Loaded::with_deps(NonEmpty(res.0), deps))
+  }
+
+  fn accept(&self, new: &Self, ctx: &mut u32) -> Result<(), Self::Error> {
+    if new.0.is_empty() {
+      *ctx += 1;
+      Err(TestErr::WrongKey(SimpleKey::from(new.0.as_str())))
+    } else {
+      Ok(())
+    }
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn reject_reloaded_value() {
+  with_store(|mut store| {
+    let mut rejections = 0;
+
+    let key = Path::new("non-empty.txt").into();
+    let path = store.root().join("non-empty.txt");
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"Hello, world!");
+    }
+
+    let r: Res<NonEmpty> = store.get(&key, &mut rejections).expect("initial load");
+
+    // truncate the file
+    File::create(&path).unwrap();
+
+    wait_until(|| {
+      store.sync(&mut rejections);
+      rejections != 0
+    });
+
+    assert_eq!(r.borrow().0.as_str(), "Hello, world!");
+  })
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+struct Events {
+  loaded: Vec<SimpleKey>,
+  reloaded: Vec<SimpleKey>,
+  overflows: Vec<usize>,
+  roots: Vec<&'static str>,
+}
+
+struct Recorder(::std::rc::Rc<::std::cell::RefCell<Events>>);
+
+impl warmy::StoreObserver<SimpleKey> for Recorder {
+  fn loaded(&mut self, key: &SimpleKey) {
+    self.0.borrow_mut().loaded.push(key.clone());
+  }
+
+  fn reloaded(&mut self, key: &SimpleKey) {
+    self.0.borrow_mut().reloaded.push(key.clone());
+  }
+
+  fn overflowed(&mut self, pending: usize) {
+    self.0.borrow_mut().overflows.push(pending);
+  }
+
+  fn root_lost(&mut self, _: &Path) {
+    self.0.borrow_mut().roots.push("lost");
+  }
+
+  fn root_restored(&mut self, _: &Path) {
+    self.0.borrow_mut().roots.push("restored");
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn observe_store() {
+  with_tmp_dir(|tmp_dir| {
+    let events = ::std::rc::Rc::new(::std::cell::RefCell::new(Events::default()));
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_observer(Recorder(events.clone()));
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    let key: SimpleKey = Path::new("foo.txt").into();
+    let path = store.root().join("foo.txt");
+    let prepared_key = SimpleKey::from(path.clone());
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"Hello, world!");
+    }
+
+    let _: Res<Foo> = store.get(&key, ctx).expect("initial load");
+    let _: Res<Foo> = store.get(&key, ctx).expect("cached load");
+    assert_eq!(events.borrow().loaded, vec![prepared_key.clone()]);
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"Bye!");
+    }
+
+    wait_until(|| {
+      store.sync(ctx);
+      !events.borrow().reloaded.is_empty()
+    });
+
+    assert_eq!(events.borrow().reloaded[0], prepared_key);
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn group_reload() {
+  with_store(|mut store| {
+    let mut rejections = 0;
+
+    let a_key = Path::new("a.txt").into();
+    let b_key = Path::new("b.txt").into();
+    let a_path = store.root().join("a.txt");
+    let b_path = store.root().join("b.txt");
+
+    for path in &[&a_path, &b_path] {
+      let mut fh = File::create(path).unwrap();
+      let _ = fh.write_all(b"old");
+    }
+
+    let a: Res<NonEmpty> = store.get(&a_key, &mut rejections).unwrap();
+    let b: Res<NonEmpty> = store.get(&b_key, &mut rejections).unwrap();
+    store.set_group(&a_key, "level");
+    store.set_group(&b_key, "level");
+    assert_eq!(store.group(&a_key), Some("level"));
+
+    // change a and break b: none of them should be replaced
+    {
+      let mut fh = File::create(&a_path).unwrap();
+      let _ = fh.write_all(b"new");
+    }
+    File::create(&b_path).unwrap();
+
+    // let both events reach the store so that they’re handled in the same synchronization
+    ::std::thread::sleep(::std::time::Duration::from_millis(500));
+
+    wait_until(|| {
+      store.sync(&mut rejections);
+      rejections != 0
+    });
+
+    assert_eq!(a.borrow().0.as_str(), "old");
+    assert_eq!(b.borrow().0.as_str(), "old");
+
+    // fix b: both should be replaced
+    {
+      let mut fh = File::create(&b_path).unwrap();
+      let _ = fh.write_all(b"new");
+    }
+
+    wait_until(|| {
+      store.sync(&mut rejections);
+      b.borrow().0.as_str() == "new"
+    });
+
+    assert_eq!(a.borrow().0.as_str(), "new");
+  })
+}
+
+// A logical resource counting how many times it was loaded in its context.
+struct Counted;
+
+impl Load<u32, SimpleKey> for Counted {
+  type Error = TestErr;
+
+  fn load(
+    _: SimpleKey,
+    _: &mut Storage<u32, SimpleKey>,
+    ctx: &mut u32,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    *ctx += 1;
+    Ok(Counted.into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn ttl_refresh() {
+  with_store(|mut store| {
+    let mut loads = 0;
+    let key = "db/query".into();
+
+    let _: Res<Counted> = store.get(&key, &mut loads).unwrap();
+    store.set_ttl(&key, ::std::time::Duration::from_millis(10));
+    assert_eq!(store.ttl(&key), Some(::std::time::Duration::from_millis(10)));
+
+    wait_until(|| {
+      store.sync(&mut loads);
+      loads >= 3
+    });
+  })
+}
+
+// Like Foo, but fails instead of panicking when the file cannot be read.
+#[derive(Debug, Default, Eq, PartialEq)]
+struct Text(String);
+
+impl<C> Load<C, SimpleKey> for Text {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    _: &mut Storage<C, SimpleKey>,
+    _: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let content = match key {
+      SimpleKey::Path(ref path) => ::std::fs::read_to_string(path).ok(),
+      _ => None,
+    };
+
+    content.map(|s| Text(s).into()).ok_or(TestErr::WrongKey(key))
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn proxy_replaced() {
+  with_store(|mut store| {
+    let ctx = &mut ();
+    let key = Path::new("proxied.txt").into();
+    let path = store.root().join("proxied.txt");
+
+    let r: Res<Text> = store
+      .get_proxied(&key, || Text("proxy".to_owned()), ctx)
+      .unwrap();
+    assert_eq!(r.borrow().0.as_str(), "proxy");
+    assert!(store.is_proxy(&key));
+
+    let error = store.last_error(&key).unwrap();
+    assert_eq!(error.error().to_string(), format!("wrong key: {}", path.display()));
+    assert_eq!(error.key(), &SimpleKey::from_path(&path));
+    assert!(error.type_name().ends_with("Text"));
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"real");
+    }
+
+    wait_until(|| {
+      store.sync(ctx);
+      !store.is_proxy(&key)
+    });
+
+    assert_eq!(r.borrow().0.as_str(), "real");
+    assert!(store.last_error(&key).is_none());
+  })
+}
+
+// A logical resource logging its key in the context every time it gets loaded.
+struct Logged;
+
+impl Load<Vec<String>, SimpleKey> for Logged {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    _: &mut Storage<Vec<String>, SimpleKey>,
+    ctx: &mut Vec<String>,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    ctx.push(key.to_string());
+    Ok(Logged.into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn reload_priorities() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_sync_budget(::std::time::Duration::from_millis(0));
+    let mut store: Store<Vec<String>, SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let mut log = Vec::new();
+
+    let low = "low".into();
+    let high = "high".into();
+    let _: Res<Logged> = store.get(&low, &mut log).unwrap();
+    let _: Res<Logged> = store.get(&high, &mut log).unwrap();
+    store.set_priority(&high, 10);
+    assert_eq!(store.priority(&high), 10);
+    assert_eq!(store.priority(&low), 0);
+
+    // force both resources to be reloaded on the next synchronization
+    store.set_ttl(&low, ::std::time::Duration::from_millis(0));
+    store.set_ttl(&high, ::std::time::Duration::from_millis(0));
+    log.clear();
+
+    // the budget only allows for a single reload per synchronization
+    store.sync(&mut log);
+    assert_eq!(log, vec!["high".to_owned()]);
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn frame_sync() {
+  with_store(|mut store: Store<Vec<String>, SimpleKey>| {
+    let mut log = Vec::new();
+    let mut frame_sync = warmy::FrameSync::new()
+      .set_interval(3)
+      .set_budget(::std::time::Duration::from_millis(0));
+
+    for key in &["a", "b"] {
+      let _: Res<Logged> = store.get(&(*key).into(), &mut log).unwrap();
+    }
+
+    store.invalidate_where(|_| true);
+    log.clear();
+
+    // the store is synchronized every three frames, reloading a single resource each time
+    let synced: Vec<bool> =
+      (0..6).map(|_| frame_sync.update(&mut store, &mut log).is_some()).collect();
+    assert_eq!(synced, vec![true, false, false, true, false, false]);
+    assert_eq!(frame_sync.frames(), 6);
+    assert_eq!(log.len(), 2);
+
+    // the budget of the frame synchronization doesn’t leak into the store
+    store.invalidate_where(|_| true);
+    log.clear();
+    store.sync(&mut log);
+    assert_eq!(log.len(), 2);
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn discovery_glob_filter() {
+  with_tmp_dir(|tmp_dir| {
+    let discovered = ::std::rc::Rc::new(::std::cell::RefCell::new(Vec::new()));
+    let discovered_ = discovered.clone();
+    let discovery = warmy::Discovery::for_glob("sub/*.txt", move |path, _, _| {
+      discovered_.borrow_mut().push(path.to_owned());
+    })
+    .unwrap();
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_discovery(discovery);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    ::std::fs::create_dir(store.root().join("sub")).unwrap();
+    // give the watcher some time to watch the new directory
+    ::std::thread::sleep(::std::time::Duration::from_millis(200));
+
+    let _ = File::create(store.root().join("ignored.txt")).unwrap();
+    let _ = File::create(store.root().join("sub/ignored.png")).unwrap();
+    let _ = File::create(store.root().join("sub/found.txt")).unwrap();
+
+    wait_until(|| {
+      store.sync(ctx);
+      !discovered.borrow().is_empty()
+    });
+
+    ::std::thread::sleep(::std::time::Duration::from_millis(200));
+    store.sync(ctx);
+
+    assert_eq!(*discovered.borrow(), vec![store.root().join("sub/found.txt")]);
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn discovery_removal() {
+  with_tmp_dir(|tmp_dir| {
+    let removed = ::std::rc::Rc::new(::std::cell::RefCell::new(Vec::new()));
+    let removed_ = removed.clone();
+    let discovery = warmy::Discovery::for_extensions(&["txt"], |_, _, _| {})
+      .set_on_remove(move |path, _, _| removed_.borrow_mut().push(path.to_owned()));
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_discovery(discovery);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    let path = store.root().join("gone.txt");
+    let _ = File::create(&path).unwrap();
+    ::std::thread::sleep(::std::time::Duration::from_millis(200));
+    store.sync(ctx);
+
+    ::std::fs::remove_file(&path).unwrap();
+
+    wait_until(|| {
+      store.sync(ctx);
+      !removed.borrow().is_empty()
+    });
+
+    assert_eq!(*removed.borrow(), vec![path]);
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn typed_discovery() {
+  with_tmp_dir(|tmp_dir| {
+    let events = ::std::rc::Rc::new(::std::cell::RefCell::new(Events::default()));
+    let discovery = warmy::TypedDiscovery::new().with::<Text, ()>("txt");
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_discovery(discovery.into())
+      .set_observer(Recorder(events.clone()));
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    let path = store.root().join("discovered.txt");
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"discovered");
+    }
+
+    wait_until(|| {
+      store.sync(ctx);
+      !events.borrow().loaded.is_empty()
+    });
+
+    assert_eq!(events.borrow().loaded, vec![SimpleKey::from(path)]);
+
+    let text: Res<Text> = store.get(&Path::new("discovered.txt").into(), ctx).unwrap();
+    assert_eq!(text.borrow().0.as_str(), "discovered");
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn scan_on_start() {
+  with_tmp_dir(|tmp_dir| {
+    for name in &["a.txt", "b.txt", "c.bin"] {
+      let mut fh = File::create(tmp_dir.join(name)).unwrap();
+      let _ = fh.write_all(name.as_bytes());
+    }
+
+    let events = ::std::rc::Rc::new(::std::cell::RefCell::new(Events::default()));
+    let discovery = warmy::TypedDiscovery::new().with::<Text, ()>("txt");
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_discovery(discovery.into())
+      .set_observer(Recorder(events.clone()))
+      .set_scan_on_start(true);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    store.sync(ctx);
+
+    let loaded = &events.borrow().loaded;
+    assert_eq!(loaded.len(), 2);
+    assert!(loaded.contains(&SimpleKey::from(store.root().join("a.txt"))));
+    assert!(loaded.contains(&SimpleKey::from(store.root().join("b.txt"))));
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn event_cap() {
+  with_tmp_dir(|tmp_dir| {
+    let names = ["a.txt", "b.txt", "c.txt"];
+
+    for name in &names {
+      File::create(tmp_dir.join(name)).unwrap().write_all(b"old").unwrap();
+    }
+
+    let events = ::std::rc::Rc::new(::std::cell::RefCell::new(Events::default()));
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_observer(Recorder(events.clone()))
+      .set_event_cap(1);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    for name in &names {
+      let _: Res<Text> = store.get(&Path::new(name).into(), ctx).unwrap();
+    }
+
+    for name in &names {
+      File::create(tmp_dir.join(name)).unwrap().write_all(b"new").unwrap();
+    }
+
+    // let all the events reach the store
+    ::std::thread::sleep(::std::time::Duration::from_millis(500));
+
+    // a single event is processed per synchronization; the others are carried over
+    let mut overflowed = false;
+    wait_until(|| {
+      let report = store.sync(ctx);
+      assert!(report.reloaded().len() <= 1);
+      overflowed |= report.overflowed();
+      events.borrow().reloaded.len() >= names.len()
+    });
+
+    assert!(overflowed);
+    assert!(events.borrow().overflows.iter().all(|&pending| pending > 1));
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn rescan_on_overflow() {
+  with_tmp_dir(|tmp_dir| {
+    for name in &["a.txt", "b.txt"] {
+      File::create(tmp_dir.join(name)).unwrap().write_all(b"old").unwrap();
+    }
+
+    let events = ::std::rc::Rc::new(::std::cell::RefCell::new(Events::default()));
+    let discovery = warmy::TypedDiscovery::new().with::<Text, ()>("txt");
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_discovery(discovery.into())
+      .set_observer(Recorder(events.clone()))
+      .set_event_cap(1)
+      .set_rescan_on_overflow(true);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    let a: Res<Text> = store.get(&Path::new("a.txt").into(), ctx).unwrap();
+    let b: Res<Text> = store.get(&Path::new("b.txt").into(), ctx).unwrap();
+
+    for name in &["a.txt", "b.txt", "c.txt"] {
+      File::create(tmp_dir.join(name)).unwrap().write_all(b"new").unwrap();
+    }
+
+    wait_until(|| {
+      let report = store.sync(ctx);
+
+      // the pending events are dropped in favor of a rescan handling everything at once
+      if report.overflowed() {
+        assert_eq!(report.reloaded().len(), 2);
+      }
+
+      report.overflowed()
+    });
+
+    assert_eq!(a.borrow().0, "new");
+    assert_eq!(b.borrow().0, "new");
+    assert!(events.borrow().loaded.contains(&SimpleKey::from(store.root().join("c.txt"))));
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn batched_discovery() {
+  with_tmp_dir(|tmp_dir| {
+    for name in &["a.txt", "b.txt", "c.txt"] {
+      let _ = File::create(tmp_dir.join(name)).unwrap();
+    }
+
+    let batches = ::std::rc::Rc::new(::std::cell::RefCell::new(Vec::new()));
+    let batches_ = batches.clone();
+    let discovery =
+      warmy::Discovery::batched(move |paths, _, _| batches_.borrow_mut().push(paths.len()))
+        .set_batch_size(2);
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_discovery(discovery)
+      .set_scan_on_start(true);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    store.sync(ctx);
+
+    assert_eq!(*batches.borrow(), vec![2, 1]);
+  })
+}
+
+#[test]
+fn resource_type() {
+  with_tmp_dir(|tmp_dir| {
+    let mut store: Store<(), SimpleKey> =
+      warmy::Store::new(warmy::StoreOpt::default().set_root(tmp_dir.to_owned()))
+        .expect("create store");
+    let ctx = &mut ();
+    let key = SimpleKey::from_path("typed.txt");
+
+    let _ = File::create(store.root().join("typed.txt")).unwrap();
+
+    assert_eq!(store.resource_type(&key), None);
+
+    let _: Res<Text> = store.get(&key, ctx).unwrap();
+
+    let ty = store.resource_type(&key).expect("resource type");
+    assert!(ty.is::<Text>());
+    assert_eq!(ty, warmy::ResourceType::of::<Text>());
+    assert!(store.is_loaded_as::<Text>(&key));
+    assert!(!store.is_loaded_as::<Counted>(&key));
+  })
+}
+
+#[test]
+fn logical_namespaces() {
+  let key = SimpleKey::from("shaders/post/blur");
+
+  assert_eq!(key.parent(), Some("shaders/post".into()));
+  assert_eq!(SimpleKey::from("shaders").parent(), None);
+  assert_eq!(SimpleKey::from("shaders").join("post").join("blur"), key);
+  assert!(key.starts_with(&"shaders".into()));
+  assert!(key.starts_with(&"shaders/post/blur".into()));
+  assert!(!key.starts_with(&"shad".into()));
+  assert!(!key.starts_with(&SimpleKey::from_path("/shaders")));
+  assert!(key.is_child_of(&"shaders/post".into()));
+  assert!(!key.is_child_of(&"shaders".into()));
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn invalidate_namespace() {
+  with_store(|mut store| {
+    let mut loads = 0;
+    let shaders = SimpleKey::from("shaders");
+
+    for key in &["shaders/blur", "shaders/bloom", "textures/hero"] {
+      let _: Res<Counted> = store.get(&(*key).into(), &mut loads).unwrap();
+    }
+
+    assert_eq!(loads, 3);
+    assert_eq!(store.invalidate_where(|key| key.starts_with(&shaders)), 2);
+    assert!(!store.invalidate(&"unknown".into()));
+
+    store.sync(&mut loads);
+    assert_eq!(loads, 5);
+
+    assert!(store.invalidate(&"textures/hero".into()));
+
+    store.sync(&mut loads);
+    assert_eq!(loads, 6);
+  })
+}
+
+#[test]
+fn uri_key_parse() {
+  let key: warmy::UriKey = "PAK://textures/hero.png".parse().unwrap();
+
+  assert_eq!(key.source(), "pak");
+  assert_eq!(key.path(), Path::new("textures/hero.png"));
+  assert_eq!(key.to_string(), "pak://textures/hero.png");
+  assert!(!key.is_file());
+  assert!(warmy::UriKey::from(Path::new("/a.txt")).is_file());
+  assert_eq!(
+    warmy::UriKey::parse("textures/hero.png"),
+    Err(warmy::UriKeyError::MissingScheme("textures/hero.png".to_owned()))
+  );
+  assert_eq!(
+    warmy::UriKey::parse("1pak://hero.png"),
+    Err(warmy::UriKeyError::InvalidScheme("1pak".to_owned()))
+  );
+}
+
+struct Sourced(String);
+
+impl<C> Load<C, warmy::UriKey> for Sourced {
+  type Error = ::std::io::Error;
+
+  fn load(
+    key: warmy::UriKey,
+    _: &mut Storage<C, warmy::UriKey>,
+    _: &mut C,
+  ) -> Result<Loaded<Self, warmy::UriKey>, Self::Error> {
+    match key.source() {
+      "file" => {
+        let content = ::std::fs::read_to_string(key.path())?;
+        Ok(Sourced(content).into())
+      }
+
+      "mem" => Ok(Sourced(key.path().display().to_string()).into()),
+      source => Err(::std::io::Error::other(format!("unknown source: {}", source))),
+    }
+  }
+}
+
+#[test]
+fn uri_key_sources() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned());
+    let mut store: Store<(), warmy::UriKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    {
+      let mut fh = File::create(store.root().join("a.txt")).unwrap();
+      let _ = fh.write_all(b"from file");
+    }
+
+    let file: Res<Sourced> = store.get(&"file:///a.txt".parse().unwrap(), ctx).unwrap();
+    let mem: Res<Sourced> = store.get(&"mem://from memory".parse().unwrap(), ctx).unwrap();
+
+    assert_eq!(file.borrow().0, "from file");
+    assert_eq!(mem.borrow().0, "from memory");
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn case_insensitive_keys() {
+  with_tmp_dir(|tmp_dir| {
+    let normalization = warmy::Normalization::default().set_case_insensitive(true);
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_normalization(normalization);
+    let mut store: Store<u32, SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let mut loads = 0;
+
+    let path = store.root().join("Hero.png");
+    let _ = File::create(&path).unwrap();
+
+    let _: Res<Counted> = store.get(&SimpleKey::from_path("/Hero.png"), &mut loads).unwrap();
+    let _: Res<Counted> = store.get(&SimpleKey::from_path("/hero.PNG"), &mut loads).unwrap();
+    assert_eq!(loads, 1);
+
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+
+    {
+      let mut fh = File::create(&path).unwrap();
+      let _ = fh.write_all(b"changed");
+    }
+
+    wait_until(|| {
+      store.sync(&mut loads);
+      loads == 2
+    });
+  })
+}
+
+#[test]
+fn unicode_normalization() {
+  let normalization = warmy::Normalization::default().set_unicode(true);
+  let composed = SimpleKey::from_path("/caf\u{e9}.txt");
+  let decomposed = SimpleKey::from_path("/cafe\u{301}.txt");
+
+  assert_ne!(composed, decomposed);
+  assert_eq!(decomposed.normalize(&normalization), composed);
+  assert_eq!(
+    SimpleKey::from("caf\u{e9}").normalize(&normalization),
+    SimpleKey::from("caf\u{e9}")
+  );
+}
+
+#[test]
+fn logical_normalization() {
+  let key = SimpleKey::from(" /UI//Button/ ");
+  assert_eq!(key.clone().normalize(&warmy::Normalization::default()), key);
+
+  let normalization = warmy::Normalization::default().set_logical_trim(true);
+  assert_eq!(key.clone().normalize(&normalization), SimpleKey::from("/UI//Button/"));
+
+  let normalization = normalization.set_logical_collapse_separators(true);
+  assert_eq!(key.clone().normalize(&normalization), SimpleKey::from("UI/Button"));
+
+  let normalization = normalization.set_logical_case_insensitive(true);
+  assert_eq!(key.clone().normalize(&normalization), SimpleKey::from("ui/button"));
+
+  // paths are left to the path normalization
+  let path = SimpleKey::from_path("/UI//Button.png");
+  assert_eq!(path.clone().normalize(&normalization), path);
+
+  // different spellings are the same resource
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_normalization(normalization.clone());
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+
+    let zoo: Res<Zoo> = store.get(&"ui//Button".into(), &mut ()).unwrap();
+    assert_eq!(zoo.borrow().0, "ui/button");
+    assert!(store.is_loaded_as::<Zoo>(&" UI/button/".into()));
+    assert_eq!(store.snapshot().resources.len(), 1);
+  });
+}
+
+#[test]
+fn shared_keys() {
+  use std::sync::Arc;
+
+  // cloning a key shares its content
+  let key = SimpleKey::from_path("/maps/a.txt");
+  match (&key, &key.clone()) {
+    (SimpleKey::Path(a), SimpleKey::Path(b)) => assert!(Arc::ptr_eq(a, b)),
+    _ => unreachable!(),
+  }
+
+  // so does normalizing a key with nothing to normalize
+  let key = SimpleKey::from("ui/button");
+  match (&key, &key.clone().normalize(&warmy::Normalization::default())) {
+    (SimpleKey::Logical(a), SimpleKey::Logical(b)) => assert!(Arc::ptr_eq(a, b)),
+    _ => unreachable!(),
+  }
+}
+
+#[test]
+fn bytes_keys() {
+  // a content hash, which is not valid UTF-8
+  let hash = [0xde, 0xad, 0xbe, 0xef];
+  let key = SimpleKey::from_bytes(hash);
+
+  assert_eq!(key, SimpleKey::from(&hash[..]));
+  assert_eq!(key.to_string(), "deadbeef");
+  assert_eq!(key.parent(), None);
+  assert!(key.starts_with(&SimpleKey::from_bytes([0xde])));
+  assert!(!key.starts_with(&SimpleKey::from("de")));
+  assert_eq!(key.join("lod"), SimpleKey::from(b"\xde\xad\xbe\xeflod".to_vec()));
+
+  // bytes keys are never normalized
+  let normalization = warmy::Normalization::default().set_logical_case_insensitive(true);
+  let upper = SimpleKey::from(b"ABC".to_vec());
+  assert_eq!(upper.clone().normalize(&normalization), upper);
+
+  with_store(|mut store: Store<u32, SimpleKey>| {
+    let mut loads = 0;
+
+    let _: Res<Counted> = store.get(&key, &mut loads).unwrap();
+    let _: Res<Counted> = store.get(&SimpleKey::from_bytes(hash), &mut loads).unwrap();
+    assert_eq!(loads, 1);
+    assert!(store.is_loaded_as::<Counted>(&key));
+    assert!(!store.is_loaded_as::<Counted>(&SimpleKey::from("deadbeef")));
+  })
+}
+
+#[test]
+fn verbatim_prefix() {
+  use warmy::key::strip_verbatim_prefix;
+
+  let path = PathBuf::from("/assets/hero.png");
+  assert_eq!(strip_verbatim_prefix(path.clone()), path);
+}
+
+#[cfg(windows)]
+#[test]
+fn windows_verbatim_prefix() {
+  use warmy::key::strip_verbatim_prefix;
+
+  assert_eq!(
+    strip_verbatim_prefix(PathBuf::from(r"\\?\C:\assets\hero.png")),
+    PathBuf::from(r"C:\assets\hero.png")
+  );
+  assert_eq!(
+    strip_verbatim_prefix(PathBuf::from(r"\\?\UNC\server\share\assets\hero.png")),
+    PathBuf::from(r"\\server\share\assets\hero.png")
+  );
+  assert_eq!(
+    strip_verbatim_prefix(PathBuf::from(r"C:\assets\hero.png")),
+    PathBuf::from(r"C:\assets\hero.png")
+  );
+  assert_eq!(
+    strip_verbatim_prefix(PathBuf::from(r"\\server\share\hero.png")),
+    PathBuf::from(r"\\server\share\hero.png")
+  );
+
+  // canonicalized roots are not verbatim, so that they match the paths of filesystem events
+  with_store(|mut store: Store<(), SimpleKey>| {
+    assert!(!store.root().to_string_lossy().starts_with(r"\\?\"));
+
+    let path = store.root().join("hero.txt");
+    ::std::fs::write(&path, "idle").unwrap();
+    let hero: Res<Text> = store.get(&SimpleKey::from_path("/hero.txt"), &mut ()).unwrap();
+    assert_eq!(hero.borrow().0, "idle");
+
+    #[cfg(feature = "hot-reload")]
+    {
+      ::std::thread::sleep(::std::time::Duration::from_millis(100));
+      ::std::fs::write(&path, "running").unwrap();
+
+      wait_until(|| {
+        store.sync(&mut ());
+        hero.borrow().0 == "running"
+      });
+    }
+  });
+}
+
+// A collection of all the maps living in the maps directory.
+struct Maps(Vec<String>);
+
+impl<C> Load<C, SimpleKey> for Maps {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    storage: &mut Storage<C, SimpleKey>,
+    _: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let paths = storage.glob(&key, "maps/*.txt").unwrap();
+    let maps = paths
+      .iter()
+      .map(|path| ::std::fs::read_to_string(storage.root().join(path.strip_prefix("/").unwrap())))
+      .collect::<Result<_, _>>()
+      .map_err(|_| TestErr::WrongKey(key))?;
+
+    Ok(Loaded::with_deps(Maps(maps), paths))
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn glob_collection() {
+  with_store(|mut store| {
+    let ctx = &mut ();
+    let maps_dir = store.root().join("maps");
+
+    ::std::fs::create_dir(&maps_dir).unwrap();
+    // give the watcher some time to watch the new directory
+    ::std::thread::sleep(::std::time::Duration::from_millis(200));
+    ::std::fs::write(maps_dir.join("a.txt"), "a").unwrap();
+    ::std::fs::write(maps_dir.join("ignored.png"), "ignored").unwrap();
+
+    let maps: Res<Maps> = store.get(&"maps".into(), ctx).unwrap();
+    assert_eq!(maps.borrow().0, vec!["a"]);
+
+    let wait_for = |store: &mut Store<(), SimpleKey>, expected: Vec<&str>| {
+      wait_until(|| {
+        store.sync(&mut ());
+        maps.borrow().0 == expected
+      });
+    };
+
+    ::std::fs::write(maps_dir.join("b.txt"), "b").unwrap();
+    wait_for(&mut store, vec!["a", "b"]);
+
+    ::std::fs::write(maps_dir.join("a.txt"), "a2").unwrap();
+    wait_for(&mut store, vec!["a2", "b"]);
+
+    ::std::fs::remove_file(maps_dir.join("b.txt")).unwrap();
+    wait_for(&mut store, vec!["a2"]);
+  })
+}
+
+// A text file localized according to the locales of the store.
+struct Localized(String);
+
+impl<C> Load<C, SimpleKey> for Localized {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    storage: &mut Storage<C, SimpleKey>,
+    _: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let path = match key {
+      SimpleKey::Path(ref path) => storage.localize(&key, path),
+      _ => return Err(TestErr::WrongKey(key)),
+    };
+    let content = ::std::fs::read_to_string(path).map_err(|_| TestErr::WrongKey(key))?;
+
+    Ok(Localized(content).into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn localized_resource() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_locales(vec!["fr", "en"]);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    ::std::fs::write(store.root().join("strings.txt"), "default").unwrap();
+    ::std::fs::write(store.root().join("strings.en.txt"), "hello").unwrap();
+
+    let strings: Res<Localized> = store.get(&Path::new("strings.txt").into(), ctx).unwrap();
+    assert_eq!(strings.borrow().0, "hello");
+
+    let wait_for = |store: &mut Store<(), SimpleKey>, expected: &str| {
+      wait_until(|| {
+        store.sync(&mut ());
+        strings.borrow().0 == expected
+      });
+    };
+
+    ::std::fs::write(store.root().join("strings.fr.txt"), "bonjour").unwrap();
+    wait_for(&mut store, "bonjour");
+
+    store.set_locales(vec!["de"]);
+    store.sync(ctx);
+    assert_eq!(strings.borrow().0, "default");
+  })
+}
+
+// A text file selected according to the variants of the store.
+struct Variant(String);
+
+impl<C> Load<C, SimpleKey> for Variant {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    storage: &mut Storage<C, SimpleKey>,
+    _: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let path = match key {
+      SimpleKey::Path(ref path) => storage.select_variant(&key, path),
+      _ => return Err(TestErr::WrongKey(key)),
+    };
+    let content = ::std::fs::read_to_string(path).map_err(|_| TestErr::WrongKey(key))?;
+
+    Ok(Variant(content).into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn variant_resource() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir.to_owned())
+      .set_variants(vec!["hi"]);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    ::std::fs::write(store.root().join("texture.png"), "default").unwrap();
+    ::std::fs::write(store.root().join("texture.hi.png"), "high").unwrap();
+    ::std::fs::write(store.root().join("texture.lo.png"), "low").unwrap();
+
+    let texture: Res<Variant> = store.get(&Path::new("texture.png").into(), ctx).unwrap();
+    assert_eq!(texture.borrow().0, "high");
+
+    store.set_variants(vec!["lo", "hi"]);
+    store.sync(ctx);
+    assert_eq!(texture.borrow().0, "low");
+
+    store.set_variants(Vec::<String>::new());
+    store.sync(ctx);
+    assert_eq!(texture.borrow().0, "default");
+  })
+}
+
+#[test]
+fn key_outside_root() {
+  with_store(|mut store| {
+    let ctx = &mut ();
+
+    let key = SimpleKey::from_path("/../../etc/passwd");
+    let result = store.get::<Foo>(&key, ctx);
+    let expected = StoreErrorOr::StoreError(StoreError::KeyOutsideRoot(key.clone()));
+    assert_eq!(result.err(), Some(expected));
+
+    let proxied = store.get_proxied::<Foo, _>(&key, || Foo(String::new()), ctx);
+    assert_eq!(proxied.err(), Some(StoreError::KeyOutsideRoot(key)));
+
+    // going up is fine as long as the key remains in the root
+    ::std::fs::write(store.root().join("foo.txt"), "foo").unwrap();
+    let foo = store.get::<Foo>(&SimpleKey::from_path("/sub/../foo.txt"), ctx).unwrap();
+    assert_eq!(foo.borrow().0, "foo");
+  })
+}
+
+// A resource counting its loads in the first element of a pair context.
+struct Paired;
+
+impl<C> Load<C, SimpleKey> for Paired
+where Self: for<'a> Inspect<'a, C, (&'a mut u32, &'a mut String)> {
+  type Error = TestErr;
+
+  fn load(
+    _: SimpleKey,
+    _: &mut Storage<C, SimpleKey>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let (counter, _) = Self::inspect(ctx);
+    *counter += 1;
+
+    Ok(Paired.into())
+  }
+}
+
+#[test]
+fn tuple_context() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned());
+    let mut store: Store<(u32, String), SimpleKey> = Store::new(opt).expect("create store");
+    let ctx = &mut (0, "library-provided".to_owned());
+
+    let _: Res<Paired> = store.get(&"paired".into(), ctx).unwrap();
+    assert_eq!(ctx.0, 1);
+
+    let (counter, name): (&u32, &String) = <Paired as Inspect<_, _>>::inspect(ctx);
+    assert_eq!((*counter, name.as_str()), (1, "library-provided"));
+  })
+}
+
+// A resource requiring a renderer service to load.
+struct Texture;
+
+struct Renderer {
+  uploads: u32,
+}
+
+impl<C> Load<C, SimpleKey> for Texture
+where Self: for<'a> Inspect<'a, C, Option<&'a mut Renderer>> {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    _: &mut Storage<C, SimpleKey>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let renderer = Self::inspect(ctx).ok_or(TestErr::WrongKey(key))?;
+    renderer.uploads += 1;
+
+    Ok(Texture.into())
+  }
+}
+
+#[test]
+fn any_context() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned());
+    let mut store: Store<AnyContext, SimpleKey> = Store::new(opt).expect("create store");
+    let ctx = &mut AnyContext::new();
+
+    let key = SimpleKey::from("texture");
+    assert!(store.get::<Texture>(&key, ctx).is_err());
+
+    ctx.insert(Renderer { uploads: 0 });
+    assert!(store.get::<Texture>(&key, ctx).is_ok());
+    assert_eq!(ctx.get::<Renderer>().map(|renderer| renderer.uploads), Some(1));
+
+    assert!(ctx.contains::<Renderer>());
+    assert_eq!(ctx.remove::<Renderer>().map(|renderer| renderer.uploads), Some(1));
+    assert!(!ctx.contains::<Renderer>());
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn owned_context() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned());
+    let mut store = Store::with_context(opt, Ctx::new()).expect("create store");
+    let path = store.root().join("foo.txt");
+
+    ::std::fs::write(&path, "Hello, world!").unwrap();
+
+    let key = Path::new("foo.txt").into();
+    let r: Res<FooWithCtx> = store.get_owned(&key).unwrap();
+    assert_eq!(r.borrow().0, "Hello, world!");
+    assert_eq!(store.context().map(|ctx| ctx.foo_nb), Some(1));
+
+    ::std::fs::write(&path, "Bye!").unwrap();
+
+    wait_until(|| {
+      store.sync_owned().unwrap();
+      r.borrow().0 == "Bye!"
+    });
+
+    assert_eq!(store.context_mut().map(|ctx| ctx.foo_nb), Some(2));
+    drop(store);
+
+    // stores created without context report it instead of panicking
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<Ctx, SimpleKey> = Store::new(opt).expect("create store");
+
+    match store.get_owned::<FooWithCtx>(&key) {
+      Err(StoreErrorOr::StoreError(StoreError::NoOwnedContext)) => (),
+      Err(e) => panic!("expected a missing context, got {:?}", e),
+      Ok(_) => panic!("expected a missing context"),
+    }
+
+    assert!(matches!(store.sync_owned(), Err(StoreError::NoOwnedContext)));
+  })
+}
+
+// A resource only reading its context, which is the prefix of its content.
+#[derive(Debug)]
+struct Prefixed(String);
+
+impl LoadShared<String, SimpleKey> for Prefixed {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    _: &mut Storage<String, SimpleKey>,
+    ctx: &String,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let path = match key {
+      SimpleKey::Path(ref path) => path,
+      _ => return Err(TestErr::WrongKey(key)),
+    };
+    let content = ::std::fs::read_to_string(path).map_err(|_| TestErr::WrongKey(key.clone()))?;
+
+    Ok(Prefixed(format!("{}{}", ctx, content)).into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn shared_context() {
+  with_store(|mut store: Store<String, SimpleKey>| {
+    let mut ctx = "> ".to_owned();
+    let path = store.root().join("prefixed.txt");
+
+    ::std::fs::write(&path, "hello").unwrap();
+
+    // the context can be borrowed elsewhere while loading
+    let prefix = &ctx;
+    let key = Path::new("prefixed.txt").into();
+    let prefixed: Res<Prefixed> = store.get_shared(&key, &ctx).unwrap();
+    assert_eq!(prefixed.borrow().0, "> hello");
+    assert_eq!(prefix, "> ");
+
+    // reloading goes through the regular synchronization
+    ctx = ">> ".to_owned();
+    ::std::fs::write(&path, "world").unwrap();
+
+    wait_until(|| {
+      store.sync(&mut ctx);
+      prefixed.borrow().0 == ">> world"
+    });
+  })
+}
+
+// Methods loading a Counter, each one counting in its own part of the context.
+struct ByFoo;
+struct ByPew;
+
+struct Counter;
+
+impl<'a> Inspect<'a, Ctx, &'a mut u32, ByFoo> for Counter {
+  fn inspect(ctx: &mut Ctx) -> &mut u32 {
+    &mut ctx.foo_nb
+  }
+}
+
+impl<'a> Inspect<'a, Ctx, &'a mut u32, ByPew> for Counter {
+  fn inspect(ctx: &mut Ctx) -> &mut u32 {
+    &mut ctx.pew_nb
+  }
+}
+
+impl<C> Load<C, SimpleKey, ByFoo> for Counter
+where Self: for<'a> Inspect<'a, C, &'a mut u32, ByFoo> {
+  type Error = TestErr;
+
+  fn load(
+    _: SimpleKey,
+    _: &mut Storage<C, SimpleKey>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    *<Self as Inspect<_, _, ByFoo>>::inspect(ctx) += 1;
+    Ok(Counter.into())
+  }
+}
+
+impl<C> Load<C, SimpleKey, ByPew> for Counter
+where Self: for<'a> Inspect<'a, C, &'a mut u32, ByPew> {
+  type Error = TestErr;
+
+  fn load(
+    _: SimpleKey,
+    _: &mut Storage<C, SimpleKey>,
+    ctx: &mut C,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    *<Self as Inspect<_, _, ByPew>>::inspect(ctx) += 1;
+    Ok(Counter.into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn inspect_by_method() {
+  with_store(|mut store| {
+    let mut ctx = Ctx::new();
+
+    let _: Res<Counter> = store.get_by(&"counter-0".into(), &mut ctx, ByFoo).unwrap();
+    let _: Res<Counter> = store.get_by(&"counter-1".into(), &mut ctx, ByPew).unwrap();
+    let _: Res<Counter> = store.get_by(&"counter-2".into(), &mut ctx, ByPew).unwrap();
+
+    assert_eq!((ctx.foo_nb, ctx.pew_nb), (1, 2));
+
+    assert!(store.invalidate(&"counter-0".into()));
+    assert!(store.invalidate(&"counter-1".into()));
+    store.sync(&mut ctx);
+
+    // reloading goes through the same methods
+    assert_eq!((ctx.foo_nb, ctx.pew_nb), (2, 3));
+  })
+}
+
+#[test]
+fn std_errors() {
+  let error: Box<dyn ::std::error::Error> =
+    Box::new(StoreError::KeyOutsideRoot(SimpleKey::from_path("/../passwd")));
+  assert_eq!(error.to_string(), "key outside of the root: /../passwd");
+  assert!(error.source().is_none());
+
+  let error: Box<dyn ::std::error::Error> = Box::new("pak".parse::<warmy::UriKey>().unwrap_err());
+  assert_eq!(error.to_string(), "missing scheme in URI pak");
+}
+
+// A logical resource which loading succeeds only if the context allows it.
+struct Allowed;
+
+impl Load<bool, SimpleKey> for Allowed {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    _: &mut Storage<bool, SimpleKey>,
+    allowed: &mut bool,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    if *allowed {
+      Ok(Allowed.into())
+    } else {
+      Err(TestErr::WrongKey(key))
+    }
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn sync_report() {
+  with_store(|mut store: Store<bool, SimpleKey>| {
+    let mut allowed = true;
+    let key = SimpleKey::from("allowed");
+
+    let _: Res<Allowed> = store.get(&key, &mut allowed).unwrap();
+
+    allowed = false;
+    store.invalidate(&key);
+    let report = store.sync(&mut allowed);
+
+    assert!(!report.is_ok());
+    assert!(report.reloaded().is_empty());
+    assert_eq!(report.failed().len(), 1);
+
+    let error = &report.failed()[0];
+    assert_eq!(error.key(), &key);
+    assert!(error.type_name().ends_with("Allowed"));
+    assert_eq!(error.method_name(), "()");
+    assert_eq!(error.downcast_ref::<TestErr>(), Some(&TestErr::WrongKey(key.clone())));
+    assert!(error.to_string().ends_with("with method (): wrong key: allowed"));
+
+    allowed = true;
+    store.invalidate(&key);
+    let report = store.sync(&mut allowed);
+
+    assert!(report.is_ok());
+    assert_eq!(report.reloaded(), &[key]);
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn debug_dump() {
+  with_store(|mut store: Store<bool, SimpleKey>| {
+    let mut allowed = true;
+    let key = SimpleKey::from("allowed");
+
+    let _: Res<Allowed> = store.get(&key, &mut allowed).unwrap();
+    store.invalidate(&key);
+
+    let dump = store.debug_dump();
+    assert!(dump.contains("Logical(\"allowed\")"));
+    assert!(dump.contains("Allowed [dirty]"));
+
+    allowed = false;
+    store.sync(&mut allowed);
+
+    let dump = store.debug_dump();
+    assert!(!dump.contains("[dirty]"));
+    assert!(dump.contains("last error: wrong key: allowed"));
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn store_snapshot() {
+  with_tmp_dir(|tmp_dir| {
+    // write the file before watching the root so that no event gets in the way
+    ::std::fs::write(tmp_dir.join("foo.txt"), "foo").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned());
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let ctx = &mut ();
+    let path = store.root().join("foo.txt");
+
+    let _: Res<LogicalFoo> = store.get(&"foo.txt".into(), ctx).unwrap();
+    assert!(store.invalidate(&"foo.txt".into()));
+    store.sync(ctx);
+
+    let snapshot = store.snapshot();
+    assert_eq!(snapshot.resources.len(), 2);
+    assert_eq!(snapshot.edges, vec![("foo.txt".into(), SimpleKey::from_path(&path))]);
+
+    let logical = snapshot.resources.iter().find(|info| info.key == "foo.txt".into()).unwrap();
+    assert!(logical.type_name.ends_with("LogicalFoo"));
+    assert_eq!((logical.reloads, logical.dependencies, logical.dependents), (1, 1, 0));
+    assert!(logical.last_reload.is_some());
+    assert!(logical.last_error.is_none());
+    assert!(!logical.dirty);
+
+    let foo = snapshot.resources.iter().find(|info| info.key != "foo.txt".into()).unwrap();
+    assert_eq!((foo.reloads, foo.dependencies, foo.dependents), (0, 0, 1));
+    assert!(foo.last_reload.is_none());
+  })
+}
+
+// A logical resource which loading panics if the context is zero.
+struct Generation(u32);
+
+impl Load<u32, SimpleKey> for Generation {
+  type Error = TestErr;
+
+  fn load(
+    _: SimpleKey,
+    _: &mut Storage<u32, SimpleKey>,
+    generation: &mut u32,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    assert_ne!(*generation, 0, "generation zero");
+    Ok(Generation(*generation).into())
+  }
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn catch_panics() {
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned()).set_catch_panics(true);
+    let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+    let key = SimpleKey::from("generation");
+    let mut generation = 1;
+
+    let res: Res<Generation> = store.get(&key, &mut generation).unwrap();
+
+    generation = 0;
+    store.invalidate(&key);
+    let report = store.sync(&mut generation);
+
+    assert_eq!(report.failed().len(), 1);
+    let panic = report.failed()[0].downcast_ref::<warmy::ReloadPanic>().unwrap();
+    assert!(panic.message().contains("generation zero"));
+    assert_eq!(res.borrow().0, 1);
+
+    generation = 2;
+    store.invalidate(&key);
+    assert!(store.sync(&mut generation).is_ok());
+    assert_eq!(res.borrow().0, 2);
+  })
+}
+
+#[cfg(feature = "debug-server")]
+fn http_request(addr: std::net::SocketAddr, method: &str, target: &str) -> String {
+  let mut stream = std::net::TcpStream::connect(addr).expect("connect to the debug server");
+  write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, target).unwrap();
+
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  response
+}
+
+#[cfg(feature = "debug-server")]
+#[test]
+fn debug_server() {
+  with_tmp_dir(|tmp_dir| {
+    // write the file before watching the root so that no event gets in the way
+    ::std::fs::write(tmp_dir.join("foo.txt"), "foo").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir.to_owned());
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let ctx = &mut ();
+
+    let _: Res<LogicalFoo> = store.get(&"foo.txt".into(), ctx).unwrap();
+    let addr = store.serve_debug("127.0.0.1:0").expect("start the debug server");
+
+    // a client that never sends its request must not block the others
+    let _silent = std::net::TcpStream::connect(addr).expect("connect to the debug server");
+
+    let resources = http_request(addr, "GET", "/resources");
+    assert!(resources.starts_with("HTTP/1.1 200 OK"));
+    assert!(resources.contains("LogicalFoo"));
+    assert!(resources.contains("\"reloads\":0"));
+
+    let errors = http_request(addr, "GET", "/errors");
+    assert!(errors.ends_with("\r\n\r\n[]"));
+
+    let key = format!("{:?}", SimpleKey::from("foo.txt")).replace('"', "%22");
+    let reload = http_request(addr, "POST", &format!("/reload?key={}", key));
+    assert!(reload.starts_with("HTTP/1.1 202 Accepted"));
+
+    assert!(http_request(addr, "GET", "/nope").starts_with("HTTP/1.1 404 Not Found"));
+
+    store.sync(ctx);
+
+    let logical = store.snapshot().resources.into_iter().find(|info| info.key == "foo.txt".into());
+    assert_eq!(logical.unwrap().reloads, 1);
+    assert!(http_request(addr, "GET", "/resources").contains("\"reloads\":1"));
+  })
+}
+
+#[cfg(all(feature = "json", feature = "rayon"))]
+#[test]
+fn get_many_par() {
+  use warmy::json::{Json, JsonError};
+
+  with_tmp_dir(|tmp_dir| {
+    // write the files before watching the root so that no event gets in the way
+    for i in 0..8 {
+      ::std::fs::write(tmp_dir.join(format!("{}.json", i)), format!("{}", i)).unwrap();
+    }
+
+    ::std::fs::write(tmp_dir.join("broken.json"), "{").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+
+    // one resource is already cached
+    let cached: Res<u32> = store.get_by(&Path::new("/3.json").into(), &mut (), Json).unwrap();
+
+    let mut keys: Vec<SimpleKey> =
+      (0..8).map(|i| Path::new(&format!("/{}.json", i)).into()).collect();
+    keys.push(Path::new("/broken.json").into());
+    keys.push(Path::new("/0.json").into());
+
+    let results: Vec<Result<Res<u32>, _>> = store.get_many_par_by(&keys, &(), Json);
+    assert_eq!(results.len(), keys.len());
+
+    for (i, result) in results[..8].iter().enumerate() {
+      assert_eq!(*result.as_ref().unwrap().borrow(), i as u32);
+    }
+
+    match results[8] {
+      Err(StoreErrorOr::ResError(JsonError::JsonError(_))) => (),
+      _ => panic!("broken.json should have failed to load"),
+    }
+
+    // resources are shared with the cache
+    *cached.borrow_mut() = 33;
+    assert_eq!(*results[3].as_ref().unwrap().borrow(), 33);
+
+    *results[0].as_ref().unwrap().borrow_mut() = 42;
+    assert_eq!(*results[9].as_ref().unwrap().borrow(), 42);
+  })
+}
+
+#[cfg(not(feature = "hot-reload"))]
+#[test]
+fn static_store() {
+  with_tmp_dir(|tmp_dir| {
+    let path = tmp_dir.join("foo.txt");
+    File::create(&path).unwrap().write_all(b"Hello, world!").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let key = Path::new("/foo.txt").into();
+
+    let r: Res<Foo> = store.get(&key, &mut ()).unwrap();
+    File::create(&path).unwrap().write_all(b"Bye!").unwrap();
+
+    // resources are cached but never reloaded
+    assert!(store.invalidate(&key));
+    let report = store.sync(&mut ());
+    assert!(report.is_ok() && report.reloaded().is_empty());
+    assert_eq!(r.borrow().0, "Hello, world!");
+
+    let again: Res<Foo> = store.get(&key, &mut ()).unwrap();
+    assert_eq!(again.borrow().0, "Hello, world!");
+    assert!(!store.snapshot().resources[0].dirty);
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "specs-impl"))]
+#[test]
+fn specs_handles() {
+  use specs::{RunNow, System, World, WorldExt};
+  use warmy::{Handles, ReadHandles};
+
+  struct Observe(Option<u32>);
+
+  impl<'a> System<'a> for Observe {
+    type SystemData = ReadHandles<'a, SimpleKey>;
+
+    fn run(&mut self, handles: Self::SystemData) {
+      let res = handles.get::<Generation>(&"generation".into());
+      self.0 = res.map(|res| res.borrow().0);
+    }
+  }
+
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+    let key = SimpleKey::from("generation");
+    let mut generation = 1;
+
+    let mut world = World::new();
+    let mut handles = Handles::new();
+    handles.fetch::<Generation, _>(&mut store, &key, &mut generation).unwrap();
+    world.insert(handles);
+
+    let mut observe = Observe(None);
+    observe.run_now(&world);
+    assert_eq!(observe.0, Some(1));
+
+    // reloads are visible from the world without touching it
+    generation = 2;
+    assert!(store.invalidate(&key));
+    store.sync(&mut generation);
+
+    observe.run_now(&world);
+    assert_eq!(observe.0, Some(2));
+  })
+}
+
+#[cfg(all(feature = "arc", feature = "hot-reload"))]
+#[test]
+fn subscribe() {
+  use warmy::StoreEvent;
+
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir).set_catch_panics(true);
+    let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+    let key = SimpleKey::from("generation");
+    let mut generation = 1;
+
+    let rx = store.subscribe();
+    let dropped = store.subscribe();
+    drop(dropped);
+
+    // events are received on another thread
+    let listener = ::std::thread::spawn(move || rx.iter().take(3).collect::<Vec<_>>());
+
+    let _: Res<Generation> = store.get(&key, &mut generation).unwrap();
+
+    generation = 2;
+    store.invalidate(&key);
+    store.sync(&mut generation);
+
+    generation = 0;
+    store.invalidate(&key);
+    store.sync(&mut generation);
+
+    let events = listener.join().unwrap();
+    assert_eq!(events.len(), 3);
+
+    match (&events[0], &events[1], &events[2]) {
+      (StoreEvent::Loaded(a), StoreEvent::Reloaded(b), StoreEvent::ReloadFailed(e)) => {
+        assert_eq!(a, &key);
+        assert_eq!(b, &key);
+        assert_eq!(e.key(), &key);
+      }
+      _ => panic!("unexpected events: {:?}", events),
+    }
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "lua-impl"))]
+#[test]
+fn lua_script() {
+  use warmy::lua::LuaScript;
+
+  with_tmp_dir(|tmp_dir| {
+    ::std::fs::create_dir(tmp_dir.join("lib")).unwrap();
+    ::std::fs::write(tmp_dir.join("lib/shared.lua"), "return require('lib.math')").unwrap();
+    ::std::fs::write(tmp_dir.join("lib/math.lua"), "return { answer = 42 }").unwrap();
+    ::std::fs::write(
+      tmp_dir.join("main.lua"),
+      "-- require 'commented'\nlocal shared = require \"lib.shared\"\nreturn shared.answer",
+    )
+    .unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let script: Res<LuaScript> = store.get(&Path::new("main.lua").into(), &mut ()).unwrap();
+
+    let mut requires = script.borrow().requires().to_vec();
+    requires.sort();
+    assert_eq!(requires, vec!["lib.math", "lib.shared"]);
+
+    let run = |script: &Res<LuaScript>| {
+      let lua = mlua::Lua::new();
+      let root = tmp_dir.display();
+      lua.load(format!("package.path = '{}/?.lua'", root)).exec().unwrap();
+      script.borrow().chunk(&lua).eval::<i32>().unwrap()
+    };
+    assert_eq!(run(&script), 42);
+
+    // editing a module required indirectly reloads the script
+    ::std::fs::write(tmp_dir.join("lib/math.lua"), "return { answer = 43 }").unwrap();
+
+    wait_until(|| !store.sync(&mut ()).reloaded().is_empty());
+
+    assert_eq!(run(&script), 43);
+
+    // syntax errors make the reload fail
+    ::std::fs::write(tmp_dir.join("main.lua"), "return (").unwrap();
+
+    wait_until(|| !store.sync(&mut ()).failed().is_empty());
+
+    assert_eq!(run(&script), 43);
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "wasmi-impl"))]
+#[test]
+fn wasm_module() {
+  use warmy::wasm::WasmModule;
+
+  // a module exporting an `answer` function returning n; n must fit in a single byte of LEB128
+  let answer = |n: u8| {
+    let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+    wasm.extend_from_slice(b"\x01\x05\x01\x60\0\x01\x7f"); // type () -> i32
+    wasm.extend_from_slice(b"\x03\x02\x01\0"); // function of type 0
+    wasm.extend_from_slice(b"\x07\x0a\x01\x06answer\0\0"); // export of function 0
+    wasm.extend_from_slice(&[0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, n, 0x0b]); // i32.const n
+    wasm
+  };
+
+  with_tmp_dir(|tmp_dir| {
+    ::std::fs::write(tmp_dir.join("logic.wasm"), answer(42)).unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<wasmi::Engine, SimpleKey> = Store::new(opt).expect("create store");
+    let mut engine = wasmi::Engine::default();
+    let key = Path::new("logic.wasm").into();
+    let module: Res<WasmModule> = store.get(&key, &mut engine).unwrap();
+
+    let run = |engine: &wasmi::Engine, module: &Res<WasmModule>| {
+      let mut store = wasmi::Store::new(engine, ());
+      let linker = wasmi::Linker::new(engine);
+      let instance = linker
+        .instantiate(&mut store, module.borrow().module())
+        .and_then(|instance| instance.start(&mut store))
+        .unwrap();
+      let answer = instance.get_typed_func::<(), i32>(&store, "answer").unwrap();
+      answer.call(&mut store, ()).unwrap()
+    };
+    assert_eq!(run(&engine, &module), 42);
+
+    ::std::fs::write(tmp_dir.join("logic.wasm"), answer(43)).unwrap();
+
+    wait_until(|| !store.sync(&mut engine).reloaded().is_empty());
+
+    assert_eq!(run(&engine, &module), 43);
+
+    // modules that don’t compile keep the current one
+    ::std::fs::write(tmp_dir.join("logic.wasm"), b"\0asm garbage").unwrap();
+
+    wait_until(|| !store.sync(&mut engine).failed().is_empty());
+
+    assert_eq!(run(&engine, &module), 43);
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "dylib-impl", target_os = "linux"))]
+#[test]
+fn dylib() {
+  use warmy::dylib::{DyLib, DyLibError};
+
+  // any library will do; the math library is available on all glibc systems
+  let candidates = [
+    "/lib/x86_64-linux-gnu/libm.so.6",
+    "/usr/lib/x86_64-linux-gnu/libm.so.6",
+    "/lib64/libm.so.6",
+    "/usr/lib64/libm.so.6",
+    "/usr/lib/libm.so.6",
+  ];
+  let libm = candidates.iter().map(Path::new).find(|path| path.is_file()).expect("no libm found");
+
+  with_tmp_dir(|tmp_dir| {
+    ::std::fs::copy(libm, tmp_dir.join("libm.so")).unwrap();
+
+    // libraries are refused unless the store trusts them
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    match store.get::<DyLib>(&Path::new("libm.so").into(), &mut ()) {
+      Err(StoreErrorOr::ResError(DyLibError::Untrusted)) => (),
+      Err(e) => panic!("expected an untrusted library, got {:?}", e),
+      Ok(_) => panic!("expected an untrusted library"),
+    }
+    drop(store);
+
+    let opt = unsafe { warmy::StoreOpt::default().set_root(tmp_dir).set_trust_dylibs(true) };
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let lib: Res<DyLib> = store.get(&Path::new("libm.so").into(), &mut ()).unwrap();
+
+    let cos = |lib: &Res<DyLib>| unsafe {
+      let lib = lib.borrow();
+      let cos = lib.get::<unsafe extern "C" fn(f64) -> f64>(b"cos").unwrap();
+      cos(0.)
+    };
+    assert_eq!(cos(&lib), 1.);
+
+    let first_copy = lib.borrow().copy_path().to_owned();
+    assert!(first_copy.is_file());
+
+    // rebuilding the library overwrites it
+    ::std::fs::copy(libm, tmp_dir.join("libm.so")).unwrap();
+
+    wait_until(|| !store.sync(&mut ()).reloaded().is_empty());
+
+    assert_eq!(cos(&lib), 1.);
+    assert!(!first_copy.exists());
+
+    let last_copy = lib.borrow().copy_path().to_owned();
+    drop(lib);
+    drop(store);
+    assert!(!last_copy.exists());
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "naga-impl"))]
+#[test]
+fn wgsl_shader() {
+  use warmy::shader::{ShaderError, WgslShader};
+
+  const SHADER: &str =
+    "@fragment\nfn main() -> @location(0) vec4<f32> {\n  return vec4<f32>(1.0);\n}\n";
+
+  with_tmp_dir(|tmp_dir| {
+    ::std::fs::write(tmp_dir.join("color.wgsl"), SHADER).unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let key = Path::new("color.wgsl").into();
+    let shader: Res<WgslShader> = store.get(&key, &mut ()).unwrap();
+    assert_eq!(shader.borrow().module().entry_points.len(), 1);
+
+    // returning a scalar instead of a vector is caught while reloading
+    ::std::fs::write(tmp_dir.join("color.wgsl"), SHADER.replace("vec4<f32>(1.0)", "1.0")).unwrap();
+
+    let mut report = None;
+    wait_until(|| {
+      report = Some(store.sync(&mut ())).filter(|report| !report.failed().is_empty());
+      report.is_some()
+    });
+    let report = report.unwrap();
+
+    // the diagnostic points at the faulty line
+    match report.failed()[0].downcast_ref::<ShaderError>() {
+      Some(ShaderError::ParseError(diagnostic)) => assert!(diagnostic.contains("color.wgsl:3")),
+      e => panic!("unexpected error: {:?}", e),
+    }
+
+    assert_eq!(shader.borrow().source(), SHADER);
+  })
+}
+
+// A text file turned uppercase – an expensive transform, as far as the derived cache is concerned;
+// the context counts the transforms that actually ran.
+struct Shouting(String);
+
+impl Load<u32, SimpleKey> for Shouting {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    storage: &mut Storage<u32, SimpleKey>,
+    transforms: &mut u32,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let path: Option<PathBuf> = key.clone().into();
+    let path = path.ok_or_else(|| TestErr::WrongKey(key.clone()))?;
+    let source = ::std::fs::read(path).map_err(|_| TestErr::WrongKey(key.clone()))?;
+    let output = storage.derive(&key, "uppercase-v1", &source, |source| {
+      *transforms += 1;
+      Ok::<_, TestErr>(source.to_ascii_uppercase())
+    })?;
+
+    Ok(Shouting(String::from_utf8(output).unwrap()).into())
+  }
+}
+
+#[test]
+fn derived_cache() {
+  let cache_dir = Builder::new().prefix("warmy-derived").tempdir().unwrap();
+
+  with_tmp_dir(|tmp_dir| {
+    let key = Path::new("/hello.txt").into();
+    let mut transforms = 0;
+    ::std::fs::write(tmp_dir.join("hello.txt"), "hello").unwrap();
+
+    // outputs are reused across stores – and thus runs
+    for _ in 0..2 {
+      let opt = warmy::StoreOpt::default().set_root(tmp_dir).set_derived_cache(cache_dir.path());
+      let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+      let shouting: Res<Shouting> = store.get(&key, &mut transforms).unwrap();
+
+      assert_eq!(shouting.borrow().0, "HELLO");
+      assert_eq!(transforms, 1);
+    }
+
+    // changing the source invalidates the output
+    ::std::fs::write(tmp_dir.join("hello.txt"), "bye").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir).set_derived_cache(cache_dir.path());
+    let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+    let shouting: Res<Shouting> = store.get(&key, &mut transforms).unwrap();
+
+    assert_eq!(shouting.borrow().0, "BYE");
+    assert_eq!(transforms, 2);
+    assert_eq!(::std::fs::read_dir(cache_dir.path()).unwrap().count(), 1);
+
+    // outputs are named after the path of the key and the transform, whatever the key type hashes
+    let fnv = |bytes: &[u8]| {
+      let step = |hash: u64, &byte: &u8| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+      bytes.iter().fold(0xcbf2_9ce4_8422_2325, step)
+    };
+    let path = store.root().join("hello.txt");
+    let entry = fnv(format!("path:{}\0uppercase-v1", path.display()).as_bytes());
+    let output = ::std::fs::read_dir(cache_dir.path()).unwrap().next().unwrap().unwrap();
+    assert!(output.file_name().to_str().unwrap().starts_with(&format!("{:016x}.", entry)));
+  })
+}
+
+#[test]
+fn registry() {
+  use warmy::registry::{Registered, RegistryError};
+  use warmy::LoaderRegistry;
+
+  #[derive(Debug)]
+  struct Words(Vec<String>);
+
+  with_tmp_dir(|tmp_dir| {
+    ::std::fs::write(tmp_dir.join("list.txt"), "a b c").unwrap();
+    ::std::fs::write(tmp_dir.join("list.csv"), "d,e").unwrap();
+
+    let mut registry = LoaderRegistry::new();
+    registry.register("txt", |bytes: &[u8], loads: &mut u32| {
+      *loads += 1;
+      let words = ::std::str::from_utf8(bytes)?.split_whitespace().map(str::to_owned).collect();
+      Ok::<_, ::std::str::Utf8Error>(Words(words))
+    });
+
+    assert!(registry.is_registered::<Words>("TXT"));
+    assert!(!registry.is_registered::<Words>("csv"));
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir).set_registry(registry);
+    let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+    let mut loads = 0;
+
+    let txt_key = Path::new("/list.txt").into();
+    let words: Res<Words> = store.get_by(&txt_key, &mut loads, Registered).unwrap();
+    assert_eq!(words.borrow().0, ["a", "b", "c"]);
+    assert_eq!(loads, 1);
+
+    // no loader is registered for CSV files yet
+    let csv_key: SimpleKey = Path::new("/list.csv").into();
+    match store.get_by::<Words, _>(&csv_key, &mut loads, Registered) {
+      Err(StoreErrorOr::ResError(RegistryError::NoLoader(_, ext))) => assert_eq!(ext, "csv"),
+      Err(e) => panic!("expected a missing loader, got {:?}", e),
+      Ok(_) => panic!("expected a missing loader"),
+    }
+
+    // loaders can be registered after the store was created
+    store.registry_mut().register("csv", |bytes: &[u8], _: &mut u32| {
+      let words = ::std::str::from_utf8(bytes)?.split(',').map(str::to_owned).collect();
+      Ok::<_, ::std::str::Utf8Error>(Words(words))
+    });
+
+    let words: Res<Words> = store.get_by(&csv_key, &mut loads, Registered).unwrap();
+    assert_eq!(words.borrow().0, ["d", "e"]);
+    assert_eq!(loads, 1);
+  })
+}
+
+#[test]
+fn dyn_load() {
+  use std::any::TypeId;
+  use warmy::registry::Registered;
+  use warmy::{DynLoad, DynLoader};
+
+  with_store(|mut store: Store<(), SimpleKey>| {
+    let key: SimpleKey = Path::new("/foo.txt").into();
+    ::std::fs::write(store.root().join("foo.txt"), "foo").unwrap();
+
+    let loaders: Vec<Box<dyn DynLoad<(), SimpleKey>>> = vec![
+      Box::new(DynLoader::<Foo>::new()),
+      Box::new(DynLoader::<Foo, Stupid>::new()),
+      Box::new(DynLoader::<Bar>::new()),
+    ];
+
+    // keys are given to loaders once prepared by the store
+    let prepared_key = key.clone().prepare_key(store.root());
+    let loaded: Vec<_> = loaders
+      .iter()
+      .map(|loader| loader.load(prepared_key.clone(), &mut store, &mut ()).unwrap().res)
+      .collect();
+
+    assert_eq!(loaders[0].res_type_id(), TypeId::of::<Foo>());
+    assert_eq!(loaders[2].res_type_id(), TypeId::of::<Bar>());
+    assert_eq!(loaded[0].downcast_ref::<Foo>(), Some(&Foo("foo".to_owned())));
+    assert_eq!(loaded[1].downcast_ref::<Foo>(), Some(&Foo("stupid".to_owned())));
+    assert_eq!(loaded[2].downcast_ref::<Bar>(), Some(&Bar("bar".to_owned())));
+
+    // loader objects can be registered, so that the store loads with them
+    store.registry_mut().register_dyn("txt", Box::new(DynLoader::<Foo, Stupid>::new()));
+
+    let foo: Res<Foo> = store.get_by(&key, &mut (), Registered).unwrap();
+    assert_eq!(*foo.borrow(), Foo("stupid".to_owned()));
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn registered_loader_rejects_reload() {
+  use warmy::registry::Registered;
+  use warmy::DynLoader;
+
+  with_store(|mut store: Store<u32, SimpleKey>| {
+    let mut rejections = 0;
+    let key: SimpleKey = Path::new("/non-empty.txt").into();
+    let path = store.root().join("non-empty.txt");
+    ::std::fs::write(&path, "Hello, world!").unwrap();
+
+    store.registry_mut().register_dyn("txt", Box::new(DynLoader::<NonEmpty>::new()));
+    let r: Res<NonEmpty> = store.get_by(&key, &mut rejections, Registered).unwrap();
+
+    // truncate the file; the registered loader must refuse the empty value
+    File::create(&path).unwrap();
+
+    wait_until(|| {
+      store.sync(&mut rejections);
+      rejections != 0
+    });
+
+    assert_eq!(r.borrow().0.as_str(), "Hello, world!");
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "json"))]
+#[test]
+fn combinators() {
+  use std::convert::TryFrom;
+  use warmy::combinator::{AndThenError, Combine, Method};
+  use warmy::json::Json;
+
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let doubled_key = SimpleKey::from_path("/doubled.json");
+    let byte_key = SimpleKey::from_path("/byte.json");
+    let dep_key = SimpleKey::from_path("/dep.json");
+    ::std::fs::write(tmp_dir.join("doubled.json"), "21").unwrap();
+    ::std::fs::write(tmp_dir.join("byte.json"), "256").unwrap();
+    ::std::fs::write(tmp_dir.join("dep.json"), "0").unwrap();
+
+    let doubled: Res<u64> = store
+      .get_with(&doubled_key, &mut (), Json.map(|n: u32| u64::from(n) * 2))
+      .unwrap();
+    assert_eq!(*doubled.borrow(), 42);
+
+    // failing transformations fail the loading
+    match store.get_with(&byte_key, &mut (), Json.and_then(|n: u32| u8::try_from(n))) {
+      Err(StoreErrorOr::ResError(AndThenError::ThenError(_))) => (),
+      Err(e) => panic!("expected a transformation error, got {:?}", e),
+      Ok(_) => panic!("256 doesn’t fit a byte"),
+    }
+
+    // the loader is kept to reload the resource, along with the extra dependencies
+    let _: Res<u32> = store.get_by(&dep_key, &mut (), Json).unwrap();
+    let dep = dep_key.clone();
+    let loader = Json.and_then(|n: u32| u8::try_from(n)).with_deps(move |_| vec![dep.clone()]);
+    ::std::fs::write(tmp_dir.join("byte.json"), "255").unwrap();
+    let byte: Res<u8> = store.get_with(&byte_key, &mut (), loader).unwrap();
+    assert_eq!(*byte.borrow(), 255);
+
+    ::std::fs::write(tmp_dir.join("byte.json"), "7").unwrap();
+    store.invalidate(&dep_key);
+    store.sync(&mut ());
+    assert_eq!(*byte.borrow(), 7);
+
+    ::std::fs::write(tmp_dir.join("doubled.json"), "4").unwrap();
+    store.invalidate(&doubled_key);
+    store.sync(&mut ());
+    assert_eq!(*doubled.borrow(), 8);
+  })
+}
+
+#[cfg(all(feature = "json", feature = "ron-impl", feature = "toml-impl"))]
+#[test]
+fn first_of() {
+  use std::collections::HashMap;
+  use warmy::fallback::{FirstOf, FirstOfError};
+  use warmy::json::{Json, JsonError};
+  use warmy::ron::Ron;
+  use warmy::toml::Toml;
+
+  type Legacy = FirstOf<(Ron, Json, Toml)>;
+
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    ::std::fs::write(tmp_dir.join("a.ron"), r#"{ "level": 1 }"#).unwrap();
+    ::std::fs::write(tmp_dir.join("b.json"), r#"{ "level": 2 }"#).unwrap();
+    ::std::fs::write(tmp_dir.join("c.toml"), "level = 3").unwrap();
+    ::std::fs::write(tmp_dir.join("d.txt"), "level: 4").unwrap();
+
+    for (file, level) in &[("/a.ron", 1), ("/b.json", 2), ("/c.toml", 3)] {
+      let key = SimpleKey::from_path(file);
+      let config: Res<HashMap<String, u32>> = store.get_by(&key, &mut (), Legacy::new()).unwrap();
+      assert_eq!(config.borrow()["level"], *level);
+    }
+
+    // every error is reported when no method succeeds
+    let key = SimpleKey::from_path("/d.txt");
+    let res = store.get_by::<HashMap<String, u32>, _>(&key, &mut (), Legacy::new());
+    let err: FirstOfError = match res {
+      Err(StoreErrorOr::ResError(e)) => e,
+      Err(e) => panic!("expected a resource error, got {:?}", e),
+      Ok(_) => panic!("d.txt is in none of the formats"),
+    };
+
+    assert_eq!(err.errors().len(), 3);
+    assert!(err.errors()[1].1.downcast_ref::<JsonError>().is_some());
+  })
+}
+
+#[test]
+fn get_or_default() {
+  with_store(|mut store| {
+    let ctx = &mut ();
+    let missing_key = Path::new("missing.txt").into();
+    let present_key = Path::new("present.txt").into();
+    ::std::fs::write(store.root().join("present.txt"), "present").unwrap();
+
+    let missing: Res<Text> = store.get_or_default(&missing_key, ctx).unwrap();
+    assert_eq!(*missing.borrow(), Text::default());
+    assert!(store.is_proxy(&missing_key));
+    assert!(store.last_error(&missing_key).unwrap().error().to_string().starts_with("wrong key"));
+
+    let present: Res<Text> = store.get_or_default(&present_key, ctx).unwrap();
+    assert_eq!(present.borrow().0, "present");
+    assert!(!store.is_proxy(&present_key));
+    assert!(store.last_error(&present_key).is_none());
+  })
+}
+
+#[cfg(all(feature = "hot-reload", feature = "toml-impl"))]
+#[test]
+fn layered_config() {
+  use std::collections::HashMap;
+  use warmy::layered::{Layered, LayeredConfig};
+
+  type Tables = HashMap<String, HashMap<String, String>>;
+
+  #[derive(Debug)]
+  struct Config(Tables);
+
+  impl<'de> serde::Deserialize<'de> for Config {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+      Tables::deserialize(deserializer).map(Config)
+    }
+  }
+
+  impl Layered for Config {
+    const ENV_PREFIX: Option<&'static str> = Some("WARMY_TEST_LAYERED");
+  }
+
+  with_tmp_dir(|tmp_dir| {
+    let dir = tmp_dir.join("config");
+    ::std::fs::create_dir(&dir).unwrap();
+    ::std::fs::write(dir.join("base.toml"), "[server]\nhost = \"a\"\nname = \"a\"\nport = \"1\"")
+      .unwrap();
+    // not a TOML value, so taken as a string
+    ::std::env::set_var("WARMY_TEST_LAYERED__SERVER__PORT", "three");
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let key = SimpleKey::from_path("/config");
+    let config: Res<LayeredConfig<Config>> = store.get(&key, &mut ()).unwrap();
+
+    assert_eq!(config.borrow().0["server"]["host"], "a");
+    assert_eq!(config.borrow().0["server"]["port"], "three");
+
+    // creating the local layer merges the layers again
+    ::std::fs::write(dir.join("local.toml"), "[server]\nhost = \"b\"\nport = \"2\"").unwrap();
+
+    wait_until(|| {
+      store.sync(&mut ());
+      config.borrow().0["server"]["host"] == "b"
+    });
+
+    assert_eq!(config.borrow().0["server"]["name"], "a");
+    assert_eq!(config.borrow().0["server"]["port"], "three");
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn secrets() {
+  use warmy::secrets::{Secrets, SecretsError};
+
+  with_tmp_dir(|tmp_dir| {
+    let path = tmp_dir.join(".env");
+    let env = "# credentials\nexport API_KEY=hunter2 # inline comment\n\nPASSWORD='a b # c'\n\
+               TOKEN=\"line\\nbreak \\\"quoted\\\"\"\n";
+    ::std::fs::write(&path, env).unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let key = SimpleKey::from_path("/.env");
+    let secrets: Res<Secrets> = store.get(&key, &mut ()).unwrap();
+
+    {
+      let secrets = secrets.borrow();
+      assert_eq!(secrets.len(), 3);
+      assert_eq!(secrets.get("API_KEY").unwrap().expose(), "hunter2");
+      assert_eq!(secrets.get("PASSWORD").unwrap().expose(), "a b # c");
+      assert_eq!(secrets.get("TOKEN").unwrap().expose(), "line\nbreak \"quoted\"");
+
+      // values never show up when formatted
+      let debug = format!("{:?}", *secrets);
+      assert!(debug.contains("API_KEY"));
+      assert!(!debug.contains("hunter2"));
+      assert_eq!(secrets.get("API_KEY").unwrap().to_string(), "[REDACTED]");
+    }
+
+    // rotating a secret reloads them
+    ::std::fs::write(&path, "API_KEY=correct-horse\n").unwrap();
+
+    wait_until(|| {
+      store.sync(&mut ());
+      secrets.borrow().get("API_KEY").unwrap().expose() == "correct-horse"
+    });
+
+    assert_eq!(secrets.borrow().len(), 1);
+
+    // malformed lines are reported without their content
+    ::std::fs::write(tmp_dir.join("bad.env"), "A=1\nnot a variable\n").unwrap();
+    let key = SimpleKey::from_path("/bad.env");
+
+    match store.get::<Secrets>(&key, &mut ()) {
+      Err(StoreErrorOr::ResError(SecretsError::ParseError(_, 2))) => (),
+      r => panic!("unexpected result: {:?}", r.map(|_| ())),
+    }
+  })
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn record_replay() {
+  use warmy::replay::{EventLog, RecordedEvent, Replay};
+
+  with_tmp_dir(|tmp_dir| {
+    let key = SimpleKey::from_path("/data.txt");
+    let recorded_dir = tmp_dir.join("recorded");
+    let replayed_dir = tmp_dir.join("replayed");
+    ::std::fs::create_dir(&recorded_dir).unwrap();
+    ::std::fs::create_dir(&replayed_dir).unwrap();
+
+    // record a reload
+    let path = recorded_dir.join("data.txt");
+    ::std::fs::write(&path, "old").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(&recorded_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    store.start_recording();
+
+    let text: Res<Text> = store.get(&key, &mut ()).unwrap();
+    ::std::fs::write(&path, "new").unwrap();
+
+    wait_until(|| {
+      store.sync(&mut ());
+      text.borrow().0 == "new"
+    });
+
+    let log = store.stop_recording().unwrap();
+    let events = log.events();
+    assert!(store.recording().is_none());
+    assert!(matches!(events[0], RecordedEvent::Loaded(_)));
+    assert!(events.contains(&RecordedEvent::Write("data.txt".into())));
+    assert!(events.iter().any(|event| matches!(event, RecordedEvent::Reloaded(_))));
+    assert_eq!(events.last(), Some(&RecordedEvent::Sync));
+
+    // logs survive a round-trip through text
+    assert_eq!(log.to_string().parse::<EventLog>().unwrap(), log);
+    assert_eq!("sync\nbogus\n".parse::<EventLog>().unwrap_err().line(), 2);
+
+    // replay it in another directory, where the file changes without the store knowing when
+    let path = replayed_dir.join("data.txt");
+    ::std::fs::write(&path, "old").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(&replayed_dir);
+    let mut store: Store<(), SimpleKey> = Store::new(opt).expect("create store");
+    let text: Res<Text> = store.get(&key, &mut ()).unwrap();
+    ::std::fs::write(&path, "new").unwrap();
+
+    let mut replay = Replay::new(log);
+    let reports = replay.run(&mut store, &mut ());
+
+    assert!(replay.is_done());
+    assert_eq!(reports.iter().map(|report| report.reloaded().len()).sum::<usize>(), 1);
+    assert_eq!(text.borrow().0, "new");
+  })
+}
+
+#[test]
+fn mock_store() {
+  use warmy::testing::MockStore;
+
+  // a greeting, depending on the name it greets
+  struct Greeting(String);
+
+  impl Load<(), SimpleKey> for Greeting {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      let greeting = storage.read_file_to_string(&path.unwrap())?;
+      let name = storage.read_file_to_string(&storage.root().join("name.txt"))?;
+      let dep = SimpleKey::from_path("/name.txt");
+
+      Ok(Loaded::with_deps(Greeting(format!("{}, {}!", greeting, name)), vec![dep]))
+    }
+  }
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let key = SimpleKey::from_path("/greeting.txt");
+
+  assert!(store.get::<Greeting>(&key, &mut ()).is_err());
+
+  store.set_file("/greeting.txt", "Hello");
+  store.set_file("/name.txt", "world");
+  assert_eq!(store.file("/name.txt"), Some(&b"world"[..]));
+
+  let greeting: Res<Greeting> = store.get(&key, &mut ()).unwrap();
+  assert_eq!(greeting.borrow().0, "Hello, world!");
+
+  #[cfg(feature = "hot-reload")]
+  {
+    // changing a dependency reloads the resource
+    store.set_file("/name.txt", "warmy");
+    assert_eq!(store.sync(&mut ()).reloaded().len(), 1);
+    assert_eq!(greeting.borrow().0, "Hello, warmy!");
+
+    // so does an atomic save
+    store.set_file("/greeting.tmp", "Bye");
+    assert!(store.rename_file("/greeting.tmp", "/greeting.txt"));
+    store.sync(&mut ());
+    assert_eq!(greeting.borrow().0, "Bye, warmy!");
+
+    // failing reloads keep the resource
+    assert!(store.remove_file("/name.txt"));
+    assert!(store.touch("/greeting.txt"));
+    assert_eq!(store.sync(&mut ()).failed().len(), 1);
+    assert_eq!(greeting.borrow().0, "Bye, warmy!");
+    assert!(!store.touch("/name.txt"));
+  }
+}
+
+#[cfg(all(feature = "hot-reload", feature = "test-util"))]
+#[test]
+fn test_util() {
+  use std::time::Duration;
+  use warmy::test_util::{sync_until, sync_until_timeout, with_store};
+
+  with_store(|mut store: Store<(), SimpleKey>| {
+    let path = store.root().join("text.txt");
+    ::std::fs::write(&path, "old").unwrap();
+
+    let key = SimpleKey::from_path("/text.txt");
+    let text: Res<Text> = store.get(&key, &mut ()).unwrap();
+
+    ::std::fs::write(&path, "new").unwrap();
+    sync_until(&mut store, &mut (), || text.borrow().0 == "new");
+
+    let timeout = Duration::from_millis(10);
+    assert!(!sync_until_timeout(&mut store, &mut (), timeout, || false));
+  });
+}
+
+#[test]
+fn get_when_available() {
+  use std::thread;
+  use std::time::Duration;
+
+  with_store(|mut store: Store<(), SimpleKey>| {
+    let path = store.root().join("late.txt");
+    let key = SimpleKey::from_path("/late.txt");
+
+    // the file appears – atomically – after the store starts waiting for it
+    let tmp_path = store.root().join("late.tmp");
+    let writer = thread::spawn(move || {
+      thread::sleep(Duration::from_millis(100));
+      ::std::fs::write(&tmp_path, "finally").unwrap();
+      ::std::fs::rename(&tmp_path, &path).unwrap();
+    });
+
+    let text: Res<Text> = store
+      .get_when_available(&key, &mut (), Duration::from_secs(5))
+      .unwrap();
+    assert_eq!(text.borrow().0, "finally");
+    writer.join().unwrap();
+
+    // a file that never appears times out
+    let missing = SimpleKey::from_path("/missing.txt");
+    match store.get_when_available::<Text>(&missing, &mut (), Duration::from_millis(10)) {
+      Err(StoreErrorOr::StoreError(StoreError::Timeout(key))) => assert_eq!(key, missing),
+      _ => panic!("expected a timeout"),
+    }
+  });
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn lock_files() {
+  use warmy::testing::MockStore;
+
+  struct Asset(String);
+
+  impl Load<(), SimpleKey> for Asset {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      storage.read_file_to_string(&path.unwrap()).map(|s| Asset(s).into())
+    }
+  }
+
+  let opt = warmy::StoreOpt::default().set_lock_suffix(".lock");
+  assert_eq!(opt.lock_suffix(), Some(".lock"));
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(opt);
+  store.set_file("/mesh.obj", "v1");
+
+  let key = SimpleKey::from_path("/mesh.obj");
+  let asset: Res<Asset> = store.get(&key, &mut ()).unwrap();
+
+  // the exporter locks the file while writing it
+  store.set_file("/mesh.obj.lock", "");
+  store.set_file("/mesh.obj", "v2, half-written");
+  assert!(store.sync(&mut ()).reloaded().is_empty());
+  assert_eq!(asset.borrow().0, "v1");
+
+  store.set_file("/mesh.obj", "v2");
+  assert!(store.sync(&mut ()).reloaded().is_empty());
+
+  // the held change is handled once the lock disappears
+  store.remove_file("/mesh.obj.lock");
+  assert_eq!(store.sync(&mut ()).reloaded().len(), 1);
+  assert_eq!(asset.borrow().0, "v2");
+
+  // unlocked files reload right away
+  store.set_file("/mesh.obj", "v3");
+  assert_eq!(store.sync(&mut ()).reloaded().len(), 1);
+  assert_eq!(asset.borrow().0, "v3");
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn write_resource() {
+  use std::thread;
+  use std::time::{Duration, Instant};
+
+  with_store(|mut store: Store<(), SimpleKey>| {
+    let path = store.root().join("level.txt");
+    ::std::fs::write(&path, "draft").unwrap();
+
+    let key = SimpleKey::from_path("/level.txt");
+    let text: Res<Text> = store.get(&key, &mut ()).unwrap();
+
+    store.write_resource(&key, "final").unwrap();
+    assert_eq!(::std::fs::read_to_string(&path).unwrap(), "final");
+    assert_eq!(::std::fs::read_dir(store.root()).unwrap().count(), 1);
+
+    // the write is reloaded exactly once, whatever the watcher reports
+    assert_eq!(store.sync(&mut ()).reloaded().len(), 1);
+    assert_eq!(text.borrow().0, "final");
+
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_millis(500) {
+      assert!(store.sync(&mut ()).reloaded().is_empty());
+      thread::sleep(Duration::from_millis(10));
+    }
+
+    // logical keys don’t address files
+    assert!(store.write_resource(&SimpleKey::from("logical"), "nope").is_err());
+  });
+}
+
+#[test]
+fn file_metadata() {
+  use warmy::testing::MockStore;
+
+  // the size of a file, as reported to the loader
+  struct FileSize(u64);
+
+  impl Load<(), SimpleKey> for FileSize {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      storage.key_metadata(&key).map(|metadata| FileSize(metadata.size).into())
+    }
+  }
+
+  with_store(|mut store: Store<(), SimpleKey>| {
+    ::std::fs::write(store.root().join("data.txt"), "12345").unwrap();
+
+    let key = SimpleKey::from_path("/data.txt");
+    let size: Res<FileSize> = store.get(&key, &mut ()).unwrap();
+    assert_eq!(size.borrow().0, 5);
+
+    let metadata = store.file_metadata(&store.root().join("data.txt")).unwrap();
+    assert!(metadata.modified.is_some());
+    assert!(store.key_metadata(&SimpleKey::from("logical")).is_err());
+  });
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/data.txt", "123");
+
+  let size: Res<FileSize> = store.get(&SimpleKey::from_path("/data.txt"), &mut ()).unwrap();
+  assert_eq!(size.borrow().0, 3);
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn try_sync_skips_borrowed() {
+  use warmy::testing::MockStore;
+
+  struct Sprite(String);
+
+  impl Load<(), SimpleKey> for Sprite {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      storage.read_file_to_string(&path.unwrap()).map(|s| Sprite(s).into())
+    }
+  }
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/hero.png", "idle");
+
+  let key = SimpleKey::from_path("/hero.png");
+  let sprite: Res<Sprite> = store.get(&key, &mut ()).unwrap();
+
+  store.set_file("/hero.png", "running");
+
+  {
+    let borrowed = sprite.borrow();
+    let report = store.try_sync(&mut ());
+
+    assert_eq!(report.skipped().len(), 1);
+    assert!(report.reloaded().is_empty());
+    assert_eq!(borrowed.0, "idle");
+  }
+
+  // the reload was postponed to the next synchronization
+  let report = store.try_sync(&mut ());
+  assert!(report.skipped().is_empty());
+  assert_eq!(report.reloaded().len(), 1);
+  assert_eq!(sprite.borrow().0, "running");
+}
+
+#[test]
+fn read_only() {
+  use warmy::testing::MockStore;
+
+  struct Level(String);
+
+  impl Load<(), SimpleKey> for Level {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      storage.read_file_to_string(&path.unwrap()).map(|s| Level(s).into())
+    }
+  }
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/level1.txt", "forest");
+  store.set_file("/level2.txt", "castle");
+
+  // preload, then forbid loading anything else
+  let level1 = SimpleKey::from_path("/level1.txt");
+  let level2 = SimpleKey::from_path("/level2.txt");
+  store.get::<Level>(&level1, &mut ()).unwrap();
+  store.set_read_only(true);
+  assert!(store.is_read_only());
+
+  let level: Res<Level> = store.get(&level1, &mut ()).unwrap();
+  assert_eq!(level.borrow().0, "forest");
+
+  match store.get::<Level>(&level2, &mut ()) {
+    Err(StoreErrorOr::StoreError(StoreError::CacheMiss(key))) => assert_eq!(key, level2),
+    _ => panic!("expected a cache miss"),
+  }
+
+  store.set_read_only(false);
+  let level: Res<Level> = store.get(&level2, &mut ()).unwrap();
+  assert_eq!(level.borrow().0, "castle");
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn failure_propagation() {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+  use warmy::testing::MockStore;
+  use warmy::FailurePropagation;
+
+  // a base failing to load when its file is “bad”
+  struct Base;
+
+  impl Load<(), SimpleKey> for Base {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+
+      match storage.read_file_to_string(&path.unwrap())?.as_str() {
+        "bad" => Err(::std::io::ErrorKind::InvalidData.into()),
+        _ => Ok(Base.into()),
+      }
+    }
+  }
+
+  // a resource depending on the base, tolerating it being bad
+  struct Top(String);
+
+  impl Load<(), SimpleKey> for Top {
+    type Error = ::std::io::Error;
+
+    fn load(
+      _: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let base = storage.read_file_to_string(&storage.root().join("base.txt"))?;
+      let dep = SimpleKey::from_path("/base.txt");
+
+      Ok(Loaded::with_deps(Top(format!("top of {}", base)), vec![dep]))
+    }
+  }
+
+  fn break_base(propagation: FailurePropagation<SimpleKey>) -> String {
+    let opt = warmy::StoreOpt::default().set_failure_propagation(propagation);
+    let mut store: MockStore<(), SimpleKey> = MockStore::new(opt);
+    store.set_file("/base.txt", "good");
+
+    store.get::<Base>(&SimpleKey::from_path("/base.txt"), &mut ()).unwrap();
+    let top: Res<Top> = store.get(&SimpleKey::from("top"), &mut ()).unwrap();
+
+    store.set_file("/base.txt", "bad");
+    assert_eq!(store.sync(&mut ()).failed().len(), 1);
+
+    let top = top.borrow().0.clone();
+    top
+  }
+
+  assert_eq!(break_base(FailurePropagation::Stop), "top of good");
+  assert_eq!(break_base(FailurePropagation::ReloadDependents), "top of bad");
+
+  let calls = Rc::new(RefCell::new(Vec::new()));
+  let propagation = {
+    let calls = calls.clone();
+    FailurePropagation::callback(move |error, dependents: &[SimpleKey]| {
+      calls.borrow_mut().push((error.key().clone(), dependents.to_owned()));
+      false
+    })
+  };
+
+  assert_eq!(break_base(propagation), "top of good");
+  assert_eq!(calls.borrow().len(), 1);
+  assert_eq!(calls.borrow()[0].1, vec![SimpleKey::from("top")]);
+}
+
+#[test]
+fn store_chain() {
+  use warmy::chain::StoreChain;
+  use warmy::testing::MockStore;
+
+  struct Asset(String);
+
+  impl Load<(), SimpleKey> for Asset {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      storage.read_file_to_string(&path.unwrap()).map(|s| Asset(s).into())
+    }
+  }
+
+  let mut local: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  local.set_file("/hero.txt", "local hero");
+
+  let mut shared: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  shared.set_file("/hero.txt", "shared hero");
+  shared.set_file("/villain.txt", "shared villain");
+
+  let mut chain = StoreChain::new(local.into_store());
+  chain.push(shared.into_store());
+  assert_eq!(chain.stores().len(), 2);
+
+  let hero: Res<Asset> = chain.get(&SimpleKey::from_path("/hero.txt"), &mut ()).unwrap();
+  let villain: Res<Asset> = chain.get(&SimpleKey::from_path("/villain.txt"), &mut ()).unwrap();
+  assert_eq!(hero.borrow().0, "local hero");
+  assert_eq!(villain.borrow().0, "shared villain");
+
+  // only the stores having the resources loaded them
+  assert!(chain.stores()[0].is_loaded_as::<Asset>(&SimpleKey::from_path("/hero.txt")));
+  assert!(!chain.stores()[0].is_loaded_as::<Asset>(&SimpleKey::from_path("/villain.txt")));
+  assert!(!chain.stores()[1].is_loaded_as::<Asset>(&SimpleKey::from_path("/hero.txt")));
+
+  match chain.get::<Asset>(&SimpleKey::from_path("/sidekick.txt"), &mut ()) {
+    Err(StoreErrorOr::ResError(e)) => assert_eq!(e.kind(), ::std::io::ErrorKind::NotFound),
+    _ => panic!("expected a missing file"),
+  }
+
+  assert_eq!(chain.sync(&mut ()).len(), 2);
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn scoped_stores() {
+  use std::thread;
+  use std::time::Duration;
+
+  with_tmp_dir(|tmp_dir| {
+    // directories created after the watcher are only watched once it notices them: create the
+    // root of the child beforehand so that its first events aren’t missed
+    ::std::fs::create_dir(tmp_dir.join("ui")).unwrap();
+    ::std::fs::write(tmp_dir.join("ui/button.txt"), "up").unwrap();
+    ::std::fs::write(tmp_dir.join("world.txt"), "flat").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let mut ui: Store<(), SimpleKey> = store.scoped("ui").unwrap();
+    assert_eq!(ui.root(), store.root().join("ui"));
+    assert!(store.scoped("audio").is_err());
+    assert!(store.scoped("..").is_err());
+
+    // keys of the child are relative to its own root
+    let button: Res<Text> = ui.get(&SimpleKey::from_path("/button.txt"), &mut ()).unwrap();
+    let world: Res<Text> = store.get(&SimpleKey::from_path("/world.txt"), &mut ()).unwrap();
+
+    ::std::fs::write(store.root().join("ui/button.txt"), "down").unwrap();
+    ::std::fs::write(store.root().join("world.txt"), "round").unwrap();
+
+    // each store gets the events of its own tree, whichever store receives them
+    wait_until(|| {
+      store.sync(&mut ());
+      ui.sync(&mut ());
+      thread::sleep(Duration::from_millis(1));
+      button.borrow().0 == "down" && world.borrow().0 == "round"
+    });
+
+    // children outlive their parent
+    drop(store);
+    ui.sync(&mut ());
+  });
+}
+
+#[test]
+fn tags() {
+  use warmy::testing::MockStore;
+
+  // counts the loads in the context
+  struct Chunk(String);
+
+  impl Load<usize, SimpleKey> for Chunk {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<usize, SimpleKey>,
+      loads: &mut usize,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      *loads += 1;
+
+      let path: Option<PathBuf> = key.clone().into();
+      let path = path.unwrap();
+      let level = path.parent().unwrap().file_name().unwrap().to_string_lossy().into_owned();
+      storage.add_tag(&key, level);
+      storage.add_tag(&key, "chunk");
+      storage.read_file_to_string(&path).map(|s| Chunk(s).into())
+    }
+  }
+
+  let mut store: MockStore<usize, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/level1/a.txt", "a");
+  store.set_file("/level1/b.txt", "b");
+  store.set_file("/level2/c.txt", "c");
+
+  let a = SimpleKey::from_path("/level1/a.txt");
+  let b = SimpleKey::from_path("/level1/b.txt");
+  let c = SimpleKey::from_path("/level2/c.txt");
+  let mut loads = 0;
+
+  // handle the creation of the files before loading anything
+  store.sync(&mut loads);
+
+  for key in &[&a, &b, &c] {
+    store.get::<Chunk>(key, &mut loads).unwrap();
+  }
+
+  assert_eq!(loads, 3);
+
+  let mut tags = store.tags(&a);
+  tags.sort();
+  assert_eq!(tags, vec!["chunk", "level1"]);
+  assert!(store.has_tag(&c, "level2"));
+  assert!(!store.has_tag(&c, "level1"));
+
+  // keys are prepared, but still refer to the same resources
+  let level1 = store.keys_tagged("level1");
+  assert_eq!(level1.len(), 2);
+  assert!(level1.iter().all(|key| store.is_loaded_as::<Chunk>(key)));
+  assert!(level1.iter().all(|key| store.has_tag(key, "level1")));
+  assert_eq!(store.keys_tagged("chunk").len(), 3);
+  assert!(store.keys_tagged("level3").is_empty());
+
+  // tags can be attached before loading and detached
+  let d = SimpleKey::from_path("/level2/d.txt");
+  store.add_tag(&d, "level2");
+  assert_eq!(store.keys_tagged("level2").len(), 2);
+  assert!(store.remove_tag(&d, "level2"));
+  assert!(!store.remove_tag(&d, "level2"));
+  assert_eq!(store.keys_tagged("level2").len(), 1);
+
+  // only loaded resources are invalidated
+  store.add_tag(&d, "level2");
+  assert_eq!(store.invalidate_tagged("level2"), 1);
+  assert_eq!(store.invalidate_tagged("level3"), 0);
+
+  store.sync(&mut loads);
+  assert_eq!(loads, if cfg!(feature = "hot-reload") { 4 } else { 3 });
+  loads = 0;
+
+  // leaving the first level drops its chunks, which are loaded again when needed
+  let old_a: Res<Chunk> = store.get(&a, &mut loads).unwrap();
+  assert_eq!(store.remove_tagged("level1"), 2);
+  assert_eq!(store.remove_tagged("level1"), 0);
+  assert!(store.keys_tagged("level1").is_empty());
+  assert!(!store.is_loaded_as::<Chunk>(&a));
+  assert!(store.is_loaded_as::<Chunk>(&c));
+  assert_eq!(old_a.borrow().0, "a");
+
+  store.set_file("/level1/a.txt", "A");
+  store.sync(&mut loads);
+  assert_eq!(old_a.borrow().0, "a");
+  assert_eq!(loads, 0);
+
+  let new_a: Res<Chunk> = store.get(&a, &mut loads).unwrap();
+  assert_eq!(new_a.borrow().0, "A");
+  assert_eq!(loads, 1);
+  assert!(store.has_tag(&a, "level1"));
+
+  assert!(store.remove(&c));
+  assert!(!store.remove(&c));
+  assert!(!store.is_loaded_as::<Chunk>(&c));
+}
+
+#[test]
+fn load_info() {
+  use std::time::Instant;
+  use warmy::testing::MockStore;
+
+  struct Shader(String);
+
+  impl Load<(), SimpleKey> for Shader {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      storage.read_file_to_string(&path.unwrap()).map(|s| Shader(s).into())
+    }
+  }
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/shader.frag", "v1");
+  store.sync(&mut ());
+
+  let key = SimpleKey::from_path("/shader.frag");
+  assert_eq!(store.load_info(&key), None);
+
+  let before = Instant::now();
+  let shader: Res<Shader> = store.get(&key, &mut ()).unwrap();
+  let info = store.load_info(&key).unwrap();
+  assert!(!info.reload);
+  assert!(info.at >= before && info.at <= Instant::now());
+  assert!(info.at.duration_since(before) >= info.duration);
+
+  // proxies were never loaded
+  let missing = SimpleKey::from_path("/missing.frag");
+  let _: Res<Shader> = store.get_proxied(&missing, || Shader(String::new()), &mut ()).unwrap();
+  assert_eq!(store.load_info(&missing), None);
+
+  store.set_file("/shader.frag", "v2");
+  store.sync(&mut ());
+
+  if cfg!(feature = "hot-reload") {
+    let reloaded = store.load_info(&key).unwrap();
+    assert!(reloaded.reload);
+    assert!(reloaded.at >= info.at);
+    assert_eq!(shader.borrow().0, "v2");
+  } else {
+    assert_eq!(store.load_info(&key), Some(info));
+  }
+}
+
+#[test]
+fn key_rewriter() {
+  use warmy::testing::MockStore;
+
+  struct Sprite(String);
+
+  impl Load<(), SimpleKey> for Sprite {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      storage.read_file_to_string(&path.unwrap()).map(|s| Sprite(s).into())
+    }
+  }
+
+  // the hero sprite was renamed
+  let opt = warmy::StoreOpt::default().set_key_rewriter(|key| {
+    if key == SimpleKey::from_path("/hero_old.txt") {
+      SimpleKey::from_path("/sprites/hero.txt")
+    } else {
+      key
+    }
+  });
+  assert!(opt.key_rewriter().is_some());
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(opt);
+  store.set_file("/sprites/hero.txt", "hero");
+  store.set_file("/villain.txt", "villain");
+  store.sync(&mut ());
+
+  let old = SimpleKey::from_path("/hero_old.txt");
+  let new = SimpleKey::from_path("/sprites/hero.txt");
+  let hero: Res<Sprite> = store.get(&old, &mut ()).unwrap();
+  assert_eq!(hero.borrow().0, "hero");
+
+  // both keys refer to the same resource
+  let same: Res<Sprite> = store.get(&new, &mut ()).unwrap();
+  assert_eq!(same.borrow().0, "hero");
+  assert!(store.is_loaded_as::<Sprite>(&old));
+
+  let villain: Res<Sprite> = store.get(&SimpleKey::from_path("/villain.txt"), &mut ()).unwrap();
+  assert_eq!(villain.borrow().0, "villain");
+  assert_eq!(store.snapshot().resources.len(), 2);
+
+  // the file the resource is actually loaded from reloads it
+  store.set_file("/sprites/hero.txt", "hero v2");
+  store.sync(&mut ());
+
+  #[cfg(feature = "hot-reload")]
+  assert_eq!(same.borrow().0, "hero v2");
+}
+
+#[test]
+fn resolve_relative() {
+  use warmy::key::vfs_substitute_path;
+  use warmy::testing::MockStore;
+
+  // a material made of the textures listed in its file, relative to it
+  struct Material(Vec<String>);
+
+  impl Load<(), SimpleKey> for Material {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<(), SimpleKey>,
+      _: &mut (),
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.clone().into();
+      let source = storage.read_file_to_string(&path.unwrap())?;
+      let mut textures = Vec::new();
+      let mut deps = Vec::new();
+
+      for line in source.lines() {
+        let texture = storage.resolve_relative(&key, line);
+        let path: Option<PathBuf> = texture.clone().into();
+        let path = vfs_substitute_path(&path.unwrap(), storage.root());
+        textures.push(storage.read_file_to_string(&path)?);
+        deps.push(texture);
+      }
+
+      Ok(Loaded::with_deps(Material(textures), deps))
+    }
+  }
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let stone = SimpleKey::from_path("/materials/stone.mat");
+
+  let resolve = |current: &SimpleKey, path| store.resolve_relative(current, path);
+  assert_eq!(resolve(&stone, "albedo.png"), SimpleKey::from_path("/materials/albedo.png"));
+  assert_eq!(resolve(&stone, "./maps/./normal.png"), SimpleKey::from_path("/materials/maps/normal.png"));
+  assert_eq!(resolve(&stone, "../shared/noise.png"), SimpleKey::from_path("/shared/noise.png"));
+  assert_eq!(resolve(&stone, "/noise.png"), SimpleKey::from_path("/noise.png"));
+  assert_eq!(resolve(&"logical".into(), "noise.png"), SimpleKey::from_path("/noise.png"));
+
+  // escaping the root is refused when getting the resource
+  let outside = store.resolve_relative(&stone, "../../passwd");
+  assert!(store.get::<Text>(&outside, &mut ()).is_err());
+
+  store.set_file("/materials/stone.mat", "albedo.png\n../shared/noise.png");
+  store.set_file("/materials/albedo.png", "grey");
+  store.set_file("/shared/noise.png", "perlin");
+  store.sync(&mut ());
+
+  let material: Res<Material> = store.get(&stone, &mut ()).unwrap();
+  assert_eq!(material.borrow().0, vec!["grey", "perlin"]);
+
+  // textures are dependencies of the material
+  store.set_file("/shared/noise.png", "worley");
+  store.sync(&mut ());
+
+  #[cfg(feature = "hot-reload")]
+  assert_eq!(material.borrow().0, vec!["grey", "worley"]);
+}
+
+#[test]
+fn loaded_deps() {
+  use warmy::testing::MockStore;
+
+  let loaded: Loaded<(), SimpleKey> = Loaded::with_deps((), vec!["a", "b", "a"]);
+  assert_eq!(loaded.deps, vec![SimpleKey::from("a"), SimpleKey::from("b")]);
+
+  let loaded = loaded.dep("c").dep(SimpleKey::from("b"));
+  assert_eq!(loaded.deps, vec!["a".into(), "b".into(), "c".into()]);
+
+  // counts the loads in the context; depends on itself and twice on its base
+  struct Config;
+
+  impl Load<usize, SimpleKey> for Config {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<usize, SimpleKey>,
+      loads: &mut usize,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      *loads += 1;
+
+      let path: Option<PathBuf> = key.clone().into();
+      storage.read_file(&path.unwrap())?;
+
+      let base = SimpleKey::from_path("/base.txt");
+      let mut loaded = Loaded::with_deps(Config, vec![key, base.clone()]);
+      loaded.deps.push(base);
+      Ok(loaded)
+    }
+  }
+
+  let mut store: MockStore<usize, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/config.txt", "");
+  store.set_file("/base.txt", "");
+
+  let mut loads = 0;
+  store.sync(&mut loads);
+
+  let _: Res<Config> = store.get(&SimpleKey::from_path("/config.txt"), &mut loads).unwrap();
+  assert_eq!(loads, 1);
+
+  #[cfg(feature = "hot-reload")]
+  {
+    // reloaded once per change
+    store.touch("/base.txt");
+    store.sync(&mut loads);
+    assert_eq!(loads, 2);
+
+    store.touch("/config.txt");
+    store.sync(&mut loads);
+    assert_eq!(loads, 3);
+  }
+}
+
+#[test]
+fn dep_events() {
+  use warmy::testing::MockStore;
+  use warmy::DepEvents;
+
+  assert!(DepEvents::default().contains(DepEvents::WRITE | DepEvents::CREATE));
+  assert!(!DepEvents::default().contains(DepEvents::REMOVE));
+  assert!(DepEvents::ALL.contains(DepEvents::METADATA));
+
+  // counts its loads in the context; only cares about files being added to or removed from a
+  // directory
+  struct Listing;
+
+  impl Load<usize, SimpleKey> for Listing {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<usize, SimpleKey>,
+      loads: &mut usize,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      *loads += 1;
+
+      let dir = SimpleKey::from_path("/levels");
+      storage.set_dep_events(&key, &dir, DepEvents::CREATE | DepEvents::REMOVE);
+      Ok(Loaded::from(Listing).dep(dir))
+    }
+  }
+
+  let mut store: MockStore<usize, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let key = SimpleKey::from("listing");
+  let mut loads = 0;
+
+  let _: Res<Listing> = store.get(&key, &mut loads).unwrap();
+  assert_eq!(loads, 1);
+  assert_eq!(
+    store.dep_events(&key, &SimpleKey::from_path("/levels")),
+    DepEvents::CREATE | DepEvents::REMOVE
+  );
+  assert_eq!(store.dep_events(&key, &SimpleKey::from_path("/other")), DepEvents::default());
+
+  store.set_file("/levels/forest.txt", "trees");
+  store.sync(&mut loads);
+
+  store.set_file("/levels/forest.txt", "more trees");
+  store.sync(&mut loads);
+
+  store.set_file("/castle.txt", "walls");
+  store.sync(&mut loads);
+
+  store.remove_file("/levels/forest.txt");
+  store.sync(&mut loads);
+
+  // created and removed, but not written to
+  #[cfg(feature = "hot-reload")]
+  assert_eq!(loads, 3);
+}
+
+#[test]
+fn sync_forever() {
+  with_store(|mut store: Store<Vec<String>, SimpleKey>| {
+    let mut log = Vec::new();
+    let _: Res<Logged> = store.get(&"a".into(), &mut log).unwrap();
+
+    store.invalidate_where(|_| true);
+
+    let mut reloaded = Vec::new();
+    store.sync_forever(&mut log, ::std::time::Duration::from_millis(1), |report| {
+      reloaded.push(report.reloaded().len());
+      reloaded.len() < 3
+    });
+
+    // the callback stops the loop after the third synchronization
+    #[cfg(feature = "hot-reload")]
+    assert_eq!(reloaded, vec![1, 0, 0]);
+    #[cfg(not(feature = "hot-reload"))]
+    assert_eq!(reloaded, vec![0, 0, 0]);
+  })
+}
+
+#[test]
+fn sync_scope() {
+  with_store(|mut store: Store<Vec<String>, SimpleKey>| {
+    let mut log = Vec::new();
+
+    {
+      let mut scope = store.sync_scope(&mut log);
+      let (store, log) = scope.split();
+      let _: Res<Logged> = store.get(&"a".into(), log).unwrap();
+      assert_eq!(log.len(), 1);
+
+      scope.invalidate_where(|_| true);
+    }
+
+    // the resource was reloaded when the scope ended
+    #[cfg(feature = "hot-reload")]
+    assert_eq!(log.len(), 2);
+
+    store.invalidate_where(|_| true);
+    let report = store.sync_scope(&mut log).finish();
+
+    #[cfg(feature = "hot-reload")]
+    {
+      assert_eq!(report.reloaded().len(), 1);
+      assert_eq!(log.len(), 3);
+    }
+    #[cfg(not(feature = "hot-reload"))]
+    assert!(report.reloaded().is_empty());
+  })
+}
+
+#[cfg(feature = "arc")]
+#[test]
+fn auto_sync() {
+  use std::time::Duration;
+
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_auto_sync(Duration::from_millis(200));
+    assert_eq!(opt.auto_sync(), Some(Duration::from_millis(200)));
+
+    let mut store: Store<Vec<String>, SimpleKey> = Store::new(opt).expect("create store");
+    let mut log = Vec::new();
+    let _: Res<Logged> = store.get(&"a".into(), &mut log).unwrap();
+    store.invalidate_where(|_| true);
+
+    // wait for the timer to mark the store
+    let ticks = store.auto_sync_ticks().expect("auto-sync ticks");
+    ticks.recv_timeout(Duration::from_millis(QUEUE_TIMEOUT_MS)).expect("auto-sync tick");
+    assert!(store.needs_sync());
+
+    let report = store.sync_if_due(&mut log).expect("due synchronization");
+    assert!(!store.needs_sync());
+    assert!(store.sync_if_due(&mut log).is_none());
+
+    #[cfg(feature = "hot-reload")]
+    assert_eq!(report.reloaded().len(), 1);
+    #[cfg(not(feature = "hot-reload"))]
+    assert!(report.reloaded().is_empty());
+  });
+
+  // without auto-sync, the store is never due
+  with_store(|mut store: Store<Vec<String>, SimpleKey>| {
+    assert!(store.auto_sync_ticks().is_none());
+    assert!(!store.needs_sync());
+    assert!(store.sync_if_due(&mut Vec::new()).is_none());
+  })
+}
+
+#[test]
+fn has_pending_changes() {
+  use warmy::testing::MockStore;
+
+  let mut store: MockStore<u32, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let key = SimpleKey::from("config");
+  let mut loads = 0;
+
+  let _: Res<Counted> = store.get(&key, &mut loads).unwrap();
+  assert!(!store.has_pending_changes());
+
+  // filesystem events
+  store.set_file("/level.txt", "trees");
+  #[cfg(feature = "hot-reload")]
+  assert!(store.has_pending_changes());
+  store.sync(&mut loads);
+  assert!(!store.has_pending_changes());
+
+  // invalidated resources
+  store.invalidate_where(|_| true);
+  #[cfg(feature = "hot-reload")]
+  assert!(store.has_pending_changes());
+  store.sync(&mut loads);
+  assert!(!store.has_pending_changes());
+
+  // expired resources
+  store.set_ttl(&key, ::std::time::Duration::from_millis(0));
+  #[cfg(feature = "hot-reload")]
+  assert!(store.has_pending_changes());
+  #[cfg(not(feature = "hot-reload"))]
+  assert!(!store.has_pending_changes());
+}
+
+#[test]
+fn sync_only() {
+  use warmy::testing::MockStore;
+
+  // counts its loads like Counted, but is expensive to reload
+  struct Heavy;
+
+  impl Load<u32, SimpleKey> for Heavy {
+    type Error = TestErr;
+
+    fn load(
+      _: SimpleKey,
+      _: &mut Storage<u32, SimpleKey>,
+      loads: &mut u32,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      *loads += 1;
+      Ok(Heavy.into())
+    }
+  }
+
+  let mut store: MockStore<u32, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let config = SimpleKey::from("config");
+  let texture = SimpleKey::from("texture");
+  let mut loads = 0;
+
+  let _: Res<Counted> = store.get(&config, &mut loads).unwrap();
+  let _: Res<Heavy> = store.get(&texture, &mut loads).unwrap();
+  store.invalidate_where(|_| true);
+
+  // only the configuration is reloaded; the texture waits
+  let report = store.sync_only::<Counted>(&mut loads);
+  assert!(report.skipped().is_empty());
+
+  #[cfg(feature = "hot-reload")]
+  {
+    assert_eq!(report.reloaded().to_vec(), vec![config]);
+    assert_eq!(loads, 3);
+    assert!(store.has_pending_changes());
+  }
+
+  let report = store.sync(&mut loads);
+
+  #[cfg(feature = "hot-reload")]
+  {
+    assert_eq!(report.reloaded().to_vec(), vec![texture]);
+    assert_eq!(loads, 4);
+  }
+  #[cfg(not(feature = "hot-reload"))]
+  {
+    assert!(report.reloaded().is_empty());
+    assert_eq!(loads, 2);
+  }
+}
+
+#[test]
+fn reload_all() {
+  use warmy::testing::MockStore;
+
+  // depends on the resource named in its file
+  struct Layer;
+
+  impl Load<Vec<String>, SimpleKey> for Layer {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<Vec<String>, SimpleKey>,
+      log: &mut Vec<String>,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      let content = storage.read_file_to_string(&path.unwrap())?;
+      log.push(content.clone());
+
+      let mut loaded = Loaded::from(Layer);
+
+      if let Some(base) = content.strip_prefix("over ") {
+        let base = SimpleKey::from_path(base);
+        let _: Res<Layer> = storage
+          .get(&base, log)
+          .map_err(|e| ::std::io::Error::other(e.to_string()))?;
+        loaded = loaded.dep(base);
+      }
+
+      Ok(loaded)
+    }
+  }
+
+  let mut store: MockStore<Vec<String>, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let mut log = Vec::new();
+
+  store.set_file("/base.txt", "base");
+  store.set_file("/mod.txt", "over /base.txt");
+  store.sync(&mut log);
+
+  let _: Res<Layer> = store.get(&SimpleKey::from_path("/mod.txt"), &mut log).unwrap();
+  assert_eq!(log, vec!["over /base.txt".to_owned(), "base".to_owned()]);
+  log.clear();
+
+  let report = store.reload_all(&mut log);
+
+  // the dependency is reloaded first, reloading its dependent once
+  #[cfg(feature = "hot-reload")]
+  {
+    assert_eq!(report.reloaded().len(), 2);
+    assert_eq!(log, vec!["base".to_owned(), "over /base.txt".to_owned()]);
+  }
+  #[cfg(not(feature = "hot-reload"))]
+  {
+    assert!(report.reloaded().is_empty());
+    assert!(log.is_empty());
+  }
+
+  // a more important dependent doesn’t get ahead of its dependency
+  store.set_priority(&SimpleKey::from_path("/mod.txt"), 10);
+  log.clear();
+  store.reload_all(&mut log);
+
+  #[cfg(feature = "hot-reload")]
+  assert_eq!(log, vec!["base".to_owned(), "over /base.txt".to_owned()]);
+}
+
+#[test]
+fn reload_after_all_dependencies() {
+  use warmy::testing::MockStore;
+
+  // content of a file
+  struct Part(String);
+
+  impl Load<u32, SimpleKey> for Part {
+    type Error = ::std::io::Error;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<u32, SimpleKey>,
+      _: &mut u32,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path: Option<PathBuf> = key.into();
+      Ok(Part(storage.read_file_to_string(&path.unwrap())?).into())
+    }
+  }
+
+  // content of two parts, counting its loads
+  struct Joined(String);
+
+  impl Load<u32, SimpleKey> for Joined {
+    type Error = ::std::io::Error;
+
+    fn load(
+      _: SimpleKey,
+      storage: &mut Storage<u32, SimpleKey>,
+      loads: &mut u32,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let keys = vec![SimpleKey::from_path("/a.txt"), SimpleKey::from_path("/b.txt")];
+      let mut content = String::new();
+
+      for key in &keys {
+        let part: Res<Part> = storage
+          .get(key, loads)
+          .map_err(|e| ::std::io::Error::other(e.to_string()))?;
+        content.push_str(&part.borrow().0);
+      }
+
+      *loads += 1;
+      Ok(Loaded::with_deps(Joined(content), keys))
+    }
+  }
+
+  let mut store: MockStore<u32, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let mut loads = 0;
+
+  store.set_file("/a.txt", "a1");
+  store.set_file("/b.txt", "b1");
+  store.sync(&mut loads);
+
+  let joined: Res<Joined> = store.get(&"joined".into(), &mut loads).unwrap();
+  assert_eq!(joined.borrow().0, "a1b1");
+
+  // both dependencies change in the same synchronization
+  store.set_file("/a.txt", "a2");
+  store.set_file("/b.txt", "b2");
+  store.sync(&mut loads);
+
+  // the dependent is reloaded once, after both of them
+  #[cfg(feature = "hot-reload")]
+  {
+    assert_eq!(joined.borrow().0, "a2b2");
+    assert_eq!(loads, 2);
+  }
+  #[cfg(not(feature = "hot-reload"))]
+  {
+    assert_eq!(joined.borrow().0, "a1b1");
+    assert_eq!(loads, 1);
+  }
+}
+
+#[test]
+fn remove_all() {
+  use warmy::testing::MockStore;
+
+  let mut store: MockStore<u32, SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  let mut loads = 1;
+
+  for key in &["audio/step", "audio/jump"] {
+    let _: Res<Counted> = store.get(&(*key).into(), &mut loads).unwrap();
+  }
+
+  let config = SimpleKey::from("config");
+  let _: Res<Generation> = store.get(&config, &mut loads).unwrap();
+
+  assert_eq!(store.remove_all::<Counted>(), 2);
+  assert_eq!(store.remove_all::<Counted>(), 0);
+  assert!(!store.is_loaded_as::<Counted>(&"audio/step".into()));
+  assert!(store.is_loaded_as::<Generation>(&config));
+
+  // removed resources are loaded again the next time they’re asked for
+  let _: Res<Counted> = store.get(&"audio/step".into(), &mut loads).unwrap();
+  assert_eq!(loads, 4);
+}
+
+#[test]
+fn registry_dyn_format() {
+  use warmy::registry::RegistryError;
+  use warmy::testing::MockStore;
+  use warmy::LoaderRegistry;
+
+  #[derive(Debug)]
+  struct Words(Vec<String>);
+
+  let mut registry = LoaderRegistry::new();
+  registry.register("csv", |bytes: &[u8], loads: &mut u32| {
+    *loads += 1;
+    let words = ::std::str::from_utf8(bytes)?.split(',').map(str::to_owned).collect();
+    Ok::<_, ::std::str::Utf8Error>(Words(words))
+  });
+
+  let opt = warmy::StoreOpt::default().set_registry(registry);
+  let mut store: MockStore<u32, SimpleKey> = MockStore::new(opt);
+  let mut loads = 0;
+
+  store.set_file("/list.data", "d,e");
+  store.set_file("/other.data", "f,g");
+  store.sync(&mut loads);
+
+  // the format is chosen at runtime, whatever the extension
+  let key = SimpleKey::from_path("/list.data");
+  let words: Res<Words> = store.get_dyn(&key, &mut loads, "CSV").unwrap();
+  assert_eq!(words.borrow().0, ["d", "e"]);
+  assert_eq!(loads, 1);
+
+  match store.get_dyn::<Words>(&SimpleKey::from_path("/other.data"), &mut loads, "xml") {
+    Err(StoreErrorOr::ResError(RegistryError::NoLoader(_, format))) => assert_eq!(format, "xml"),
+    Err(e) => panic!("expected a missing loader, got {:?}", e),
+    Ok(_) => panic!("expected a missing loader"),
+  }
+
+  // the format is kept to reload the resource
+  store.set_file("/list.data", "d,e,f");
+  store.sync(&mut loads);
+
+  #[cfg(feature = "hot-reload")]
+  {
+    assert_eq!(words.borrow().0, ["d", "e", "f"]);
+    assert_eq!(loads, 2);
+  }
+}
+
+#[test]
+fn expected_resources() {
+  assert_eq!(warmy::StoreOpt::<(), SimpleKey>::default().expected_resources(), 0);
+
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir).set_expected_resources(16);
+    assert_eq!(opt.expected_resources(), 16);
+
+    let mut store: Store<u32, SimpleKey> = Store::new(opt).expect("create store");
+    let mut loads = 0;
+
+    // the hint is not a limit
+    for i in 0..32 {
+      let _: Res<Counted> = store.get(&format!("counted/{}", i).into(), &mut loads).unwrap();
+    }
+
+    assert_eq!(loads, 32);
+    assert_eq!(store.snapshot().resources.len(), 32);
+  })
+}
+
+#[test]
+fn error_codes() {
+  use warmy::registry::Registered;
+  use warmy::testing::MockStore;
+  use warmy::{CodedError, ErrorCode, LoaderRegistry};
+
+  struct Words;
+
+  let opt = warmy::StoreOpt::<(), SimpleKey>::default().set_root("/does/not/exist");
+  match Store::new(opt) {
+    Err(e) => assert_eq!(e.code(), ErrorCode::RootDoesNotExist),
+    Ok(_) => panic!("the root doesn’t exist"),
+  }
+
+  let mut registry = LoaderRegistry::new();
+  registry.register("txt", |bytes: &[u8], _: &mut ()| {
+    ::std::str::from_utf8(bytes).map(|_| Words)
+  });
+
+  let opt = warmy::StoreOpt::default().set_registry(registry);
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(opt);
+  store.set_file("/words.csv", "a,b");
+  store.set_file("/binary.txt", [0xff, 0xfe]);
+
+  let code = |key: &str, store: &mut MockStore<(), SimpleKey>| {
+    match store.get_by::<Words, _>(&SimpleKey::from_path(key), &mut (), Registered) {
+      Err(e) => e.code(),
+      Ok(_) => panic!("{} should fail to load", key),
+    }
+  };
+
+  assert_eq!(code("/missing.txt", &mut store), ErrorCode::FileMissing);
+  assert_eq!(code("/words.csv", &mut store), ErrorCode::NoLoader);
+  assert_eq!(code("/binary.txt", &mut store), ErrorCode::LoaderError);
+  assert_eq!(code("/../outside.txt", &mut store), ErrorCode::KeyOutsideRoot);
+
+  assert_eq!(ErrorCode::FileMissing.as_str(), "file-missing");
+  assert_eq!(ErrorCode::KeyConflict.to_string(), "key-conflict");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_error_codes() {
+  use std::collections::HashMap;
+  use warmy::json::Json;
+  use warmy::testing::MockStore;
+  use warmy::{CodedError, ErrorCode};
+
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/broken.json", "{ level: ");
+
+  let mut code = |key: SimpleKey| {
+    match store.get_by::<HashMap<String, u32>, _>(&key, &mut (), Json) {
+      Err(e) => e.code(),
+      Ok(_) => panic!("{} should fail to load", key),
+    }
+  };
+
+  assert_eq!(code(SimpleKey::from_path("/broken.json")), ErrorCode::ParseError);
+  assert_eq!(code(SimpleKey::from_path("/missing.json")), ErrorCode::FileMissing);
+  assert_eq!(code(SimpleKey::from("logical")), ErrorCode::NoKey);
+}
+
+// A configuration living outside the root, at the path given by the context.
+struct Config(String);
+
+impl Load<PathBuf, SimpleKey> for Config {
+  type Error = TestErr;
+
+  fn load(
+    key: SimpleKey,
+    _: &mut Storage<PathBuf, SimpleKey>,
+    path: &mut PathBuf,
+  ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+    let config = ::std::fs::read_to_string(path).map_err(|_| TestErr::WrongKey(key))?;
+    Ok(Config(config).into())
+  }
+}
+
+#[test]
+fn watch_file() {
+  with_tmp_dir(|etc_dir| {
+    with_store(|mut store: Store<PathBuf, SimpleKey>| {
+      let key = SimpleKey::from("config");
+      let mut path = etc_dir.join("config.toml");
+
+      assert!(store.watch_file(&path, &key).is_err());
+
+      ::std::fs::write(&path, "level = 1").unwrap();
+      store.watch_file(&path, &key).unwrap();
+
+      let config: Res<Config> = store.get(&key, &mut path).unwrap();
+      assert_eq!(config.borrow().0, "level = 1");
+
+      #[cfg(feature = "hot-reload")]
+      {
+        ::std::thread::sleep(::std::time::Duration::from_millis(100));
+        ::std::fs::write(&path, "level = 2").unwrap();
+
+        wait_until(|| {
+          store.sync(&mut path);
+          config.borrow().0 == "level = 2"
+        });
+
+        // the other files of its directory are not watched
+        ::std::fs::write(etc_dir.join("other.toml"), "level = 3").unwrap();
+        ::std::thread::sleep(::std::time::Duration::from_millis(300));
+        assert!(store.sync(&mut path).reloaded().is_empty());
+      }
+    })
+  });
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn root_lost_and_restored() {
+  with_tmp_dir(|tmp_dir| {
+    let root = tmp_dir.join("assets");
+    ::std::fs::create_dir(&root).unwrap();
+    ::std::fs::write(root.join("hero.txt"), "idle").unwrap();
+
+    let events = ::std::rc::Rc::new(::std::cell::RefCell::new(Events::default()));
+    let opt = warmy::StoreOpt::default().set_root(&root).set_observer(Recorder(events.clone()));
+    let mut store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+
+    let hero: Res<Text> = store.get(&SimpleKey::from_path("/hero.txt"), &mut ()).unwrap();
+
+    // a clean build removes the root
+    ::std::fs::remove_dir_all(&root).unwrap();
+
+    let report = store.sync(&mut ());
+    assert!(report.root_lost());
+    assert!(!report.root_restored());
+    assert!(store.sync(&mut ()).root_lost());
+    assert_eq!(events.borrow().roots, vec!["lost"]);
+
+    // and the build brings it back
+    ::std::fs::create_dir(&root).unwrap();
+    ::std::fs::write(root.join("hero.txt"), "running").unwrap();
+    assert!(store.has_pending_changes());
+
+    let report = store.sync(&mut ());
+    assert!(!report.root_lost());
+    assert!(report.root_restored());
+    assert_eq!(events.borrow().roots, vec!["lost", "restored"]);
+    assert_eq!(hero.borrow().0, "running");
+
+    // the root is watched again
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    ::std::fs::write(root.join("hero.txt"), "jumping").unwrap();
+
+    wait_until(|| {
+      store.sync(&mut ());
+      hero.borrow().0 == "jumping"
+    });
+  });
+}
+
+#[cfg(feature = "hot-reload")]
+#[test]
+fn close_store() {
+  with_tmp_dir(|tmp_dir| {
+    ::std::fs::create_dir(tmp_dir.join("maps")).unwrap();
+    ::std::fs::write(tmp_dir.join("maps/a.txt"), "a").unwrap();
+
+    let opt = warmy::StoreOpt::default().set_root(tmp_dir);
+    let store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+    let mut maps = store.scoped("maps").unwrap();
+
+    // the child still shares the watcher of its closed parent
+    store.close();
+
+    let a: Res<Text> = maps.get(&SimpleKey::from_path("/a.txt"), &mut ()).unwrap();
+    ::std::thread::sleep(::std::time::Duration::from_millis(100));
+    ::std::fs::write(tmp_dir.join("maps/a.txt"), "a2").unwrap();
+
+    wait_until(|| {
+      maps.sync(&mut ());
+      a.borrow().0 == "a2"
+    });
+
+    maps.close();
+  });
+
+  // the timer is stopped without waiting for its next tick
+  #[cfg(feature = "arc")]
+  with_tmp_dir(|tmp_dir| {
+    let opt = warmy::StoreOpt::default()
+      .set_root(tmp_dir)
+      .set_auto_sync(::std::time::Duration::from_secs(60));
+    let store: Store<(), SimpleKey> = warmy::Store::new(opt).expect("create store");
+
+    let start = ::std::time::Instant::now();
+    store.close();
+    assert!(start.elapsed() < ::std::time::Duration::from_secs(10));
+  });
+}
+
+#[test]
+fn provenance() {
+  use warmy::testing::MockStore;
+  use warmy::FileSource;
+
+  // A text file selected according to the variants of the store, read through the storage.
+  struct Picked;
+
+  impl<C> Load<C, SimpleKey> for Picked {
+    type Error = TestErr;
+
+    fn load(
+      key: SimpleKey,
+      storage: &mut Storage<C, SimpleKey>,
+      _: &mut C,
+    ) -> Result<Loaded<Self, SimpleKey>, Self::Error> {
+      let path = match key {
+        SimpleKey::Path(ref path) => storage.select_variant(&key, path),
+        _ => return Err(TestErr::WrongKey(key)),
+      };
+      storage.read_file(&path).map_err(|_| TestErr::WrongKey(key))?;
+
+      Ok(Picked.into())
+    }
+  }
+
+  let key = SimpleKey::from_path("/texture.png");
+  let opt = warmy::StoreOpt::default().set_variants(vec!["hi"]).set_track_provenance(true);
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(opt);
+  store.set_file("/texture.png", "default");
+  store.set_file("/texture.hi.png", "high");
+  store.set_file("/texture.lo.png", "low!");
+  store.sync(&mut ());
+
+  assert!(store.provenance(&key).is_none());
+  let _: Res<Picked> = store.get(&key, &mut ()).unwrap();
+
+  let high = store.provenance(&key).unwrap().clone();
+  assert_eq!(high.source, FileSource::Memory);
+  assert!(high.path.ends_with("texture.hi.png"));
+  assert_eq!(high.size, 4);
+
+  #[cfg(feature = "hot-reload")]
+  {
+    store.set_variants(vec!["lo", "hi"]);
+    store.sync(&mut ());
+
+    let low = store.provenance(&key).unwrap();
+    assert!(low.path.ends_with("texture.lo.png"));
+    assert_eq!(low.size, 4);
+    assert_ne!(low.hash, high.hash);
+  }
+
+  // nothing is tracked by default
+  let mut store: MockStore<(), SimpleKey> = MockStore::new(warmy::StoreOpt::default());
+  store.set_file("/texture.png", "default");
+  let _: Res<Picked> = store.get(&key, &mut ()).unwrap();
+  assert!(store.provenance(&key).is_none());
+}
@@ -0,0 +1,296 @@
+//! Derive macros for [warmy].
+//!
+//! You shouldn’t depend on this crate directly: enable the `"derive"` feature-gate of [warmy]
+//! instead.
+//!
+//! [warmy]: https://crates.io/crates/warmy
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+  parse_macro_input, parse_quote, Data, DeriveInput, Error, Field, Fields, Ident, Index, LitStr,
+  Member, WhereClause,
+};
+
+/// Implement `Key` for a struct or an enum.
+///
+/// Fields marked with `#[key(path)]` are considered file system paths: they have their VFS path
+/// substituted with the root of the store when the key is prepared and they are normalized along
+/// with the key. Such fields must be of type `PathBuf`. All the other fields are left untouched.
+/// The first path field of a struct or variant is also its file system path – see
+/// `Key::fs_path`.
+///
+/// The type must also implement `Clone`, `Eq` and `Hash`. With `#[key(debug)]` on the type, keys
+/// are described in diagnostics with their `Debug` implementation – see `Key::describe`.
+///
+/// ```ignore
+/// #[derive(Clone, Debug, Eq, Hash, Key, PartialEq)]
+/// #[key(debug)]
+/// enum AssetKey {
+///   Texture(#[key(path)] PathBuf),
+///   Shader { #[key(path)] path: PathBuf, stage: u8 },
+///   Builtin(String),
+/// }
+/// ```
+#[proc_macro_derive(Key, attributes(key))]
+pub fn derive_key(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  key_impl(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Implement `Load` for a type by delegating to a universal method, so that the type can be loaded
+/// with `Storage::get` instead of stating the method with `Storage::get_by` at every call site.
+///
+/// The `format` is one of `"json"`, `"toml"` and `"ron"`, which require the matching feature-gate
+/// of warmy. The optional `ext` – given without the leading dot – also implements `Extension`, so
+/// that `TypedDiscovery::with_type` discovers the files of the type.
+///
+/// ```ignore
+/// #[derive(Deserialize, Load)]
+/// #[load(format = "json", ext = "json")]
+/// struct Config {
+///   volume: f32,
+/// }
+/// ```
+#[proc_macro_derive(Load, attributes(load))]
+pub fn derive_load(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  load_impl(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn key_impl(input: DeriveInput) -> Result<TokenStream2, Error> {
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let prepare = quote! { ::warmy::key::vfs_substitute_path(&path, root) };
+  let normalize = quote! { normalization.normalize_path(path) };
+
+  let (prepare_body, normalize_body, fs_path_body) = match input.data {
+    Data::Struct(ref data) => {
+      let (pattern, prepared) = rebuild(quote! { #ident }, &data.fields, &prepare)?;
+      let (_, normalized) = rebuild(quote! { #ident }, &data.fields, &normalize)?;
+      let fs_path = fs_path_arm(quote! { #ident }, &data.fields)?;
+
+      (
+        quote! { let #pattern = self; #prepared },
+        quote! { let #pattern = self; #normalized },
+        quote! { match self { #fs_path } },
+      )
+    }
+
+    Data::Enum(ref data) => {
+      let mut prepare_arms = Vec::with_capacity(data.variants.len());
+      let mut normalize_arms = Vec::with_capacity(data.variants.len());
+      let mut fs_path_arms = Vec::with_capacity(data.variants.len());
+
+      for variant in &data.variants {
+        let variant_ident = &variant.ident;
+        let path = quote! { #ident::#variant_ident };
+        let (pattern, prepared) = rebuild(path.clone(), &variant.fields, &prepare)?;
+        let (_, normalized) = rebuild(path.clone(), &variant.fields, &normalize)?;
+
+        prepare_arms.push(quote! { #pattern => #prepared, });
+        normalize_arms.push(quote! { #pattern => #normalized, });
+        fs_path_arms.push(fs_path_arm(path, &variant.fields)?);
+      }
+
+      (
+        quote! { match self { #(#prepare_arms)* } },
+        quote! { match self { #(#normalize_arms)* } },
+        quote! { match self { #(#fs_path_arms)* } },
+      )
+    }
+
+    Data::Union(_) => return Err(Error::new_spanned(ident, "Key cannot be derived for unions")),
+  };
+
+  let describe = if is_debug(&input)? {
+    Some(quote! {
+      fn describe(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::std::fmt::Debug::fmt(self, f)
+      }
+    })
+  } else {
+    None
+  };
+
+  Ok(quote! {
+    impl #impl_generics ::warmy::Key for #ident #ty_generics #where_clause {
+      fn prepare_key(self, root: &::std::path::Path) -> Self {
+        #prepare_body
+      }
+
+      fn normalize(self, normalization: &::warmy::Normalization) -> Self {
+        #normalize_body
+      }
+
+      fn fs_path(&self) -> ::std::option::Option<&::std::path::Path> {
+        #fs_path_body
+      }
+
+      #describe
+    }
+  })
+}
+
+/// Generate a pattern destructuring a struct or enum variant along with an expression rebuilding
+/// it, transforming the path fields with `transform`.
+///
+/// `transform` is an expression using a `path: PathBuf` variable.
+fn rebuild(
+  path: TokenStream2,
+  fields: &Fields,
+  transform: &TokenStream2,
+) -> Result<(TokenStream2, TokenStream2), Error> {
+  let mut members = Vec::with_capacity(fields.len());
+  let mut bindings = Vec::with_capacity(fields.len());
+  let mut values = Vec::with_capacity(fields.len());
+
+  for (i, field) in fields.iter().enumerate() {
+    let member = match field.ident {
+      Some(ref ident) => Member::Named(ident.clone()),
+      None => Member::Unnamed(Index::from(i)),
+    };
+    let binding = Ident::new(&format!("__field_{}", i), Span::call_site());
+
+    let value = if is_path(field)? {
+      quote! {{ let path = #binding; #transform }}
+    } else {
+      quote! { #binding }
+    };
+
+    members.push(member);
+    bindings.push(binding);
+    values.push(value);
+  }
+
+  let pattern = quote! { #path { #(#members: #bindings),* } };
+  let rebuilt = quote! { #path { #(#members: #values),* } };
+
+  Ok((pattern, rebuilt))
+}
+
+/// Generate a match arm returning the first path field of a struct or enum variant, if any.
+fn fs_path_arm(path: TokenStream2, fields: &Fields) -> Result<TokenStream2, Error> {
+  for (i, field) in fields.iter().enumerate() {
+    if is_path(field)? {
+      let member = match field.ident {
+        Some(ref ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(Index::from(i)),
+      };
+
+      return Ok(quote! {
+        #path { #member: path, .. } => ::std::option::Option::Some(path.as_path()),
+      });
+    }
+  }
+
+  Ok(quote! { #path { .. } => ::std::option::Option::None, })
+}
+
+/// Check whether a type is marked with `#[key(debug)]`.
+fn is_debug(input: &DeriveInput) -> Result<bool, Error> {
+  let mut debug = false;
+
+  for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("key")) {
+    attr.parse_nested_meta(|meta| {
+      if meta.path.is_ident("debug") {
+        debug = true;
+        Ok(())
+      } else {
+        Err(meta.error("unsupported key attribute; expected #[key(debug)]"))
+      }
+    })?;
+  }
+
+  Ok(debug)
+}
+
+/// Check whether a field is marked with `#[key(path)]`.
+fn is_path(field: &Field) -> Result<bool, Error> {
+  let mut path = false;
+
+  for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("key")) {
+    attr.parse_nested_meta(|meta| {
+      if meta.path.is_ident("path") {
+        path = true;
+        Ok(())
+      } else {
+        Err(meta.error("unsupported key attribute; expected #[key(path)]"))
+      }
+    })?;
+  }
+
+  Ok(path)
+}
+
+fn load_impl(input: DeriveInput) -> Result<TokenStream2, Error> {
+  let ident = &input.ident;
+  let mut format = None;
+  let mut ext = None;
+
+  for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("load")) {
+    attr.parse_nested_meta(|meta| {
+      if meta.path.is_ident("format") {
+        format = Some(meta.value()?.parse::<LitStr>()?);
+        Ok(())
+      } else if meta.path.is_ident("ext") {
+        ext = Some(meta.value()?.parse::<LitStr>()?);
+        Ok(())
+      } else {
+        Err(meta.error("unsupported load attribute; expected format or ext"))
+      }
+    })?;
+  }
+
+  let format = format
+    .ok_or_else(|| Error::new_spanned(ident, "missing #[load(format = \"…\")] attribute"))?;
+  let method = match format.value().as_str() {
+    "json" => quote! { ::warmy::json::Json },
+    "toml" => quote! { ::warmy::toml::Toml },
+    "ron" => quote! { ::warmy::ron::Ron },
+    _ => return Err(Error::new_spanned(format, "unsupported format; expected json, toml or ron")),
+  };
+
+  // the context and key types are added to the generics of the type
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let mut load_generics = input.generics.clone();
+  load_generics.params.push(parse_quote! { __C });
+  load_generics.params.push(parse_quote! { __K: ::warmy::Key });
+  let (load_impl_generics, _, _) = load_generics.split_for_impl();
+
+  let mut load_where_clause = where_clause.cloned().unwrap_or_else(|| WhereClause {
+    where_token: Default::default(),
+    predicates: Default::default(),
+  });
+  load_where_clause.predicates.push(parse_quote! { Self: ::warmy::Load<__C, __K, #method> });
+
+  let extension = ext.map(|ext| {
+    quote! {
+      impl #impl_generics ::warmy::Extension for #ident #ty_generics #where_clause {
+        const EXTENSION: &'static str = #ext;
+      }
+    }
+  });
+
+  Ok(quote! {
+    impl #load_impl_generics ::warmy::Load<__C, __K> for #ident #ty_generics #load_where_clause {
+      type Error = <Self as ::warmy::Load<__C, __K, #method>>::Error;
+
+      fn load(
+        key: __K,
+        storage: &mut ::warmy::Storage<__C, __K>,
+        ctx: &mut __C,
+      ) -> ::std::result::Result<::warmy::Loaded<Self, __K>, Self::Error> {
+        <Self as ::warmy::Load<__C, __K, #method>>::load(key, storage, ctx)
+      }
+    }
+
+    #extension
+  })
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+use warmy::{Key, Normalization};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, warmy_derive::Key)]
+#[key(debug)]
+enum AssetKey {
+  Texture(#[key(path)] PathBuf),
+  Shader {
+    #[key(path)]
+    path: PathBuf,
+    stage: u8,
+  },
+  Builtin(String),
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, warmy_derive::Key)]
+struct LevelKey {
+  #[key(path)]
+  path: PathBuf,
+  chunk: u32,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, warmy_derive::Key)]
+struct Unit;
+
+// keys don’t have to implement Debug
+#[derive(Clone, Eq, Hash, PartialEq, warmy_derive::Key)]
+struct Opaque(u32);
+
+struct Describe<K>(K);
+
+impl<K> std::fmt::Display for Describe<K> where K: Key {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    self.0.describe(f)
+  }
+}
+
+#[test]
+fn derive_enum() {
+  let root = Path::new("/root");
+
+  assert_eq!(
+    AssetKey::Texture("/hero.png".into()).prepare_key(root),
+    AssetKey::Texture("/root/hero.png".into())
+  );
+  assert_eq!(
+    AssetKey::Shader { path: "blur.glsl".into(), stage: 1 }.prepare_key(root),
+    AssetKey::Shader { path: "/root/blur.glsl".into(), stage: 1 }
+  );
+  assert_eq!(
+    AssetKey::Builtin("white".to_owned()).prepare_key(root),
+    AssetKey::Builtin("white".to_owned())
+  );
+
+  assert_eq!(AssetKey::Texture("hero.png".into()).fs_path(), Some(Path::new("hero.png")));
+  assert_eq!(
+    AssetKey::Shader { path: "blur.glsl".into(), stage: 1 }.fs_path(),
+    Some(Path::new("blur.glsl"))
+  );
+  assert_eq!(AssetKey::Builtin("white".to_owned()).fs_path(), None);
+}
+
+#[test]
+fn derive_struct() {
+  let key = LevelKey { path: "/Levels/One.map".into(), chunk: 3 };
+  let normalization = Normalization::default().set_case_insensitive(true);
+
+  assert_eq!(
+    key.clone().prepare_key(Path::new("/root")),
+    LevelKey { path: "/root/Levels/One.map".into(), chunk: 3 }
+  );
+  assert_eq!(
+    key.normalize(&normalization),
+    LevelKey { path: "/levels/one.map".into(), chunk: 3 }
+  );
+  assert_eq!(Unit.prepare_key(Path::new("/root")), Unit);
+  assert_eq!(Unit.fs_path(), None);
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize, warmy_derive::Load)]
+#[load(format = "json", ext = "json")]
+struct Config {
+  volume: f32,
+}
+
+#[derive(Debug, serde::Deserialize, warmy_derive::Load)]
+#[load(format = "json")]
+struct Wrapper<T> {
+  inner: T,
+}
+
+#[test]
+fn derive_load() {
+  use warmy::{Extension, Res, SimpleKey, Store, StoreOpt};
+
+  let tmp_dir = tempfile::tempdir().unwrap();
+  std::fs::write(tmp_dir.path().join("config.json"), r#"{ "volume": 0.5 }"#).unwrap();
+  std::fs::write(tmp_dir.path().join("wrapper.json"), r#"{ "inner": 3 }"#).unwrap();
+
+  let opt = StoreOpt::default().set_root(tmp_dir.path());
+  let mut store: Store<(), SimpleKey> = Store::new(opt).unwrap();
+
+  let config: Res<Config> = store.get(&Path::new("/config.json").into(), &mut ()).unwrap();
+  assert_eq!(*config.borrow(), Config { volume: 0.5 });
+  assert_eq!(Config::EXTENSION, "json");
+
+  let key = Path::new("/wrapper.json").into();
+  let wrapper: Res<Wrapper<u32>> = store.get(&key, &mut ()).unwrap();
+  assert_eq!(wrapper.borrow().inner, 3);
+}
+
+#[test]
+fn derive_describe() {
+  assert_eq!(Describe(AssetKey::Builtin("white".to_owned())).to_string(), r#"Builtin("white")"#);
+  assert_eq!(Describe(Opaque(3)).to_string(), "<key>");
+}